@@ -0,0 +1,165 @@
+//! The per-frame paths: the bellows math, the voices as the audio thread
+//! mixes them, and the live resampler behind meend.
+//!
+//! `cargo bench` runs them all; `cargo bench -- bellows` just one group.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Duration;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use harmonium_core::audio::{AudioEngine, CaptureSink};
+use harmonium_core::bellows::{AirModel, BellowsParams, BellowsState, VelocityEstimator};
+use harmonium_core::latency::{LatencyProbe, ProbeSource};
+use harmonium_core::meter::{MeterSource, VoiceMeter};
+use harmonium_core::pitch_bend::{BendHandle, BendSource};
+use harmonium_core::simulation::Trajectory;
+use harmonium_core::voice::Sample;
+use rodio::Source;
+use rodio::dynamic_mixer;
+
+/// Sensor samples per bellows run: ten seconds at 120 Hz.
+const SENSOR_SAMPLES: usize = 1200;
+
+/// Samples pulled per audio bench: one output buffer.
+const BLOCK: usize = 1024;
+
+const RATE: u32 = 48_000;
+
+fn bellows(c: &mut Criterion) {
+    let pump = Trajectory::Pump {
+        center_deg: 60.0,
+        amplitude_deg: 20.0,
+        period_sec: 1.2,
+    };
+    let angles: Vec<(f32, Duration)> = (0..SENSOR_SAMPLES)
+        .map(|i| {
+            let t = i as f32 / 120.0;
+            (pump.angle_at(t), Duration::from_secs_f32(t))
+        })
+        .collect();
+
+    let setups = [
+        ("finite_difference", VelocityEstimator::FiniteDifference, AirModel::Envelope),
+        ("alpha_beta", VelocityEstimator::AlphaBeta, AirModel::Envelope),
+        ("spring_mass", VelocityEstimator::FiniteDifference, AirModel::SpringMass),
+    ];
+
+    let mut group = c.benchmark_group("bellows");
+    group.throughput(Throughput::Elements(SENSOR_SAMPLES as u64));
+    for (name, estimator, air_model) in setups {
+        let params = BellowsParams {
+            estimator,
+            air_model,
+            ..BellowsParams::default()
+        };
+        group.bench_function(BenchmarkId::new("update", name), |b| {
+            b.iter(|| {
+                let mut state = BellowsState::new(params.clone());
+                for &(theta, t) in &angles {
+                    black_box(state.update(black_box(theta), t));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// A second of sine at `freq`, standing in for a sample.
+fn sine(freq: f32) -> Arc<Sample> {
+    let samples = (0..RATE)
+        .map(|n| (std::f32::consts::TAU * freq * n as f32 / RATE as f32).sin())
+        .collect();
+    Arc::new(Sample::new(samples, 1, RATE))
+}
+
+/// A voice's source as the engine builds it, on a sine instead of a sample.
+fn voice(
+    freq: f32,
+    bend: &BendHandle,
+    probe: &Arc<LatencyProbe>,
+) -> impl Source<Item = f32> + Send + 'static {
+    let meter = Arc::new(VoiceMeter::default());
+    let bent = BendSource::new(sine(freq), bend.clone());
+    ProbeSource::new(MeterSource::new(bent, meter), probe.clone())
+}
+
+fn mixer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mixer");
+    group.throughput(Throughput::Elements(BLOCK as u64));
+
+    // What the audio thread runs: every voice pulled and summed, at its volume.
+    for voices in [1, 8, 24] {
+        let bend = BendHandle::default();
+        let probe = Arc::new(LatencyProbe::default());
+        let (controller, mut mix) = dynamic_mixer::mixer::<f32>(2, RATE);
+        for v in 0..voices {
+            let freq = 130.8 * 2f32.powf(v as f32 / 12.0);
+            controller.add(voice(freq, &bend, &probe).amplify(0.5));
+        }
+
+        group.bench_function(BenchmarkId::new("pull", voices), |b| {
+            b.iter(|| {
+                for _ in 0..BLOCK {
+                    black_box(mix.next());
+                }
+            })
+        });
+    }
+    group.finish();
+
+    // What the UI thread runs every frame: the bellows level into every
+    // voice's volume.
+    let mut group = c.benchmark_group("mixer_volumes");
+    let samples = concat!(env!("CARGO_MANIFEST_DIR"), "/harmonium-sounds");
+    let mut engine = AudioEngine::with_output(samples, Box::new(CaptureSink::default()));
+    let notes = ["c3", "d3", "e3", "f3", "g3", "a3", "b3", "c4"];
+    for note in notes {
+        engine.note_on(note).expect("sample in harmonium-sounds");
+    }
+    let mut a = 0.0;
+    group.bench_function(BenchmarkId::new("set_bellows", notes.len()), |b| {
+        b.iter(|| {
+            a = (a + 0.01) % 1.0;
+            engine.set_bellows(black_box(a));
+        })
+    });
+    group.finish();
+}
+
+fn resampler(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resampler");
+    group.throughput(Throughput::Elements(BLOCK as u64));
+
+    for (name, semitones) in [("unbent", 0.0), ("up_a_tone", 2.0), ("down_an_octave", -12.0)] {
+        let bend = BendHandle::default();
+        bend.set_semitones(semitones);
+        let mut source = BendSource::new(sine(261.6), bend);
+
+        group.bench_function(BenchmarkId::new("bend", name), |b| {
+            b.iter(|| {
+                for _ in 0..BLOCK {
+                    black_box(source.next());
+                }
+            })
+        });
+    }
+
+    // A held note through the meend slide: the ratio changes every block.
+    let bend = BendHandle::default();
+    let mut source = BendSource::new(sine(261.6), bend.clone());
+    let mut step = 0u32;
+    group.bench_function(BenchmarkId::new("bend", "sliding"), |b| {
+        b.iter(|| {
+            step = (step + 1) % 200;
+            bend.set_semitones(step as f32 / 100.0 - 1.0);
+            for _ in 0..BLOCK {
+                black_box(source.next());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bellows, mixer, resampler);
+criterion_main!(benches);
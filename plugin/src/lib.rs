@@ -0,0 +1,236 @@
+//! The harmonium as a CLAP / VST3 instrument: the DAW's MIDI plays the keys
+//! and the automatable **Bellows** parameter is the air, so a part can be
+//! written with its bellows as an automation lane (or a controller mapped to
+//! it) instead of a laptop lid.
+
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use harmonium_core::bank::SAMPLES_ROOT;
+use harmonium_core::voice::{LoopReader, Sample};
+use nih_plug::prelude::*;
+
+mod samples;
+
+use samples::SampleSet;
+
+/// Environment variable naming the sample folder to load.
+const SAMPLES_ENV: &str = "HARMONIUM_SAMPLES";
+
+/// Fade in / out at key down / up, so notes start and stop without clicks.
+const RAMP_SEC: f32 = 0.01;
+
+/// The sample folder: `$HARMONIUM_SAMPLES`, else `~/harmonium-sounds`.
+fn samples_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(SAMPLES_ENV) {
+        return PathBuf::from(dir);
+    }
+    dirs::home_dir().unwrap_or_default().join(SAMPLES_ROOT)
+}
+
+#[derive(Params)]
+struct HarmoniumParams {
+    /// Air in the bellows (0..1); every note's level follows it.
+    #[id = "bellows"]
+    bellows: FloatParam,
+
+    /// Master volume on top of the bellows.
+    #[id = "volume"]
+    volume: FloatParam,
+}
+
+impl Default for HarmoniumParams {
+    fn default() -> Self {
+        let unit = || FloatRange::Linear { min: 0.0, max: 1.0 };
+        Self {
+            bellows: FloatParam::new("Bellows", 0.7, unit())
+                .with_smoother(SmoothingStyle::Linear(30.0))
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+                .with_unit("%"),
+            volume: FloatParam::new("Volume", 0.8, unit())
+                .with_smoother(SmoothingStyle::Linear(30.0))
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+                .with_unit("%"),
+        }
+    }
+}
+
+/// One sounding note: loops its sample through the app's [`LoopReader`],
+/// for as long as the key is held.
+struct Voice {
+    midi: u8,
+    reader: LoopReader,
+
+    /// Fade level (0..1) and whether it is fading out after key up.
+    level: f32,
+    releasing: bool,
+}
+
+impl Voice {
+    /// The next output sample at `host_rate`, resampled from the sample's
+    /// own rate.
+    fn next(&mut self, sample: &Sample, host_rate: f32) -> f32 {
+        let mut out = [0.0];
+        let step = f64::from(sample.rate) / f64::from(host_rate);
+        self.reader.read(sample, step, &mut out);
+
+        let step = 1.0 / (RAMP_SEC * host_rate);
+        self.level = if self.releasing {
+            (self.level - step).max(0.0)
+        } else {
+            (self.level + step).min(1.0)
+        };
+        out[0] * self.level
+    }
+
+    fn is_done(&self) -> bool {
+        self.releasing && self.level <= 0.0
+    }
+}
+
+struct HarmoniumPlugin {
+    params: Arc<HarmoniumParams>,
+    samples: Option<SampleSet>,
+    voices: Vec<Voice>,
+    sample_rate: f32,
+}
+
+impl Default for HarmoniumPlugin {
+    fn default() -> Self {
+        Self {
+            params: Arc::new(HarmoniumParams::default()),
+            samples: None,
+            // One per MIDI note at most, so the audio thread never allocates.
+            voices: Vec::with_capacity(128),
+            sample_rate: 44_100.0,
+        }
+    }
+}
+
+impl HarmoniumPlugin {
+    fn note_on(&mut self, midi: u8) {
+        match self.voices.iter_mut().find(|v| v.midi == midi) {
+            // Played again while fading out: it is simply held again.
+            Some(voice) => voice.releasing = false,
+            None => self.voices.push(Voice {
+                midi,
+                reader: LoopReader::default(),
+                level: 0.0,
+                releasing: false,
+            }),
+        }
+    }
+
+    fn note_off(&mut self, midi: u8) {
+        for voice in self.voices.iter_mut().filter(|v| v.midi == midi) {
+            voice.releasing = true;
+        }
+    }
+}
+
+impl Plugin for HarmoniumPlugin {
+    const NAME: &'static str = "Harmonium";
+    const VENDOR: &'static str = "harmonium-in-mac";
+    const URL: &'static str = "https://github.com/chintan-27/harmonium-in-mac";
+    const EMAIL: &'static str = "";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+        main_input_channels: None,
+        main_output_channels: NonZeroU32::new(2),
+        ..AudioIOLayout::const_default()
+    }];
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn initialize(
+        &mut self,
+        _layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        _context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.sample_rate = buffer_config.sample_rate;
+        if self.samples.is_none() {
+            let dir = samples_dir();
+            let samples = SampleSet::load(&dir);
+            nih_log!("{} samples from {}", samples.note_count(), dir.display());
+            self.samples = Some(samples);
+        }
+        true
+    }
+
+    fn reset(&mut self) {
+        self.voices.clear();
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        let mut next_event = context.next_event();
+        for (sample_id, channel_samples) in buffer.iter_samples().enumerate() {
+            while let Some(event) = next_event {
+                if event.timing() > sample_id as u32 {
+                    break;
+                }
+                match event {
+                    NoteEvent::NoteOn { note, .. } => self.note_on(note),
+                    NoteEvent::NoteOff { note, .. } => self.note_off(note),
+                    _ => {}
+                }
+                next_event = context.next_event();
+            }
+
+            // As in the app: keys pick the notes, the bellows sets the level.
+            let air = self.params.bellows.smoothed.next() * self.params.volume.smoothed.next();
+            let mut mix = 0.0;
+            if let Some(samples) = &self.samples {
+                for voice in &mut self.voices {
+                    if let Some(sample) = samples.get(voice.midi) {
+                        mix += voice.next(sample, self.sample_rate);
+                    }
+                }
+            }
+            for out in channel_samples {
+                *out = mix * air;
+            }
+        }
+
+        self.voices.retain(|v| !v.is_done());
+        ProcessStatus::KeepAlive
+    }
+}
+
+impl ClapPlugin for HarmoniumPlugin {
+    const CLAP_ID: &'static str = "com.github.chintan-27.harmonium";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("Sampled harmonium whose bellows are an automatable parameter");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::Instrument,
+        ClapFeature::Sampler,
+        ClapFeature::Stereo,
+    ];
+}
+
+impl Vst3Plugin for HarmoniumPlugin {
+    const VST3_CLASS_ID: [u8; 16] = *b"HarmoniumBellows";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+        &[Vst3SubCategory::Instrument, Vst3SubCategory::Sampler];
+}
+
+nih_export_clap!(HarmoniumPlugin);
+nih_export_vst3!(HarmoniumPlugin);
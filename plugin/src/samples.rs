@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use harmonium_core::audio;
+use harmonium_core::bank;
+use harmonium_core::note::Note;
+use harmonium_core::voice::Sample;
+use nih_plug::nih_log;
+
+/// Every sample of a bank by MIDI note, decoded (and mixed down to mono) up
+/// front so the audio thread never touches the disk.
+pub struct SampleSet {
+    by_midi: Vec<Option<Sample>>,
+}
+
+impl SampleSet {
+    /// Decode the bank in `dir` (named like the app's: `c3.wav`, `d#3.flac`)
+    /// the way the app does. Notes without a usable file stay silent.
+    pub fn load(dir: &Path) -> Self {
+        let by_midi = (0..128)
+            .map(|midi| {
+                let note = Note::from_midi(midi)?;
+                let path = bank::sample_path(dir, &note.name())?;
+                let sample = audio::load_sample(&path).inspect_err(|e| nih_log!("{e}")).ok()?;
+                Some(sample.to_mono())
+            })
+            .collect();
+        Self { by_midi }
+    }
+
+    pub fn get(&self, midi: u8) -> Option<&Sample> {
+        self.by_midi.get(midi as usize)?.as_ref()
+    }
+
+    /// How many notes have a sample.
+    pub fn note_count(&self) -> usize {
+        self.by_midi.iter().flatten().count()
+    }
+}
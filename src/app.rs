@@ -1,338 +1,4993 @@
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
-use crate::audio::AudioEngine;
-use crate::bellows::{BellowsOutput, BellowsParams, BellowsState};
-use crate::keymap::{KeyMap, PressedKeys};
-use crate::sensor::{SensorMsg, SensorSample};
+use egui_plot::{Bar, BarChart, HLine, Legend, Line, Plot};
+use tracing::{Level, error, info, warn};
+
+use crate::arpeggiator::{self, ArpPattern, Arpeggiator};
+use crate::arrow_bellows::ArrowBellows;
+use crate::audio::{AudioEngine, AudioError};
+use crate::backing::{self, BackingConfig, BackingTrack};
+use crate::bank::{self, SampleBank};
+use crate::beat_wheel::BeatWheel;
+use crate::bellows::{AirModel, BellowsMode, BellowsOutput, BellowsState, VelocityEstimator};
+use crate::demo::Demo;
+use crate::dock::{Dock, DockLayout, Panel};
+use crate::dual::{DualConfig, SecondInstrument};
+use crate::events::{
+    self, BellowsEvent, ConfigEvent, Event, EventBus, LoadEvent, SensorEvent, Subscriber,
+};
+use crate::cli::Overrides;
+use crate::config::{self, AppConfig, AudioConfig, DeviceConfig, FakeInputConfig, Theme, UiConfig};
+use crate::fusion::{FusionMode, SensorFusion};
+use crate::gesture::{GestureLooper, LooperState};
+use crate::harmonizer::{HarmonyInterval, Harmonizer};
+use crate::looper::{PhraseLooper, PhraseLooperState};
+use crate::history::{BellowsHistory, MAX_HISTORY_SEC};
+use crate::hud::FrameTimer;
+use crate::keymap::{
+    KeyId, KeyMap, KeyMods, KeymapError, KeymapFormat, Latch, MonoKeys, MonoMove, PhysicalKey,
+    PressedKeys, Voicing,
+};
+use crate::latency::{LatencyMeter, LatencySample};
+use crate::lesson::{self, Exercise, Lesson, StepResult};
+use crate::lid_view::LidView;
+use crate::loader::{LoadJob, LoadMsg, Loader};
+use crate::layout::{self, LayoutTemplate};
+use crate::logging::LogBuffer;
+use crate::meter;
+use crate::metronome::{self, Metronome, Tala};
+use crate::midi::{
+    self, MidiError, MidiEvent, MidiIn, MidiLearn, MidiOut, MidiParam, MidiRecorder,
+};
+use crate::note::{Note, NoteName, PITCH_NAMES, SARGAM_NAMES};
+use crate::pitch_bend::{AttackScoop, Meend};
+use crate::practice::{Ducker, PracticeConfig};
+use crate::preset::{self, PRESET_HOTKEYS, Preset, PresetLibrary};
+use crate::punch::{Punch, PunchConfig, PunchStage};
+use crate::remote::{
+    NoteLevel, RemoteCommand, RemoteConfig, RemoteError, RemoteServer, RemoteState,
+};
+use crate::scale::{LockMode, Scale, ScaleLock};
+use crate::scripting::{self, ScriptHooks};
+use crate::session::{self, Session, SessionPlayer, SessionRecorder};
+use crate::sensor::{
+    self, HidDevice, SensorConfig, SensorError, SensorKind, SensorRecorder, SensorSample,
+    SensorSlot,
+};
+use crate::shortcuts::{Action, Shortcuts};
+use crate::simulation::{self, SimSummary, Trajectory};
+use crate::stats::{self, BELLOWS_BINS, PracticeStats, StatsTracker};
+use crate::toast::Toasts;
+use crate::tremor::VibratoTarget;
+use crate::tuner;
+use crate::wizard::WizardStep;
+
+/// Where the angle feeding the bellows comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BellowsInput {
+    /// The selected sensor (or blend).
+    Sensor,
+
+    /// Built-in sine / pump trajectory.
+    Fake,
+
+    /// Up/Down arrow keys drive a virtual lid.
+    ArrowKeys,
+}
+
+impl BellowsInput {
+    fn label(self) -> &'static str {
+        match self {
+            BellowsInput::Sensor => "sensor",
+            BellowsInput::Fake => "fake (sine)",
+            BellowsInput::ArrowKeys => "arrow keys",
+        }
+    }
+}
+
+/// How long the feeding sensor may stay silent (after an error) before the
+/// automatic fallback takes over.
+const FALLBACK_AFTER_SEC: f32 = 2.0;
+
+/// Keymap file read at startup and written by the remapping flow, as
+/// `key-map.json`, `key-map.toml` or `key-map.yaml` (the first that exists).
+const KEYMAP_STEM: &str = "key-map";
+
+/// The letter block of a QWERTY keyboard, as drawn by the help overlay.
+const QWERTY_ROWS: [&[egui::Key]; 4] = {
+    use egui::Key::*;
+    [
+        &[Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Num0, Minus, Equals],
+        &[Q, W, E, R, T, Y, U, I, O, P, OpenBracket, CloseBracket, Backslash],
+        &[A, S, D, F, G, H, J, K, L, Semicolon, Quote],
+        &[Z, X, C, V, B, N, M, Comma, Period, Slash],
+    ]
+};
+
+/// Range of the UI scale slider.
+const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.75..=2.0;
+
+/// How often the tuner looks for the output's pitch.
+const TUNER_INTERVAL_SEC: f32 = 0.2;
+
+/// Hold to glide every sounding note up (meend).
+const GLIDE_KEY: egui::Key = egui::Key::Backtick;
+
+/// A learned key that is already bound to something else.
+struct LearnConflict {
+    key: KeyId,
+    note: NoteName,
+    current: Vec<NoteName>,
+}
+
+/// What a detected lid flick (accent) does besides the amplitude boost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AccentAction {
+    BoostOnly,
+    ToggleAirHold,
+    StopAllNotes,
+}
+
+/// The recorders a count-in can start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recorder {
+    Midi,
+    Session,
+}
 
 pub struct HarmoniumApp {
-    // ---- Sensor channel (real angle input) ----
-    rx: std::sync::mpsc::Receiver<SensorMsg>,
-    sensor_status: String,
-    sensor_error: Option<String>,
+    /// Transient problems and confirmations, shown briefly in a corner.
+    toasts: Toasts,
+
+    /// Recent log events, and the log panel's level / module filter.
+    log: LogBuffer,
+    log_level: Level,
+    log_module: Option<String>,
+
+    /// Full-screen stage view: big meter, notes, Sa, preset; no controls.
+    performance_mode: bool,
+
+    /// Keys bound to app actions (never played as notes), and the action
+    /// waiting for its next key press.
+    shortcuts: Shortcuts,
+    learning_shortcut: Option<Action>,
+
+    /// First-run setup page being shown, if any, and whether it has been
+    /// finished or skipped (then it only opens from the settings row).
+    wizard: Option<WizardStep>,
+    setup_done: bool,
+
+    /// Note held by the setup's test button.
+    test_note: Option<Note>,
+
+    /// Demo phrase or recorded session playing, and the notes it holds down
+    /// right now.
+    demo: Option<Demo>,
+    session_player: Option<SessionPlayer>,
+    scripted_notes: Vec<Note>,
+    /// Performance being recorded as note and bellows events, for replay.
+    session_recorder: Option<SessionRecorder>,
+    session_recording_path: String,
+
+    /// Session left by a run that didn't exit cleanly, until restored or
+    /// discarded (no autosave in the meantime, so it isn't overwritten).
+    recovered: Option<Session>,
+    last_autosave: Instant,
+
+    /// Performance overlay (frame time, sensor rate, audio stalls, voices).
+    show_hud: bool,
+
+    /// Keyboard help overlay: the keymap drawn on a QWERTY keyboard.
+    show_help: bool,
+
+    /// Practice totals for this session and all of them, and whether the
+    /// stats window is open (showing the lifetime totals or the session's).
+    stats: StatsTracker,
+    show_stats: bool,
+    stats_lifetime: bool,
+
+    /// Theme and UI scale as set, and as last handed to egui.
+    ui_config: UiConfig,
+    applied_ui: Option<(Theme, f32)>,
+
+    /// Which panel sits where (or is hidden), and the docks' sizes.
+    dock: DockLayout,
+    frame_timer: FrameTimer,
+
+    // ---- Sensors (real angle input) ----
+    sensors: Vec<SensorSlot>,
+    active_sensor: usize,
+    blend_sensor: Option<usize>,
+    fusion: SensorFusion,
+    new_sensor_kind: SensorKind,
+    sensor_config: SensorConfig,
+    sensor_recorder: Option<SensorRecorder>,
+    sensor_error: Option<SensorError>,
+    /// HID devices found by the last scan (scanned when the HID backend is picked).
+    hid_devices: Option<Vec<HidDevice>>,
+
+    /// What feeds the bellows: the selected sensor's sample, or a blend of two.
     latest_sample: Option<SensorSample>,
     last_sample_age_sec: f32,
 
     // ---- Time / fake input ----
     start_time: Instant,
-    fake_enabled: bool,
+    bellows_input: BellowsInput,
+    fake_pump_shape: bool,
     fake_frequency_hz: f32,
     fake_amplitude_deg: f32,
+    arrow_bellows: ArrowBellows,
+    arrows_held: (bool, bool),
+
+    // ---- Automatic fallback when the sensor is missing ----
+    auto_fallback: bool,
+    fallback_input: BellowsInput,
+    /// Set while the fallback is active: why the sensor was abandoned.
+    fallback_reason: Option<String>,
 
     // ---- Bellows ----
+    dry_run: Option<(SimSummary, SimSummary)>,
     bellows: BellowsState,
     bellows_out: BellowsOutput,
+    /// Recent outputs for the Live Values plots, and how many seconds they show.
+    history: BellowsHistory,
+    plot_window_sec: f64,
+
+    /// Tuner: the output's last detected pitch, and when it was checked.
+    tuner_hz: Option<f32>,
+    tuner_checked: Instant,
+
+    // ---- Infinite air pedal ----
+    air_hold: bool,
+    air_hold_use_fixed: bool,
+    air_hold_fixed_level: f32,
+    air_hold_level: f32,
+    /// None until one is assigned: any default would shadow that key's note.
+    air_pedal_key: Option<egui::Key>,
+    learning_pedal_key: bool,
+
+    // ---- Bellows gesture looper ----
+    gesture: GestureLooper,
+
+    // ---- Phrase looper ----
+    phrase_looper: PhraseLooper,
+    /// Notes the phrase looper holds down right now.
+    loop_notes: Vec<Note>,
+
+    // ---- Hook script ----
+    script: Option<ScriptHooks>,
+    script_path: String,
+    /// Notes the script holds down with `play()`.
+    script_notes: Vec<Note>,
+    /// Sounding notes (other than the script's) as of the last hook call.
+    script_seen_notes: Vec<NoteName>,
+
+    // ---- Rhythm ----
+    metronome: Metronome,
+    backing: BackingConfig,
+    backing_tracks: Vec<BackingTrack>,
+    backing_index: usize,
+    /// Take of the backing track playing, and its speed.
+    backing_playing: Option<(PathBuf, f32)>,
+    /// Practice track: its settings, whether it is playing (and paused), and
+    /// the ducking under the instrument.
+    practice: PracticeConfig,
+    practice_playing: bool,
+    practice_paused: bool,
+    ducker: Ducker,
+    arpeggiator: Arpeggiator,
+    /// Exercise being played against the metronome, and the file it is from.
+    lesson: Option<Lesson>,
+    lesson_path: String,
+
+    // ---- Accents ----
+    accent_action: AccentAction,
+    accent_count: u32,
 
     // ---- Keymap / input ----
     keymap: Option<KeyMap>,
-    keymap_error: Option<String>,
+    keymap_path: String,
+    keymap_error: Option<KeymapError>,
+    /// The keymap has edits its file doesn't (the save failed).
+    keymap_dirty: bool,
+    /// Keymap and samples being read in the background; whether its keymap
+    /// is to replace the one playing; sample files read, of how many.
+    loader: Option<Loader>,
+    awaiting_keymap: bool,
+    load_progress: Option<(usize, usize)>,
+    /// Remapping: the note waiting for a key, and a clash waiting for confirmation.
+    learning_note: Option<NoteName>,
+    learn_conflict: Option<LearnConflict>,
+    layout_template: LayoutTemplate,
+    layout_start_midi: i32,
     pressed: PressedKeys,
+    /// Note held down on the on-screen keyboard (by mouse or touch).
+    screen_key: Option<Note>,
+    /// Global octave shift applied to every new note.
+    octave_shift: i32,
+    /// Every key toggles its notes instead of sounding while held.
+    sticky_keys: bool,
+    /// Pitch bend from the MIDI wheel / glide key, and the last bend applied (-1..1).
+    meend: Meend,
+    bend_amount: f32,
+    /// Mono (legato): one melody note at a time, gliding from key to key
+    /// over `portamento_ms`.
+    mono: bool,
+    portamento_ms: f32,
+    mono_keys: MonoKeys,
+    scale_lock: ScaleLock,
+    /// Stop adding a fifth, fourth or lower octave to every played note.
+    harmonizer: Harmonizer,
+
+    // ---- Latency diagnostic ----
+    latency_mode: bool,
+    latency: LatencyMeter,
+
+    // ---- Lid-close auto-mute ----
+    lid_mute_enabled: bool,
+    lid_close_deg: f32,
+    lid_fade_ms: f32,
+    lid_closed: bool,
+    lid_gain: f32,
+    frame_dt_sec: f32,
 
     // ---- Audio ----
     audio: Option<AudioEngine>,
-    audio_error: Option<String>,
+    audio_error: Option<AudioError>,
+    /// Sample banks (voices) found under `samples_root`, and the one playing;
+    /// MIDI program N picks bank N.
+    samples_root: PathBuf,
+    banks: Vec<SampleBank>,
+    bank_index: usize,
     master_gain: f32,
     audio_enabled: bool,
+    /// Reed speech: new notes start flat under little air.
+    attack_scoop: AttackScoop,
+    /// Polyphony cap of each engine.
+    max_voices: usize,
+    /// Dual mode: a second engine for the zones marked instrument 2, and
+    /// its settings while it is off.
+    second: Option<SecondInstrument>,
+    second_config: DualConfig,
+
+    // ---- Presets (named instrument setups) ----
+    presets: PresetLibrary,
+    /// The preset last recalled or saved.
+    preset_index: Option<usize>,
+    /// Name typed for the next save.
+    preset_name: String,
+
+    /// Notes, bellows, sensor and config events of the current frame.
+    bus: EventBus,
+
+    // ---- MIDI output ----
+    midi_out: Option<MidiOut>,
+    /// Output ports found by the last scan.
+    midi_ports: Option<Vec<String>>,
+    midi_out_port: String,
+    /// 1..16.
+    midi_channel: u8,
+    /// Send MPE (a channel per note) instead of one channel.
+    midi_mpe: bool,
+    midi_error: Option<MidiError>,
+    /// Performance being recorded to a MIDI file.
+    midi_recorder: Option<MidiRecorder>,
+    midi_recording_path: String,
+    /// Count-in and punch points for the recorders, and the recorder armed
+    /// on the metronome (counting in, or punched in until punch-out).
+    punch: PunchConfig,
+    armed: Option<(Recorder, Punch)>,
+
+    // ---- MIDI input (control surface) ----
+    midi_in: Option<MidiIn>,
+    midi_in_ports: Option<Vec<String>>,
+    midi_in_port: String,
+    midi_learn: MidiLearn,
+    /// Sustain pedal (CC64) on the MIDI input is down.
+    sustain_down: bool,
+
+    // ---- Remote control (phone / tablet page) ----
+    remote_config: RemoteConfig,
+    remote: Option<RemoteServer>,
+    remote_error: Option<RemoteError>,
+
+    /// Settings given on the command line, kept out of the settings file.
+    overrides: Overrides,
 }
 
 impl HarmoniumApp {
-    pub fn new(rx: std::sync::mpsc::Receiver<SensorMsg>) -> Self {
-        // Try loading keymap.json from the current working directory.
-        let (keymap, keymap_error) = match KeyMap::load_from_file("key-map.json") {
-            Ok(km) => (Some(km), None),
-            Err(e) => (None, Some(e)),
-        };
+    /// `config` is what the last run saved (the sensor in `first_sensor` was
+    /// already started from it).
+    pub fn new(
+        mut first_sensor: SensorSlot,
+        config: AppConfig,
+        log: LogBuffer,
+    ) -> Self {
+        // The keymap comes from the current working directory (the built-in
+        // default if there is none), read by the loader with the samples.
+        let keymap_path = config.keymap_path.clone().unwrap_or_else(find_keymap_path);
+
+        // Zero-point offsets saved by an earlier run.
+        let mut sensor_config = config.devices.sensor_settings.clone();
+        let mut sensor_error = None;
+        match sensor::load_zero_offsets(sensor::DEFAULT_CALIBRATION_PATH) {
+            Ok(offsets) => sensor_config.zero_offsets_deg = offsets,
+            Err(e) => sensor_error = Some(e),
+        }
+        first_sensor.zero_offset_deg = sensor_config.zero_offset(first_sensor.kind);
 
         // Create bellows math state
-        let params = BellowsParams::default();
+        let params = config.bellows.clone();
         let bellows = BellowsState::new(params);
 
+        // CC → slider bindings saved by an earlier run.
+        let midi_learn = match MidiLearn::load(midi::DEFAULT_MIDI_LEARN_PATH) {
+            Ok(l) => l,
+            Err(e) => {
+                warn!("{e}");
+                MidiLearn::empty(midi::DEFAULT_MIDI_LEARN_PATH)
+            }
+        };
+
+        let presets = match PresetLibrary::load(preset::presets_path()) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("{e}");
+                PresetLibrary::empty(preset::presets_path())
+            }
+        };
+
         // Try creating audio engine (will fail if no audio device etc.)
-        let (audio, audio_error) = match AudioEngine::new("harmonium-sounds") {
+        let samples_root = config.audio.samples_root.clone();
+        let banks = bank::scan_banks(&samples_root);
+        let saved_dir = config.audio.sample_dir.as_ref();
+        let bank_index = banks.iter().position(|b| Some(&b.dir) == saved_dir).unwrap_or(0);
+        let first_dir = banks.get(bank_index).map_or(samples_root.clone(), |b| b.dir.clone());
+        let (audio, audio_error) = match AudioEngine::new(&first_dir) {
             Ok(a) => (Some(a), None),
             Err(e) => (None, Some(e)),
         };
+        let second_config = config.audio.second.clone();
+        let second = if second_config.enabled && audio.is_some() {
+            let dir = second_config.sample_dir.clone().unwrap_or(first_dir);
+            SecondInstrument::open(&second_config, &dir)
+                .inspect_err(|e| warn!("second instrument: {e}"))
+                .ok()
+        } else {
+            None
+        };
+
+        let fake = &config.fake_input;
+        let devices = &config.devices;
+        let mut app = Self {
+            toasts: Toasts::default(),
+            log,
+            log_level: Level::INFO,
+            log_module: None,
 
-        Self {
-            rx,
-            sensor_status: "Starting sensor...".to_string(),
-            sensor_error: None,
+            performance_mode: false,
+            shortcuts: config.shortcuts.clone(),
+            learning_shortcut: None,
+            wizard: (!config.setup_done).then_some(WizardStep::Samples),
+            setup_done: config.setup_done,
+            test_note: None,
+            demo: None,
+            session_player: None,
+            scripted_notes: Vec::new(),
+            session_recorder: None,
+            session_recording_path: session::DEFAULT_SESSION_RECORDING_PATH.to_string(),
+            recovered: match Session::load(session::session_path()) {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("{e}");
+                    None
+                }
+            },
+            last_autosave: Instant::now(),
+            show_hud: false,
+            show_help: false,
+            stats: StatsTracker::new(PracticeStats::load(stats::stats_path()).unwrap_or_else(|e| {
+                error!("{e}");
+                PracticeStats::default()
+            })),
+            show_stats: false,
+            stats_lifetime: false,
+            ui_config: config.ui.clone(),
+            applied_ui: None,
+            dock: config.dock.clone(),
+            frame_timer: FrameTimer::default(),
+
+            sensors: vec![first_sensor],
+            active_sensor: 0,
+            blend_sensor: None,
+            fusion: SensorFusion::new(),
+            new_sensor_kind: SensorKind::Fake,
+            sensor_config,
+            sensor_recorder: None,
+            sensor_error,
+            hid_devices: None,
             latest_sample: None,
             last_sample_age_sec: 0.0,
 
             start_time: Instant::now(),
-            fake_enabled: true,
-            fake_frequency_hz: 0.6,
-            fake_amplitude_deg: 30.0,
+            // Without a lid to read, play with the arrow keys from the start.
+            bellows_input: if SensorKind::Lid.is_available() {
+                BellowsInput::Sensor
+            } else {
+                BellowsInput::ArrowKeys
+            },
+            fake_pump_shape: fake.pump_shape,
+            fake_frequency_hz: fake.frequency_hz,
+            fake_amplitude_deg: fake.amplitude_deg,
+            arrow_bellows: ArrowBellows::default(),
+            arrows_held: (false, false),
 
-            bellows,
-            bellows_out: BellowsOutput::default(),
+            auto_fallback: true,
+            fallback_input: BellowsInput::Fake,
+            fallback_reason: None,
 
-            keymap,
-            keymap_error,
-            pressed: PressedKeys::new(),
+            dry_run: None,
 
-            audio,
-            audio_error,
-            master_gain: 0.8,
-            audio_enabled: true,
-        }
-    }
+            bellows,
+            bellows_out: BellowsOutput::default(),
+            history: BellowsHistory::default(),
+            plot_window_sec: 10.0,
+            tuner_hz: None,
+            tuner_checked: Instant::now(),
 
-    pub fn ui(&mut self, ctx: &egui::Context) {
-        // 0) Pull any sensor messages that arrived since last frame
-        self.drain_sensor_messages();
+            air_hold: false,
+            air_hold_use_fixed: false,
+            air_hold_fixed_level: 0.6,
+            air_hold_level: 0.0,
+            air_pedal_key: None,
+            learning_pedal_key: false,
 
-        // 1) Read keyboard input and update pressed notes (and trigger audio)
-        self.handle_keyboard(ctx);
+            gesture: GestureLooper::new(),
+            phrase_looper: PhraseLooper::new(),
+            loop_notes: Vec::new(),
+            script: None,
+            script_path: scripting::DEFAULT_SCRIPT_PATH.to_string(),
+            script_notes: Vec::new(),
+            script_seen_notes: Vec::new(),
+            metronome: Metronome::new(config.metronome.clone()),
+            backing: config.backing.clone(),
+            backing_tracks: backing::scan_backing(&config.backing.dir),
+            backing_index: 0,
+            backing_playing: None,
+            practice: config.practice.clone(),
+            practice_playing: false,
+            practice_paused: false,
+            ducker: Ducker::default(),
+            arpeggiator: config.arpeggiator.clone(),
+            lesson: None,
+            lesson_path: lesson::DEFAULT_LESSON_PATH.to_string(),
 
-        // 2) Update bellows (fake or real depending on toggle)
-        self.update_bellows();
+            accent_action: AccentAction::BoostOnly,
+            accent_count: 0,
 
-        // 3) Apply bellows amplitude to audio every frame
-        self.update_audio_from_bellows();
+            keymap: None,
+            keymap_path,
+            keymap_error: None,
+            keymap_dirty: false,
+            loader: None,
+            awaiting_keymap: false,
+            load_progress: None,
+            learning_note: None,
+            learn_conflict: None,
+            layout_template: LayoutTemplate::BottomTwoRows,
+            layout_start_midi: 36, // c2, the lowest sample
+            pressed: PressedKeys::new(),
+            screen_key: None,
+            octave_shift: 0,
+            sticky_keys: false,
+            meend: Meend::default(),
+            mono: false,
+            portamento_ms: 120.0,
+            mono_keys: MonoKeys::default(),
+            bend_amount: 0.0,
+            scale_lock: ScaleLock::default(),
+            harmonizer: Harmonizer::default(),
 
-        // 4) Draw the UI
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Harmonium (Phase 2: Audio)");
+            latency_mode: false,
+            latency: LatencyMeter::default(),
 
-            self.ui_sensor_status(ui);
+            lid_mute_enabled: true,
+            lid_close_deg: 20.0,
+            lid_fade_ms: 150.0,
+            lid_closed: false,
+            lid_gain: 1.0,
+            frame_dt_sec: 0.0,
 
-            ui.separator();
-            self.ui_audio_status(ui);
+            audio,
+            audio_error,
+            samples_root,
+            banks,
+            bank_index,
+            master_gain: config.audio.master_gain,
+            audio_enabled: config.audio.enabled,
+            attack_scoop: config.audio.attack_scoop,
+            max_voices: config.audio.max_voices,
+            second,
+            second_config,
 
-            ui.separator();
+            presets,
+            preset_index: None,
+            preset_name: String::new(),
 
-            ui.columns(2, |cols| {
-                cols[0].heading("Controls");
-                self.ui_controls(&mut cols[0]);
+            bus: EventBus::default(),
+            midi_out: None,
+            midi_ports: None,
+            midi_out_port: devices.midi_out_port.clone(),
+            midi_channel: devices.midi_channel,
+            midi_mpe: devices.midi_mpe,
+            midi_error: None,
+            midi_recorder: None,
+            midi_recording_path: midi::DEFAULT_MIDI_RECORDING_PATH.to_string(),
+            punch: config.punch.clone(),
+            armed: None,
 
-                cols[1].heading("Live Values");
-                self.ui_live_values(&mut cols[1]);
-            });
+            midi_in: None,
+            midi_in_ports: None,
+            midi_in_port: devices.midi_in_port.clone(),
+            midi_learn,
+            sustain_down: false,
 
-            ui.separator();
+            remote_config: config.remote.clone(),
+            remote: None,
+            remote_error: None,
 
-            self.ui_keymap_status(ui);
-            self.ui_active_notes(ui);
-        });
+            overrides: Overrides::default(),
+        };
 
-        // Keep repainting so meters update smoothly.
-        ctx.request_repaint();
+        app.start_loading(true);
+        app.reconnect_midi(devices);
+        if app.remote_config.enabled {
+            app.start_remote();
+        }
+        app
     }
 
-    fn ui_sensor_status(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Sensor");
-
-        ui.label(format!("Status: {}", self.sensor_status));
-
-        if let Some(err) = &self.sensor_error {
-            ui.colored_label(egui::Color32::RED, format!("Error: {err}"));
+    /// Reopen the MIDI ports that were connected when the app last quit.
+    fn reconnect_midi(&mut self, devices: &DeviceConfig) {
+        if devices.midi_out_connected {
+            match MidiOut::connect(&self.midi_out_port, self.midi_channel, self.midi_mpe) {
+                Ok(m) => self.midi_out = Some(m),
+                Err(e) => self.midi_error = Some(e),
+            }
         }
 
-        if let Some(s) = &self.latest_sample {
-            ui.label(format!("Latest angle: {:6.2} deg   source={}", s.theta_deg, s.source));
-            ui.label(format!("Last sample age: {:5.2} sec", self.last_sample_age_sec));
-        } else {
-            ui.label("No samples yet.");
+        if devices.midi_in_connected {
+            match MidiIn::connect(&self.midi_in_port) {
+                Ok(m) => self.midi_in = Some(m),
+                Err(e) => self.midi_error = Some(e),
+            }
         }
     }
 
-    fn ui_audio_status(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Audio");
-
-        if let Some(err) = &self.audio_error {
-            ui.colored_label(egui::Color32::RED, format!("Audio error: {err}"));
-        } else if self.audio.is_some() {
-            ui.colored_label(egui::Color32::GREEN, "Audio engine ready");
-        } else {
-            ui.colored_label(egui::Color32::YELLOW, "Audio engine not available");
+    /// The current settings, in the shape of the settings file.
+    fn to_config(&self) -> AppConfig {
+        let sensor = self.sensors.get(self.active_sensor).map(|s| s.kind);
+        AppConfig {
+            bellows: self.bellows.params.clone(),
+            audio: AudioConfig {
+                master_gain: self.master_gain,
+                enabled: self.audio_enabled,
+                samples_root: self.samples_root.clone(),
+                sample_dir: self.banks.get(self.bank_index).map(|b| b.dir.clone()),
+                second: self.second.as_ref().map_or_else(
+                    || DualConfig { enabled: false, ..self.second_config.clone() },
+                    SecondInstrument::config,
+                ),
+                attack_scoop: self.attack_scoop,
+                max_voices: self.max_voices,
+            },
+            fake_input: FakeInputConfig {
+                pump_shape: self.fake_pump_shape,
+                frequency_hz: self.fake_frequency_hz,
+                amplitude_deg: self.fake_amplitude_deg,
+            },
+            keymap_path: Some(self.keymap_path.clone()),
+            devices: DeviceConfig {
+                sensor: sensor.unwrap_or_else(SensorKind::default_for_platform).id().to_string(),
+                sensor_settings: self.sensor_config.clone(),
+                midi_out_port: self.midi_out_port.clone(),
+                midi_out_connected: self.midi_out.is_some(),
+                midi_channel: self.midi_channel,
+                midi_mpe: self.midi_mpe,
+                midi_in_port: self.midi_in_port.clone(),
+                midi_in_connected: self.midi_in.is_some(),
+            },
+            shortcuts: self.shortcuts.clone(),
+            setup_done: self.setup_done,
+            ui: self.ui_config.clone(),
+            dock: self.dock.clone(),
+            metronome: self.metronome.config().clone(),
+            backing: self.backing.clone(),
+            practice: self.practice.clone(),
+            punch: self.punch.clone(),
+            arpeggiator: self.arpeggiator.clone(),
+            remote: self.remote_config.clone(),
         }
+    }
 
-        ui.checkbox(&mut self.audio_enabled, "Enable audio output");
-
-        // Master gain slider (will affect volume)
-        ui.add(egui::Slider::new(&mut self.master_gain, 0.0..=1.5).text("master volume"));
+    /// Settings that came from the command line rather than the settings
+    /// file; they're swapped back for the saved ones when saving.
+    pub fn set_overrides(&mut self, overrides: Overrides) {
+        self.overrides = overrides;
+    }
 
-        // If audio exists, apply master gain live
-        if let Some(a) = &mut self.audio {
-            a.set_master_gain(self.master_gain);
+    /// Write the settings file; called on exit and from "Save settings".
+    pub fn save_config(&self) {
+        let mut config = self.to_config();
+        self.overrides.restore(&mut config);
+        match config.save(config::config_path()) {
+            Ok(()) => info!("settings saved to {}", config::config_path().display()),
+            Err(e) => error!("{e}"),
         }
+    }
 
-        if ui.button("Stop all notes").clicked() {
-            if let Some(a) = &mut self.audio {
-                a.stop_all();
+    /// A clean exit: save the settings and drop the session, so the next
+    /// start doesn't offer to recover it (unless it never got the chance to).
+    pub fn shutdown(&mut self) {
+        self.save_config();
+        self.save_stats();
+        self.stop_session_recording();
+        if self.recovered.is_none() {
+            if let Err(e) = Session::clear(session::session_path()) {
+                error!("{e}");
             }
         }
     }
 
-    fn drain_sensor_messages(&mut self) {
-        while let Ok(msg) = self.rx.try_recv() {
-            match msg {
-                SensorMsg::Status(s) => {
-                    self.sensor_status = s;
-                    self.sensor_error = None;
-                }
-                SensorMsg::Error(e) => {
-                    self.sensor_error = Some(e);
-                }
-                SensorMsg::Sample(sample) => {
-                    self.latest_sample = Some(sample);
-                }
+    /// Every `AUTOSAVE_INTERVAL`, write what a crash would lose: the setup,
+    /// unsaved keymap edits, and the MIDI recording so far.
+    fn autosave_session(&mut self) {
+        if self.recovered.is_some() || self.last_autosave.elapsed() < session::AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        self.save_stats();
+
+        let mut unsaved_keymap = false;
+        if let Some(km) = self.keymap.as_ref().filter(|_| self.keymap_dirty) {
+            match km.save_to_file(session::session_keymap_path()) {
+                Ok(()) => unsaved_keymap = true,
+                Err(e) => warn!("{e}"),
             }
         }
+        if let Some(Err(e)) = self.midi_recorder.as_ref().map(MidiRecorder::checkpoint) {
+            warn!("{e}");
+        }
 
-        self.last_sample_age_sec = if let Some(s) = &self.latest_sample {
-            (Instant::now() - s.t).as_secs_f32()
-        } else {
-            0.0
+        let rec = self.midi_recorder.as_ref();
+        let session = Session {
+            setup: self.capture_preset(&self.preset_name),
+            preset_index: self.preset_index,
+            unsaved_keymap,
+            midi_recording: rec.map(|r| r.path().to_path_buf()),
+            midi_recording_sec: rec.map_or(0.0, MidiRecorder::elapsed_sec),
         };
-    }
-
-    fn update_bellows(&mut self) {
-        if self.fake_enabled {
-            self.update_bellows_fake_input();
-        } else {
-            self.update_bellows_real_input();
+        if let Err(e) = session.save(session::session_path()) {
+            warn!("{e}");
         }
     }
 
-    fn update_bellows_fake_input(&mut self) {
-        let now = Instant::now();
-        let t = (now - self.start_time).as_secs_f32();
+    /// After a crash: offer to pick up where the last run left off.
+    fn ui_recover(&mut self, ctx: &egui::Context) {
+        let Some(s) = &self.recovered else {
+            return;
+        };
 
-        let theta = self.fake_amplitude_deg
-            * (2.0 * std::f32::consts::PI * self.fake_frequency_hz * t).sin();
+        let (mut restore, mut discard) = (false, false);
+        egui::Window::new("Restore last session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("The last run didn't exit cleanly. It left behind:");
+                let setup = match s.setup.name.as_str() {
+                    "" => "the instrument setup (bellows, voice, keymap, tuning)".to_string(),
+                    name => format!("the instrument setup, preset '{name}'"),
+                };
+                ui.label(format!("• {setup}"));
+                if s.unsaved_keymap {
+                    ui.label("• keymap edits that weren't saved to its file");
+                }
+                if let Some(path) = &s.midi_recording {
+                    ui.label(format!(
+                        "• a MIDI recording: {:.0} s are in {}",
+                        s.midi_recording_sec,
+                        path.display()
+                    ));
+                }
+
+                ui.horizontal(|ui| {
+                    restore = ui.button("Restore").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
 
-        self.bellows_out = self.bellows.update(theta, now);
+        if restore {
+            self.restore_session();
+        } else if discard {
+            self.recovered = None;
+        }
     }
 
-    fn update_bellows_real_input(&mut self) {
-        let Some(s) = &self.latest_sample else {
+    fn restore_session(&mut self) {
+        let Some(s) = self.recovered.take() else {
             return;
         };
 
-        self.bellows_out = self.bellows.update(s.theta_deg, s.t);
-    }
+        // Only point at the preset if it's still the same one.
+        let index = s
+            .preset_index
+            .filter(|i| self.presets.get(*i).is_some_and(|p| p.name == s.setup.name));
+        self.apply_preset(s.setup);
+        self.preset_index = index;
 
-    fn update_audio_from_bellows(&mut self) {
-        if !self.audio_enabled {
-            // If audio disabled, we force bellows to 0 volume.
-            if let Some(a) = &mut self.audio {
-                a.set_bellows(0.0);
+        if s.unsaved_keymap {
+            match KeyMap::load_from_file(session::session_keymap_path()) {
+                Ok(km) => {
+                    self.keymap = Some(km);
+                    self.keymap_dirty = true;
+                    self.awaiting_keymap = false;
+                    let text = format!("Keymap edits restored; not saved to {} yet", self.keymap_path);
+                    self.toasts.warn(text);
+                }
+                Err(e) => self.toasts.error(e.to_string()),
             }
-            return;
         }
-
-        if let Some(a) = &mut self.audio {
-            a.set_bellows(self.bellows_out.a);
+        if let Some(path) = s.midi_recording {
+            self.toasts.info(format!("The interrupted MIDI recording is in {}", path.display()));
         }
+        info!("last session restored");
     }
 
-    fn ui_controls(&mut self, ui: &mut egui::Ui) {
-        ui.checkbox(&mut self.fake_enabled, "Use fake angle input (sine wave)");
-        ui.label("Turn OFF fake input to use real screen angle from the device.");
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        self.frame_dt_sec = ctx.input(|i| i.unstable_dt);
+        self.frame_timer.push(self.frame_dt_sec);
 
-        ui.add(
-            egui::Slider::new(&mut self.fake_frequency_hz, 0.05..=3.0)
-                .text("fake frequency (Hz)"),
-        );
-        ui.add(
-            egui::Slider::new(&mut self.fake_amplitude_deg, 1.0..=80.0)
-                .text("fake amplitude (deg)"),
-        );
+        // 0) Pull any sensor messages that arrived since last frame
+        self.drain_sensor_messages();
+        self.update_loader();
+        self.update_fallback();
 
-        ui.separator();
-        ui.label("Bellows tuning:");
+        // 1) Read keyboard input and update pressed notes (and trigger audio)
+        self.handle_keyboard(ctx);
+        self.handle_midi_input();
+        self.update_demo();
+        self.update_session_player();
+        self.update_phrase_looper();
+        self.update_metronome();
+        self.update_punch();
+        self.update_backing();
+        self.update_lesson();
+        self.update_arpeggiator();
+        self.update_pitch_bend();
+        self.update_harmonizer();
 
-        let p = &mut self.bellows.params;
+        // 2) Update bellows (fake or real depending on toggle)
+        self.update_bellows();
+        self.update_script();
+        self.gesture.feed(self.bellows_out.a, Instant::now());
+        let t = self.start_time.elapsed().as_secs_f64();
+        self.history.push(t, self.bellows_out);
 
-        ui.add(
-            egui::Slider::new(&mut p.deadzone_deg_per_s, 0.0..=40.0).text("deadzone (deg/s)"),
-        );
-        ui.add(
-            egui::Slider::new(&mut p.vmax_deg_per_s, 10.0..=500.0).text("vmax (deg/s)"),
-        );
-        ui.add(egui::Slider::new(&mut p.gamma, 0.3..=4.0).text("gamma (curve)"));
-        ui.add(egui::Slider::new(&mut p.ema_alpha, 0.01..=0.5).text("EMA alpha (smoothing)"));
-        ui.add(egui::Slider::new(&mut p.attack_ms, 0.0..=400.0).text("attack (ms)"));
-        ui.add(egui::Slider::new(&mut p.release_ms, 0.0..=1200.0).text("release (ms)"));
+        // 3) Apply bellows amplitude to audio every frame
+        let arm_latency_probe = self.observe_latency();
+        self.update_lid_mute();
+        self.update_audio_from_bellows();
+        self.update_panic();
+        self.update_practice();
+        self.finish_latency(arm_latency_probe);
 
-        ui.separator();
+        // 4) Draw the UI
+        self.apply_ui_config(ctx);
+        if self.performance_mode {
+            egui::CentralPanel::default().show(ctx, |ui| self.ui_performance(ui));
+        } else {
+            self.ui_main(ctx);
+            if let Some(step) = self.wizard {
+                self.ui_wizard(ctx, step);
+            }
+        }
 
-        if ui.button("Reset bellows state").clicked() {
-            self.bellows.reset();
-            self.bellows_out = BellowsOutput::default();
+        if self.show_hud {
+            self.ui_hud(ctx);
         }
-    }
+        if self.show_help {
+            self.ui_help(ctx);
+        }
+        if self.show_stats {
+            self.ui_stats(ctx);
+        }
+        self.toasts.show(ctx);
+        self.ui_recover(ctx);
+        self.autosave_session();
 
-    fn ui_live_values(&mut self, ui: &mut egui::Ui) {
-        let o = self.bellows_out;
+        // 5) Hand the frame's events to MIDI out, the recorders and stats
+        // (after drawing, so on-screen keyboard clicks from this frame go out too)
+        self.dispatch_events();
+        self.update_remote();
 
-        ui.label(format!("theta_deg:        {:8.3}", o.theta_deg));
-        ui.label(format!("dt_sec:           {:8.4}", o.dt_sec));
-        ui.label(format!("omega_deg_per_s:  {:8.3}", o.omega_deg_per_s));
-        ui.label(format!("speed_raw:        {:8.3}", o.speed_raw));
-        ui.label(format!("speed_smooth:     {:8.3}", o.speed_smooth));
-        ui.label(format!("a_target:         {:8.3}", o.a_target));
-        ui.label(format!("a (final):        {:8.3}", o.a));
+        // Keep repainting so meters update smoothly.
+        ctx.request_repaint();
+    }
 
-        ui.separator();
+    /// The full window: every panel and control.
+    fn ui_main(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("settings").show(ctx, |ui| {
+            ui.heading("Harmonium (Phase 2: Audio)");
+            self.ui_settings(ui);
+        });
 
-        ui.label("Bellows meter (A):");
-        ui.add(
-            egui::ProgressBar::new(o.a.clamp(0.0, 1.0))
-                .show_percentage()
-                .animate(true),
-        );
+        // Side and bottom docks first: the centre gets what's left.
+        if !self.dock.panels_in(Dock::Bottom).is_empty() {
+            let r = egui::TopBottomPanel::bottom("dock_bottom")
+                .resizable(true)
+                .default_height(self.dock.bottom_height)
+                .show(ctx, |ui| self.ui_dock(ui, Dock::Bottom));
+            self.dock.bottom_height = r.response.rect.height();
+        }
+        if !self.dock.panels_in(Dock::Left).is_empty() {
+            let r = egui::SidePanel::left("dock_left")
+                .resizable(true)
+                .default_width(self.dock.left_width)
+                .show(ctx, |ui| self.ui_dock(ui, Dock::Left));
+            self.dock.left_width = r.response.rect.width();
+        }
+        if !self.dock.panels_in(Dock::Right).is_empty() {
+            let r = egui::SidePanel::right("dock_right")
+                .resizable(true)
+                .default_width(self.dock.right_width)
+                .show(ctx, |ui| self.ui_dock(ui, Dock::Right));
+            self.dock.right_width = r.response.rect.width();
+        }
+        egui::CentralPanel::default().show(ctx, |ui| self.ui_dock(ui, Dock::Center));
     }
 
-    fn ui_keymap_status(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Keymap");
+    /// The panels in one dock, scrolling if they don't fit: stacked on the
+    /// sides, side by side in the centre and at the bottom.
+    fn ui_dock(&mut self, ui: &mut egui::Ui, dock: Dock) {
+        let panels = self.dock.panels_in(dock);
+        egui::ScrollArea::vertical()
+            .id_salt(dock.label())
+            .auto_shrink(false)
+            .show(ui, |ui| match dock {
+                Dock::Left | Dock::Right => {
+                    for (i, panel) in panels.into_iter().enumerate() {
+                        if i > 0 {
+                            ui.separator();
+                        }
+                        self.ui_panel(ui, panel);
+                    }
+                }
+                _ if panels.is_empty() => {}
+                _ => ui.columns(panels.len(), |cols| {
+                    for (col, panel) in cols.iter_mut().zip(panels) {
+                        self.ui_panel(col, panel);
+                    }
+                }),
+            });
+    }
 
-        if let Some(err) = &self.keymap_error {
-            ui.colored_label(egui::Color32::RED, format!("Keymap error: {err}"));
-        } else if self.keymap.is_some() {
-            ui.colored_label(egui::Color32::GREEN, "keymap.json loaded OK");
-        } else {
-            ui.colored_label(egui::Color32::YELLOW, "No keymap loaded");
+    fn ui_panel(&mut self, ui: &mut egui::Ui, panel: Panel) {
+        match panel {
+            Panel::Sensor => self.ui_sensor_status(ui),
+            Panel::Audio => {
+                self.ui_audio_status(ui);
+                self.ui_presets(ui);
+                self.ui_midi_out(ui);
+                self.ui_midi_in(ui);
+                self.ui_remote(ui);
+            }
+            Panel::Controls => {
+                ui.heading("Controls");
+                self.ui_controls(ui);
+            }
+            Panel::Meters => {
+                ui.heading("Live Values");
+                self.ui_live_values(ui);
+            }
+            Panel::Keymap => {
+                self.ui_keymap_status(ui);
+                self.ui_script(ui);
+                self.ui_shortcuts(ui);
+                self.ui_active_notes(ui);
+                self.ui_tuner(ui);
+                self.ui_onscreen_keyboard(ui);
+            }
+            Panel::Log => self.ui_log(ui),
+            Panel::Rhythm => {
+                ui.heading("Rhythm & practice");
+                self.ui_metronome(ui);
+                ui.separator();
+                self.ui_backing(ui);
+                ui.separator();
+                self.ui_practice(ui);
+                ui.separator();
+                self.ui_arpeggiator(ui);
+                ui.separator();
+                self.ui_lesson(ui);
+            }
         }
+    }
 
-        if ui.button("Reload keymap.json").clicked() {
-            match KeyMap::load_from_file("keymap.json") {
-                Ok(km) => {
-                    self.keymap = Some(km);
-                    self.keymap_error = None;
-                }
-                Err(e) => {
-                    self.keymap = None;
-                    self.keymap_error = Some(e);
+    /// Move panels between docks, or hide them.
+    fn ui_dock_menu(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("dock_menu").show(ui, |ui| {
+            for panel in Panel::ALL {
+                ui.label(panel.label());
+                let place = self.dock.place_mut(panel);
+                for dock in Dock::ALL {
+                    ui.radio_value(place, dock, dock.label());
                 }
+                ui.end_row();
             }
+        });
+        if ui.button("Reset layout").clicked() {
+            self.dock = DockLayout::default();
         }
     }
 
-    fn ui_active_notes(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Active notes");
-        let notes = self.pressed.active_notes();
+    /// On-stage view, readable from a distance: preset and Sa on top, a big
+    /// bellows meter, and the sounding notes. Keys and bellows keep working.
+    fn ui_performance(&mut self, ui: &mut egui::Ui) {
+        let preset = self.preset_index.and_then(|i| self.presets.get(i));
+        let lock = &self.scale_lock;
 
-        if notes.is_empty() {
-            ui.label("None (press keys like z, x, c, v, ...)");
-        } else {
-            ui.label(notes.join("  "));
+        ui.horizontal(|ui| {
+            let name = preset.map_or("—", |p| p.name.as_str());
+            ui.label(egui::RichText::new(name).size(36.0).strong());
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let exit = self.shortcut_label("Exit", Action::PerformanceMode);
+                if ui.button(exit).clicked() {
+                    self.set_performance_mode(ui.ctx(), false);
+                }
+
+                let sa = PITCH_NAMES[lock.tonic as usize].to_uppercase();
+                let mut tuning = format!("Sa = {sa}");
+                if lock.retune {
+                    tuning.push_str(" (retuned)");
+                }
+                if lock.enabled {
+                    tuning.push_str(&format!("   {}", lock.scale.label()));
+                }
+                if self.octave_shift != 0 {
+                    tuning.push_str(&format!("   octave {:+}", self.octave_shift));
+                }
+                ui.label(egui::RichText::new(tuning).size(28.0));
+            });
+        });
+
+        ui.add_space(16.0);
+
+        // Bellows meter: a wide bar, a third of the window tall.
+        let a = (self.effective_bellows_a() * self.lid_gain).clamp(0.0, 1.0);
+        let size = egui::vec2(ui.available_width(), ui.available_height() / 3.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 8.0, ui.visuals().extreme_bg_color);
+        let fill = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * a, rect.height()));
+        let color = if self.air_hold {
+            egui::Color32::LIGHT_BLUE
+        } else {
+            egui::Color32::from_rgb(230, 140, 40)
+        };
+        painter.rect_filled(fill, 8.0, color);
+
+        ui.add_space(16.0);
+
+        let notes = self.pressed.active_notes();
+        let text = if notes.is_empty() {
+            "·".to_string()
+        } else {
+            let labels: Vec<String> = notes.iter().map(|n| self.with_sargam(n)).collect();
+            labels.join("   ")
+        };
+        ui.vertical_centered(|ui| {
+            ui.label(egui::RichText::new(text).size(56.0).strong());
+        });
+    }
+
+    /// Where the settings are kept (saved automatically on exit).
+    fn ui_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("settings: {}", config::config_path().display()));
+            if ui.button("Save settings").on_hover_text("Also saved on quit").clicked() {
+                self.save_config();
+            }
+            ui.checkbox(&mut self.show_hud, "Performance HUD");
+            let enter = self.shortcut_label("Performance mode", Action::PerformanceMode);
+            if ui.button(enter).clicked() {
+                self.set_performance_mode(ui.ctx(), true);
+            }
+            if ui.button("Setup wizard").clicked() {
+                self.wizard = Some(WizardStep::Samples);
+            }
+            self.ui_demo_button(ui);
+            ui.toggle_value(&mut self.show_stats, "Practice stats");
+            ui.menu_button("Panels", |ui| self.ui_dock_menu(ui));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("theme:");
+            for theme in Theme::ALL {
+                ui.radio_value(&mut self.ui_config.theme, theme, theme.label());
+            }
+            ui.add(
+                egui::Slider::new(&mut self.ui_config.scale, UI_SCALE_RANGE)
+                    .step_by(0.05)
+                    .text("UI scale"),
+            );
+        });
+    }
+
+    /// The loaded keymap drawn on a QWERTY keyboard: each key shows the note
+    /// it plays now (octave shift included), sounding ones highlighted, with
+    /// the other special keys listed underneath.
+    fn ui_help(&mut self, ctx: &egui::Context) {
+        let active = self.pressed.active_notes();
+        let mut open = true;
+
+        egui::Window::new("Keyboard")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let Some(km) = &self.keymap else {
+                    ui.label("No keymap loaded.");
+                    return;
+                };
+
+                for (row, keys) in QWERTY_ROWS.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        // Stagger the rows like a real keyboard.
+                        ui.add_space(row as f32 * 14.0);
+                        for key in keys.iter() {
+                            let id = km.resolve(egui_key_to_char(*key), physical_key_of(*key));
+                            let notes = id.map(|id| km.notes_for_key(id, self.octave_shift));
+                            help_key(ui, *key, notes.as_deref().unwrap_or_default(), &active);
+                        }
+                    });
+                }
+
+                ui.add_space(8.0);
+                let pedal = match self.air_pedal_key {
+                    Some(key) => format!("{}: infinite air   ", key.symbol_or_name()),
+                    None => String::new(),
+                };
+                ui.label(format!(
+                    "{pedal}{}: glide (meend)   ↑ / ↓: arrow-key bellows",
+                    GLIDE_KEY.symbol_or_name(),
+                ));
+                let shortcuts: Vec<String> = Action::ALL
+                    .into_iter()
+                    .filter_map(|a| {
+                        let key = self.shortcuts.key_for(a)?;
+                        Some(format!("{}: {}", key.name(), a.label()))
+                    })
+                    .collect();
+                ui.label(shortcuts.join("   "));
+                ui.label("Shift: octave up   Cmd / Ctrl: octave down");
+            });
+
+        if !open {
+            self.show_help = false;
+        }
+    }
+
+    /// Time played, notes per minute, how hard the bellows were worked and
+    /// the notes played most, for this session or every session.
+    fn ui_stats(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut reset = false;
+
+        egui::Window::new("Practice stats").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.stats_lifetime, false, "This session");
+                ui.radio_value(&mut self.stats_lifetime, true, "All sessions");
+            });
+            let lifetime = self.stats.lifetime();
+            let s = if self.stats_lifetime { &lifetime } else { &self.stats.session };
+
+            egui::Grid::new("stats_totals").show(ui, |ui| {
+                ui.label("time played:");
+                ui.label(format_played(s.played_sec));
+                ui.end_row();
+                ui.label("notes played:");
+                ui.label(s.note_count.to_string());
+                ui.end_row();
+                ui.label("notes per minute:");
+                ui.label(format!("{:.0}", s.notes_per_minute()));
+                ui.end_row();
+                if self.stats_lifetime {
+                    ui.label("sessions:");
+                    ui.label(s.sessions.to_string());
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ui.label("Time at each bellows level:");
+            let total: f64 = s.bellows_histogram().iter().sum();
+            let bars: Vec<Bar> = s
+                .bellows_histogram()
+                .into_iter()
+                .enumerate()
+                .map(|(i, sec)| {
+                    let share = if total > 0.0 { 100.0 * sec / total } else { 0.0 };
+                    Bar::new((i as f64 + 0.5) / BELLOWS_BINS as f64, share)
+                        .width(0.9 / BELLOWS_BINS as f64)
+                })
+                .collect();
+            Plot::new("stats_bellows")
+                .height(120.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .x_axis_label("bellows level")
+                .y_axis_label("% of time")
+                .show(ui, |plot| plot.bar_chart(BarChart::new(bars)));
+
+            ui.separator();
+            ui.label("Most played notes:");
+            let top = s.top_notes(10);
+            let most = top.first().map_or(1, |(_, n)| *n).max(1);
+            for (note, count) in top {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{note:>4}"));
+                    let bar = egui::ProgressBar::new(count as f32 / most as f32)
+                        .desired_width(160.0)
+                        .text(count.to_string());
+                    ui.add(bar);
+                });
+            }
+
+            if self.stats_lifetime {
+                ui.separator();
+                reset = ui.button("Reset all stats").clicked();
+            }
+        });
+
+        if reset {
+            self.stats.reset();
+            self.save_stats();
+        }
+        if !open {
+            self.show_stats = false;
+        }
+    }
+
+    fn save_stats(&self) {
+        if let Err(e) = self.stats.lifetime().save(stats::stats_path()) {
+            error!("{e}");
+        }
+    }
+
+    /// Hand the theme and scale to egui when they change. Zooming with the
+    /// keyboard (Cmd +/-) comes back the other way, so it is saved too.
+    fn apply_ui_config(&mut self, ctx: &egui::Context) {
+        if let Some((_, scale)) = self.applied_ui {
+            let zoom = ctx.zoom_factor();
+            if zoom != scale {
+                self.ui_config.scale = zoom;
+                self.applied_ui = Some((self.ui_config.theme, zoom));
+            }
+        }
+
+        let wanted = (self.ui_config.theme, self.ui_config.scale);
+        if self.applied_ui == Some(wanted) {
+            return;
+        }
+        ctx.set_theme(wanted.0.preference());
+        ctx.set_zoom_factor(wanted.1);
+        self.applied_ui = Some(wanted);
+    }
+
+    /// First-run setup: samples, keymap, calibration and a test note, one
+    /// page at a time, over the main window.
+    fn ui_wizard(&mut self, ctx: &egui::Context, step: WizardStep) {
+        let mut goto = Some(step);
+        let total = WizardStep::ALL.len();
+
+        egui::Window::new("Setup")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.heading(format!("Step {} of {total}: {}", step.number(), step.title()));
+                ui.add_space(8.0);
+
+                match step {
+                    WizardStep::Samples => self.ui_wizard_samples(ui),
+                    WizardStep::Keymap => self.ui_wizard_keymap(ui),
+                    WizardStep::Calibrate => self.ui_wizard_calibrate(ui),
+                    WizardStep::TestAudio => self.ui_wizard_test_audio(ui),
+                }
+
+                ui.add_space(8.0);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(step.prev().is_some(), egui::Button::new("Back")).clicked() {
+                        goto = step.prev();
+                    }
+                    let next = if step.next().is_some() { "Next" } else { "Finish" };
+                    if ui.button(next).clicked() {
+                        goto = step.next();
+                    }
+                    if ui.button("Skip setup").clicked() {
+                        goto = None;
+                    }
+                });
+            });
+
+        if goto != Some(step) {
+            self.hold_test_note(false);
+        }
+        if goto.is_none() {
+            self.setup_done = true;
+            self.save_config();
+        }
+        self.wizard = goto;
+    }
+
+    fn ui_wizard_samples(&mut self, ui: &mut egui::Ui) {
+        ui.label("Notes are played from recorded samples: one file per note, named after");
+        ui.label("it (c2.wav, c#2.wav, ...), with a subfolder per voice if you have several.");
+
+        let mut rescan = false;
+        ui.horizontal(|ui| {
+            ui.label("samples folder:");
+            let mut root = self.samples_root.display().to_string();
+            if ui.text_edit_singleline(&mut root).changed() {
+                self.samples_root = PathBuf::from(root);
+            }
+            rescan = ui.button("Look again").clicked();
+        });
+        if rescan {
+            self.banks = bank::scan_banks(&self.samples_root);
+            self.bank_index = 0;
+            self.select_bank(0);
+        }
+
+        if self.banks.is_empty() {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                "No samples found there. Point this at your sample pack, or copy one in.",
+            );
+            if !self.samples_root.exists() && ui.button("Create the folder").clicked() {
+                if let Err(e) = std::fs::create_dir_all(&self.samples_root) {
+                    self.toasts.error(format!("Failed to create {:?}: {e}", self.samples_root));
+                }
+            }
+        } else {
+            let names: Vec<&str> = self.banks.iter().map(|b| b.name.as_str()).collect();
+            ui.colored_label(
+                egui::Color32::GREEN,
+                format!("Found {} voice(s): {}", names.len(), names.join(", ")),
+            );
+        }
+    }
+
+    fn ui_wizard_keymap(&mut self, ui: &mut egui::Ui) {
+        ui.label("The keymap says which computer key plays which note.");
+        self.ui_keymap_state(ui);
+        ui.add_space(4.0);
+        ui.label("Keep it, or generate a piano-style layout:");
+        self.ui_layout_controls(ui);
+    }
+
+    fn ui_wizard_calibrate(&mut self, ui: &mut egui::Ui) {
+        let latest = self.sensors.get(self.active_sensor).and_then(|s| s.latest.as_ref());
+        if latest.is_none() && self.bellows_input == BellowsInput::Sensor {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                "No angle from the sensor yet. Check the Sensor panel, or play without one:",
+            );
+            if ui.button("Use the arrow keys as bellows (↑ / ↓)").clicked() {
+                self.bellows_input = BellowsInput::ArrowKeys;
+                self.fallback_reason = None;
+            }
+        } else {
+            ui.label("Open the screen to where you usually start and press Zero, then pump");
+            ui.label("it gently: the bellows should light up. Keep it still for a moment");
+            ui.label("afterwards so the deadzone can learn the sensor's noise.");
+            let can_zero = latest.is_some() && self.bellows_input == BellowsInput::Sensor;
+            if ui.add_enabled(can_zero, egui::Button::new("Zero")).clicked() {
+                self.zero_sensor(self.active_sensor);
+            }
+        }
+
+        let o = self.bellows_out;
+        ui.add(LidView::new(o.theta_deg, o.a));
+        ui.add(egui::ProgressBar::new(o.a.clamp(0.0, 1.0)).show_percentage());
+    }
+
+    fn ui_wizard_test_audio(&mut self, ui: &mut egui::Ui) {
+        match (&self.audio, &self.audio_error) {
+            (_, Some(err)) => {
+                ui.colored_label(egui::Color32::RED, format!("Audio error: {err}"));
+                if err.is_device() && ui.button("Retry").clicked() {
+                    self.retry_audio();
+                }
+            }
+            (None, None) => {
+                ui.colored_label(egui::Color32::YELLOW, "Audio engine not available");
+            }
+            (Some(_), None) => {
+                ui.label("Hold the button and pump the bellows: you should hear Sa.");
+            }
+        }
+        ui.checkbox(&mut self.audio_enabled, "Enable audio output");
+        let range = MidiParam::MasterGain.range();
+        ui.add(egui::Slider::new(&mut self.master_gain, range).text("master volume"));
+
+        let button = ui.add(egui::Button::new("Hold to play").sense(egui::Sense::click_and_drag()));
+        self.hold_test_note(button.is_pointer_button_down_on());
+        ui.add(egui::ProgressBar::new(self.bellows_out.a.clamp(0.0, 1.0)).text("bellows"));
+
+        ui.horizontal(|ui| {
+            ui.label("Or hear a short phrase, bellows and all:");
+            self.ui_demo_button(ui);
+        });
+    }
+
+    /// Play / stop the demo, with how far it has got.
+    fn ui_demo_button(&mut self, ui: &mut egui::Ui) {
+        match self.demo {
+            Some(demo) => {
+                ui.add(egui::ProgressBar::new(demo.progress(Instant::now())).desired_width(60.0));
+                if ui.button("Stop demo").clicked() {
+                    self.stop_demo();
+                }
+            }
+            None => {
+                let play = ui.button("Play demo").on_hover_text("A scripted phrase, no sensor needed");
+                if play.clicked() {
+                    self.stop_session_replay();
+                    self.demo = Some(Demo::start(Instant::now()));
+                    self.bellows.reset();
+                    info!("demo started");
+                }
+            }
+        }
+    }
+
+    /// Move the demo's notes on, and end it after the last one.
+    fn update_demo(&mut self) {
+        let Some(demo) = self.demo else {
+            return;
+        };
+        let now = Instant::now();
+        if demo.is_done(now) {
+            self.stop_demo();
+        } else {
+            self.set_scripted_notes(demo.notes_at(now, self.scale_lock.tonic));
+        }
+    }
+
+    fn stop_demo(&mut self) {
+        self.set_scripted_notes(Vec::new());
+        self.demo = None;
+        self.bellows.reset();
+    }
+
+    /// Hold exactly `wanted` down, like keys, so the audio, the MIDI output
+    /// and the displays all follow the demo or replay.
+    fn set_scripted_notes(&mut self, wanted: Vec<Note>) {
+        let held = std::mem::take(&mut self.scripted_notes);
+        self.scripted_notes = self.hold_notes(held, wanted, KeyId::OnScreen);
+    }
+
+    /// Same for the notes the phrase looper plays.
+    fn set_loop_notes(&mut self, wanted: Vec<Note>) {
+        let held = std::mem::take(&mut self.loop_notes);
+        self.loop_notes = self.hold_notes(held, wanted, KeyId::Looper);
+    }
+
+    /// And for the notes the hook script holds.
+    fn set_script_notes(&mut self, wanted: Vec<Note>) {
+        let held = std::mem::take(&mut self.script_notes);
+        self.script_notes = self.hold_notes(held, wanted, KeyId::Script);
+    }
+
+    /// Release the `held` notes not in `wanted` and press the new ones, each
+    /// on its own `key`. Returns what is held now.
+    fn hold_notes(
+        &mut self,
+        held: Vec<Note>,
+        wanted: Vec<Note>,
+        key: fn(u8) -> KeyId,
+    ) -> Vec<Note> {
+        let mut now_held = Vec::new();
+        for note in held {
+            if wanted.contains(&note) {
+                now_held.push(note);
+                continue;
+            }
+            let notes = self.pressed.key_up(key(note.midi()));
+            if let Some(a) = &mut self.audio {
+                a.chord_off(&notes);
+            }
+        }
+
+        for note in wanted {
+            if now_held.contains(&note) {
+                continue;
+            }
+            now_held.push(note);
+            let notes = self.pressed.key_down(key(note.midi()), vec![note.name()]);
+            if !notes.is_empty() && self.audio_enabled {
+                if let Some(a) = &mut self.audio {
+                    if let Err(e) = a.chord_on(&notes, 1.0, 0.0) {
+                        report_audio_error(&mut self.toasts, &e);
+                    }
+                }
+            }
+        }
+        now_held
+    }
+
+    /// Feed the phrase looper what is played live, and hold down what it plays.
+    fn update_phrase_looper(&mut self) {
+        let now = Instant::now();
+        let looped: Vec<NoteName> = self.loop_notes.iter().map(|n| n.name()).collect();
+        let live: Vec<NoteName> =
+            self.pressed.active_notes().into_iter().filter(|n| !looped.contains(n)).collect();
+        self.phrase_looper.feed(&live, now);
+
+        let wanted = self.phrase_looper.notes_at(now);
+        self.set_loop_notes(wanted.iter().filter_map(|n| Note::parse(n)).collect());
+    }
+
+    /// Sound Sa in the middle octave while `held`, like an on-screen key.
+    fn hold_test_note(&mut self, held: bool) {
+        if self.test_note.is_some() == held {
+            return;
+        }
+
+        if let Some(old) = self.test_note.take() {
+            let notes = self.pressed.key_up(KeyId::OnScreen(old.midi()));
+            if let Some(a) = &mut self.audio {
+                a.chord_off(&notes);
+            }
+            return;
+        }
+
+        let Some(sa) = Note::from_midi(60 + self.scale_lock.tonic as i32) else {
+            return;
+        };
+        self.test_note = Some(sa);
+        let notes = self.pressed.key_down(KeyId::OnScreen(sa.midi()), vec![sa.name()]);
+        if !notes.is_empty() && self.audio_enabled {
+            if let Some(a) = &mut self.audio {
+                if let Err(e) = a.chord_on(&notes, 1.0, 0.0) {
+                    report_audio_error(&mut self.toasts, &e);
+                }
+            }
+        }
+    }
+
+    fn set_performance_mode(&mut self, ctx: &egui::Context, on: bool) {
+        self.performance_mode = on;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(on));
+    }
+
+    /// `text (Key)`, or just `text` if the action has no key.
+    fn shortcut_label(&self, text: &str, action: Action) -> String {
+        match self.shortcuts.key_for(action) {
+            Some(key) => format!("{text} ({})", key.name()),
+            None => text.to_string(),
+        }
+    }
+
+    /// Bind keys to app actions. A bound key never plays a note, so keys the
+    /// keymap also uses are flagged.
+    fn ui_shortcuts(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Shortcuts").show(ui, |ui| {
+            egui::Grid::new("shortcuts").num_columns(4).show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+
+                    let key = self.shortcuts.key_for(action);
+                    let text = if self.learning_shortcut == Some(action) {
+                        "Press a key...".to_string()
+                    } else {
+                        key.map_or("—".to_string(), |k| k.name().to_string())
+                    };
+                    if ui.button(text).on_hover_text("Click, then press a key").clicked() {
+                        self.learning_shortcut = Some(action);
+                    }
+                    if ui.add_enabled(key.is_some(), egui::Button::new("Clear")).clicked() {
+                        self.shortcuts.unbind(action);
+                    }
+
+                    match key.and_then(|k| self.keymap_notes_for(k)) {
+                        Some(notes) => ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("also mapped to {notes} in the keymap; the shortcut wins"),
+                        ),
+                        None => ui.label(""),
+                    };
+                    ui.end_row();
+                }
+            });
+
+            if ui.button("Reset to defaults").clicked() {
+                self.shortcuts = Shortcuts::default();
+                self.learning_shortcut = None;
+            }
+        });
+    }
+
+    /// The notes the keymap would play for `key`, if it maps it at all.
+    fn keymap_notes_for(&self, key: egui::Key) -> Option<String> {
+        let km = self.keymap.as_ref()?;
+        let id = km.resolve(egui_key_to_char(key), physical_key_of(key))?;
+        Some(km.notes_for_key(id, 0).join(" "))
+    }
+
+    /// Recent log events from every module, newest at the bottom.
+    fn ui_log(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Log").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("level:");
+                egui::ComboBox::from_id_salt("log_level")
+                    .selected_text(self.log_level.as_str())
+                    .show_ui(ui, |ui| {
+                        for level in [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG] {
+                            ui.selectable_value(&mut self.log_level, level, level.as_str());
+                        }
+                    });
+
+                ui.label("module:");
+                egui::ComboBox::from_id_salt("log_module")
+                    .selected_text(self.log_module.as_deref().unwrap_or("all"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.log_module, None, "all");
+                        for m in self.log.modules() {
+                            ui.selectable_value(&mut self.log_module, Some(m.clone()), m);
+                        }
+                    });
+
+                if ui.button("Clear").clicked() {
+                    self.log.clear();
+                }
+            });
+
+            let entries = self.log.filtered(self.log_level, self.log_module.as_deref());
+            egui::ScrollArea::vertical()
+                .max_height(160.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for e in entries {
+                        let color = match e.level {
+                            Level::ERROR => egui::Color32::RED,
+                            Level::WARN => egui::Color32::YELLOW,
+                            _ => ui.visuals().text_color(),
+                        };
+                        let line = format!(
+                            "{:8.2}  {:5}  {:8}  {}",
+                            e.t_sec,
+                            e.level.as_str(),
+                            e.module,
+                            e.message
+                        );
+                        ui.label(egui::RichText::new(line).monospace().color(color));
+                    }
+                });
+        });
+    }
+
+    /// Corner overlay with what each subsystem is doing, so a stutter can be
+    /// pinned on the UI, the sensor or the audio thread.
+    fn ui_hud(&self, ctx: &egui::Context) {
+        let frame = format!(
+            "frame   {:5.1} ms  (worst {:5.1})",
+            self.frame_timer.average_ms(),
+            self.frame_timer.worst_ms()
+        );
+        let rate = self.sensors.get(self.active_sensor).and_then(|s| s.health.rate_hz());
+        let sensor = match rate {
+            Some(hz) => format!("sensor  {hz:5.1} Hz"),
+            None => "sensor     no samples".to_string(),
+        };
+
+        egui::Area::new(egui::Id::new("perf_hud"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.monospace(frame);
+                    ui.monospace(sensor);
+                    match &self.audio {
+                        Some(a) => {
+                            let underruns = a.underruns();
+                            let text = format!("audio   {underruns} underruns");
+                            let color = if underruns > 0 {
+                                egui::Color32::RED
+                            } else {
+                                ui.visuals().text_color()
+                            };
+                            ui.label(egui::RichText::new(text).monospace().color(color));
+                            ui.monospace(format!("voices  {}", a.voice_count()));
+                        }
+                        None => {
+                            ui.monospace("audio   off");
+                        }
+                    }
+                });
+            });
+    }
+
+    fn ui_sensor_status(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Sensor");
+
+        self.ui_add_sensor(ui);
+        self.ui_sensor_list(ui);
+
+        if self.blend_sensor.is_some() {
+            ui.horizontal(|ui| {
+                ui.label("fusion:");
+                let f = &mut self.fusion;
+                ui.radio_value(&mut f.mode, FusionMode::Weighted, "weighted")
+                    .on_hover_text("Average of the two angles");
+                ui.radio_value(&mut f.mode, FusionMode::Max, "max")
+                    .on_hover_text("Follow whichever sensor moves more");
+            });
+            if self.fusion.mode == FusionMode::Weighted {
+                ui.add(
+                    egui::Slider::new(&mut self.fusion.weight, 0.0..=1.0)
+                        .text("blend (feed → blend sensor)"),
+                );
+            }
+        }
+
+        if let Some(err) = &self.sensor_error {
+            ui.colored_label(egui::Color32::RED, format!("Error: {err}"));
+        }
+
+        self.ui_fallback(ui);
+        self.ui_sensor_recorder(ui);
+
+        if let Some(s) = &self.latest_sample {
+            ui.label(format!("Latest angle: {:6.2} deg   source={}", s.theta_deg, s.source));
+            ui.label(format!("Last sample age: {:5.2} sec", self.last_sample_age_sec));
+        } else {
+            ui.label("No samples yet.");
+        }
+    }
+
+    fn ui_fallback(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.auto_fallback, "If the sensor is missing, fall back to");
+            egui::ComboBox::from_id_salt("fallback_input")
+                .selected_text(self.fallback_input.label())
+                .show_ui(ui, |ui| {
+                    for input in [BellowsInput::Fake, BellowsInput::ArrowKeys] {
+                        ui.selectable_value(&mut self.fallback_input, input, input.label());
+                    }
+                });
+        });
+
+        if let Some(reason) = &self.fallback_reason {
+            ui.colored_label(
+                egui::Color32::from_rgb(230, 160, 0),
+                format!(
+                    "Sensor unavailable, using {} input instead ({reason}). \
+                     Switches back when the sensor delivers samples again.",
+                    self.bellows_input.label()
+                ),
+            );
+        }
+    }
+
+    /// Switch to the fallback input while the feeding sensor is failing, and
+    /// back to the sensor once it delivers samples again.
+    fn update_fallback(&mut self) {
+        let stale = match &self.latest_sample {
+            Some(_) => self.last_sample_age_sec > FALLBACK_AFTER_SEC,
+            None => self.start_time.elapsed().as_secs_f32() > FALLBACK_AFTER_SEC,
+        };
+
+        if self.fallback_reason.is_some() {
+            if !stale {
+                self.bellows_input = BellowsInput::Sensor;
+                self.fallback_reason = None;
+            }
+            return;
+        }
+
+        if !self.auto_fallback || self.bellows_input != BellowsInput::Sensor || !stale {
+            return;
+        }
+
+        let reason = match self.sensors.get(self.active_sensor) {
+            None => "no sensor selected".to_string(),
+            Some(slot) => match &slot.health.last_error {
+                Some(e) => format!("{}: {e}", slot.kind.label()),
+                None => return,
+            },
+        };
+
+        self.bellows_input = self.fallback_input;
+        self.fallback_reason = Some(reason);
+    }
+
+    /// Backend picker + its settings + "Add" button.
+    fn ui_add_sensor(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("new_sensor_kind")
+                .selected_text(self.new_sensor_kind.label())
+                .show_ui(ui, |ui| {
+                    for k in SensorKind::ALL.into_iter().filter(|k| k.is_available()) {
+                        ui.selectable_value(&mut self.new_sensor_kind, k, k.label());
+                    }
+                });
+
+            match self.new_sensor_kind {
+                SensorKind::Osc => {
+                    ui.add(
+                        egui::DragValue::new(&mut self.sensor_config.osc_port)
+                            .range(1024..=65535)
+                            .prefix("UDP port "),
+                    );
+                }
+                SensorKind::WebSocket => {
+                    ui.add(
+                        egui::DragValue::new(&mut self.sensor_config.websocket_port)
+                            .range(1024..=65535)
+                            .prefix("TCP port "),
+                    );
+                }
+                SensorKind::Companion => {
+                    ui.add(
+                        egui::DragValue::new(&mut self.sensor_config.companion_port)
+                            .range(1024..=65535)
+                            .prefix("UDP port "),
+                    );
+                }
+                SensorKind::Serial => {
+                    let cfg = &mut self.sensor_config;
+                    egui::ComboBox::from_id_salt("serial_port")
+                        .selected_text(cfg.serial_port.as_str())
+                        .show_ui(ui, |ui| {
+                            for port in sensor::available_serial_ports() {
+                                ui.selectable_value(&mut cfg.serial_port, port.clone(), port);
+                            }
+                        });
+                    ui.add(
+                        egui::DragValue::new(&mut cfg.serial_baud)
+                            .range(300..=2_000_000)
+                            .suffix(" baud"),
+                    );
+                    ui.add(egui::DragValue::new(&mut cfg.serial_scale).speed(0.01).prefix("×"));
+                }
+                SensorKind::Hid => {
+                    if ui.button("Scan").clicked() || self.hid_devices.is_none() {
+                        self.hid_devices = Some(sensor::available_hid_devices());
+                    }
+                    let devices = self.hid_devices.as_deref().unwrap_or_default();
+                    let cfg = &mut self.sensor_config;
+
+                    egui::ComboBox::from_id_salt("hid_device")
+                        .selected_text(cfg.hid_device.as_str())
+                        .show_ui(ui, |ui| {
+                            for dev in devices {
+                                ui.selectable_value(&mut cfg.hid_device, dev.name.clone(), &dev.name);
+                            }
+                        });
+
+                    let axes = devices
+                        .iter()
+                        .find(|d| d.name == cfg.hid_device)
+                        .map(|d| d.axes.clone())
+                        .unwrap_or_default();
+                    egui::ComboBox::from_id_salt("hid_axis")
+                        .selected_text(cfg.hid_axis.as_str())
+                        .show_ui(ui, |ui| {
+                            for axis in axes {
+                                ui.selectable_value(&mut cfg.hid_axis, axis.clone(), axis);
+                            }
+                        });
+                }
+                _ => {}
+            }
+
+            if ui.button("Add sensor").clicked() {
+                self.sensors.push(SensorSlot::start(self.new_sensor_kind, &self.sensor_config));
+            }
+        });
+    }
+
+    /// Every running sensor with its live value; pick which one feeds the bellows.
+    fn ui_sensor_list(&mut self, ui: &mut egui::Ui) {
+        let mut restart = None;
+        let mut remove = None;
+        let mut zero = None;
+
+        egui::Grid::new("sensor_list").striped(true).show(ui, |ui| {
+            ui.label("feed");
+            ui.label("blend");
+            ui.label("backend");
+            ui.label("angle");
+            ui.label("rate");
+            ui.label("jitter");
+            ui.label("drops");
+            ui.label("status");
+            ui.end_row();
+
+            for i in 0..self.sensors.len() {
+                ui.radio_value(&mut self.active_sensor, i, "");
+
+                let mut blend = self.blend_sensor == Some(i);
+                if ui.checkbox(&mut blend, "").changed() {
+                    self.blend_sensor = blend.then_some(i);
+                    self.fusion.reset();
+                }
+
+                let slot = &self.sensors[i];
+                ui.label(slot.kind.label());
+                match &slot.latest {
+                    Some(s) => ui.label(format!("{:6.2}°", s.theta_deg)),
+                    None => ui.label("–"),
+                };
+
+                let h = &slot.health;
+                match h.rate_hz() {
+                    Some(hz) => ui.label(format!("{hz:5.1} Hz")),
+                    None => ui.label("–"),
+                };
+                match h.jitter_ms() {
+                    Some(ms) => ui.label(format!("±{ms:4.1} ms")),
+                    None => ui.label("–"),
+                };
+                ui.label(h.dropouts.to_string());
+
+                let status = match &slot.error {
+                    Some(err) => ui.colored_label(egui::Color32::RED, err.to_string()),
+                    None => ui.label(&slot.status),
+                };
+                let mut hover = format!(
+                    "{} samples total, {} dropped as stale",
+                    h.total_samples, h.stale_dropped
+                );
+                if let Some(err) = &h.last_error {
+                    hover.push_str(&format!("\nlast error: {err}"));
+                    if let Some(hint) = err.hint() {
+                        hover.push_str(&format!("\n{hint}"));
+                    }
+                }
+                status.on_hover_text(hover);
+
+                ui.horizontal(|ui| {
+                    let zero_hover = format!(
+                        "Set the current angle as zero (offset now {:.2}°)",
+                        slot.zero_offset_deg
+                    );
+                    if ui.small_button("0").on_hover_text(zero_hover).clicked() {
+                        zero = Some(i);
+                    }
+                    if ui.small_button("↻").on_hover_text("Reconnect").clicked() {
+                        restart = Some(i);
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        if let Some(i) = restart {
+            self.sensors[i].restart(&self.sensor_config);
+            self.bellows.reset();
+        }
+        if let Some(i) = remove {
+            self.remove_sensor(i);
+        }
+        if let Some(i) = zero {
+            self.zero_sensor(i);
+        }
+    }
+
+    /// Make the sensor's current angle read as 0° from now on, for every
+    /// sensor of the same backend, and save the offset for the next run.
+    fn zero_sensor(&mut self, i: usize) {
+        let slot = &self.sensors[i];
+        let Some(latest) = &slot.latest else {
+            return;
+        };
+
+        let kind = slot.kind;
+        let offset = slot.zero_offset_deg + latest.theta_deg;
+        self.sensor_config.zero_offsets_deg.insert(kind, offset);
+
+        for s in self.sensors.iter_mut().filter(|s| s.kind == kind) {
+            if let Some(latest) = &mut s.latest {
+                latest.theta_deg -= offset - s.zero_offset_deg;
+            }
+            s.zero_offset_deg = offset;
+        }
+        self.bellows.reset();
+
+        if let Err(e) = sensor::save_zero_offsets(
+            sensor::DEFAULT_CALIBRATION_PATH,
+            &self.sensor_config.zero_offsets_deg,
+        ) {
+            self.sensor_error = Some(e);
+        }
+    }
+
+    fn remove_sensor(&mut self, i: usize) {
+        self.sensors.remove(i);
+
+        // Keep the feed/blend selections pointing at the same sensors.
+        let shift = |idx: usize| if idx > i { idx - 1 } else { idx };
+
+        if self.active_sensor == i {
+            self.active_sensor = 0;
+            self.bellows.reset();
+        } else {
+            self.active_sensor = shift(self.active_sensor);
+        }
+
+        self.blend_sensor = match self.blend_sensor {
+            Some(b) if b == i => None,
+            other => other.map(shift),
+        };
+        self.fusion.reset();
+    }
+
+    /// Open the audio output again, e.g. after it was busy or unplugged at
+    /// startup.
+    fn retry_audio(&mut self) {
+        let dir = self.banks.get(self.bank_index).map_or(&self.samples_root, |b| &b.dir);
+        match AudioEngine::new(dir) {
+            Ok(a) => {
+                self.audio = Some(a);
+                self.audio_error = None;
+                self.toasts.info("Audio output open");
+                self.start_loading(false);
+            }
+            Err(e) => self.audio_error = Some(e),
+        }
+    }
+
+    fn ui_audio_status(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Audio");
+
+        if let Some(err) = &self.audio_error {
+            ui.colored_label(egui::Color32::RED, format!("Audio error: {err}"));
+            if err.is_device()
+                && ui
+                    .button("Retry")
+                    .on_hover_text("Open the audio output again (once it is free or plugged in)")
+                    .clicked()
+            {
+                self.retry_audio();
+            }
+        } else if self.audio.is_some() {
+            ui.colored_label(egui::Color32::GREEN, "Audio engine ready");
+        } else {
+            ui.colored_label(egui::Color32::YELLOW, "Audio engine not available");
+        }
+        if let Some((done, total)) = self.load_progress {
+            let bar = egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                .text(format!("loading samples {done}/{total}"));
+            ui.add(bar);
+        }
+
+        ui.checkbox(&mut self.audio_enabled, "Enable audio output");
+
+        // Master gain slider (will affect volume)
+        let master = ui.add(
+            egui::Slider::new(&mut self.master_gain, MidiParam::MasterGain.range())
+                .text("master volume"),
+        );
+        midi_learn_menu(&master, &mut self.midi_learn, MidiParam::MasterGain, &mut self.midi_error);
+
+        let scoop = &mut self.attack_scoop;
+        ui.add(
+            egui::Slider::new(&mut scoop.depth_cents, 0.0..=100.0)
+                .text("attack scoop (cents, 0 = off)"),
+        )
+        .on_hover_text("Notes start this much flat with the bellows at rest, less with more air");
+        if scoop.depth_cents > 0.0 {
+            ui.add(egui::Slider::new(&mut scoop.time_ms, 10.0..=400.0).text("scoop time (ms)"));
+        }
+
+        ui.add(egui::Slider::new(&mut self.max_voices, 4..=64).text("max voices"))
+            .on_hover_text("Past this many notes, a new one cuts the note sounding longest");
+
+        // If audio exists, apply master gain, the scoop and the cap live
+        if let Some(a) = &mut self.audio {
+            a.set_master_gain(self.master_gain);
+            a.set_attack_scoop(self.attack_scoop);
+            a.set_max_voices(self.max_voices);
+        }
+        if let Some(s) = &mut self.second {
+            s.set_master_gain(self.master_gain);
+            s.engine.set_attack_scoop(self.attack_scoop);
+            s.engine.set_max_voices(self.max_voices);
+        }
+
+        self.ui_banks(ui);
+        self.ui_harmonizer(ui);
+        self.ui_second_instrument(ui);
+
+        ui.checkbox(&mut self.lid_mute_enabled, "Mute when the lid closes");
+        if self.lid_mute_enabled {
+            ui.add(
+                egui::Slider::new(&mut self.lid_close_deg, 0.0..=90.0).text("closed below (deg)"),
+            );
+            ui.add(egui::Slider::new(&mut self.lid_fade_ms, 0.0..=1000.0).text("lid fade (ms)"));
+        }
+        if self.lid_closed {
+            ui.colored_label(egui::Color32::YELLOW, "Lid closed: audio suspended");
+        }
+
+        let stop = self.shortcut_label("Stop all notes", Action::StopAll);
+        if ui.button(stop).clicked() {
+            self.stop_all_notes();
+        }
+    }
+
+    /// Panic: whatever state the notes got into, start clean. Every voice
+    /// fades out, every key, latch and mono note is forgotten with the
+    /// sustain pedal and infinite air, and the bellows starts from rest.
+    fn stop_all_notes(&mut self) {
+        self.release_mono();
+        self.phrase_looper.stop(Instant::now());
+        if let Some(script) = &mut self.script {
+            script.release_all();
+        }
+        self.pressed.clear();
+        if let Some(a) = &mut self.audio {
+            a.panic();
+        }
+        if let Some(s) = &mut self.second {
+            s.engine.panic();
+        }
+        self.set_sustain(false);
+        self.air_hold = false;
+        self.bellows.reset();
+        self.bellows_out = BellowsOutput::default();
+        self.bus.publish(Event::Config(ConfigEvent::StopAll));
+    }
+
+    /// After a panic: let the engines clean up once the fade is over, and
+    /// bring the backing loop back if an output had to be reopened.
+    fn update_panic(&mut self) {
+        let engines = self.audio.iter_mut().chain(self.second.iter_mut().map(|s| &mut s.engine));
+        let mut reopened = false;
+        for a in engines {
+            match a.finish_panic() {
+                Ok(r) => reopened |= r,
+                Err(e) => report_audio_error(&mut self.toasts, &e),
+            }
+        }
+        if reopened {
+            self.toasts.info("Audio output reopened");
+            if self.backing_playing.is_some() {
+                self.start_backing();
+            }
+        }
+    }
+
+    /// Let go of the mono note and forget the mono keys held, so the next
+    /// press starts afresh.
+    fn release_mono(&mut self) {
+        self.mono_keys.clear();
+        let notes = self.pressed.key_up(KeyId::Mono);
+        if let Some(a) = &mut self.audio {
+            a.chord_off(&notes);
+        }
+    }
+
+    /// Pick the sample bank (voice / stop preset); also done by MIDI program change.
+    fn ui_banks(&mut self, ui: &mut egui::Ui) {
+        let mut picked = None;
+        ui.horizontal(|ui| {
+            ui.label("Voice:");
+            let current = self.banks.get(self.bank_index).map_or("(no samples)", |b| &b.name);
+            egui::ComboBox::from_id_salt("sample_bank")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (i, b) in self.banks.iter().enumerate() {
+                        let text = format!("{i}: {}", b.name);
+                        if ui.selectable_label(i == self.bank_index, text).clicked() {
+                            picked = Some(i);
+                        }
+                    }
+                });
+            if ui.button("Rescan").clicked() {
+                self.banks = bank::scan_banks(&self.samples_root);
+                self.bank_index = self.bank_index.min(self.banks.len().saturating_sub(1));
+            }
+        });
+
+        if let Some(i) = picked {
+            self.select_bank(i);
+        }
+    }
+
+    /// Save the current setup under a name, recall or delete saved ones.
+    fn ui_presets(&mut self, ui: &mut egui::Ui) {
+        let mut picked = None;
+        let mut save = false;
+        let mut delete = false;
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            let current = self
+                .preset_index
+                .and_then(|i| self.presets.get(i))
+                .map_or("(none)", |p| p.name.as_str());
+            egui::ComboBox::from_id_salt("preset")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (i, p) in self.presets.presets().iter().enumerate() {
+                        let text = if i < PRESET_HOTKEYS {
+                            format!("Alt+{}: {}", i + 1, p.name)
+                        } else {
+                            p.name.clone()
+                        };
+                        if ui.selectable_label(Some(i) == self.preset_index, text).clicked() {
+                            picked = Some(i);
+                        }
+                    }
+                });
+
+            ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("name"));
+            let can_save = !self.preset_name.trim().is_empty();
+            save = ui
+                .add_enabled(can_save, egui::Button::new("Save"))
+                .on_hover_text("Bellows, voice, keymap, octave and Sa / raga")
+                .clicked();
+            delete = ui
+                .add_enabled(self.preset_index.is_some(), egui::Button::new("Delete"))
+                .clicked();
+        });
+
+        if let Some(i) = picked {
+            self.recall_preset(i);
+        }
+        if save {
+            let preset = self.capture_preset(self.preset_name.trim());
+            match self.presets.store(preset) {
+                Ok(i) => {
+                    info!("preset '{}' saved", self.preset_name.trim());
+                    self.preset_index = Some(i);
+                }
+                Err(e) => error!("{e}"),
+            }
+        }
+        if delete {
+            if let Some(i) = self.preset_index.take() {
+                if let Err(e) = self.presets.remove(i) {
+                    error!("{e}");
+                }
+            }
+        }
+    }
+
+    /// The current setup as a preset called `name`.
+    fn capture_preset(&self, name: &str) -> Preset {
+        Preset {
+            name: name.to_string(),
+            bellows: self.bellows.params.clone(),
+            sample_dir: self.banks.get(self.bank_index).map(|b| b.dir.clone()),
+            keymap_path: Some(self.keymap_path.clone()),
+            octave_shift: self.octave_shift,
+            scale_lock: self.scale_lock.clone(),
+            harmonizer: self.harmonizer.clone(),
+        }
+    }
+
+    /// Switch to a saved preset (also Alt+1..9). Unknown indexes are ignored.
+    fn recall_preset(&mut self, index: usize) {
+        let Some(p) = self.presets.get(index).cloned() else {
+            return;
+        };
+        info!("preset '{}' recalled", p.name);
+        self.toasts.info(format!("Preset: {}", p.name));
+        self.apply_preset(p);
+        self.preset_index = Some(index);
+    }
+
+    /// Take on a preset's setup (its name goes in the name field).
+    fn apply_preset(&mut self, p: Preset) {
+        self.preset_name = p.name;
+
+        self.bellows.params = p.bellows;
+        self.octave_shift = p.octave_shift;
+        self.scale_lock = p.scale_lock;
+        self.harmonizer = p.harmonizer;
+
+        let bank = p.sample_dir.and_then(|dir| self.banks.iter().position(|b| b.dir == dir));
+        if let Some(i) = bank {
+            if i != self.bank_index {
+                self.select_bank(i);
+            }
+        }
+
+        if let Some(path) = p.keymap_path {
+            if path != self.keymap_path {
+                self.keymap_path = path;
+                self.load_keymap();
+            }
+        }
+    }
+
+    /// The harmonizer stop: an interval added to every note, kept in the
+    /// scale picked under Scale lock.
+    fn ui_harmonizer(&mut self, ui: &mut egui::Ui) {
+        let h = &mut self.harmonizer;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut h.enabled, "Harmonizer:");
+            for interval in HarmonyInterval::ALL {
+                ui.radio_value(&mut h.interval, interval, interval.label());
+            }
+        });
+        if h.enabled {
+            ui.add(egui::Slider::new(&mut h.level, 0.0..=1.0).text("harmony level"));
+        }
+    }
+
+    /// Hand the harmonizer stop and the scale to the audio engine.
+    fn update_harmonizer(&mut self) {
+        if let Some(a) = &mut self.audio {
+            a.set_harmonizer(&self.harmonizer, &self.scale_lock);
+        }
+        if let Some(s) = &mut self.second {
+            s.update_harmonizer(&self.scale_lock);
+        }
+    }
+
+    /// Dual mode: a second bank (e.g. a female-reed drone under a male-reed
+    /// lead) for the keymap zones marked `"instrument": 2`, at its own level
+    /// and with its own harmonizer stop.
+    fn ui_second_instrument(&mut self, ui: &mut egui::Ui) {
+        let toggled = egui::CollapsingHeader::new("Second instrument").show(ui, |ui| {
+            let mut enabled = self.second.is_some();
+            let toggled = ui.checkbox(&mut enabled, "Dual mode").changed().then_some(enabled);
+            let Some(s) = &mut self.second else {
+                ui.label("Zones marked instrument 2 play the main voice until this is on.");
+                return toggled;
+            };
+
+            let mut picked = None;
+            let current = self
+                .banks
+                .iter()
+                .find(|b| b.dir == s.sample_dir)
+                .map_or("(samples root)", |b| &b.name);
+            egui::ComboBox::from_id_salt("second_bank").selected_text(current).show_ui(
+                ui,
+                |ui| {
+                    for b in &self.banks {
+                        if ui.selectable_label(b.dir == s.sample_dir, &b.name).clicked() {
+                            picked = Some(b.dir.clone());
+                        }
+                    }
+                },
+            );
+            if let Some(dir) = picked {
+                if let Err(e) = s.set_samples_dir(&dir) {
+                    report_audio_error(&mut self.toasts, &e);
+                }
+            }
+
+            ui.add(egui::Slider::new(&mut s.gain, 0.0..=1.5).text("level"));
+
+            let h = &mut s.harmonizer;
+            ui.checkbox(&mut h.enabled, "Harmonizer");
+            if h.enabled {
+                ui.horizontal(|ui| {
+                    for interval in HarmonyInterval::ALL {
+                        ui.radio_value(&mut h.interval, interval, interval.label());
+                    }
+                });
+                ui.add(egui::Slider::new(&mut h.level, 0.0..=1.0).text("harmony level"));
+            }
+            toggled
+        });
+        if let Some(enabled) = toggled.body_returned.flatten() {
+            self.set_second_enabled(enabled);
+        }
+    }
+
+    /// Open or close the second engine; closing keeps its settings for later.
+    fn set_second_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            if let Some(s) = self.second.take() {
+                self.second_config = s.config();
+            }
+            return;
+        }
+
+        let first = self.banks.get(self.bank_index).map_or(&self.samples_root, |b| &b.dir);
+        let dir = self.second_config.sample_dir.clone().unwrap_or_else(|| first.clone());
+        match SecondInstrument::open(&self.second_config, &dir) {
+            Ok(s) => {
+                self.second = Some(s);
+                self.start_loading(false);
+            }
+            Err(e) => report_audio_error(&mut self.toasts, &e),
+        }
+    }
+
+    /// Switch voices. Out-of-range programs are ignored.
+    fn select_bank(&mut self, index: usize) {
+        let Some(b) = self.banks.get(index) else {
+            return;
+        };
+        self.bank_index = index;
+
+        if let Some(a) = &mut self.audio {
+            if let Err(e) = a.set_samples_dir(&b.dir) {
+                report_audio_error(&mut self.toasts, &e);
+            }
+        }
+        self.start_loading(false);
+    }
+
+    /// Pick a MIDI output port and channel; notes and bellows (CC11) go there.
+    fn ui_midi_out(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("MIDI output").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Scan").clicked() || self.midi_ports.is_none() {
+                    self.midi_ports = Some(midi::available_output_ports());
+                }
+
+                let ports = self.midi_ports.as_deref().unwrap_or_default();
+                egui::ComboBox::from_id_salt("midi_out_port")
+                    .selected_text(self.midi_out_port.as_str())
+                    .show_ui(ui, |ui| {
+                        for port in ports {
+                            ui.selectable_value(&mut self.midi_out_port, port.clone(), port);
+                        }
+                    });
+
+                ui.add_enabled(
+                    !self.midi_mpe,
+                    egui::DragValue::new(&mut self.midi_channel).range(1..=16).prefix("ch "),
+                );
+                ui.add_enabled(
+                    self.midi_out.is_none(),
+                    egui::Checkbox::new(&mut self.midi_mpe, "MPE"),
+                )
+                .on_hover_text("One channel per note, each with its own pressure from the bellows");
+            });
+
+            ui.horizontal(|ui| {
+                match &self.midi_out {
+                    Some(m) => {
+                        let mode = if m.is_mpe() { " (MPE)" } else { "" };
+                        let status = format!("Sending to {}{mode}", m.port_name());
+                        ui.colored_label(egui::Color32::GREEN, status);
+                        if ui.button("Disconnect").clicked() {
+                            self.midi_out = None;
+                        }
+                    }
+                    None => {
+                        let can_connect = !self.midi_out_port.is_empty();
+                        if ui.add_enabled(can_connect, egui::Button::new("Connect")).clicked() {
+                            let port = &self.midi_out_port;
+                            match MidiOut::connect(port, self.midi_channel, self.midi_mpe) {
+                                Ok(mut m) => match self.bus.join(&mut m) {
+                                    Ok(()) => {
+                                        self.midi_error = None;
+                                        self.midi_out = Some(m);
+                                    }
+                                    Err(e) => self.midi_error = Some(e),
+                                },
+                                Err(e) => self.midi_error = Some(e),
+                            }
+                        }
+                    }
+                }
+            });
+
+            self.ui_midi_recorder(ui);
+            self.ui_session_recorder(ui);
+            self.ui_punch(ui);
+
+            if let Some(err) = &self.midi_error {
+                ui.colored_label(egui::Color32::RED, err.to_string());
+                if let Some(hint) = err.hint() {
+                    ui.label(hint);
+                }
+            }
+        });
+    }
+
+    /// Record notes + bellows (CC11) to a standard MIDI file, port or not.
+    fn ui_midi_recorder(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("record to:");
+            ui.add_enabled(
+                self.midi_recorder.is_none(),
+                egui::TextEdit::singleline(&mut self.midi_recording_path).desired_width(160.0),
+            );
+
+            match self.midi_recorder.take() {
+                Some(rec) => {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!("● {:.1} s", rec.elapsed_sec()),
+                    );
+                    self.midi_recorder = Some(rec);
+                    if ui.button("Stop").clicked() {
+                        self.toggle_midi_recording();
+                    }
+                }
+                None if self.armed_for(Recorder::Midi) => self.ui_armed(ui),
+                None => {
+                    if ui.button("Record MIDI").clicked() {
+                        self.toggle_midi_recording();
+                    }
+                }
+            }
+        });
+    }
+
+    /// Start a MIDI recording (after the count-in, if there is one), or
+    /// finish the one running or cancel the count-in.
+    fn toggle_midi_recording(&mut self) {
+        if self.armed_for(Recorder::Midi) && self.midi_recorder.is_none() {
+            self.armed = None;
+            return;
+        }
+        match self.midi_recorder.take() {
+            Some(rec) => {
+                self.armed = None;
+                self.midi_error = rec.finish().err();
+            }
+            None => self.record(Recorder::Midi),
+        }
+    }
+
+    fn start_midi_recording(&mut self) {
+        let mut rec = MidiRecorder::start(&self.midi_recording_path);
+        match self.bus.join(&mut rec) {
+            Ok(()) => self.midi_recorder = Some(rec),
+            Err(e) => self.toasts.error(e.to_string()),
+        }
+    }
+
+    /// Start `recorder` now, or arm it on the metronome when there is a
+    /// count-in or a punch-in to wait for.
+    fn record(&mut self, recorder: Recorder) {
+        if self.punch.count_in_beats == 0 && !self.punch.punch {
+            self.start_recorder(recorder);
+            return;
+        }
+        let now = Instant::now();
+        if !self.metronome.is_running() {
+            self.metronome.start(now);
+        }
+        self.armed = Some((recorder, Punch::arm(&self.metronome, &self.punch, now)));
+    }
+
+    fn start_recorder(&mut self, recorder: Recorder) {
+        match recorder {
+            Recorder::Midi => self.start_midi_recording(),
+            Recorder::Session => self.start_session_recording(),
+        }
+    }
+
+    fn stop_recorder(&mut self, recorder: Recorder) {
+        match recorder {
+            Recorder::Midi => {
+                if let Some(rec) = self.midi_recorder.take() {
+                    self.midi_error = rec.finish().err();
+                }
+            }
+            Recorder::Session => self.stop_session_recording(),
+        }
+    }
+
+    fn recording(&self, recorder: Recorder) -> bool {
+        match recorder {
+            Recorder::Midi => self.midi_recorder.is_some(),
+            Recorder::Session => self.session_recorder.is_some(),
+        }
+    }
+
+    fn armed_for(&self, recorder: Recorder) -> bool {
+        self.armed.is_some_and(|(r, _)| r == recorder)
+    }
+
+    /// The count-in of the armed recorder, with a way out.
+    fn ui_armed(&mut self, ui: &mut egui::Ui) {
+        let Some((_, punch)) = self.armed else {
+            return;
+        };
+        let beats = self.metronome.beats_at(Instant::now()).unwrap_or(0.0);
+        let text = match punch.stage(beats) {
+            PunchStage::CountIn(left) => format!("count-in {left}…"),
+            _ => "waiting for sam…".to_string(),
+        };
+        ui.colored_label(egui::Color32::YELLOW, text);
+        if ui.button("Cancel").clicked() {
+            self.armed = None;
+        }
+    }
+
+    /// Start the armed recorder when its punch-in falls, stop it at the
+    /// punch-out. Stopping the metronome drops a count-in; a recording
+    /// already running carries on until stopped.
+    fn update_punch(&mut self) {
+        let Some((recorder, punch)) = self.armed else {
+            return;
+        };
+        let Some(beats) = self.metronome.beats_at(Instant::now()) else {
+            if !self.recording(recorder) {
+                self.toasts.info("Count-in cancelled: the metronome stopped");
+            }
+            self.armed = None;
+            return;
+        };
+
+        match punch.stage(beats) {
+            PunchStage::CountIn(_) | PunchStage::Waiting => {}
+            PunchStage::Recording => {
+                if !self.recording(recorder) {
+                    info!("punch in at beat {beats:.2}");
+                    self.start_recorder(recorder);
+                }
+            }
+            PunchStage::Done => {
+                info!("punch out at beat {beats:.2}");
+                self.armed = None;
+                self.stop_recorder(recorder);
+            }
+        }
+    }
+
+    /// Count-in and punch points for both recorders, on the metronome.
+    fn ui_punch(&mut self, ui: &mut egui::Ui) {
+        let p = &mut self.punch;
+        ui.add(egui::Slider::new(&mut p.count_in_beats, 0..=16).text("count-in (beats, 0 = off)"));
+        ui.checkbox(&mut p.punch, "Punch in / out on sam");
+        if p.punch {
+            ui.add(
+                egui::Slider::new(&mut p.punch_in_cycles, 0..=8).text("punch in after (cycles)"),
+            );
+            ui.add(
+                egui::Slider::new(&mut p.punch_length_cycles, 0..=16)
+                    .text("record for (cycles, 0 = until stopped)"),
+            );
+        }
+    }
+
+    /// Record every note on/off and the bellows level to a compact event file,
+    /// and play such a file back through the instrument (practice review, bug
+    /// reports).
+    fn ui_session_recorder(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("session file:");
+            ui.add_enabled(
+                self.session_recorder.is_none() && self.session_player.is_none(),
+                egui::TextEdit::singleline(&mut self.session_recording_path).desired_width(160.0),
+            );
+
+            match &self.session_recorder {
+                Some(rec) => {
+                    let label =
+                        format!("⏹ Stop ({:.1} s, {} events)", rec.elapsed_sec(), rec.count());
+                    if ui.button(label).clicked() {
+                        self.armed = None;
+                        self.stop_session_recording();
+                    }
+                }
+                None if self.armed_for(Recorder::Session) => self.ui_armed(ui),
+                None => {
+                    let record = ui.add_enabled(
+                        self.session_player.is_none(),
+                        egui::Button::new("⏺ Record session"),
+                    );
+                    if record.clicked() {
+                        self.record(Recorder::Session);
+                    }
+                }
+            }
+
+            match &self.session_player {
+                Some(player) => {
+                    let progress = player.progress(Instant::now());
+                    ui.add(egui::ProgressBar::new(progress).desired_width(60.0));
+                    if ui.button("Stop replay").clicked() {
+                        self.stop_session_replay();
+                    }
+                }
+                None => {
+                    let replay = ui.add_enabled(
+                        self.session_recorder.is_none(),
+                        egui::Button::new("▶ Replay"),
+                    );
+                    if replay.clicked() {
+                        self.start_session_replay();
+                    }
+                }
+            }
+        });
+    }
+
+    fn start_session_recording(&mut self) {
+        match SessionRecorder::create(&self.session_recording_path) {
+            Ok(mut rec) => match self.bus.join(&mut rec) {
+                Ok(()) => self.session_recorder = Some(rec),
+                Err(e) => self.toasts.error(e.to_string()),
+            },
+            Err(e) => self.toasts.error(e.to_string()),
+        }
+    }
+
+    fn stop_session_recording(&mut self) {
+        if let Some(rec) = self.session_recorder.take() {
+            if let Err(e) = rec.finish() {
+                self.toasts.error(e.to_string());
+            }
+        }
+    }
+
+    fn start_session_replay(&mut self) {
+        match SessionPlayer::load(&self.session_recording_path, Instant::now()) {
+            Ok(player) => {
+                if self.demo.is_some() {
+                    self.stop_demo();
+                }
+                self.session_player = Some(player);
+                info!("replaying {}", self.session_recording_path);
+            }
+            Err(e) => self.toasts.error(e),
+        }
+    }
+
+    fn stop_session_replay(&mut self) {
+        if self.session_player.take().is_some() {
+            self.set_scripted_notes(Vec::new());
+            self.bellows.reset();
+        }
+    }
+
+    /// Move a replay on to now, and end it after the last event.
+    fn update_session_player(&mut self) {
+        let Some(player) = &mut self.session_player else {
+            return;
+        };
+        player.poll(Instant::now());
+        if player.is_done() {
+            self.stop_session_replay();
+            return;
+        }
+        let wanted = player.notes().iter().filter_map(|n| Note::parse(n)).collect();
+        self.set_scripted_notes(wanted);
+    }
+
+    /// Publish this frame's level and notes (the level first, so a note
+    /// starts the way it was played), then hand everything published this
+    /// frame to each subscriber in turn.
+    fn dispatch_events(&mut self) {
+        self.bus.publish(Event::Bellows(BellowsEvent {
+            a: (self.effective_bellows_a() * self.lid_gain).clamp(0.0, 1.0),
+            bend: self.bend_amount,
+            dt_sec: self.frame_dt_sec,
+        }));
+        self.bus.publish_notes(&self.pressed.active_notes());
+        let events = self.bus.take();
+
+        if let Some(m) = &mut self.midi_out {
+            if let Err(e) = events::deliver(m, &events) {
+                self.toasts.warn(format!("MIDI output closed: {e}"));
+                self.midi_out = None;
+            }
+        }
+        if let Some(rec) = &mut self.session_recorder {
+            if let Err(e) = events::deliver(rec, &events) {
+                self.toasts.error(e.to_string());
+                self.session_recorder = None;
+            }
+        }
+
+        // These can't fail: their error type is `Infallible`.
+        if let Some(rec) = &mut self.midi_recorder {
+            let Ok(()) = events::deliver(rec, &events);
+        }
+        let Ok(()) = events::deliver(&mut self.stats, &events);
+        let Ok(()) = events::deliver(self, &events);
+    }
+
+    /// A control surface: its knobs/faders can be bound to sliders (right-click
+    /// a slider → MIDI learn).
+    /// Serve a page that shows the bellows and notes on a phone or tablet and
+    /// can stop all notes or switch presets from across the stage.
+    fn ui_remote(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Remote control").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let toggled = ui.checkbox(&mut self.remote_config.enabled, "Serve").changed();
+                ui.add_enabled(
+                    self.remote.is_none(),
+                    egui::DragValue::new(&mut self.remote_config.port).prefix("port "),
+                );
+                if toggled {
+                    if self.remote_config.enabled {
+                        self.start_remote();
+                    } else {
+                        self.remote = None;
+                    }
+                }
+            });
+
+            if let Some(r) = &self.remote {
+                ui.label(format!("Open http://<this-mac>:{}/ on a phone or tablet", r.port()));
+                ui.label(format!("{} connected", r.client_count()));
+            }
+            if let Some(err) = &self.remote_error {
+                ui.colored_label(egui::Color32::RED, err.to_string());
+                if err.is_port_busy() {
+                    ui.label("Another program is using the port: close it or pick another port");
+                }
+            }
+        });
+    }
+
+    fn start_remote(&mut self) {
+        match RemoteServer::start(self.remote_config.port) {
+            Ok(server) => {
+                self.remote = Some(server);
+                self.remote_error = None;
+            }
+            Err(e) => {
+                self.remote_config.enabled = false;
+                self.remote_error = Some(e);
+            }
+        }
+    }
+
+    /// Stream the state to the remote pages and carry out their commands.
+    fn update_remote(&mut self) {
+        let Some(remote) = &mut self.remote else {
+            return;
+        };
+
+        let notes = self
+            .pressed
+            .active_notes()
+            .into_iter()
+            .map(|note| {
+                let level = self.audio.as_ref().and_then(|a| a.voice_level(&note));
+                NoteLevel {
+                    level: level.unwrap_or(0.0),
+                    note,
+                }
+            })
+            .collect();
+        let out = &self.bellows_out;
+        let state = RemoteState {
+            theta_deg: out.theta_deg,
+            speed: out.speed_smooth,
+            a_target: out.a_target,
+            a: out.a,
+            notes,
+            presets: self.presets.presets().iter().map(|p| p.name.clone()).collect(),
+            preset: self.preset_index,
+        };
+        remote.publish(&state, Instant::now());
+
+        for command in remote.commands() {
+            info!("remote control: {command:?}");
+            match command {
+                RemoteCommand::StopAll => self.stop_all_notes(),
+                RemoteCommand::Preset { index } => self.recall_preset(index),
+            }
+        }
+    }
+
+    fn ui_midi_in(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("MIDI input").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Scan").clicked() || self.midi_in_ports.is_none() {
+                    self.midi_in_ports = Some(midi::available_input_ports());
+                }
+
+                let ports = self.midi_in_ports.as_deref().unwrap_or_default();
+                egui::ComboBox::from_id_salt("midi_in_port")
+                    .selected_text(self.midi_in_port.as_str())
+                    .show_ui(ui, |ui| {
+                        for port in ports {
+                            ui.selectable_value(&mut self.midi_in_port, port.clone(), port);
+                        }
+                    });
+
+                match &self.midi_in {
+                    Some(m) => {
+                        let status = format!("Listening to {}", m.port_name());
+                        ui.colored_label(egui::Color32::GREEN, status);
+                        if ui.button("Disconnect").clicked() {
+                            self.midi_in = None;
+                            self.set_sustain(false);
+                        }
+                    }
+                    None => {
+                        let can_connect = !self.midi_in_port.is_empty();
+                        if ui.add_enabled(can_connect, egui::Button::new("Connect")).clicked() {
+                            match MidiIn::connect(&self.midi_in_port) {
+                                Ok(m) => {
+                                    self.midi_in = Some(m);
+                                    self.midi_error = None;
+                                }
+                                Err(e) => self.midi_error = Some(e),
+                            }
+                        }
+                    }
+                }
+            });
+
+            if self.sustain_down {
+                ui.colored_label(egui::Color32::LIGHT_BLUE, "Sustain pedal down");
+            }
+
+            if let Some(param) = self.midi_learn.learning() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Move a knob or fader to control {}...", param.label()),
+                    );
+                    if ui.button("Cancel").clicked() {
+                        self.midi_learn.cancel();
+                    }
+                });
+            }
+
+            let bound: Vec<String> = MidiParam::ALL
+                .into_iter()
+                .filter_map(|p| Some(format!("{} = CC {}", p.label(), self.midi_learn.cc_for(p)?)))
+                .collect();
+            if bound.is_empty() {
+                ui.label(
+                    "Right-click a slider (deadzone, vmax, gamma, master volume) to MIDI-learn it.",
+                );
+            } else {
+                ui.label(format!("Bound: {}", bound.join(", ")));
+            }
+        });
+    }
+
+    /// Apply incoming MIDI: the sustain pedal, then MIDI learn and bound sliders.
+    fn handle_midi_input(&mut self) {
+        let Some(m) = &mut self.midi_in else {
+            return;
+        };
+
+        for event in m.drain() {
+            match event {
+                MidiEvent::Control { cc: midi::CC_SUSTAIN, value } => self.set_sustain(value >= 64),
+                MidiEvent::PitchBend(amount) => self.meend.midi_bend = amount,
+                MidiEvent::ProgramChange(program) => self.select_bank(program as usize),
+                MidiEvent::Control { cc, value } => {
+                    if let Err(e) = self.midi_learn.finish_learning(cc) {
+                        self.midi_error = Some(e);
+                    }
+                    if let Some((param, v)) = self.midi_learn.target(cc, value) {
+                        let p = &mut self.bellows.params;
+                        match param {
+                            MidiParam::Deadzone => p.deadzone_deg_per_s = v,
+                            MidiParam::Vmax => p.vmax_deg_per_s = v,
+                            MidiParam::Gamma => p.gamma = v,
+                            MidiParam::MasterGain => self.master_gain = v,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Glide the meend toward its target and bend the sounding notes, with
+    /// the lid tremor's vibrato on top.
+    fn update_pitch_bend(&mut self) {
+        self.bend_amount = self.meend.update(self.frame_dt_sec);
+        let mut semitones = self.meend.semitones(self.bend_amount);
+        let tremor = &self.bellows.params.tremor;
+        if tremor.vibrato == VibratoTarget::Pitch {
+            semitones += self.bellows_out.vibrato * tremor.depth_cents / 100.0;
+        }
+        if let Some(a) = &mut self.audio {
+            a.set_bend_semitones(semitones);
+        }
+        if let Some(s) = &mut self.second {
+            s.engine.set_bend_semitones(semitones);
+        }
+    }
+
+    /// Sustain pedal: released notes keep sounding until pedal-up, in our
+    /// audio and on the MIDI output alike.
+    fn set_sustain(&mut self, down: bool) {
+        if self.sustain_down == down {
+            return;
+        }
+        self.sustain_down = down;
+
+        if let Some(a) = &mut self.audio {
+            a.set_sustain(down);
+        }
+        if let Some(s) = &mut self.second {
+            s.engine.set_sustain(down);
+        }
+        self.bus.publish(Event::Config(ConfigEvent::Sustain(down)));
+    }
+
+    /// Start recording sensor samples to `path`: the replay file, or the one
+    /// given with `--record` (which isn't saved as the replay file).
+    pub fn start_sensor_recording(&mut self, path: &Path) {
+        match SensorRecorder::create(path) {
+            Ok(rec) => self.sensor_recorder = Some(rec),
+            Err(e) => self.sensor_error = Some(e),
+        }
+    }
+
+    /// Record incoming samples to the replay file, so they can be played back
+    /// later with the "Replay recording" backend.
+    fn ui_sensor_recorder(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("recording file:");
+
+            let mut path_text = self.sensor_config.replay_path.display().to_string();
+            let edit = ui.add_enabled(
+                self.sensor_recorder.is_none(),
+                egui::TextEdit::singleline(&mut path_text),
+            );
+            if edit.changed() {
+                self.sensor_config.replay_path = path_text.into();
+            }
+
+            match &self.sensor_recorder {
+                None => {
+                    if ui.button("⏺ Record samples").clicked() {
+                        let path = self.sensor_config.replay_path.clone();
+                        self.start_sensor_recording(&path);
+                    }
+                }
+                Some(rec) => {
+                    let label = format!("⏹ Stop ({} samples)", rec.count());
+                    if ui.button(label).clicked() {
+                        if let Some(rec) = self.sensor_recorder.take() {
+                            if let Err(e) = rec.finish() {
+                                self.sensor_error = Some(e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn drain_sensor_messages(&mut self) {
+        // Every sensor keeps draining so its live value stays fresh in the list.
+        let was_live: Vec<bool> = self.sensors.iter().map(|s| s.live).collect();
+        let mut fresh: Vec<Vec<SensorSample>> =
+            self.sensors.iter_mut().map(|s| s.drain()).collect();
+
+        // A device that went away or came back is an event; failing to open
+        // in the first place only shows in the sensor list.
+        for (slot, was_live) in self.sensors.iter().zip(was_live) {
+            let event = match (&slot.error, was_live, slot.live) {
+                (Some(error), true, false) => SensorEvent::Lost {
+                    kind: slot.kind,
+                    error: Arc::clone(error),
+                },
+                (_, false, true) if slot.health.last_error.is_some() => {
+                    SensorEvent::Reconnected(slot.kind)
+                }
+                _ => continue,
+            };
+            self.bus.publish(Event::Sensor(event));
+        }
+
+        let blend = self.blend_sensor.filter(|&b| b != self.active_sensor);
+        let before = self.latest_sample.as_ref().map(|s| s.t);
+        self.latest_sample = self.effective_sample(blend);
+
+        // Record exactly what feeds the bellows: every sample of the selected
+        // sensor, or one blended sample per frame when blending.
+        let to_record: Vec<SensorSample> = match (blend, &self.latest_sample) {
+            (None, _) => fresh
+                .get_mut(self.active_sensor)
+                .map(std::mem::take)
+                .unwrap_or_default(),
+            (Some(_), Some(s)) if Some(s.t) != before => vec![s.clone()],
+            _ => Vec::new(),
+        };
+        if let Some(rec) = &mut self.sensor_recorder {
+            for sample in &to_record {
+                if let Err(e) = rec.push(sample) {
+                    self.sensor_error = Some(e);
+                    self.sensor_recorder = None;
+                    break;
+                }
+            }
+        }
+
+        self.last_sample_age_sec = if let Some(s) = &self.latest_sample {
+            (Instant::now() - s.t).as_secs_f32()
+        } else {
+            0.0
+        };
+    }
+
+    /// The sample that feeds the bellows: the selected sensor's, or the
+    /// selected sensor fused with `blend` (see [`SensorFusion`]).
+    fn effective_sample(&mut self, blend: Option<usize>) -> Option<SensorSample> {
+        let a = self.sensors.get(self.active_sensor)?.latest.clone()?;
+
+        let Some(b) = blend.and_then(|i| self.sensors.get(i)).and_then(|s| s.latest.clone())
+        else {
+            return Some(a);
+        };
+
+        // Only fuse when one of the streams actually moved on.
+        if let Some(prev) = &self.latest_sample {
+            if prev.t == a.t.max(b.t) {
+                return Some(prev.clone());
+            }
+        }
+        Some(self.fusion.fuse(&a, &b))
+    }
+
+    fn update_bellows(&mut self) {
+        match self.bellows_input {
+            // The demo brings its own lid movement, whatever the input.
+            _ if self.demo.is_some() => self.update_bellows_demo_input(),
+            _ if self.session_player.is_some() => self.update_bellows_replay_input(),
+            BellowsInput::Sensor => self.update_bellows_real_input(),
+            BellowsInput::Fake => self.update_bellows_fake_input(),
+            BellowsInput::ArrowKeys => self.update_bellows_arrow_input(),
+        }
+
+        if self.bellows_out.accent {
+            self.on_accent();
+        }
+    }
+
+    fn on_accent(&mut self) {
+        self.accent_count += 1;
+
+        match self.accent_action {
+            AccentAction::BoostOnly => {}
+            AccentAction::ToggleAirHold => {
+                self.air_hold = !self.air_hold;
+                self.air_hold_level = self.bellows_out.a;
+            }
+            AccentAction::StopAllNotes => {
+                self.release_mono();
+                if let Some(a) = &mut self.audio {
+                    a.stop_all();
+                }
+                if let Some(s) = &mut self.second {
+                    s.engine.stop_all();
+                }
+                self.bus.publish(Event::Config(ConfigEvent::StopAll));
+            }
+        }
+    }
+
+    fn update_bellows_fake_input(&mut self) {
+        let t = Instant::now() - self.start_time;
+        let theta = self.fake_trajectory().angle_at(t.as_secs_f32());
+
+        self.bellows_out = self.bellows.update(theta, t);
+    }
+
+    /// The demo's own lid movement, through the real bellows math.
+    fn update_bellows_demo_input(&mut self) {
+        let Some(demo) = self.demo else {
+            return;
+        };
+        let now = Instant::now();
+        self.bellows_out = self.bellows.update(demo.angle_at(now), now - self.start_time);
+    }
+
+    /// A replayed session's bellows level, as it was recorded (after the
+    /// bellows math, so nothing is recomputed).
+    fn update_bellows_replay_input(&mut self) {
+        let Some(player) = &self.session_player else {
+            return;
+        };
+        let a = player.bellows();
+        self.bellows_out = BellowsOutput { a, a_target: a, ..Default::default() };
+    }
+
+    fn update_bellows_arrow_input(&mut self) {
+        let (up, down) = self.arrows_held;
+        let theta = self.arrow_bellows.step(up, down, self.frame_dt_sec);
+
+        let t = Instant::now() - self.start_time;
+        self.bellows_out = self.bellows.update(theta, t);
+    }
+
+    fn update_bellows_real_input(&mut self) {
+        let Some(s) = &self.latest_sample else {
+            return;
+        };
+
+        // Samples can predate the app (the sensor thread starts first).
+        let t = s.t.saturating_duration_since(self.start_time);
+        self.bellows_out = self.bellows.update(s.theta_deg, t);
+    }
+
+    /// The trajectory used when fake input is on (sine or constant-speed pump).
+    fn fake_trajectory(&self) -> Trajectory {
+        if self.fake_pump_shape {
+            Trajectory::Pump {
+                center_deg: 0.0,
+                amplitude_deg: self.fake_amplitude_deg,
+                period_sec: 1.0 / self.fake_frequency_hz.max(0.01),
+            }
+        } else {
+            Trajectory::Sine {
+                center_deg: 0.0,
+                amplitude_deg: self.fake_amplitude_deg,
+                frequency_hz: self.fake_frequency_hz,
+            }
+        }
+    }
+
+    fn update_audio_from_bellows(&mut self) {
+        if !self.audio_enabled {
+            // If audio disabled, we force bellows to 0 volume.
+            if let Some(a) = &mut self.audio {
+                a.set_bellows(0.0);
+            }
+            if let Some(s) = &mut self.second {
+                s.engine.set_bellows(0.0);
+            }
+            return;
+        }
+
+        let mut a_now = self.effective_bellows_a() * self.lid_gain;
+        let tremor = &self.bellows.params.tremor;
+        if tremor.vibrato == VibratoTarget::Amplitude {
+            a_now *= (1.0 + self.bellows_out.vibrato * tremor.depth_gain).max(0.0);
+        }
+        if let Some(a) = &mut self.audio {
+            a.set_bellows(a_now);
+        }
+        if let Some(s) = &mut self.second {
+            s.engine.set_bellows(a_now);
+        }
+    }
+
+    /// Latency diagnostic, part 1: watch fresh bellows outputs for an onset.
+    /// Returns true if the probe should be armed once the new volume is applied.
+    fn observe_latency(&mut self) -> bool {
+        // Repeated frames without a new sample don't carry a new output.
+        if !self.latency_mode || self.bellows_out.dt_sec <= 0.0 {
+            return false;
+        }
+
+        let now = Instant::now();
+        let sensor_t = match &self.latest_sample {
+            Some(s) if self.bellows_input == BellowsInput::Sensor => s.t,
+            _ => now,
+        };
+
+        self.latency.observe(self.bellows_out.a_target, sensor_t, now)
+    }
+
+    /// Latency diagnostic, part 2: arm the audio probe and collect its answer.
+    fn finish_latency(&mut self, arm_probe: bool) {
+        let Some(a) = &self.audio else {
+            return;
+        };
+
+        if arm_probe {
+            a.latency_probe().arm();
+        }
+        self.latency.poll(a.latency_probe());
+    }
+
+    fn ui_latency(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.latency_mode, "Latency diagnostic");
+        if !self.latency_mode {
+            return;
+        }
+
+        ui.label("Hold a key, rest the lid, then start pumping.");
+
+        let ms = |d: std::time::Duration| d.as_secs_f32() * 1000.0;
+        let show = |ui: &mut egui::Ui, name: &str, s: LatencySample| {
+            ui.label(format!(
+                "{name}: sensor→bellows {:5.1} ms  bellows→audio {:5.1} ms  total {:5.1} ms",
+                ms(s.sensor_to_bellows),
+                ms(s.bellows_to_audio),
+                ms(s.total())
+            ));
+        };
+
+        if let Some(last) = self.latency.last() {
+            show(ui, "last", last);
+        }
+        if let Some(avg) = self.latency.average() {
+            show(ui, &format!("avg of {}", self.latency.count()), avg);
+        }
+        if self.latency.is_waiting_for_audio() {
+            ui.label("waiting for the audio thread (is a note held?)");
+        }
+
+        if ui.button("Clear measurements").clicked() {
+            self.latency.clear();
+        }
+    }
+
+    /// Closing the lid fades everything out and suspends audio; reopening resumes.
+    ///
+    /// Only real sensor input counts (the fake sine is centered on 0 deg).
+    fn update_lid_mute(&mut self) {
+        // Re-open a few degrees above the close threshold so it doesn't chatter.
+        const REOPEN_MARGIN_DEG: f32 = 5.0;
+
+        let angle = match &self.latest_sample {
+            Some(s) if self.lid_mute_enabled && self.bellows_input == BellowsInput::Sensor => {
+                Some(s.theta_deg)
+            }
+            _ => None,
+        };
+
+        self.lid_closed = match angle {
+            Some(theta) if self.lid_closed => theta < self.lid_close_deg + REOPEN_MARGIN_DEG,
+            Some(theta) => theta < self.lid_close_deg,
+            None => false,
+        };
+
+        // Ramp the lid gain toward 0 (closed) or 1 (open).
+        let target = if self.lid_closed { 0.0 } else { 1.0 };
+        let step = if self.lid_fade_ms <= 0.0 {
+            1.0
+        } else {
+            self.frame_dt_sec * 1000.0 / self.lid_fade_ms
+        };
+        self.lid_gain += (target - self.lid_gain).clamp(-step, step);
+
+        let engines = self.audio.iter_mut().chain(self.second.iter_mut().map(|s| &mut s.engine));
+        for a in engines {
+            if self.lid_closed && self.lid_gain <= 0.0 && !a.is_suspended() {
+                a.suspend();
+            } else if !self.lid_closed && a.is_suspended() {
+                a.resume();
+            }
+        }
+    }
+
+    /// Bellows amplitude after the infinite-air pedal and gesture looper are applied.
+    fn effective_bellows_a(&self) -> f32 {
+        if !self.air_hold {
+            return self
+                .gesture
+                .value(Instant::now())
+                .unwrap_or(self.bellows_out.a);
+        }
+
+        if self.air_hold_use_fixed {
+            self.air_hold_fixed_level
+        } else {
+            self.air_hold_level
+        }
+    }
+
+    fn ui_gesture_looper(&mut self, ui: &mut egui::Ui) {
+        ui.label("Bellows gesture looper:");
+
+        let now = Instant::now();
+
+        ui.horizontal(|ui| match self.gesture.state() {
+            LooperState::Idle => {
+                if ui.button("⏺ Record").clicked() {
+                    self.gesture.start_recording(now);
+                }
+                if self.gesture.has_loop() && ui.button("▶ Loop").clicked() {
+                    self.gesture.start_looping(now);
+                }
+            }
+            LooperState::Recording { started } => {
+                if ui.button("⏹ Stop + loop").clicked() {
+                    self.gesture.stop_recording_and_loop(now);
+                }
+                ui.label(format!("recording {:4.1} s", (now - started).as_secs_f32()));
+            }
+            LooperState::Looping { .. } => {
+                if ui.button("⏹ Stop loop").clicked() {
+                    self.gesture.stop();
+                }
+                ui.label(format!("looping {:4.1} s", self.gesture.length_sec()));
+            }
+        });
+    }
+
+    /// Tempo, tala and click level, with the cycle drawn as a beat wheel.
+    fn ui_metronome(&mut self, ui: &mut egui::Ui) {
+        let now = Instant::now();
+
+        ui.horizontal(|ui| {
+            ui.label("Metronome:");
+            if self.metronome.is_running() {
+                if ui.button("⏹ Stop").clicked() {
+                    self.metronome.stop();
+                }
+            } else if ui.button("▶ Start").clicked() {
+                self.metronome.start(now);
+            }
+        });
+
+        let mut bpm = self.metronome.bpm();
+        if ui.add(egui::Slider::new(&mut bpm, metronome::BPM_RANGE).text("BPM")).changed() {
+            self.metronome.set_bpm(bpm, now);
+        }
+
+        let mut tala = self.metronome.tala();
+        egui::ComboBox::from_label("tala")
+            .selected_text(tala.label())
+            .show_ui(ui, |ui| {
+                for t in Tala::ALL {
+                    ui.selectable_value(&mut tala, t, t.label());
+                }
+            });
+        if tala != self.metronome.tala() {
+            self.metronome.set_tala(tala, now);
+            if self.backing_playing.is_some() {
+                self.start_backing();
+            }
+        }
+
+        let mut volume = self.metronome.config().volume;
+        if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0).text("click volume")).changed() {
+            self.metronome.set_volume(volume);
+        }
+
+        ui.add(BeatWheel::new(tala, self.metronome.position(now)));
+    }
+
+    /// A tabla / lehra loop under the playing, kept on the metronome's tempo.
+    fn ui_backing(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Backing:");
+            let current = self.backing_tracks.get(self.backing_index).map_or("(none)", |t| &t.name);
+            egui::ComboBox::from_id_salt("backing_track")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (i, track) in self.backing_tracks.iter().enumerate() {
+                        ui.selectable_value(&mut self.backing_index, i, &track.name);
+                    }
+                });
+            if ui.button("Rescan").clicked() {
+                self.backing_tracks = backing::scan_backing(&self.backing.dir);
+                self.backing_index = 0;
+            }
+        });
+
+        if self.backing_tracks.is_empty() {
+            ui.label(format!(
+                "No loops in {}/ (name them like teental-80bpm.wav)",
+                self.backing.dir.display()
+            ));
+            return;
+        }
+
+        ui.horizontal(|ui| match &self.backing_playing {
+            Some((path, speed)) => {
+                let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+                let label = format!("{name} at {speed:.2}×");
+                if ui.button("⏹ Stop").clicked() {
+                    self.stop_backing();
+                }
+                ui.label(label);
+            }
+            None => {
+                if ui.button("▶ Play with metronome").clicked() {
+                    self.start_backing();
+                }
+            }
+        });
+
+        let volume = egui::Slider::new(&mut self.backing.volume, 0.0..=1.0).text("backing volume");
+        if ui.add(volume).changed() {
+            if let Some(a) = &mut self.audio {
+                a.set_backing_volume(self.backing.volume);
+            }
+        }
+    }
+
+    /// Start the selected backing track from its top, with the metronome
+    /// restarted on the same instant so sam lines up with the loop.
+    fn start_backing(&mut self) {
+        let Some(track) = self.backing_tracks.get(self.backing_index) else {
+            return;
+        };
+        let Some((path, speed)) = track.take_for(self.metronome.bpm()) else {
+            return;
+        };
+        let path = path.to_path_buf();
+        if !self.audio_enabled {
+            self.toasts.warn("Audio output is off");
+            return;
+        }
+        let Some(a) = &mut self.audio else {
+            return;
+        };
+
+        a.set_backing_volume(self.backing.volume);
+        match a.play_backing(&path, speed) {
+            Ok(()) => {
+                self.metronome.start(Instant::now());
+                self.backing_playing = Some((path, speed));
+            }
+            Err(e) => {
+                self.backing_playing = None;
+                report_audio_error(&mut self.toasts, &e);
+            }
+        }
+    }
+
+    fn stop_backing(&mut self) {
+        if let Some(a) = &mut self.audio {
+            a.stop_backing();
+        }
+        self.backing_playing = None;
+    }
+
+    /// A song to play along with, under the instrument: any audio file, played
+    /// once or looped, ducked while the harmonium is loud.
+    fn ui_practice(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Practice track:");
+            let mut path_text = self.practice.path.display().to_string();
+            if ui.text_edit_singleline(&mut path_text).changed() {
+                self.practice.path = path_text.into();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if self.practice_playing {
+                if ui.button("⏹ Stop").clicked() {
+                    self.stop_practice();
+                }
+                let label = if self.practice_paused { "▶ Resume" } else { "⏸ Pause" };
+                if ui.button(label).clicked() {
+                    self.practice_paused = !self.practice_paused;
+                    if let Some(a) = &mut self.audio {
+                        a.pause_practice(self.practice_paused);
+                    }
+                }
+            } else if ui.button("▶ Play").clicked() {
+                self.start_practice();
+            }
+            ui.checkbox(&mut self.practice.looped, "loop");
+        });
+
+        let volume = egui::Slider::new(&mut self.practice.volume, 0.0..=1.0).text("track volume");
+        if ui.add(volume).changed() {
+            if let Some(a) = &mut self.audio {
+                a.set_practice_volume(self.practice.volume);
+            }
+        }
+
+        ui.checkbox(&mut self.practice.duck, "Duck under the harmonium");
+        if self.practice.duck {
+            let p = &mut self.practice;
+            ui.add(
+                egui::Slider::new(&mut p.duck_threshold, 0.005..=0.5)
+                    .logarithmic(true)
+                    .text("starts at level (RMS)"),
+            );
+            ui.add(egui::Slider::new(&mut p.duck_depth, 0.0..=1.0).text("duck depth"));
+            ui.add(egui::Slider::new(&mut p.duck_attack_ms, 0.0..=500.0).text("dip (ms)"));
+            ui.add(egui::Slider::new(&mut p.duck_release_ms, 0.0..=3000.0).text("recover (ms)"));
+            let gain = self.ducker.gain();
+            ui.add(egui::ProgressBar::new(gain).text(format!("track at {:.0}%", gain * 100.0)));
+        }
+    }
+
+    fn start_practice(&mut self) {
+        if !self.audio_enabled {
+            self.toasts.warn("Audio output is off");
+            return;
+        }
+        let Some(a) = &mut self.audio else {
+            return;
+        };
+
+        a.set_practice_volume(self.practice.volume);
+        match a.play_practice(&self.practice.path, self.practice.looped) {
+            Ok(()) => {
+                self.practice_playing = true;
+                self.practice_paused = false;
+            }
+            Err(e) => report_audio_error(&mut self.toasts, &e),
+        }
+    }
+
+    fn stop_practice(&mut self) {
+        if let Some(a) = &mut self.audio {
+            a.stop_practice();
+        }
+        self.practice_playing = false;
+        self.practice_paused = false;
+    }
+
+    /// Duck the practice track under the instrument's level (both voices in
+    /// dual mode), from the voice meters, and notice when it has ended.
+    fn update_practice(&mut self) {
+        if !self.practice_playing {
+            return;
+        }
+        let Some(a) = &mut self.audio else {
+            self.practice_playing = false;
+            return;
+        };
+        if !a.practice_playing() {
+            self.practice_playing = false;
+            self.practice_paused = false;
+            return;
+        }
+
+        let second = self.second.as_ref().map_or(0.0, |s| s.engine.level());
+        let level = a.level().hypot(second);
+        let gain = self.ducker.update(level, self.frame_dt_sec, &self.practice);
+        a.set_practice_duck(gain);
+    }
+
+    /// Keep the backing on the metronome: follow tempo changes by speed, or
+    /// by switching to a take recorded nearer the new tempo. Stopping the
+    /// metronome stops the backing.
+    fn update_backing(&mut self) {
+        let Some((playing, speed)) = &self.backing_playing else {
+            return;
+        };
+        if !self.metronome.is_running() {
+            self.stop_backing();
+            return;
+        }
+
+        let Some(track) = self.backing_tracks.get(self.backing_index) else {
+            self.stop_backing();
+            return;
+        };
+        let Some((path, wanted)) = track.take_for(self.metronome.bpm()) else {
+            return;
+        };
+        if path != playing.as_path() {
+            self.start_backing();
+        } else if (wanted - speed).abs() > 1e-4 {
+            if let Some(a) = &self.audio {
+                a.set_backing_speed(wanted);
+            }
+            self.backing_playing = Some((path.to_path_buf(), wanted));
+        }
+    }
+
+    /// Pattern, rate (per metronome beat) and gate length of the arpeggiator.
+    fn ui_arpeggiator(&mut self, ui: &mut egui::Ui) {
+        let arp = &mut self.arpeggiator;
+        ui.horizontal(|ui| {
+            let toggled = ui.checkbox(&mut arp.enabled, "Arpeggiator").changed();
+            if toggled && arp.enabled && !self.metronome.is_running() {
+                self.metronome.start(Instant::now());
+            }
+            for pattern in ArpPattern::ALL {
+                ui.radio_value(&mut arp.pattern, pattern, pattern.label());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("rate:");
+            for rate in arpeggiator::RATES {
+                ui.radio_value(&mut arp.steps_per_beat, rate, format!("{rate}/beat"));
+            }
+        });
+        ui.add(egui::Slider::new(&mut arp.gate, 0.1..=1.0).text("gate"));
+    }
+
+    /// Of the held notes, let only the arpeggiator's current one be heard.
+    /// With the metronome stopped, held notes sound as usual.
+    fn update_arpeggiator(&mut self) {
+        let beats = self.metronome.beats_at(Instant::now()).filter(|_| self.arpeggiator.enabled);
+        let Some(a) = &mut self.audio else {
+            return;
+        };
+        let Some(beats) = beats else {
+            a.set_audible(None);
+            return;
+        };
+
+        let held: Vec<Note> =
+            self.pressed.active_notes().iter().filter_map(|n| Note::parse(n)).collect();
+        let note = self.arpeggiator.note_at(&held, beats).map(Note::name);
+        a.set_audible(Some(note.as_slice()));
+    }
+
+    /// Play an exercise in time with the metronome: the next note is shown
+    /// (and lit on the on-screen keyboard), and each one is scored.
+    fn ui_lesson(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Lesson:");
+            ui.add(egui::TextEdit::singleline(&mut self.lesson_path).desired_width(200.0));
+            if ui.button("▶ Start").clicked() {
+                self.start_lesson();
+            }
+            if self.lesson.is_some() && ui.button("⏹ Stop").clicked() {
+                self.lesson = None;
+            }
+        });
+
+        let Some(lesson) = &self.lesson else {
+            return;
+        };
+        let beats = self.metronome.beats_at(Instant::now());
+        let tonic = self.scale_lock.tonic;
+
+        if !lesson.exercise.title.is_empty() {
+            ui.strong(&lesson.exercise.title);
+        }
+        let big = |text: String| egui::RichText::new(text).size(22.0);
+        match (beats.and_then(|b| lesson.count_in_left(b)), lesson.next_step()) {
+            _ if beats.is_none() => {
+                ui.label("Metronome stopped");
+            }
+            (Some(left), _) => {
+                ui.label(big(format!("count-in … {left}")));
+            }
+            (None, Some(step)) => {
+                let text = format!("next: {}  ({})", step.note.sargam(tonic), step.note.name());
+                ui.label(big(text));
+            }
+            (None, None) => {
+                ui.label(big("done".to_string()));
+            }
+        }
+
+        let done = lesson.results.len() as f32 / lesson.exercise.steps.len() as f32;
+        ui.add(egui::ProgressBar::new(done).show_percentage());
+
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for (i, step) in lesson.exercise.steps.iter().enumerate() {
+                let (mark, color) = match lesson.results.get(i) {
+                    Some(StepResult::Hit(_)) => ("✔", egui::Color32::from_rgb(80, 180, 90)),
+                    Some(StepResult::Wrong) => ("✖", egui::Color32::from_rgb(220, 70, 60)),
+                    Some(StepResult::Missed) => ("○", egui::Color32::from_rgb(220, 70, 60)),
+                    None => ("·", ui.visuals().weak_text_color()),
+                };
+                ui.colored_label(color, mark).on_hover_text(step.note.sargam(tonic));
+            }
+        });
+
+        if lesson.results.is_empty() {
+            return;
+        }
+        let ms_per_beat = self.metronome.beat_sec() as f32 * 1000.0;
+        let mut score = format!("accuracy {:.0}%", lesson.accuracy() * 100.0);
+        if let (Some(error), Some(offset)) = (lesson.mean_error(), lesson.mean_offset()) {
+            score += &format!(", timing ±{:.0} ms", error * ms_per_beat);
+            if offset < -0.1 {
+                score += " (rushing)";
+            } else if offset > 0.1 {
+                score += " (dragging)";
+            }
+        }
+        ui.label(score);
+    }
+
+    /// Load the lesson file and start it after a count-in, starting the
+    /// metronome if it isn't running.
+    fn start_lesson(&mut self) {
+        match Exercise::load(&self.lesson_path, self.scale_lock.tonic) {
+            Ok(exercise) => {
+                let now = Instant::now();
+                if !self.metronome.is_running() {
+                    self.metronome.start(now);
+                }
+                let beats = self.metronome.beats_at(now).unwrap_or_default();
+                self.lesson = Some(Lesson::start(exercise, beats));
+            }
+            Err(e) => self.toasts.error(e),
+        }
+    }
+
+    fn update_lesson(&mut self) {
+        let Some(lesson) = self.lesson.as_mut().filter(|l| !l.is_done()) else {
+            return;
+        };
+        if let Some(beats) = self.metronome.beats_at(Instant::now()) {
+            lesson.update(beats, &self.pressed.active_notes());
+        }
+    }
+
+    /// Click on each beat that fell since the last frame.
+    fn update_metronome(&mut self) {
+        let Some(kind) = self.metronome.tick(Instant::now()) else {
+            return;
+        };
+        if !self.audio_enabled {
+            return;
+        }
+        if let Some(a) = &self.audio {
+            if let Err(e) = a.play_one_shot(self.metronome.click(kind)) {
+                self.metronome.stop();
+                report_audio_error(&mut self.toasts, &e);
+            }
+        }
+    }
+
+    /// Loop a played phrase and layer more over it, with per-layer mute and
+    /// undo.
+    fn ui_phrase_looper(&mut self, ui: &mut egui::Ui) {
+        ui.label("Phrase looper:");
+
+        let now = Instant::now();
+        let looper = &mut self.phrase_looper;
+
+        ui.horizontal(|ui| match looper.state() {
+            PhraseLooperState::Idle => {
+                if ui.button("⏺ Record").clicked() {
+                    looper.start_recording(now);
+                }
+                if looper.length_sec() > 0.0 && ui.button("▶ Loop").clicked() {
+                    looper.start_looping(now);
+                }
+            }
+            PhraseLooperState::Recording { .. } => {
+                if ui.button("⏹ Stop + loop").clicked() {
+                    looper.stop_recording_and_loop(now);
+                }
+                ui.label(format!("recording {:4.1} s", looper.position_sec(now)));
+            }
+            PhraseLooperState::Looping { .. } => {
+                if ui.button("⏺ Overdub").clicked() {
+                    looper.start_overdub();
+                }
+                if ui.button("⏹ Stop loop").clicked() {
+                    looper.stop(now);
+                }
+            }
+            PhraseLooperState::Overdubbing { .. } => {
+                if ui.button("✔ Keep layer").clicked() {
+                    looper.stop_overdub(now);
+                }
+                if ui.button("⏹ Stop loop").clicked() {
+                    looper.stop(now);
+                }
+            }
+        });
+
+        if looper.length_sec() <= 0.0 {
+            return;
+        }
+
+        let position = looper.position_sec(now) / looper.length_sec();
+        ui.add(egui::ProgressBar::new(position).text(format!("{:.1} s loop", looper.length_sec())));
+
+        let overdubbing = matches!(looper.state(), PhraseLooperState::Overdubbing { .. });
+        let count = looper.layers().len();
+        let mut mute = None;
+        for (i, layer) in looper.layers().iter().enumerate() {
+            ui.horizontal(|ui| {
+                let mut muted = layer.muted;
+                let label = if overdubbing && i + 1 == count {
+                    format!("layer {} (recording, {} notes)", i + 1, layer.note_count())
+                } else {
+                    format!("layer {} ({} notes)", i + 1, layer.note_count())
+                };
+                if ui.checkbox(&mut muted, "mute").changed() {
+                    mute = Some((i, muted));
+                }
+                ui.label(label);
+            });
+        }
+        if let Some((i, muted)) = mute {
+            looper.set_muted(i, muted);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("↶ Undo layer").clicked() {
+                looper.undo();
+            }
+            if ui.button("Clear").clicked() {
+                looper.clear();
+            }
+        });
+    }
+
+    fn ui_air_pedal(&mut self, ui: &mut egui::Ui) {
+        ui.label("Infinite air (hands-free sustain):");
+
+        let was_held = self.air_hold;
+        let pedal = self.air_pedal_key.map_or("none", |key| key.name());
+        ui.checkbox(&mut self.air_hold, format!("Hold air  [pedal key: {pedal}]"));
+        if self.air_hold && !was_held {
+            self.air_hold_level = self.bellows_out.a;
+        }
+
+        ui.checkbox(&mut self.air_hold_use_fixed, "Use fixed level instead of current");
+        if self.air_hold_use_fixed {
+            ui.add(egui::Slider::new(&mut self.air_hold_fixed_level, 0.0..=1.0).text("fixed air"));
+        }
+
+        let learn_label = if self.learning_pedal_key {
+            "Press a key (Esc: none)..."
+        } else {
+            "Assign pedal key"
+        };
+        if ui.button(learn_label).clicked() {
+            self.learning_pedal_key = true;
+        }
+    }
+
+    fn ui_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("input:");
+            for input in [BellowsInput::Sensor, BellowsInput::Fake, BellowsInput::ArrowKeys] {
+                if ui.radio_value(&mut self.bellows_input, input, input.label()).clicked() {
+                    // A manual choice ends the automatic fallback.
+                    self.fallback_reason = None;
+                }
+            }
+        });
+
+        match self.bellows_input {
+            BellowsInput::Sensor => {
+                ui.label("Using the real angle from the selected sensor.");
+            }
+            BellowsInput::Fake => {
+                ui.checkbox(&mut self.fake_pump_shape, "Constant-speed pump instead of sine");
+                ui.add(
+                    egui::Slider::new(&mut self.fake_frequency_hz, 0.05..=3.0)
+                        .text("fake frequency (Hz)"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.fake_amplitude_deg, 1.0..=80.0)
+                        .text("fake amplitude (deg)"),
+                );
+            }
+            BellowsInput::ArrowKeys => {
+                ui.label("Hold ↑ / ↓ to open / close a virtual lid; alternate them to pump.");
+                let ab = &mut self.arrow_bellows;
+                ui.add(
+                    egui::Slider::new(&mut ab.speed_deg_per_s, 10.0..=400.0)
+                        .text("pump speed (deg/s)"),
+                );
+                ui.add(egui::Slider::new(&mut ab.min_deg, 0.0..=180.0).text("closed at (deg)"));
+                ui.add(egui::Slider::new(&mut ab.max_deg, 0.0..=180.0).text("open at (deg)"));
+                ui.label(format!("virtual lid: {:6.2} deg", ab.angle_deg()));
+            }
+        }
+
+        ui.separator();
+        ui.label("Bellows tuning:");
+
+        let p = &mut self.bellows.params;
+        let (learn, midi_error) = (&mut self.midi_learn, &mut self.midi_error);
+
+        ui.horizontal(|ui| {
+            ui.label("mode:");
+            ui.radio_value(&mut p.mode, BellowsMode::Velocity, "velocity (pump)");
+            ui.radio_value(&mut p.mode, BellowsMode::Position, "position (hold)");
+            ui.radio_value(&mut p.mode, BellowsMode::Hybrid, "hybrid");
+        });
+
+        if p.mode != BellowsMode::Velocity {
+            ui.add(
+                egui::Slider::new(&mut p.position_min_deg, 0.0..=180.0).text("silent at (deg)"),
+            );
+            ui.add(
+                egui::Slider::new(&mut p.position_max_deg, 0.0..=180.0).text("full at (deg)"),
+            );
+        }
+
+        if p.mode == BellowsMode::Hybrid {
+            ui.add(
+                egui::Slider::new(&mut p.hybrid_blend, 0.0..=1.0)
+                    .text("blend (velocity → position)"),
+            );
+        }
+
+        ui.checkbox(&mut p.adaptive_deadzone, "Learn deadzone from sensor noise");
+        if p.adaptive_deadzone {
+            ui.add(
+                egui::Slider::new(&mut p.noise_floor_multiplier, 1.0..=10.0)
+                    .text("deadzone = noise floor ×"),
+            );
+        } else {
+            let deadzone = ui.add(
+                egui::Slider::new(&mut p.deadzone_deg_per_s, MidiParam::Deadzone.range())
+                    .text("deadzone (deg/s)"),
+            );
+            midi_learn_menu(&deadzone, learn, MidiParam::Deadzone, midi_error);
+        }
+        ui.add(
+            egui::Slider::new(&mut p.deadzone_release_deg_per_s, 0.0..=40.0)
+                .text("release below (deg/s)"),
+        );
+        ui.add(egui::Slider::new(&mut p.min_hold_ms, 0.0..=500.0).text("min hold (ms)"));
+        let vmax = ui.add(
+            egui::Slider::new(&mut p.vmax_deg_per_s, MidiParam::Vmax.range()).text("vmax (deg/s)"),
+        );
+        midi_learn_menu(&vmax, learn, MidiParam::Vmax, midi_error);
+        let gamma = ui.add(
+            egui::Slider::new(&mut p.gamma, MidiParam::Gamma.range()).text("gamma (curve)"),
+        );
+        midi_learn_menu(&gamma, learn, MidiParam::Gamma, midi_error);
+
+        ui.horizontal(|ui| {
+            ui.label("velocity:");
+            ui.radio_value(
+                &mut p.estimator,
+                VelocityEstimator::FiniteDifference,
+                "difference + EMA",
+            );
+            ui.radio_value(&mut p.estimator, VelocityEstimator::AlphaBeta, "alpha-beta");
+        });
+
+        match p.estimator {
+            VelocityEstimator::FiniteDifference => {
+                ui.add(
+                    egui::Slider::new(&mut p.ema_alpha_rise, 0.01..=0.5).text("EMA alpha (rising)"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut p.ema_alpha_fall, 0.01..=0.5)
+                        .text("EMA alpha (falling)"),
+                );
+            }
+            VelocityEstimator::AlphaBeta => {
+                ui.add(egui::Slider::new(&mut p.ab_alpha, 0.01..=1.0).text("tracker alpha (angle)"));
+                ui.add(
+                    egui::Slider::new(&mut p.ab_beta, 0.001..=0.5).text("tracker beta (velocity)"),
+                );
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("air:");
+            ui.radio_value(&mut p.air_model, AirModel::Envelope, "attack/release");
+            ui.radio_value(&mut p.air_model, AirModel::SpringMass, "spring-mass bellows");
+        });
+
+        match p.air_model {
+            AirModel::Envelope => {
+                ui.add(egui::Slider::new(&mut p.attack_ms, 0.0..=400.0).text("attack (ms)"));
+                ui.add(egui::Slider::new(&mut p.release_ms, 0.0..=1200.0).text("release (ms)"));
+            }
+            AirModel::SpringMass => {
+                ui.add(egui::Slider::new(&mut p.spring_mass, 0.1..=5.0).text("mass (inertia)"));
+                ui.add(
+                    egui::Slider::new(&mut p.spring_stiffness, 1.0..=300.0).text("stiffness"),
+                );
+                ui.add(egui::Slider::new(&mut p.spring_damping, 0.0..=60.0).text("damping"));
+                ui.add(
+                    egui::Slider::new(&mut p.spring_leak_per_s, 0.0..=10.0).text("leak (1/s)"),
+                );
+            }
+        }
+
+        ui.add(egui::Slider::new(&mut p.median_window, 1..=9).text("median window (samples)"));
+        ui.add(
+            egui::Slider::new(&mut p.drift_cutoff_hz, 0.0..=1.0)
+                .text("drift high-pass (Hz, 0 = off)"),
+        );
+        ui.add(
+            egui::Slider::new(&mut p.outlier_max_jump_deg, 0.0..=90.0)
+                .text("spike reject (deg, 0 = off)"),
+        );
+
+        ui.separator();
+        ui.label("Accents (lid flicks):");
+        ui.add(
+            egui::Slider::new(&mut p.accent_accel_deg_per_s2, 0.0..=20000.0)
+                .logarithmic(true)
+                .text("flick threshold (deg/s², 0 = off)"),
+        );
+        ui.add(egui::Slider::new(&mut p.accent_boost, 0.0..=1.0).text("accent boost"));
+        ui.add(egui::Slider::new(&mut p.accent_decay_ms, 0.0..=1000.0).text("accent decay (ms)"));
+        ui.add(
+            egui::Slider::new(&mut p.accent_cooldown_ms, 0.0..=1000.0).text("accent cooldown (ms)"),
+        );
+        egui::ComboBox::from_label("on accent")
+            .selected_text(format!("{:?}", self.accent_action))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.accent_action, AccentAction::BoostOnly, "boost only");
+                ui.selectable_value(
+                    &mut self.accent_action,
+                    AccentAction::ToggleAirHold,
+                    "toggle infinite air",
+                );
+                ui.selectable_value(
+                    &mut self.accent_action,
+                    AccentAction::StopAllNotes,
+                    "stop all notes",
+                );
+            });
+
+        ui.separator();
+        ui.label("Vibrato (lid tremor):");
+        let t = &mut self.bellows.params.tremor;
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut t.vibrato, VibratoTarget::Off, "off");
+            ui.radio_value(&mut t.vibrato, VibratoTarget::Pitch, "pitch");
+            ui.radio_value(&mut t.vibrato, VibratoTarget::Amplitude, "volume");
+        });
+        if t.vibrato != VibratoTarget::Off {
+            ui.add(egui::Slider::new(&mut t.min_hz, 1.0..=15.0).text("slowest shake (Hz)"));
+            ui.add(egui::Slider::new(&mut t.max_hz, 1.0..=20.0).text("fastest shake (Hz)"));
+            ui.add(egui::Slider::new(&mut t.min_deg, 0.0..=2.0).text("smallest swing (deg)"));
+            ui.add(egui::Slider::new(&mut t.max_deg, 0.5..=15.0).text("largest swing (deg)"));
+            match t.vibrato {
+                VibratoTarget::Pitch => {
+                    ui.add(
+                        egui::Slider::new(&mut t.depth_cents, 0.0..=100.0).text("depth (cents)"),
+                    );
+                }
+                _ => {
+                    ui.add(egui::Slider::new(&mut t.depth_gain, 0.0..=0.5).text("depth (volume)"));
+                }
+            }
+            ui.label(format!("tremor: {:+.2}", self.bellows_out.vibrato));
+        }
+
+        ui.separator();
+        self.ui_air_pedal(ui);
+
+        ui.separator();
+        self.ui_gesture_looper(ui);
+
+        ui.separator();
+        self.ui_phrase_looper(ui);
+
+        ui.separator();
+
+        if ui.button("Dry run (5 s at 60 Hz)").clicked() {
+            let p = &self.bellows.params;
+            let fake = simulation::simulate(p, &self.fake_trajectory(), 60.0, 5.0);
+            let stroke = simulation::pump_then_still(self.fake_amplitude_deg);
+            let stroke = simulation::simulate(p, &stroke, 60.0, 5.0);
+
+            self.dry_run = Some((simulation::summarize(&fake), simulation::summarize(&stroke)));
+        }
+        if let Some((fake, stroke)) = self.dry_run {
+            for (name, sum) in [("fake input", fake), ("pump + still", stroke)] {
+                let half = sum
+                    .time_to_half_peak_sec
+                    .map(|t| format!("{t:.2} s"))
+                    .unwrap_or_else(|| "never".to_string());
+                ui.label(format!(
+                    "{name}: peak a {:.3}   mean a {:.3}   half-peak after {half}",
+                    sum.peak_a, sum.mean_a
+                ));
+            }
+        }
+
+        if ui.button("Reset bellows state").clicked() {
+            self.bellows.reset();
+            self.bellows_out = BellowsOutput::default();
+        }
+    }
+
+    fn ui_live_values(&mut self, ui: &mut egui::Ui) {
+        let o = self.bellows_out;
+
+        ui.label(format!("theta_deg:        {:8.3}", o.theta_deg));
+        ui.label(format!("dt_sec:           {:8.4}", o.dt_sec));
+        ui.label(format!("omega_deg_per_s:  {:8.3}", o.omega_deg_per_s));
+        ui.label(format!("speed_raw:        {:8.3}", o.speed_raw));
+        ui.label(format!("speed_smooth:     {:8.3}", o.speed_smooth));
+        ui.label(format!("deadzone (used):  {:8.3}", self.bellows.effective_deadzone()));
+        if let Some(floor) = self.bellows.noise_floor() {
+            ui.label(format!("noise floor:      {:8.3}", floor));
+        }
+        ui.label(format!("a_target:         {:8.3}", o.a_target));
+        ui.label(format!("a (final):        {:8.3}", o.a));
+        ui.label(format!("accel_deg_per_s2: {:8.1}", o.accel_deg_per_s2));
+        ui.label(format!("accents:          {:8}", self.accent_count));
+        if self.air_hold {
+            ui.colored_label(
+                egui::Color32::LIGHT_BLUE,
+                format!("air held at:      {:8.3}", self.effective_bellows_a()),
+            );
+        } else if matches!(self.gesture.state(), LooperState::Looping { .. }) {
+            ui.colored_label(
+                egui::Color32::LIGHT_BLUE,
+                format!("air looped:       {:8.3}", self.effective_bellows_a()),
+            );
+        }
+
+        ui.separator();
+
+        self.ui_history_plots(ui);
+
+        ui.separator();
+
+        self.ui_latency(ui);
+
+        ui.separator();
+
+        ui.label("Bellows meter (A):");
+        ui.add(
+            egui::ProgressBar::new(o.a.clamp(0.0, 1.0))
+                .show_percentage()
+                .animate(true),
+        );
+        ui.add(LidView::new(o.theta_deg, o.a));
+    }
+
+    /// Scrolling plots of the bellows signals: numbers alone are hard to tune
+    /// deadzone and smoothing by.
+    fn ui_history_plots(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::Slider::new(&mut self.plot_window_sec, 2.0..=MAX_HISTORY_SEC)
+                .text("plot window (s)"),
+        );
+        let window = self.plot_window_sec;
+        let h = &self.history;
+        let deadzone = self.bellows.effective_deadzone() as f64;
+
+        Plot::new("motion_plot")
+            .height(120.0)
+            .legend(Legend::default())
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_boxed_zoom(false)
+            .show(ui, |plot| {
+                plot.line(Line::new(h.series(window, |o| o.theta_deg)).name("theta (deg)"));
+                plot.line(Line::new(h.series(window, |o| o.omega_deg_per_s)).name("omega"));
+                plot.line(Line::new(h.series(window, |o| o.speed_smooth)).name("speed_smooth"));
+                plot.hline(HLine::new(deadzone).name("deadzone"));
+            });
+
+        Plot::new("amplitude_plot")
+            .height(90.0)
+            .legend(Legend::default())
+            .include_y(0.0)
+            .include_y(1.0)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_boxed_zoom(false)
+            .show(ui, |plot| {
+                plot.line(Line::new(h.series(window, |o| o.a_target)).name("a_target"));
+                plot.line(Line::new(h.series(window, |o| o.a)).name("a"));
+            });
+    }
+
+    /// (Re)read the keymap from `keymap_path`, in the background.
+    fn load_keymap(&mut self) {
+        self.start_loading(true);
+    }
+
+    /// Read the keymap (if `keymap`) and preload the samples of its notes
+    /// from the banks playing, on the loader thread. Replaces a load still
+    /// running, keeping its keymap if it had one to bring.
+    fn start_loading(&mut self, keymap: bool) {
+        let keymap = keymap || self.awaiting_keymap;
+        let first = self.banks.get(self.bank_index).map_or(&self.samples_root, |b| &b.dir);
+        let mut sample_dirs = vec![first.clone()];
+        if let Some(s) = self.second.as_ref().filter(|s| s.sample_dir != *first) {
+            sample_dirs.push(s.sample_dir.clone());
+        }
+
+        self.awaiting_keymap = keymap;
+        self.loader = Some(Loader::start(LoadJob {
+            keymap_path: keymap.then(|| self.keymap_path.clone()),
+            keymap: if keymap { None } else { self.keymap.clone() },
+            sample_dirs,
+        }));
+    }
+
+    /// Take what the loader has read since last frame: the keymap, and the
+    /// samples for the engines. Progress and failures go out on the bus.
+    fn update_loader(&mut self) {
+        let Some(loader) = &mut self.loader else {
+            return;
+        };
+        let (msgs, finished) = loader.poll();
+        let (done, total, failed) = (loader.done, loader.total, loader.failed);
+        let moved = !msgs.is_empty();
+
+        for msg in msgs {
+            match msg {
+                LoadMsg::Keymap(result) if self.awaiting_keymap => {
+                    self.awaiting_keymap = false;
+                    self.apply_keymap(result);
+                }
+                LoadMsg::Sample { path, result: Ok(sample) } => {
+                    if let Some(s) = &mut self.second {
+                        s.engine.preload(path.clone(), Arc::clone(&sample));
+                    }
+                    if let Some(a) = &mut self.audio {
+                        a.preload(path, sample);
+                    }
+                }
+                LoadMsg::Sample { result: Err(e), .. } => {
+                    self.bus.publish(Event::Load(LoadEvent::Failed(Arc::new(e))));
+                }
+                LoadMsg::Mislabeled {
+                    path,
+                    named,
+                    detected,
+                } => {
+                    let (named, detected) = (named.name(), detected.name());
+                    self.bus.publish(Event::Load(LoadEvent::Mislabeled {
+                        path,
+                        named,
+                        detected,
+                    }));
+                }
+                LoadMsg::NoteMap { dir, map } => {
+                    let notes = map.len();
+                    if let Some(s) = &mut self.second {
+                        s.engine.set_note_map(dir.clone(), map.clone());
+                    }
+                    if let Some(a) = &mut self.audio {
+                        a.set_note_map(dir.clone(), map);
+                    }
+                    self.bus.publish(Event::Load(LoadEvent::Mapped { dir, notes }));
+                }
+                _ => {}
+            }
+        }
+
+        if finished {
+            self.loader = None;
+            self.awaiting_keymap = false;
+            let loaded = done - failed;
+            self.bus.publish(Event::Load(LoadEvent::Finished { loaded, failed }));
+        } else if moved && total > 0 {
+            self.bus.publish(Event::Load(LoadEvent::Progress { done, total }));
+        }
+    }
+
+    /// A keymap read by the loader.
+    fn apply_keymap(&mut self, result: Result<KeyMap, KeymapError>) {
+        match result {
+            Ok(km) => {
+                self.keymap = Some(km);
+                self.keymap_error = None;
+            }
+            // A mistake in the file keeps the keymap already playing.
+            Err(e) if e.is_invalid() && self.keymap.is_some() => {
+                self.toasts.error(format!("Keymap not reloaded, keeping the previous one: {e}"));
+                self.keymap_error = Some(e);
+            }
+            Err(e) => {
+                self.toasts.error(format!("Keymap not loaded: {e}"));
+                self.keymap = None;
+                self.keymap_error = Some(e);
+            }
+        }
+        self.keymap_dirty = false;
+    }
+
+    /// Write the keymap back to `keymap_path` (after edits that failed to).
+    fn save_keymap(&mut self) {
+        let Some(km) = &self.keymap else {
+            return;
+        };
+        self.keymap_error = km.save_to_file(&self.keymap_path).err();
+        self.keymap_dirty = self.keymap_error.is_some();
+    }
+
+    /// Which keymap is in use, or what went wrong loading it.
+    fn ui_keymap_state(&self, ui: &mut egui::Ui) {
+        if self.awaiting_keymap {
+            ui.label(format!("Loading {}…", self.keymap_path));
+        } else if let Some(err) = &self.keymap_error {
+            ui.colored_label(egui::Color32::RED, format!("Keymap error: {err}"));
+        } else if self.keymap.as_ref().is_some_and(|km| km.is_builtin()) {
+            ui.colored_label(
+                egui::Color32::GREEN,
+                format!("No {} found, using the built-in default keymap", self.keymap_path),
+            );
+        } else if self.keymap.is_some() {
+            ui.colored_label(egui::Color32::GREEN, format!("{} loaded OK", self.keymap_path));
+        } else {
+            ui.colored_label(egui::Color32::YELLOW, "No keymap loaded");
+        }
+    }
+
+    /// Load, reload or drop the hook script.
+    fn ui_script(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Script hooks").show(ui, |ui| {
+            let (mut load, mut unload) = (false, false);
+            ui.horizontal(|ui| {
+                ui.label("script:");
+                ui.add(egui::TextEdit::singleline(&mut self.script_path).desired_width(160.0));
+                let label = if self.script.is_some() { "Reload" } else { "Load" };
+                load = ui.button(label).clicked();
+                unload = self.script.is_some() && ui.button("Unload").clicked();
+            });
+
+            match &self.script {
+                Some(script) if script.hooks().is_empty() => {
+                    ui.label(format!("{}: no hooks running", script.path().display()));
+                }
+                Some(script) => {
+                    let hooks = script.hooks().join(", ");
+                    ui.label(format!("{}: {hooks}", script.path().display()));
+                }
+                None => {
+                    ui.label("No script loaded");
+                }
+            }
+
+            if load {
+                match ScriptHooks::load(&self.script_path) {
+                    Ok(script) => {
+                        self.unload_script();
+                        self.script = Some(script);
+                    }
+                    Err(e) => self.toasts.error(e),
+                }
+            } else if unload {
+                self.unload_script();
+            }
+        });
+    }
+
+    /// Drop the script and let go of the notes it was holding.
+    fn unload_script(&mut self) {
+        self.script = None;
+        self.script_seen_notes.clear();
+        self.set_script_notes(Vec::new());
+    }
+
+    /// Tell the script which notes started and stopped and what the bellows
+    /// did this frame (it may change the level), then hold what it plays.
+    fn update_script(&mut self) {
+        let Some(script) = &mut self.script else {
+            return;
+        };
+
+        // Its own notes don't call its hooks, so a hook that plays a note
+        // can't set itself off again.
+        let own: Vec<NoteName> = self.script_notes.iter().map(|n| n.name()).collect();
+        let active: Vec<NoteName> =
+            self.pressed.active_notes().into_iter().filter(|n| !own.contains(n)).collect();
+        for note in active.iter().filter(|n| !self.script_seen_notes.contains(n)) {
+            script.on_note_on(note);
+        }
+        for note in self.script_seen_notes.iter().filter(|n| !active.contains(n)) {
+            script.on_note_off(note);
+        }
+        self.script_seen_notes = active;
+
+        if let Some(a) = script.on_bellows_update(self.bellows_out.a) {
+            self.bellows_out.a = a;
+        }
+        if let Some(e) = script.take_error() {
+            self.toasts.error(e);
+        }
+
+        // A drone script plays its Sa as c: it moves with retune like a key.
+        let wanted = script
+            .held_notes()
+            .iter()
+            .filter_map(|n| Note::parse(&self.scale_lock.retune(n)?))
+            .collect();
+        self.set_script_notes(wanted);
+    }
+
+    fn ui_keymap_status(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Keymap");
+        self.ui_keymap_state(ui);
+
+        let (mut reload, mut save) = (false, false);
+        ui.horizontal(|ui| {
+            ui.label("file:");
+            ui.add(egui::TextEdit::singleline(&mut self.keymap_path).desired_width(140.0));
+            reload = ui.button("Reload").clicked();
+            if self.keymap_dirty && ui.button("Save").on_hover_text("Unsaved edits").clicked() {
+                save = true;
+            }
+
+            // Export writes a copy next to the current file; it is only read
+            // on startup if no file earlier in the list exists.
+            let Some(km) = &self.keymap else {
+                return;
+            };
+            ui.label("Export as:");
+            for format in KeymapFormat::ALL {
+                let path = format!("{KEYMAP_STEM}.{}", format.extension());
+                if path != self.keymap_path
+                    && ui.button(format.label()).on_hover_text(format!("Write {path}")).clicked()
+                {
+                    self.keymap_error = km.save_to_file(&path).err();
+                }
+            }
+        });
+        if reload {
+            self.load_keymap();
+        }
+        if save {
+            self.save_keymap();
+        }
+
+        if let Some(km) = self.keymap.as_ref().filter(|km| km.zones().len() > 1) {
+            let zones: Vec<String> = km
+                .zones()
+                .iter()
+                .map(|z| {
+                    let latch = if z.latch { "latched, " } else { "" };
+                    let second = if z.instrument == 2 { "instrument 2, " } else { "" };
+                    format!("{} ({latch}{second}gain {:.2})", z.name, z.gain)
+                })
+                .collect();
+            ui.label(format!("Zones: {}", zones.join(", ")));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Octave shift:");
+            let down = self.shortcut_label("Octave down", Action::OctaveDown);
+            if ui.button("−").on_hover_text(down).clicked() {
+                self.octave_shift = shift_octave(self.octave_shift, -1);
+            }
+            ui.label(format!("{:+}", self.octave_shift));
+            let up = self.shortcut_label("Octave up", Action::OctaveUp);
+            if ui.button("+").on_hover_text(up).clicked() {
+                self.octave_shift = shift_octave(self.octave_shift, 1);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Meend:");
+            ui.add(
+                egui::Slider::new(&mut self.meend.range_semitones, 0.0..=12.0)
+                    .text("bend range (semitones)"),
+            );
+            ui.add(egui::Slider::new(&mut self.meend.glide_ms, 20.0..=2000.0).text("glide (ms)"))
+                .on_hover_text(format!("Hold {} to glide up", GLIDE_KEY.symbol_or_name()));
+            ui.label(format!("{:+.2} st", self.meend.semitones(self.bend_amount)));
+        });
+
+        ui.horizontal(|ui| {
+            let mono = ui.checkbox(&mut self.mono, "Mono (legato)").on_hover_text(
+                "One melody note at a time: the next key glides the note there instead of \
+                 starting a new one (gamaka). Drone zones and sticky keys play as usual",
+            );
+            if mono.changed() && !self.mono {
+                self.release_mono();
+            }
+            let portamento = egui::Slider::new(&mut self.portamento_ms, 0.0..=1000.0)
+                .text("portamento (ms)");
+            ui.add_enabled(self.mono, portamento);
+        });
+
+        ui.checkbox(&mut self.sticky_keys, "Sticky keys")
+            .on_hover_text("Each press toggles the note on or off, for drones and long alap");
+
+        self.ui_scale_lock(ui);
+        self.ui_remap(ui);
+        self.ui_layout_generator(ui);
+    }
+
+    /// Build a whole piano-style keymap from a template and a starting note.
+    fn ui_layout_generator(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Generate layout").show(ui, |ui| self.ui_layout_controls(ui));
+    }
+
+    /// Template, lowest note and Generate (also used by the setup wizard).
+    fn ui_layout_controls(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_salt("layout_template")
+            .selected_text(self.layout_template.label())
+            .show_ui(ui, |ui| {
+                for t in LayoutTemplate::ALL {
+                    ui.selectable_value(&mut self.layout_template, t, t.label());
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.label("lowest key:");
+            ui.add(
+                egui::DragValue::new(&mut self.layout_start_midi)
+                    .range(0..=127)
+                    .speed(0.2)
+                    .custom_formatter(|v, _| {
+                        Note::from_midi(v as i32).map(Note::name).unwrap_or_default()
+                    })
+                    .custom_parser(|s| Note::parse(s).map(|n| n.midi() as f64)),
+            );
+
+            if ui
+                .button("Generate")
+                .on_hover_text(format!("Replaces the current keymap and saves {}", self.keymap_path))
+                .clicked()
+            {
+                if let Some(start) = Note::from_midi(self.layout_start_midi) {
+                    self.keymap = Some(layout::generate(self.layout_template, start));
+                    self.save_keymap();
+                }
+            }
+        });
+    }
+
+    /// "Learn" remapping: click a note, press a key, the binding is saved.
+    fn ui_remap(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Remap keys").show(ui, |ui| {
+            let Some(km) = &self.keymap else {
+                ui.label("Load a keymap first.");
+                return;
+            };
+
+            if let Some(c) = &self.learn_conflict {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!("Key '{}' already plays {}.", c.key, c.current.join(" + ")),
+                );
+                let (mut replace, mut cancel) = (false, false);
+                ui.horizontal(|ui| {
+                    replace = ui.button(format!("Replace with {}", c.note)).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+
+                if replace {
+                    if let Some(c) = self.learn_conflict.take() {
+                        self.bind_learned_key(c.key, c.note);
+                    }
+                } else if cancel {
+                    self.learn_conflict = None;
+                }
+                return;
+            }
+
+            match &self.learning_note {
+                Some(note) => {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Press a key for {note} (Esc cancels)"),
+                    );
+                }
+                None => {
+                    ui.label("Click a note, then press the key that should play it.");
+                }
+            }
+
+            let notes = match &self.audio {
+                Some(a) => a.available_notes(),
+                None => km.all_notes(),
+            };
+
+            let mut clicked = None;
+            ui.horizontal_wrapped(|ui| {
+                for note in notes {
+                    let keys: Vec<String> =
+                        km.keys_for_note(&note).iter().map(|k| k.to_string()).collect();
+                    let text = if keys.is_empty() {
+                        note.clone()
+                    } else {
+                        format!("{note} [{}]", keys.join(" "))
+                    };
+
+                    let selected = self.learning_note.as_deref() == Some(note.as_str());
+                    if ui.selectable_label(selected, text).clicked() {
+                        clicked = Some(note);
+                    }
+                }
+            });
+            if clicked.is_some() {
+                self.learning_note = clicked;
+            }
+        });
+    }
+
+    /// A key was pressed while learning: bind it, or ask first if it's taken.
+    fn learn_key(&mut self, key: KeyId) {
+        let (Some(km), Some(note)) = (&self.keymap, self.learning_note.take()) else {
+            return;
+        };
+
+        match km.notes_for(key).map(|n| n.to_vec()) {
+            Some(current) if current != [note.clone()] => {
+                self.learn_conflict = Some(LearnConflict { key, note, current });
+            }
+            _ => self.bind_learned_key(key, note),
+        }
+    }
+
+    /// Record the binding and write the keymap back to its file.
+    fn bind_learned_key(&mut self, key: KeyId, note: NoteName) {
+        let Some(km) = &mut self.keymap else {
+            return;
+        };
+
+        km.bind(key, vec![note]);
+        self.save_keymap();
+    }
+
+    fn ui_scale_lock(&mut self, ui: &mut egui::Ui) {
+        let lock = &mut self.scale_lock;
+
+        // One Sa for everything: the scale lock, the sargam labels and, with
+        // retune, the pitch of every key.
+        ui.horizontal(|ui| {
+            ui.label("Sa:");
+            egui::ComboBox::from_id_salt("scale_lock_tonic")
+                .selected_text(PITCH_NAMES[lock.tonic as usize].to_uppercase())
+                .show_ui(ui, |ui| {
+                    for (pc, name) in PITCH_NAMES.iter().enumerate() {
+                        ui.selectable_value(&mut lock.tonic, pc as u8, name.to_uppercase());
+                    }
+                });
+            ui.checkbox(&mut lock.retune, "Retune").on_hover_text(
+                "Move the whole instrument to Sa: the keys and drone play as if written \
+                 with Sa = C (notes already sounding keep their pitch)",
+            );
+            if lock.retune_semitones() != 0 {
+                ui.label(format!("({:+} semitones)", lock.retune_semitones()));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut lock.enabled, "Scale lock:");
+
+            egui::ComboBox::from_id_salt("scale_lock_scale")
+                .selected_text(lock.scale.label())
+                .show_ui(ui, |ui| {
+                    for sc in Scale::ALL {
+                        ui.selectable_value(&mut lock.scale, sc, sc.label());
+                    }
+                });
+
+            ui.radio_value(&mut lock.mode, LockMode::Filter, "mute others");
+            ui.radio_value(&mut lock.mode, LockMode::Snap, "snap to scale");
+        });
+
+        if lock.scale == Scale::Custom {
+            ui.horizontal(|ui| {
+                ui.label("notes above Sa:");
+                for (i, on) in lock.custom.iter_mut().enumerate() {
+                    let name = PITCH_NAMES[(i + lock.tonic as usize) % 12];
+                    ui.checkbox(on, format!("{} ({})", name.to_uppercase(), SARGAM_NAMES[i]));
+                }
+            });
+        }
+    }
+
+    fn ui_active_notes(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Active notes");
+        let notes = self.pressed.active_notes();
+
+        if notes.is_empty() {
+            ui.label("None (press keys like z, x, c, v, ...)");
+        } else {
+            // Each note's level in the output, so a missing or silent sample
+            // stands out.
+            ui.horizontal_wrapped(|ui| {
+                for note in &notes {
+                    ui.label(self.with_sargam(note));
+                    match self.audio.as_ref().and_then(|a| a.voice_level(note)) {
+                        Some(rms) => {
+                            let bar = egui::ProgressBar::new(meter::meter_fraction(rms))
+                                .desired_width(48.0)
+                                .desired_height(8.0);
+                            ui.add(bar).on_hover_text(format!("{:.1} dBFS", 20.0 * rms.log10()));
+                        }
+                        None => {
+                            ui.colored_label(egui::Color32::RED, "no voice");
+                        }
+                    }
+                    ui.add_space(8.0);
+                }
+            });
+        }
+
+        let drones = self.pressed.latched_notes();
+        if !drones.is_empty() {
+            let labels: Vec<String> = drones.iter().map(|n| self.with_sargam(n)).collect();
+            ui.horizontal(|ui| {
+                ui.label(format!("Latched: {}", labels.join("  ")));
+                if ui.button("Release latched").clicked() {
+                    let notes = self.pressed.release_latched();
+                    chord_off(&mut self.audio, &mut self.second, &notes);
+                }
+            });
+        }
+    }
+
+    /// What each sounding note should be (equal temperament from a4 = 440 Hz,
+    /// plus its key's cents and the meend bend) next to the pitch actually
+    /// found in the output, to check sample tuning and microtonal keys.
+    fn ui_tuner(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Tuner").show(ui, |ui| {
+            let Some(a) = &self.audio else {
+                ui.label("Audio engine not available");
+                return;
+            };
+
+            let notes = self.pressed.active_notes();
+            if notes.is_empty() {
+                ui.label("Play a note.");
+            }
+            let bend_cents = self.meend.semitones(self.bend_amount) * 100.0;
+            egui::Grid::new("tuner").num_columns(3).show(ui, |ui| {
+                for name in &notes {
+                    let Some(note) = Note::parse(name) else {
+                        continue;
+                    };
+                    let cents = a.voice_cents(name).unwrap_or(0.0) + bend_cents;
+                    ui.label(format!("{name} ({})", note.sargam(self.scale_lock.tonic)));
+                    ui.label(format!("{:.2} Hz", note.frequency_hz(cents)));
+                    if cents.abs() >= 0.5 {
+                        ui.label(format!("{cents:+.0} ¢"));
+                    } else {
+                        ui.label("");
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if self.tuner_checked.elapsed().as_secs_f32() >= TUNER_INTERVAL_SEC {
+                self.tuner_checked = Instant::now();
+                self.tuner_hz = a
+                    .output_snapshot()
+                    .and_then(|(frames, rate)| tuner::detect_fundamental(&frames, rate));
+            }
+            match self.tuner_hz.and_then(|hz| Some((hz, Note::nearest(hz)?))) {
+                Some((hz, (note, off))) => {
+                    ui.label(format!("Output: {hz:.2} Hz, {note} {off:+.0} ¢"));
+                }
+                None => {
+                    ui.label("Output: no clear pitch");
+                }
+            }
+        });
+    }
+
+    /// "c#3 (re)": the western name with its swara relative to the selected Sa.
+    fn with_sargam(&self, note: &str) -> String {
+        match Note::parse(note) {
+            Some(n) => format!("{note} ({})", n.sargam(self.scale_lock.tonic)),
+            None => note.to_string(),
+        }
+    }
+
+    /// A clickable keyboard that lights up the sounding notes. Plays through
+    /// `PressedKeys` like a real key, so aliases and drones still add up.
+    fn ui_onscreen_keyboard(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("On-screen keyboard")
+            .default_open(true)
+            .show(ui, |ui| {
+                let (lo, hi) = self.onscreen_range();
+                let keys = onscreen_key_rects(ui, lo, hi);
+                let Some((rect, response, keys)) = keys else {
+                    return;
+                };
+
+                // Black keys sit on top, so they win the hit test.
+                let target = response
+                    .interact_pointer_pos()
+                    .filter(|_| response.is_pointer_button_down_on())
+                    .and_then(|pos| {
+                        keys.iter()
+                            .rev()
+                            .find(|(_, r)| r.contains(pos))
+                            .map(|(note, _)| *note)
+                    });
+                self.set_screen_key(target);
+
+                // Held notes glow orange, latched (sticky / drone) ones blue,
+                // and a lesson's next note green.
+                let active = self.pressed.active_notes();
+                let latched = self.pressed.latched_notes();
+                let lesson_next = self.lesson.as_ref().and_then(|l| l.next_step()).map(|s| s.note);
+                let painter = ui.painter_at(rect);
+                for (note, r) in &keys {
+                    let name = note.name();
+                    let fill = if latched.contains(&name) {
+                        egui::Color32::from_rgb(90, 150, 230)
+                    } else if active.contains(&name) {
+                        egui::Color32::from_rgb(240, 170, 60)
+                    } else if lesson_next == Some(*note) {
+                        egui::Color32::from_rgb(110, 200, 120)
+                    } else if note.is_white() {
+                        egui::Color32::from_gray(235)
+                    } else {
+                        egui::Color32::from_gray(30)
+                    };
+                    painter.rect(*r, 2.0, fill, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY));
+
+                    let label_color = if note.is_white() {
+                        egui::Color32::DARK_GRAY
+                    } else {
+                        egui::Color32::LIGHT_GRAY
+                    };
+                    painter.text(
+                        r.center_bottom() - egui::vec2(0.0, 4.0),
+                        egui::Align2::CENTER_BOTTOM,
+                        note.sargam(self.scale_lock.tonic),
+                        egui::FontId::proportional(10.0),
+                        label_color,
+                    );
+                    if note.is_white() && note.pitch_class() == 0 {
+                        painter.text(
+                            r.center_bottom() - egui::vec2(0.0, 18.0),
+                            egui::Align2::CENTER_BOTTOM,
+                            name,
+                            egui::FontId::proportional(10.0),
+                            label_color,
+                        );
+                    }
+                }
+            });
+    }
+
+    /// Notes shown on the on-screen keyboard: the keymap's range widened to
+    /// whole octaves (c2–b4 without a keymap).
+    fn onscreen_range(&self) -> (Note, Note) {
+        let notes: Vec<Note> = self
+            .keymap
+            .iter()
+            .flat_map(|km| km.all_notes())
+            .filter_map(|n| Note::parse(&n))
+            .collect();
+
+        let lo = notes.iter().min().map_or(36, |n| n.midi() as i32 - n.pitch_class() as i32);
+        let hi = notes.iter().max().map_or(71, |n| n.midi() as i32 + 11 - n.pitch_class() as i32);
+        let note = |m: i32| Note::from_midi(m.clamp(0, 127)).expect("clamped to the MIDI range");
+        (note(lo), note(hi))
+    }
+
+    /// Move the on-screen press to `note` (None = released).
+    fn set_screen_key(&mut self, note: Option<Note>) {
+        if self.screen_key == note {
+            return;
+        }
+
+        if let Some(old) = self.screen_key.take() {
+            let notes = self.pressed.key_up(KeyId::OnScreen(old.midi()));
+            if let Some(a) = &mut self.audio {
+                a.chord_off(&notes);
+            }
+        }
+
+        if let Some(new) = note {
+            self.screen_key = Some(new);
+            let notes = self.pressed.key_down(KeyId::OnScreen(new.midi()), vec![new.name()]);
+            if !notes.is_empty() && self.audio_enabled {
+                if let Some(a) = &mut self.audio {
+                    if let Err(e) = a.chord_on(&notes, 1.0, 0.0) {
+                        report_audio_error(&mut self.toasts, &e);
+                    }
+                }
+            }
         }
     }
 
     fn handle_keyboard(&mut self, ctx: &egui::Context) {
         let keymap = self.keymap.as_ref();
+        let mut learned_key = None;
+        let mut preset_key = None;
+        let mut actions = Vec::new();
+
+        self.arrows_held = ctx.input(|i| {
+            (
+                i.key_down(egui::Key::ArrowUp),
+                i.key_down(egui::Key::ArrowDown),
+            )
+        });
+        self.meend.glide_held = ctx.input(|i| i.key_down(GLIDE_KEY));
 
         ctx.input(|input| {
             for event in &input.events {
                 if let egui::Event::Key {
                     key,
+                    physical_key,
                     pressed,
                     repeat,
-                    modifiers: _,
+                    modifiers,
                     ..
                 } = event
                 {
@@ -340,36 +4995,466 @@ impl HarmoniumApp {
                         continue;
                     }
 
-                    if let Some(ch) = egui_key_to_char(*key) {
+                    // Remapping: the next pressed key gets bound to the chosen note.
+                    if self.learning_note.is_some() {
                         if *pressed {
-                            // Key down
-                            if let Some(km) = keymap {
-                                if let Some(note) = self.pressed.key_down(ch, km) {
-                                    // Start audio note if possible
-                                    if self.audio_enabled {
-                                        if let Some(a) = &mut self.audio {
-                                            if let Err(e) = a.note_on(&note) {
-                                                self.audio_error = Some(e);
-                                            }
-                                        }
-                                    }
+                            if *key == egui::Key::Escape {
+                                self.learning_note = None;
+                            } else {
+                                let physical = physical_key.unwrap_or(*key);
+                                learned_key = egui_key_to_char(*key)
+                                    .map(KeyId::Char)
+                                    .or_else(|| physical_key_of(physical).map(KeyId::Physical));
+                            }
+                        }
+                        continue;
+                    }
+
+                    // Pedal key assignment: the next pressed key becomes the
+                    // pedal (Escape leaves it unassigned).
+                    if self.learning_pedal_key {
+                        if *pressed {
+                            self.air_pedal_key = Some(*key).filter(|k| *k != egui::Key::Escape);
+                            self.learning_pedal_key = false;
+                        }
+                        continue;
+                    }
+
+                    // Shortcut assignment: the next pressed key triggers the action.
+                    if let Some(action) = self.learning_shortcut {
+                        if *pressed {
+                            self.shortcuts.bind(action, *key);
+                            self.learning_shortcut = None;
+                        }
+                        continue;
+                    }
+
+                    // Alt+1..9 recalls a preset (by physical key: Option+digit
+                    // types a symbol on a Mac).
+                    let digit = physical_key.unwrap_or(*key);
+                    if let Some(i) = preset_for_key(digit).filter(|_| modifiers.alt) {
+                        if *pressed {
+                            preset_key = Some(i);
+                        }
+                        continue;
+                    }
+
+                    // Shortcut keys run their action and never play a note.
+                    if let Some(action) = self.shortcuts.action_for(*key) {
+                        if *pressed {
+                            actions.push(action);
+                        }
+                        continue;
+                    }
+
+                    // The pedal key toggles infinite air and never plays a note.
+                    if Some(*key) == self.air_pedal_key {
+                        if *pressed {
+                            self.air_hold = !self.air_hold;
+                            self.air_hold_level = self.bellows_out.a;
+                        }
+                        continue;
+                    }
+
+                    let ch = egui_key_to_char(*key);
+                    let physical = physical_key.and_then(physical_key_of);
+                    if *pressed {
+                        // Key down: the script's map_key has first say, then the keymap.
+                        let scripted = match (ch, &mut self.script) {
+                            (Some(c), Some(script)) => script.map_key(c).map(|n| (c, n)),
+                            _ => None,
+                        };
+                        let (id, notes, voicing, toggle, instrument) = match scripted {
+                            Some((c, notes)) => {
+                                (KeyId::Char(c), notes, Voicing::default(), false, 1)
+                            }
+                            None => {
+                                let Some(km) = keymap else {
+                                    continue;
+                                };
+                                let Some(id) = km.resolve(ch, physical) else {
+                                    continue;
+                                };
+
+                                let mods = KeyMods {
+                                    shift: modifiers.shift,
+                                    command: modifiers.ctrl || modifiers.command,
+                                };
+                                let octaves = mods.octave_offset() + self.octave_shift;
+                                let notes = km.notes_for_key(id, octaves);
+                                let instrument = km.instrument_of(id);
+                                (id, notes, km.voicing(id), km.is_toggle(id), instrument)
+                            }
+                        };
+                        let notes: Vec<_> =
+                            notes.into_iter().filter_map(|n| self.scale_lock.resolve(&n)).collect();
+
+                        // Mono: a melody key takes over from the note sounding.
+                        if self.mono && !(self.sticky_keys || toggle) && instrument == 1 {
+                            if let Some(note) = notes.into_iter().next() {
+                                let mv = self.mono_keys.key_down(id, note);
+                                let audio = self.audio.as_mut().filter(|_| self.audio_enabled);
+                                let glide = self.portamento_ms;
+                                let played =
+                                    play_mono(mv, &mut self.pressed, audio, voicing, glide);
+                                if let Err(e) = played {
+                                    report_audio_error(&mut self.toasts, &e);
+                                }
+                            }
+                            continue;
+                        }
+
+                        let notes = if self.sticky_keys || toggle {
+                            match self.pressed.toggle_latch(id, notes) {
+                                Latch::On(notes) => notes,
+                                Latch::Off(notes) => {
+                                    chord_off(&mut self.audio, &mut self.second, &notes);
+                                    continue;
                                 }
                             }
                         } else {
-                            // Key up
-                            if let Some(note) = self.pressed.key_up(ch) {
-                                if let Some(a) = &mut self.audio {
-                                    a.note_off(&note);
+                            self.pressed.key_down(id, notes)
+                        };
+
+                        // Start audio notes if possible, on the zone's instrument.
+                        let engine = match &mut self.second {
+                            Some(s) if instrument == 2 => Some(&mut s.engine),
+                            _ => self.audio.as_mut(),
+                        };
+                        if !notes.is_empty() && self.audio_enabled {
+                            if let Some(a) = engine {
+                                if let Err(e) = a.chord_on(&notes, voicing.gain, voicing.cents) {
+                                    report_audio_error(&mut self.toasts, &e);
                                 }
                             }
                         }
+                    } else {
+                        // Key up: release whichever id the press was held under,
+                        // even if the keymap changed in between.
+                        let ids = [physical.map(KeyId::Physical), ch.map(KeyId::Char)];
+                        for id in ids.into_iter().flatten() {
+                            let mv = self.mono_keys.key_up(id);
+                            if mv != MonoMove::Nothing {
+                                let audio = self.audio.as_mut();
+                                let glide = self.portamento_ms;
+                                let voicing = Voicing::default();
+                                let played =
+                                    play_mono(mv, &mut self.pressed, audio, voicing, glide);
+                                if let Err(e) = played {
+                                    report_audio_error(&mut self.toasts, &e);
+                                }
+                                continue;
+                            }
+                            let notes = self.pressed.key_up(id);
+                            chord_off(&mut self.audio, &mut self.second, &notes);
+                        }
                     }
                 }
             }
         });
+
+        if let Some(key) = learned_key {
+            self.learn_key(key);
+        }
+        if let Some(i) = preset_key {
+            self.recall_preset(i);
+        }
+        for action in actions {
+            self.run_action(ctx, action);
+        }
+    }
+
+    fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::StopAll => self.stop_all_notes(),
+            Action::ToggleSustain => self.set_sustain(!self.sustain_down),
+            Action::OctaveDown => self.octave_shift = shift_octave(self.octave_shift, -1),
+            Action::OctaveUp => self.octave_shift = shift_octave(self.octave_shift, 1),
+            Action::PrevPreset => self.step_preset(-1),
+            Action::NextPreset => self.step_preset(1),
+            Action::ToggleRecord => self.toggle_midi_recording(),
+            Action::PerformanceMode => self.set_performance_mode(ctx, !self.performance_mode),
+            Action::Help => self.show_help = !self.show_help,
+        }
+    }
+
+    /// Recall the preset `step` places from the current one, wrapping around.
+    fn step_preset(&mut self, step: isize) {
+        let count = self.presets.presets().len();
+        if count == 0 {
+            return;
+        }
+        let next = match self.preset_index {
+            Some(i) => (i as isize + step).rem_euclid(count as isize) as usize,
+            None if step < 0 => count - 1,
+            None => 0,
+        };
+        self.recall_preset(next);
+    }
+}
+
+/// Global octave shift range (the samples cover about 3.5 octaves).
+const OCTAVE_SHIFT_RANGE: std::ops::RangeInclusive<i32> = -3..=3;
+
+/// The UI's share of the events: a sensor that went away or came back gets a
+/// toast.
+impl Subscriber for HarmoniumApp {
+    type Error = Infallible;
+
+    fn on_event(&mut self, event: &Event, _t: Instant) -> Result<(), Infallible> {
+        match event {
+            Event::Sensor(SensorEvent::Lost { kind, error }) => {
+                self.toasts.warn(format!("{} lost: {error}", kind.label()));
+            }
+            Event::Sensor(SensorEvent::Reconnected(kind)) => {
+                self.toasts.info(format!("{} reconnected", kind.label()));
+            }
+            Event::Load(LoadEvent::Progress { done, total }) => {
+                self.load_progress = Some((*done, *total));
+            }
+            Event::Load(LoadEvent::Failed(e)) => report_audio_error(&mut self.toasts, e),
+            Event::Load(LoadEvent::Mislabeled {
+                path,
+                named,
+                detected,
+            }) => {
+                let file = path.file_name().unwrap_or_default().to_string_lossy();
+                self.toasts.warn(format!("{file} sounds like {detected}, not {named}"));
+            }
+            Event::Load(LoadEvent::Mapped { dir, notes }) => {
+                let text = format!("{notes} unnamed samples in {} played by pitch", dir.display());
+                self.toasts.info(text);
+            }
+            Event::Load(LoadEvent::Finished { loaded, failed }) => {
+                self.load_progress = None;
+                if *failed > 0 {
+                    self.toasts.warn(format!("{loaded} samples loaded, {failed} failed"));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Carry out a mono key's move. `KeyId::Mono` holds the sounding note in
+/// `pressed`, and `audio` (None: silent) glides its voice from note to note
+/// over `glide_ms`.
+fn play_mono(
+    mv: MonoMove,
+    pressed: &mut PressedKeys,
+    audio: Option<&mut AudioEngine>,
+    voicing: Voicing,
+    glide_ms: f32,
+) -> Result<(), AudioError> {
+    let (stopped, started, glide) = match mv {
+        MonoMove::Start(note) => (Vec::new(), pressed.key_down(KeyId::Mono, vec![note]), None),
+        MonoMove::Glide { from, to } => {
+            let stopped = pressed.key_up(KeyId::Mono);
+            let started = pressed.key_down(KeyId::Mono, vec![to.clone()]);
+            // A note another key holds too (a drone) can't be moved away.
+            let glides = stopped == [from.clone()] && started == [to.clone()];
+            (stopped, started, glides.then_some((from, to)))
+        }
+        MonoMove::Stop(_) => (pressed.key_up(KeyId::Mono), Vec::new(), None),
+        MonoMove::Nothing => return Ok(()),
+    };
+
+    let Some(a) = audio else {
+        return Ok(());
+    };
+    match glide {
+        Some((from, to)) => a.glide(&from, &to, glide_ms),
+        None => {
+            a.chord_off(&stopped);
+            a.chord_on(&started, voicing.gain, voicing.cents)
+        }
+    }
+}
+
+/// Release notes on whichever instrument plays them; the other ignores them.
+fn chord_off(
+    audio: &mut Option<AudioEngine>,
+    second: &mut Option<SecondInstrument>,
+    notes: &[NoteName],
+) {
+    if let Some(a) = audio {
+        a.chord_off(notes);
+    }
+    if let Some(s) = second {
+        s.engine.chord_off(notes);
+    }
+}
+
+/// A note or sound that failed to start: a missing sample is a warning (the
+/// other notes play on), anything else an error.
+fn report_audio_error(toasts: &mut Toasts, e: &AudioError) {
+    if e.is_missing_file() {
+        toasts.warn(e.to_string());
+    } else {
+        toasts.error(e.to_string());
+    }
+}
+
+/// Move the global octave shift; notes already sounding keep their pitch.
+/// Right-click menu on a slider: start MIDI learn, or forget its binding.
+fn midi_learn_menu(
+    response: &egui::Response,
+    learn: &mut MidiLearn,
+    param: MidiParam,
+    error: &mut Option<MidiError>,
+) {
+    let cc = learn.cc_for(param);
+    if let Some(cc) = cc {
+        response.clone().on_hover_text(format!("MIDI CC {cc}"));
+    }
+
+    response.context_menu(|ui| {
+        if ui.button("MIDI learn").clicked() {
+            learn.start(param);
+            ui.close_menu();
+        }
+        if let Some(cc) = cc {
+            if ui.button(format!("Forget CC {cc}")).clicked() {
+                *error = learn.forget(param).err();
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// Lay out piano keys from `lo` to `hi` across the panel width. Returns the
+/// whole area, its response, and every key's rectangle (white keys first).
+fn onscreen_key_rects(
+    ui: &mut egui::Ui,
+    lo: Note,
+    hi: Note,
+) -> Option<(egui::Rect, egui::Response, Vec<(Note, egui::Rect)>)> {
+    let notes: Vec<Note> = (lo.midi()..=hi.midi())
+        .filter_map(|m| Note::from_midi(m as i32))
+        .collect();
+    let white_count = notes.iter().filter(|n| n.is_white()).count();
+    if white_count == 0 {
+        return None;
+    }
+
+    let size = egui::vec2(ui.available_width(), 90.0);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let white_w = rect.width() / white_count as f32;
+    let black_w = white_w * 0.6;
+
+    let mut whites = Vec::new();
+    let mut blacks = Vec::new();
+    for note in notes {
+        // A black key straddles the edge after the white keys laid out so far.
+        let x = rect.left() + whites.len() as f32 * white_w;
+        if note.is_white() {
+            let r = egui::Rect::from_min_size(
+                egui::pos2(x, rect.top()),
+                egui::vec2(white_w, rect.height()),
+            );
+            whites.push((note, r));
+        } else {
+            let r = egui::Rect::from_min_size(
+                egui::pos2(x - black_w / 2.0, rect.top()),
+                egui::vec2(black_w, rect.height() * 0.6),
+            );
+            blacks.push((note, r));
+        }
+    }
+
+    whites.extend(blacks);
+    Some((rect, response, whites))
+}
+
+/// The keymap file to use: the first of `key-map.json/.toml/.yaml/.yml` that
+/// exists, or `key-map.json` (not created until something is saved).
+fn find_keymap_path() -> String {
+    let candidates = ["json", "toml", "yaml", "yml"].map(|ext| format!("{KEYMAP_STEM}.{ext}"));
+    candidates
+        .iter()
+        .find(|p| std::path::Path::new(p).exists())
+        .unwrap_or(&candidates[0])
+        .clone()
+}
+
+fn shift_octave(current: i32, step: i32) -> i32 {
+    (current + step).clamp(*OCTAVE_SHIFT_RANGE.start(), *OCTAVE_SHIFT_RANGE.end())
+}
+
+/// One key of the help overlay: its label on top, the notes it plays below.
+fn help_key(ui: &mut egui::Ui, key: egui::Key, notes: &[NoteName], active: &[NoteName]) {
+    let size = egui::vec2(44.0, 44.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let visuals = ui.visuals();
+
+    let sounding = notes.iter().any(|n| active.contains(n));
+    let fill = if sounding {
+        egui::Color32::from_rgb(230, 140, 40)
+    } else if notes.is_empty() {
+        visuals.faint_bg_color
+    } else {
+        visuals.widgets.inactive.bg_fill
+    };
+    let painter = ui.painter();
+    painter.rect(rect, 4.0, fill, visuals.widgets.noninteractive.bg_stroke);
+
+    let label = match egui_key_to_char(key) {
+        Some(c) => c.to_ascii_uppercase().to_string(),
+        None => key.symbol_or_name().to_string(),
+    };
+    painter.text(
+        rect.left_top() + egui::vec2(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        label,
+        egui::FontId::proportional(10.0),
+        visuals.weak_text_color(),
+    );
+    if let Some(first) = notes.first() {
+        let more = if notes.len() > 1 { "+" } else { "" };
+        painter.text(
+            rect.center_bottom() - egui::vec2(0.0, 6.0),
+            egui::Align2::CENTER_BOTTOM,
+            format!("{first}{more}"),
+            egui::FontId::proportional(13.0),
+            visuals.strong_text_color(),
+        );
+    }
+}
+
+/// `1 h 05 min`, `12 min 30 s` or `45 s`.
+fn format_played(sec: f64) -> String {
+    let sec = sec as u64;
+    match (sec / 3600, sec % 3600 / 60, sec % 60) {
+        (0, 0, s) => format!("{s} s"),
+        (0, m, s) => format!("{m} min {s:02} s"),
+        (h, m, _) => format!("{h} h {m:02} min"),
     }
 }
 
+/// Number keys 1..9 → preset index (with Alt).
+fn preset_for_key(key: egui::Key) -> Option<usize> {
+    use egui::Key;
+
+    let keys = [
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+    keys.iter().position(|k| *k == key)
+}
+
+/// The keymap's name for where `key` sits.
+fn physical_key_of(key: egui::Key) -> Option<PhysicalKey> {
+    PhysicalKey::from_name(key.name())
+}
+
 fn egui_key_to_char(key: egui::Key) -> Option<char> {
     use egui::Key;
 
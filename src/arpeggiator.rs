@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::note::Note;
+
+/// Steps per metronome beat the arpeggiator offers.
+pub const RATES: [u32; 4] = [1, 2, 3, 4];
+
+/// The order held notes are cycled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArpPattern {
+    Up,
+    Down,
+    UpDown,
+}
+
+impl ArpPattern {
+    pub const ALL: [ArpPattern; 3] = [ArpPattern::Up, ArpPattern::Down, ArpPattern::UpDown];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ArpPattern::Up => "up",
+            ArpPattern::Down => "down",
+            ArpPattern::UpDown => "up-down",
+        }
+    }
+}
+
+/// Turns held chords into a figure that steps through their notes in time
+/// with the metronome. Saved with the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Arpeggiator {
+    pub enabled: bool,
+    pub pattern: ArpPattern,
+
+    /// Notes per metronome beat (one of `RATES`).
+    pub steps_per_beat: u32,
+
+    /// Share of each step the note sounds for (0.1..1; 1 is legato).
+    pub gate: f32,
+}
+
+impl Default for Arpeggiator {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pattern: ArpPattern::Up,
+            steps_per_beat: 2,
+            gate: 0.7,
+        }
+    }
+}
+
+impl Arpeggiator {
+    /// One pass of the pattern over `held`. Up-down doesn't repeat the top
+    /// and bottom notes: c e g e, c e g e...
+    pub fn sequence(&self, held: &[Note]) -> Vec<Note> {
+        let mut up = held.to_vec();
+        up.sort();
+        up.dedup();
+
+        match self.pattern {
+            ArpPattern::Up => up,
+            ArpPattern::Down => up.into_iter().rev().collect(),
+            ArpPattern::UpDown if up.len() > 2 => {
+                let down = up[1..up.len() - 1].iter().rev().copied().collect::<Vec<_>>();
+                up.into_iter().chain(down).collect()
+            }
+            ArpPattern::UpDown => up,
+        }
+    }
+
+    /// The note to sound at metronome beat `beats`, or None between steps
+    /// (after the gate closes) or with nothing held.
+    pub fn note_at(&self, held: &[Note], beats: f64) -> Option<Note> {
+        let sequence = self.sequence(held);
+        if sequence.is_empty() {
+            return None;
+        }
+
+        let steps = beats.max(0.0) * self.steps_per_beat.max(1) as f64;
+        let note = sequence[steps.floor() as usize % sequence.len()];
+        (steps.fract() < self.gate.clamp(0.1, 1.0) as f64).then_some(note)
+    }
+}
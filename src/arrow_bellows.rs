@@ -0,0 +1,52 @@
+/// Keyboard stand-in for the lid: holding Up opens the virtual lid, holding
+/// Down closes it, at a fixed speed. Alternating the two is a pump, so the
+/// bellows pipeline sees the same kind of motion a real lid would produce.
+///
+/// Makes the instrument playable on desktops and screen shares with no sensor.
+#[derive(Debug, Clone)]
+pub struct ArrowBellows {
+    /// How fast the virtual lid moves while an arrow is held.
+    pub speed_deg_per_s: f32,
+
+    /// Travel limits of the virtual lid.
+    pub min_deg: f32,
+    pub max_deg: f32,
+
+    angle_deg: f32,
+}
+
+impl Default for ArrowBellows {
+    fn default() -> Self {
+        Self {
+            speed_deg_per_s: 90.0,
+            min_deg: 40.0,
+            max_deg: 140.0,
+            angle_deg: 90.0,
+        }
+    }
+}
+
+impl ArrowBellows {
+    /// Advance by `dt_sec` with the current key state and return the new angle.
+    pub fn step(&mut self, up: bool, down: bool, dt_sec: f32) -> f32 {
+        let dir = match (up, down) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        };
+
+        let (lo, hi) = if self.min_deg <= self.max_deg {
+            (self.min_deg, self.max_deg)
+        } else {
+            (self.max_deg, self.min_deg)
+        };
+
+        self.angle_deg =
+            (self.angle_deg + dir * self.speed_deg_per_s * dt_sec.max(0.0)).clamp(lo, hi);
+        self.angle_deg
+    }
+
+    pub fn angle_deg(&self) -> f32 {
+        self.angle_deg
+    }
+}
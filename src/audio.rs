@@ -1,12 +1,122 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufReader, Cursor};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, PlayError, Source, StreamError};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::bank;
+use crate::harmonizer::Harmonizer;
+use crate::hud::{AudioHealth, WatchdogSource};
+use crate::latency::{LatencyProbe, ProbeSource};
+use crate::meter::{MeterSource, VoiceMeter};
+use crate::note::{Note, NoteName};
+use crate::pitch_bend::{AttackScoop, BendHandle, BendSource, GlideHandle};
+use crate::scale::ScaleLock;
+use crate::voice::Sample;
+
+mod fade;
+mod sink;
+
+use fade::{FadeHandle, FadeSource};
+pub use sink::{AudioSink, BoxedSource, CaptureSink, CapturedTrack, RodioSink, Track};
+
+/// What can go wrong opening the output or starting a sound.
+#[derive(Debug, Error)]
+pub enum AudioError {
+    /// No output device, or it couldn't be opened (busy, unplugged).
+    #[error("Audio output init failed: {0}")]
+    Output(#[from] StreamError),
+
+    /// The output refused another stream (e.g. the device went away).
+    #[error("Failed to play sound: {0}")]
+    Play(#[from] PlayError),
+
+    /// The bank has no sample for this note.
+    #[error("No audio file found for note '{note}'. Expected something like '{note}.wav' in {dir:?}")]
+    MissingSample { note: NoteName, dir: PathBuf },
+
+    #[error("Failed to open {path:?}: {source}")]
+    Open { path: PathBuf, source: io::Error },
+
+    #[error("Failed to decode {path:?}: {source}")]
+    Decode {
+        path: PathBuf,
+        source: DecoderError,
+    },
+}
+
+impl AudioError {
+    /// The output device is at fault rather than a file: worth opening the
+    /// output again once the device is back or free.
+    pub fn is_device(&self) -> bool {
+        matches!(self, AudioError::Output(_) | AudioError::Play(_))
+    }
+
+    /// A sample or backing file isn't there. Other notes still play.
+    pub fn is_missing_file(&self) -> bool {
+        match self {
+            AudioError::MissingSample { .. } => true,
+            AudioError::Open { source, .. } => source.kind() == io::ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+}
+
+/// With retune on, a note the bank has no sample for borrows one at most this
+/// far away, resampled to its pitch.
+const MAX_BORROW_SEMITONES: i32 = 4;
+
+/// Most notes sounding at once, unless set otherwise: past it a new note
+/// takes the voice of the one playing longest.
+pub const DEFAULT_MAX_VOICES: usize = 32;
+
+/// How long a panic takes to fade every voice out.
+pub const PANIC_FADE_MS: f32 = 50.0;
+
+/// A voice still playing this long after its panic fade should have ended
+/// means the output has stopped pulling audio: it is opened again.
+const STUCK_AFTER: Duration = Duration::from_millis(250);
+
+/// A sample file decoded into memory, shared by every voice (and engine)
+/// that plays it.
+pub type SharedSample = Arc<Sample>;
+
+/// Read and decode a sample file. Slow enough (disk, large files) to do on a
+/// loader thread rather than at the first note.
+pub fn load_sample(path: &Path) -> Result<SharedSample, AudioError> {
+    let bytes = std::fs::read(path).map_err(|source| AudioError::Open {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let decoder = Decoder::new(Cursor::new(bytes)).map_err(|source| AudioError::Decode {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let channels = usize::from(decoder.channels());
+    let rate = decoder.sample_rate();
+    Ok(Arc::new(Sample::new(decoder.convert_samples::<f32>().collect(), channels, rate)))
+}
+
+/// Open and decode an audio file.
+fn decode(path: &Path) -> Result<Decoder<BufReader<File>>, AudioError> {
+    let file = File::open(path).map_err(|source| AudioError::Open {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Decoder::new(BufReader::new(file)).map_err(|source| AudioError::Decode {
+        path: path.to_path_buf(),
+        source,
+    })
+}
 
 /// Simple audio engine:
-/// - Each active note has a Sink (a mixer track).
+/// - Each active note has a track on the output (a mixer channel).
 /// - We loop the sample forever.
 /// - We control volume continuously using bellows amplitude.
 ///
@@ -14,37 +124,218 @@ use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 /// Because your samples are 7–12 seconds, but harmonium notes should sustain
 /// as long as the key is held and the bellows (screen motion) provides air.
 pub struct AudioEngine {
-    // Keep the stream alive. If these are dropped, audio stops.
-    _stream: OutputStream,
-    handle: OutputStreamHandle,
+    // Where the tracks play: the sound card, or a capture in tests.
+    output: Box<dyn AudioSink>,
 
     // Where your audio files live, e.g. "harmonium-sounds"
     samples_dir: PathBuf,
 
-    // Active note sinks: note name -> Sink
-    active: HashMap<String, Sink>,
+    // Sample files already decoded, by path: preloaded, or read at their
+    // first note.
+    samples: HashMap<PathBuf, SharedSample>,
+
+    // Bank folder -> note -> file, for samples not named after their note
+    // (found by their pitch, see `sample_pitch`).
+    note_maps: HashMap<PathBuf, HashMap<NoteName, PathBuf>>,
+
+    // Active notes: note name -> its voice
+    active: HashMap<String, Voice>,
+
+    // Polyphony: most played notes at once (their harmony voices aside), and
+    // the order voices were started in, for stealing the oldest.
+    max_voices: usize,
+    voices_started: u64,
 
     // A master volume knob (0..1-ish). We multiply bellows amplitude by this.
     master_gain: f32,
 
-    // Latest bellows amplitude (0..1). Stored so we can recompute sink volumes.
+    // Latest bellows amplitude (0..1). Stored so we can recompute track volumes.
     bellows_a: f32,
+
+    // While suspended every track is paused (e.g. the lid is closed).
+    suspended: bool,
+
+    // Sustain pedal: while it is down, released notes keep sounding and wait
+    // here until pedal-up (or until they are played again).
+    sustain: bool,
+    pending_release: HashSet<String>,
+
+    // Tells the latency diagnostic when the audio thread reads the voices.
+    probe: Arc<LatencyProbe>,
+
+    // Pitch bend (meend) applied to every voice while it plays.
+    bend: BendHandle,
+
+    // How a new voice speaks: flat at first, the more so the less air.
+    scoop: AttackScoop,
+
+    // Counts audio-thread stalls, fed by a silent watchdog stream.
+    health: Arc<AudioHealth>,
+
+    // Backing loop (tabla / lehra) on its own track, and its level under the
+    // master volume.
+    backing: Option<Box<dyn Track>>,
+    backing_volume: f32,
+
+    // Practice track (a song to play along with) on its own track: its level
+    // under the master volume, and how far it is ducked under the playing
+    // (1 = not at all), and whether the player paused it.
+    practice: Option<Box<dyn Track>>,
+    practice_volume: f32,
+    practice_duck: f32,
+    practice_paused: bool,
+
+    // Arpeggiator: when set, only these of the sounding notes are heard; the
+    // others keep playing silently, so they come back in without restarting.
+    audible: Option<HashSet<String>>,
+
+    // Harmonizer stop and the scale it keeps to. Played note -> the voice
+    // added to it, which lives and dies with it.
+    harmonizer: Harmonizer,
+    scale: ScaleLock,
+    harmony: HashMap<String, Voice>,
+
+    // Voices let go by a panic, fading out on the audio thread, and when
+    // they should all be done.
+    fading: Vec<Box<dyn Track>>,
+    fading_until: Option<Instant>,
+}
+
+/// One sounding note.
+struct Voice {
+    track: Box<dyn Track>,
+
+    /// Per-note level on top of master × bellows (e.g. a quieter drone zone).
+    gain: f32,
+
+    /// Tuning offset it was started with (kept for a bank switch).
+    cents: f32,
+
+    /// Level of its sample as the audio thread plays it.
+    meter: Arc<VoiceMeter>,
+
+    /// The note it started as, and its portamento away from it.
+    started_as: Option<Note>,
+    glide: GlideHandle,
+
+    /// Fades it out on a panic.
+    fade: FadeHandle,
+
+    /// When it started, in the engine's count of voices (for stealing).
+    started: u64,
 }
 
 impl AudioEngine {
     /// Create an audio engine. `samples_dir` is your "harmonium-sounds" folder.
-    pub fn new(samples_dir: impl AsRef<Path>) -> Result<Self, String> {
-        let (stream, handle) =
-            OutputStream::try_default().map_err(|e| format!("Audio output init failed: {e}"))?;
+    pub fn new(samples_dir: impl AsRef<Path>) -> Result<Self, AudioError> {
+        let output = RodioSink::open()?;
+
+        let health = Arc::new(AudioHealth::default());
+        output.play_once(Box::new(WatchdogSource::new(health.clone())))?;
 
-        Ok(Self {
-            _stream: stream,
-            handle,
+        info!("audio output open, samples from {}", samples_dir.as_ref().display());
+        let mut engine = Self::with_output(samples_dir, Box::new(output));
+        engine.health = health;
+        Ok(engine)
+    }
+
+    /// An engine playing into `output` instead of the sound card, e.g. a
+    /// [`CaptureSink`] in tests. There is no watchdog: `underruns` stays 0.
+    pub fn with_output(samples_dir: impl AsRef<Path>, output: Box<dyn AudioSink>) -> Self {
+        Self {
+            output,
             samples_dir: samples_dir.as_ref().to_path_buf(),
+            samples: HashMap::new(),
+            note_maps: HashMap::new(),
             active: HashMap::new(),
+            max_voices: DEFAULT_MAX_VOICES,
+            voices_started: 0,
             master_gain: 0.8,
             bellows_a: 0.0,
-        })
+            suspended: false,
+            sustain: false,
+            pending_release: HashSet::new(),
+            probe: Arc::new(LatencyProbe::default()),
+            bend: BendHandle::default(),
+            scoop: AttackScoop::default(),
+            health: Arc::new(AudioHealth::default()),
+            backing: None,
+            backing_volume: 0.6,
+            practice: None,
+            practice_volume: 0.7,
+            practice_duck: 1.0,
+            practice_paused: false,
+            audible: None,
+            harmonizer: Harmonizer::default(),
+            scale: ScaleLock::default(),
+            harmony: HashMap::new(),
+            fading: Vec::new(),
+            fading_until: None,
+        }
+    }
+
+    /// Times the audio thread fell behind (output glitched) since startup.
+    pub fn underruns(&self) -> u32 {
+        self.health.underruns()
+    }
+
+    /// Notes with a voice right now (held, latched or sustained).
+    pub fn voice_count(&self) -> usize {
+        self.active.len()
+    }
+
+    /// How loud `note` is in the output right now (RMS, 0..1): its sample's
+    /// level times its volume. None if it has no voice (e.g. no sample).
+    pub fn voice_level(&self, note: &str) -> Option<f32> {
+        let voice = self.active.get(note)?;
+        if self.suspended {
+            return Some(0.0);
+        }
+        Some(voice.meter.rms() * voice.track.volume())
+    }
+
+    /// How loud the instrument is right now (RMS, 0..1): every voice's
+    /// level, summed as power. 0 while suspended.
+    pub fn level(&self) -> f32 {
+        if self.suspended {
+            return 0.0;
+        }
+        let power: f32 = self
+            .active
+            .values()
+            .chain(self.harmony.values())
+            .map(|v| (v.meter.rms() * v.track.volume()).powi(2))
+            .sum();
+        power.sqrt()
+    }
+
+    /// Cents offset `note` was started with (its key's tuning).
+    pub fn voice_cents(&self, note: &str) -> Option<f32> {
+        self.active.get(note).map(|v| v.cents)
+    }
+
+    /// The latest audio of every voice, at its volume, summed: roughly what
+    /// the output carries (voices aren't sample-aligned, which a steady tone
+    /// doesn't mind). None if nothing is sounding.
+    pub fn output_snapshot(&self) -> Option<(Vec<f32>, u32)> {
+        let mut mix: Option<(Vec<f32>, u32)> = None;
+        for voice in self.active.values().chain(self.harmony.values()) {
+            let (frames, rate) = voice.meter.snapshot();
+            let volume = voice.track.volume();
+            match &mut mix {
+                None if !frames.is_empty() => {
+                    mix = Some((frames.iter().map(|s| s * volume).collect(), rate));
+                }
+                // Banks are one sample rate; anything else can't be mixed in.
+                Some((sum, mix_rate)) if *mix_rate == rate => {
+                    for (acc, s) in sum.iter_mut().zip(&frames) {
+                        *acc += s * volume;
+                    }
+                }
+                _ => {}
+            }
+        }
+        mix
     }
 
     /// Set master gain (slider later).
@@ -65,55 +356,517 @@ impl AudioEngine {
     /// - find a sample file in harmonium-sounds
     /// - decode it
     /// - loop it forever
-    /// - put it into a Sink
-    pub fn note_on(&mut self, note: &str) -> Result<(), String> {
+    /// - put it on its own track
+    pub fn note_on(&mut self, note: &str) -> Result<(), AudioError> {
+        self.note_on_voiced(note, 1.0, 0.0)
+    }
+
+    /// Like `note_on`, with a per-note gain (e.g. the key's zone) and a tuning
+    /// offset in cents. A note that is already sounding keeps its voicing.
+    pub fn note_on_voiced(
+        &mut self,
+        note: &str,
+        gain: f32,
+        cents: f32,
+    ) -> Result<(), AudioError> {
+        // Played again while sustained: it is simply held again.
+        self.pending_release.remove(note);
         if self.active.contains_key(note) {
             return Ok(());
         }
 
-        let path = self.find_sample_path(note).ok_or_else(|| {
-            format!(
-                "No audio file found for note '{note}'. Expected something like '{note}.wav' in {:?}",
-                self.samples_dir
-            )
-        })?;
+        let voice = self.start_voice(note, gain, cents)?;
+        while self.active.len() >= self.max_voices {
+            self.steal_oldest();
+        }
+        self.active.insert(note.to_string(), voice);
+        self.start_harmony(note);
+        self.refresh_volumes();
+        Ok(())
+    }
+
+    /// Cut the note that has been sounding longest (with its harmony), to
+    /// make room under the polyphony cap. Held or sustained alike.
+    fn steal_oldest(&mut self) {
+        let oldest = self.active.iter().min_by_key(|(_, voice)| voice.started);
+        let Some(note) = oldest.map(|(note, _)| note.clone()) else {
+            return;
+        };
+        info!("voice limit ({}): {note} stolen", self.max_voices);
+        self.pending_release.remove(&note);
+        if let Some(voice) = self.active.remove(&note) {
+            voice.track.stop();
+        }
+        if let Some(voice) = self.harmony.remove(&note) {
+            voice.track.stop();
+        }
+    }
+
+    /// Most notes that sound at once (at least 1). Notes over a lowered cap
+    /// are stolen at once, oldest first.
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        self.max_voices = max_voices.max(1);
+        while self.active.len() > self.max_voices {
+            self.steal_oldest();
+        }
+    }
+
+    /// A new, silent voice playing `note`'s sample.
+    fn start_voice(&mut self, note: &str, gain: f32, cents: f32) -> Result<Voice, AudioError> {
+        let sample = match self.sample_path(note) {
+            Some(path) => Some((path, 0)),
+            None => self.borrowed_sample(note),
+        };
+        let Some((path, shift)) = sample else {
+            let err = AudioError::MissingSample {
+                note: note.to_string(),
+                dir: self.samples_dir.clone(),
+            };
+            warn!("{err}");
+            return Err(err);
+        };
+
+        let sample = match self.samples.get(&path) {
+            Some(sample) => Arc::clone(sample),
+            None => {
+                let sample = load_sample(&path)?;
+                self.samples.insert(path.clone(), Arc::clone(&sample));
+                sample
+            }
+        };
 
-        let file = File::open(&path).map_err(|e| format!("Failed to open {path:?}: {e}"))?;
-        let decoder = Decoder::new(BufReader::new(file))
-            .map_err(|e| format!("Failed to decode {path:?}: {e}"))?;
+        // Loop the sample forever, resampled for the cents offset, a
+        // borrowed sample's shift, the live pitch bend and the attack scoop.
+        let ratio = 2f32.powf((cents + 100.0 * shift as f32) / 1200.0);
+        let meter = Arc::new(VoiceMeter::default());
+        let glide = GlideHandle::default();
+        let fade = FadeHandle::default();
+        let bent = BendSource::new(sample, self.bend.clone())
+            .with_speed(ratio)
+            .with_glide(glide.clone())
+            .with_scoop(self.scoop.semitones(self.bellows_a), self.scoop.time_ms);
+        let metered = MeterSource::new(FadeSource::new(bent, fade.clone()), Arc::clone(&meter));
+        let source = ProbeSource::new(metered, Arc::clone(&self.probe));
 
-        // Loop the decoded audio forever.
-        let source = decoder.repeat_infinite();
+        // Each note gets its own track (volume control). It starts silent;
+        // volume will be set by refresh_volumes().
+        let track = self.output.open_track(Box::new(source))?;
 
-        // Each note gets its own Sink (volume control).
-        let sink = Sink::try_new(&self.handle).map_err(|e| format!("Failed to create sink: {e}"))?;
+        // Keep playing (the track begins immediately once it has a source),
+        // unless the engine is suspended right now.
+        if self.suspended {
+            track.pause();
+        } else {
+            track.play();
+        }
+
+        self.voices_started += 1;
+        Ok(Voice {
+            track,
+            gain,
+            cents,
+            meter,
+            started_as: Note::parse(note),
+            glide,
+            fade,
+            started: self.voices_started,
+        })
+    }
+
+    /// `note`'s file in the bank playing: named after it, or found by pitch.
+    fn sample_path(&self, note: &str) -> Option<PathBuf> {
+        bank::sample_path(&self.samples_dir, note)
+            .or_else(|| self.note_maps.get(&self.samples_dir)?.get(note).cloned())
+    }
+
+    /// A retuned instrument can run past either end of the bank: take the
+    /// nearest sample (within `MAX_BORROW_SEMITONES`) and the semitones to
+    /// shift it by.
+    fn borrowed_sample(&self, note: &str) -> Option<(PathBuf, i32)> {
+        if !self.scale.retune {
+            return None;
+        }
+        let played = Note::parse(note)?;
+        (1..=MAX_BORROW_SEMITONES).find_map(|d| {
+            [d, -d].into_iter().find_map(|shift| {
+                let sample = played.transpose(-shift)?.name();
+                self.sample_path(&sample).map(|path| (path, shift))
+            })
+        })
+    }
 
-        // Start silent. Volume will be set by refresh_volumes().
-        sink.set_volume(0.0);
+    /// Add the harmonizer's note to the sounding `note`, if the stop is on.
+    /// A missing sample just leaves the note without it.
+    fn start_harmony(&mut self, note: &str) {
+        let Some(played) = self.active.get(note) else {
+            return;
+        };
+        let Some(harmony) = self.harmonizer.harmony(note, &self.scale) else {
+            return;
+        };
 
-        // Append the audio source to the sink.
-        sink.append(source);
+        let gain = played.gain * self.harmonizer.level.clamp(0.0, 1.0);
+        if let Ok(voice) = self.start_voice(&harmony, gain, played.cents) {
+            self.harmony.insert(note.to_string(), voice);
+        }
+    }
 
-        // Keep playing (sink begins immediately once it has a source).
-        sink.play();
+    /// Set the harmonizer stop and the scale it keeps to (with retune on,
+    /// notes past the bank borrow a neighbour's sample). A change re-voices
+    /// the sounding notes' harmony at once.
+    pub fn set_harmonizer(&mut self, harmonizer: &Harmonizer, scale: &ScaleLock) {
+        if *harmonizer == self.harmonizer && *scale == self.scale {
+            return;
+        }
+        self.harmonizer = harmonizer.clone();
+        self.scale = scale.clone();
 
-        self.active.insert(note.to_string(), sink);
+        for (_note, voice) in self.harmony.drain() {
+            voice.track.stop();
+        }
+        let notes: Vec<String> = self.active.keys().cloned().collect();
+        for note in notes {
+            self.start_harmony(&note);
+        }
         self.refresh_volumes();
-        Ok(())
     }
 
-    /// Stop a note immediately (Phase 2: later we'll add a short fade-out).
+    /// Stop a note at once, or at pedal-up if the sustain pedal is down.
+    /// There's no fade: a reed stops when its key lets go, and a key pressed
+    /// again straight away gets its voice back without waiting for a tail.
+    /// Only [`Self::panic`] fades, because it silences every voice at once.
     pub fn note_off(&mut self, note: &str) {
-        if let Some(sink) = self.active.remove(note) {
-            sink.stop();
+        if self.sustain && self.active.contains_key(note) {
+            self.pending_release.insert(note.to_string());
+            return;
+        }
+
+        if let Some(voice) = self.active.remove(note) {
+            voice.track.stop();
+        }
+        if let Some(voice) = self.harmony.remove(note) {
+            voice.track.stop();
+        }
+    }
+
+    /// Portamento (mono / legato playing): the voice of `from` slides to
+    /// `to` over `time_ms` instead of `to` starting afresh, and is `to`'s
+    /// voice from now on. Its harmony note restarts at the new pitch. If
+    /// `from` has no voice (or `to` already has one), `to` just starts.
+    pub fn glide(&mut self, from: &str, to: &str, time_ms: f32) -> Result<(), AudioError> {
+        let target = Note::parse(to).filter(|_| !self.active.contains_key(to));
+        let Some(voice) = self.active.remove(from) else {
+            return self.note_on(to);
+        };
+        let (Some(start), Some(target)) = (voice.started_as, target) else {
+            self.active.insert(from.to_string(), voice);
+            self.note_off(from);
+            return self.note_on(to);
+        };
+
+        self.pending_release.remove(from);
+        if let Some(harmony) = self.harmony.remove(from) {
+            harmony.track.stop();
+        }
+        let semitones = f32::from(target.midi()) - f32::from(start.midi());
+        voice.glide.glide_to(semitones, time_ms);
+        self.active.insert(to.to_string(), voice);
+        self.start_harmony(to);
+        self.refresh_volumes();
+        Ok(())
+    }
+
+    /// Start every note of a chord key. Tries them all; returns the first error.
+    pub fn chord_on(&mut self, notes: &[String], gain: f32, cents: f32) -> Result<(), AudioError> {
+        let mut first_err = None;
+        for note in notes {
+            if let Err(e) = self.note_on_voiced(note, gain, cents) {
+                first_err.get_or_insert(e);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Stop every note of a chord key.
+    pub fn chord_off(&mut self, notes: &[String]) {
+        for note in notes {
+            self.note_off(note);
+        }
+    }
+
+    /// Play from another sample folder (bank / stop preset). Sounding notes
+    /// restart with the new samples, so the switch is heard at once.
+    pub fn set_samples_dir(&mut self, samples_dir: impl AsRef<Path>) -> Result<(), AudioError> {
+        self.samples_dir = samples_dir.as_ref().to_path_buf();
+        info!("samples from {}", self.samples_dir.display());
+
+        let pending = std::mem::take(&mut self.pending_release);
+        for (_note, voice) in self.harmony.drain() {
+            voice.track.stop();
+        }
+        let mut voices: Vec<(u64, String, f32, f32)> = self
+            .active
+            .drain()
+            .map(|(note, voice)| {
+                voice.track.stop();
+                (voice.started, note, voice.gain, voice.cents)
+            })
+            .collect();
+        // Restarted in the order they were first played, so stealing still
+        // takes the oldest.
+        voices.sort_by_key(|(started, ..)| *started);
+
+        let mut first_err = None;
+        for (_started, note, gain, cents) in voices {
+            if let Err(e) = self.note_on_voiced(&note, gain, cents) {
+                first_err.get_or_insert(e);
+            }
+        }
+        self.pending_release = pending;
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Play a short sound once (a metronome click) over the notes, at its own
+    /// level: neither the master volume nor the bellows apply.
+    pub fn play_one_shot<S>(&self, source: S) -> Result<(), AudioError>
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        self.output.play_once(Box::new(source))
+    }
+
+    /// Loop `path` as the backing track at `speed` (1.0 as recorded),
+    /// replacing any backing already playing.
+    pub fn play_backing(&mut self, path: &Path, speed: f32) -> Result<(), AudioError> {
+        self.stop_backing();
+
+        let decoder = decode(path)?;
+        let track = self
+            .output
+            .open_track(Box::new(decoder.repeat_infinite().convert_samples::<f32>()))?;
+
+        track.set_speed(speed);
+        if self.suspended {
+            track.pause();
+        }
+        info!("backing {} at {speed:.2}x", path.display());
+        self.backing = Some(track);
+        self.refresh_volumes();
+        Ok(())
+    }
+
+    /// Follow a tempo change without restarting the loop.
+    pub fn set_backing_speed(&self, speed: f32) {
+        if let Some(track) = &self.backing {
+            track.set_speed(speed);
+        }
+    }
+
+    pub fn set_backing_volume(&mut self, volume: f32) {
+        self.backing_volume = volume.clamp(0.0, 1.0);
+        self.refresh_volumes();
+    }
+
+    pub fn stop_backing(&mut self) {
+        if let Some(track) = self.backing.take() {
+            track.stop();
+        }
+    }
+
+    /// Play `path` as the practice track from its start, once or looped,
+    /// replacing any practice track playing.
+    pub fn play_practice(&mut self, path: &Path, looped: bool) -> Result<(), AudioError> {
+        self.stop_practice();
+
+        let decoder = decode(path)?;
+        let source: BoxedSource = if looped {
+            Box::new(decoder.repeat_infinite().convert_samples::<f32>())
+        } else {
+            Box::new(decoder.convert_samples::<f32>())
+        };
+        let track = self.output.open_track(source)?;
+        if self.suspended {
+            track.pause();
+        }
+        info!("practice track {}", path.display());
+        self.practice = Some(track);
+        self.practice_paused = false;
+        self.refresh_volumes();
+        Ok(())
+    }
+
+    pub fn stop_practice(&mut self) {
+        if let Some(track) = self.practice.take() {
+            track.stop();
+        }
+    }
+
+    /// Hold the practice track where it is, or carry on.
+    pub fn pause_practice(&mut self, paused: bool) {
+        self.practice_paused = paused;
+        match &self.practice {
+            Some(track) if paused => track.pause(),
+            Some(track) if !self.suspended => track.play(),
+            _ => {}
+        }
+    }
+
+    /// A practice track is loaded and hasn't played to its end.
+    pub fn practice_playing(&self) -> bool {
+        self.practice.as_ref().is_some_and(|t| !t.finished())
+    }
+
+    pub fn set_practice_volume(&mut self, volume: f32) {
+        self.practice_volume = volume.clamp(0.0, 1.0);
+        self.refresh_volumes();
+    }
+
+    /// Duck the practice track to `gain` (0..1) of its volume, e.g. while
+    /// the instrument is loud.
+    pub fn set_practice_duck(&mut self, gain: f32) {
+        self.practice_duck = gain.clamp(0.0, 1.0);
+        self.refresh_volumes();
+    }
+
+    /// Hear only `notes` of the sounding ones (None: all of them).
+    pub fn set_audible(&mut self, notes: Option<&[NoteName]>) {
+        let audible = notes.map(|n| n.iter().cloned().collect());
+        if audible != self.audible {
+            self.audible = audible;
+            self.refresh_volumes();
+        }
+    }
+
+    /// Bend every note, sounding and future, by `semitones` (meend).
+    pub fn set_bend_semitones(&mut self, semitones: f32) {
+        self.bend.set_semitones(semitones);
+    }
+
+    /// How notes started from now on speak (see [`AttackScoop`]).
+    pub fn set_attack_scoop(&mut self, scoop: AttackScoop) {
+        self.scoop = scoop;
+    }
+
+    /// Sustain pedal down / up. Pedal-up stops every note released meanwhile.
+    pub fn set_sustain(&mut self, down: bool) {
+        self.sustain = down;
+        if down {
+            return;
+        }
+
+        for note in std::mem::take(&mut self.pending_release) {
+            self.note_off(&note);
         }
     }
 
     /// Stop everything (panic button).
     pub fn stop_all(&mut self) {
-        for (_note, sink) in self.active.drain() {
-            sink.stop();
+        self.pending_release.clear();
+        for (_note, voice) in self.active.drain().chain(self.harmony.drain()) {
+            voice.track.stop();
+        }
+    }
+
+    /// Stop everything, for when something is stuck: every voice fades out
+    /// over [`PANIC_FADE_MS`] (no click), and the sustain pedal, notes
+    /// waiting for pedal-up and the arpeggiator's choice are forgotten.
+    /// [`Self::finish_panic`] cleans up after the fade.
+    pub fn panic(&mut self) {
+        self.sustain = false;
+        self.pending_release.clear();
+        self.audible = None;
+        for (_note, voice) in self.active.drain().chain(self.harmony.drain()) {
+            if self.suspended {
+                // Paused: nothing would play the fade.
+                voice.track.stop();
+            } else {
+                voice.fade.fade_out(PANIC_FADE_MS);
+                self.fading.push(voice.track);
+            }
+        }
+        if !self.fading.is_empty() {
+            let fade = Duration::from_secs_f32(PANIC_FADE_MS / 1000.0);
+            self.fading_until = Some(Instant::now() + fade + STUCK_AFTER);
+        }
+        info!("panic: all voices fading out");
+    }
+
+    /// Call every frame after a [`Self::panic`]: drops the faded voices, and
+    /// once the fade is overdue stops any that are still going and opens the
+    /// output again, since it has stopped playing. True if it did (the
+    /// backing loop and practice track are gone and have to be started
+    /// again).
+    pub fn finish_panic(&mut self) -> Result<bool, AudioError> {
+        self.fading.retain(|track| !track.finished());
+        let Some(until) = self.fading_until else {
+            return Ok(false);
+        };
+        if self.fading.is_empty() {
+            self.fading_until = None;
+            return Ok(false);
+        }
+        if Instant::now() < until {
+            return Ok(false);
+        }
+
+        self.fading_until = None;
+        warn!("{} voices didn't fade out: reopening the audio output", self.fading.len());
+        for track in self.fading.drain(..) {
+            track.stop();
         }
+        self.reopen_output()?;
+        Ok(true)
+    }
+
+    /// A fresh output stream. Everything on the old one is lost, so the
+    /// backing loop and practice track are dropped and the watchdog started
+    /// again.
+    fn reopen_output(&mut self) -> Result<(), AudioError> {
+        self.stop_backing();
+        self.stop_practice();
+        for voice in self.active.values().chain(self.harmony.values()) {
+            voice.track.stop();
+        }
+        self.active.clear();
+        self.harmony.clear();
+        self.output.reopen()?;
+        self.output.play_once(Box::new(WatchdogSource::new(Arc::clone(&self.health))))
+    }
+
+    /// Pause every note without forgetting it (lid closed, etc.).
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+        for voice in self.active.values().chain(self.harmony.values()) {
+            voice.track.pause();
+        }
+        if let Some(track) = &self.backing {
+            track.pause();
+        }
+        if let Some(track) = &self.practice {
+            track.pause();
+        }
+    }
+
+    /// Resume notes paused by `suspend`.
+    pub fn resume(&mut self) {
+        self.suspended = false;
+        for voice in self.active.values().chain(self.harmony.values()) {
+            voice.track.play();
+        }
+        if let Some(track) = &self.backing {
+            track.play();
+        }
+        if let Some(track) = self.practice.as_ref().filter(|_| !self.practice_paused) {
+            track.play();
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    pub fn latency_probe(&self) -> &LatencyProbe {
+        &self.probe
     }
 
     /// Recompute the volume of every active note.
@@ -124,26 +877,55 @@ impl AudioEngine {
     fn refresh_volumes(&mut self) {
         let vol = (self.master_gain * self.bellows_a).clamp(0.0, 2.0);
 
-        for (_note, sink) in self.active.iter() {
-            sink.set_volume(vol);
+        // A harmony voice is heard with the note it was added to.
+        for (note, voice) in self.active.iter().chain(&self.harmony) {
+            let heard = self.audible.as_ref().is_none_or(|a| a.contains(note));
+            let gain = if heard { voice.gain } else { 0.0 };
+            voice.track.set_volume((vol * gain).clamp(0.0, 2.0));
+        }
+        if let Some(track) = &self.backing {
+            track.set_volume((self.master_gain * self.backing_volume).clamp(0.0, 2.0));
+        }
+        if let Some(track) = &self.practice {
+            let volume = self.master_gain * self.practice_volume * self.practice_duck;
+            track.set_volume(volume.clamp(0.0, 2.0));
         }
     }
 
-    /// Look for a file like:
-    /// harmonium-sounds/<note>.wav
-    /// harmonium-sounds/<note>.mp3
-    /// harmonium-sounds/<note>.ogg
-    /// harmonium-sounds/<note>.flac
-    fn find_sample_path(&self, note: &str) -> Option<PathBuf> {
-        let exts = ["wav", "mp3", "ogg", "flac"];
+    /// Keep a sample read elsewhere (see [`load_sample`]) for the notes that
+    /// play it.
+    pub fn preload(&mut self, path: PathBuf, sample: SharedSample) {
+        self.samples.insert(path, sample);
+    }
 
-        for ext in exts {
-            let p = self.samples_dir.join(format!("{note}.{ext}"));
-            if p.is_file() {
-                return Some(p);
-            }
-        }
+    /// Play the files of `dir` that aren't named after a note as the notes
+    /// they were found to sound (see [`crate::sample_pitch::note_map`]).
+    pub fn set_note_map(&mut self, dir: PathBuf, map: HashMap<NoteName, PathBuf>) {
+        self.note_maps.insert(dir, map);
+    }
+
+    /// Samples in memory.
+    pub fn preloaded_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Notes that have a sample file, lowest first.
+    pub fn available_notes(&self) -> Vec<NoteName> {
+        let Ok(entries) = std::fs::read_dir(&self.samples_dir) else {
+            return Vec::new();
+        };
 
-        None
+        let mapped = self.note_maps.get(&self.samples_dir).into_iter().flat_map(|m| m.keys());
+        let mut notes: Vec<Note> = entries
+            .flatten()
+            .filter_map(|e| {
+                let path = e.path();
+                Note::parse(path.file_stem()?.to_str()?)
+            })
+            .chain(mapped.filter_map(|n| Note::parse(n)))
+            .collect();
+        notes.sort();
+        notes.dedup();
+        notes.into_iter().map(Note::name).collect()
     }
 }
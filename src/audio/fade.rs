@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use rodio::Source;
+
+/// Asks a voice to fade out: set from the UI thread, acted on by the audio
+/// thread, so the fade is smooth however seldom the UI draws.
+#[derive(Debug, Clone)]
+pub struct FadeHandle(Arc<AtomicU32>);
+
+impl Default for FadeHandle {
+    fn default() -> Self {
+        // Fade time in ms as f32 bits; negative while not fading.
+        Self(Arc::new(AtomicU32::new((-1.0f32).to_bits())))
+    }
+}
+
+impl FadeHandle {
+    /// Fade to silence over `ms`, then end.
+    pub fn fade_out(&self, ms: f32) {
+        self.0.store(ms.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    fn ms(&self) -> Option<f32> {
+        let ms = f32::from_bits(self.0.load(Ordering::Relaxed));
+        (ms >= 0.0).then_some(ms)
+    }
+}
+
+/// Passes a voice through until its [`FadeHandle`] says otherwise, then
+/// ramps it down a frame at a time and ends, which empties its track.
+pub struct FadeSource<S> {
+    inner: S,
+    handle: FadeHandle,
+    channels: usize,
+
+    /// Channel of the next sample within the current frame.
+    ch: usize,
+
+    gain: f32,
+
+    /// Gain lost per frame, once fading.
+    step: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> FadeSource<S> {
+    pub fn new(inner: S, handle: FadeHandle) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        Self {
+            inner,
+            handle,
+            channels,
+            ch: 0,
+            gain: 1.0,
+            step: None,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for FadeSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.ch == 0 {
+            if self.step.is_none() {
+                let rate = self.inner.sample_rate().max(1) as f32;
+                self.step = self.handle.ms().map(|ms| 1.0 / (ms / 1000.0 * rate).max(1.0));
+            }
+            if let Some(step) = self.step {
+                self.gain -= step;
+                if self.gain <= 0.0 {
+                    return None;
+                }
+            }
+        }
+        self.ch = (self.ch + 1) % self.channels;
+        self.inner.next().map(|s| s * self.gain)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for FadeSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
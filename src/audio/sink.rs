@@ -0,0 +1,210 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+
+use super::AudioError;
+
+/// Audio handed to an [`AudioSink`].
+pub type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+/// Where the engine's sound goes: a track per voice (and the backing loop),
+/// plus one-shots like the metronome click. [`RodioSink`] is the sound card;
+/// [`CaptureSink`] plays nothing and lets tests look at the tracks.
+pub trait AudioSink {
+    /// A new track playing `source`, silent until its volume is set.
+    fn open_track(&self, source: BoxedSource) -> Result<Box<dyn Track>, AudioError>;
+
+    /// Play `source` once over the tracks, at its own level.
+    fn play_once(&self, source: BoxedSource) -> Result<(), AudioError>;
+
+    /// Open the device again, for an output that stopped playing. Tracks on
+    /// the old one go silent.
+    fn reopen(&mut self) -> Result<(), AudioError> {
+        Ok(())
+    }
+}
+
+/// One track of an [`AudioSink`]: a mixer channel with its own volume.
+pub trait Track {
+    fn volume(&self) -> f32;
+    fn set_volume(&self, volume: f32);
+    fn set_speed(&self, speed: f32);
+    fn pause(&self);
+    fn play(&self);
+    /// Stop for good; the track can't be played again.
+    fn stop(&self);
+    /// Played to the end of its source, or stopped.
+    fn finished(&self) -> bool;
+}
+
+/// The default output device, through rodio.
+pub struct RodioSink {
+    // Keep the stream alive. If it is dropped, audio stops.
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+impl RodioSink {
+    pub fn open() -> Result<Self, AudioError> {
+        let (stream, handle) = OutputStream::try_default()?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+        })
+    }
+}
+
+impl AudioSink for RodioSink {
+    fn open_track(&self, source: BoxedSource) -> Result<Box<dyn Track>, AudioError> {
+        let sink = Sink::try_new(&self.handle)?;
+        sink.set_volume(0.0);
+        sink.append(source);
+        Ok(Box::new(sink))
+    }
+
+    fn play_once(&self, source: BoxedSource) -> Result<(), AudioError> {
+        Ok(self.handle.play_raw(source)?)
+    }
+
+    fn reopen(&mut self) -> Result<(), AudioError> {
+        *self = Self::open()?;
+        Ok(())
+    }
+}
+
+impl Track for Sink {
+    fn volume(&self) -> f32 {
+        Sink::volume(self)
+    }
+
+    fn set_volume(&self, volume: f32) {
+        Sink::set_volume(self, volume);
+    }
+
+    fn set_speed(&self, speed: f32) {
+        Sink::set_speed(self, speed);
+    }
+
+    fn pause(&self) {
+        Sink::pause(self);
+    }
+
+    fn play(&self) {
+        Sink::play(self);
+    }
+
+    fn stop(&self) {
+        Sink::stop(self);
+    }
+
+    fn finished(&self) -> bool {
+        Sink::empty(self)
+    }
+}
+
+/// How a [`CaptureSink`] track was left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CapturedTrack {
+    pub volume: f32,
+    pub speed: f32,
+    pub paused: bool,
+    pub stopped: bool,
+}
+
+/// An output that plays nothing and keeps every track it opened, in order,
+/// so the engine can be checked without audio hardware. Clones share the
+/// same tracks: hand one to the engine and keep one to look at.
+///
+/// Nothing pulls its tracks, so a track only finishes when it is stopped.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureSink {
+    tracks: Arc<Mutex<Vec<Arc<Mutex<CapturedTrack>>>>>,
+    one_shots: Arc<AtomicUsize>,
+    reopened: Arc<AtomicUsize>,
+}
+
+impl CaptureSink {
+    /// Every track opened so far, stopped ones included, oldest first.
+    pub fn tracks(&self) -> Vec<CapturedTrack> {
+        let tracks = self.tracks.lock().unwrap_or_else(|e| e.into_inner());
+        tracks.iter().map(|t| *t.lock().unwrap_or_else(|e| e.into_inner())).collect()
+    }
+
+    /// The tracks not stopped yet, oldest first.
+    pub fn live_tracks(&self) -> Vec<CapturedTrack> {
+        self.tracks().into_iter().filter(|t| !t.stopped).collect()
+    }
+
+    /// How many one-shots were played.
+    pub fn one_shots(&self) -> usize {
+        self.one_shots.load(Ordering::Relaxed)
+    }
+
+    /// How many times the engine opened the output again.
+    pub fn reopened(&self) -> usize {
+        self.reopened.load(Ordering::Relaxed)
+    }
+}
+
+impl AudioSink for CaptureSink {
+    fn open_track(&self, _source: BoxedSource) -> Result<Box<dyn Track>, AudioError> {
+        let track = Arc::new(Mutex::new(CapturedTrack {
+            volume: 0.0,
+            speed: 1.0,
+            paused: false,
+            stopped: false,
+        }));
+        let mut tracks = self.tracks.lock().unwrap_or_else(|e| e.into_inner());
+        tracks.push(Arc::clone(&track));
+        Ok(Box::new(CaptureTrack(track)))
+    }
+
+    fn play_once(&self, _source: BoxedSource) -> Result<(), AudioError> {
+        self.one_shots.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn reopen(&mut self) -> Result<(), AudioError> {
+        self.reopened.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+struct CaptureTrack(Arc<Mutex<CapturedTrack>>);
+
+impl CaptureTrack {
+    fn with(&self, f: impl FnOnce(&mut CapturedTrack)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+impl Track for CaptureTrack {
+    fn volume(&self) -> f32 {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).volume
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.with(|t| t.volume = volume);
+    }
+
+    fn set_speed(&self, speed: f32) {
+        self.with(|t| t.speed = speed);
+    }
+
+    fn pause(&self) {
+        self.with(|t| t.paused = true);
+    }
+
+    fn play(&self) {
+        self.with(|t| t.paused = false);
+    }
+
+    fn stop(&self) {
+        self.with(|t| t.stopped = true);
+    }
+
+    fn finished(&self) -> bool {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).stopped
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bank::SAMPLE_EXTENSIONS;
+
+/// Folder the backing loops are looked for in.
+pub const BACKING_ROOT: &str = "backing";
+
+/// Where the backing loops are and how loud they play. Saved with the
+/// settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackingConfig {
+    pub dir: PathBuf,
+
+    /// Level (0..1) under the master volume; the bellows don't apply.
+    pub volume: f32,
+}
+
+impl Default for BackingConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from(BACKING_ROOT),
+            volume: 0.6,
+        }
+    }
+}
+
+/// One backing loop (a tabla theka, a lehra), possibly recorded at several
+/// tempos. Each file is exactly one cycle, or a whole number of them, and
+/// says its tempo in its name: `teental-80bpm.wav`, `teental-120bpm.wav`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackingTrack {
+    pub name: String,
+
+    /// (tempo in BPM, file), slowest first. A file without a tempo in its
+    /// name is listed with no tempo and plays as recorded.
+    pub takes: Vec<(Option<f32>, PathBuf)>,
+}
+
+impl BackingTrack {
+    /// The take recorded nearest `bpm`, and the speed to play it at to land
+    /// on `bpm` exactly (which shifts its pitch a little).
+    pub fn take_for(&self, bpm: f32) -> Option<(&Path, f32)> {
+        let (take_bpm, path) = self.takes.iter().min_by(|(a, _), (b, _)| {
+            let off = |t: &Option<f32>| t.map_or(f32::MAX, |t| (t - bpm).abs());
+            off(a).total_cmp(&off(b))
+        })?;
+        let speed = take_bpm.map_or(1.0, |t| bpm / t);
+        Some((path.as_path(), speed))
+    }
+}
+
+/// Split `teental-80bpm` into ("teental", 80).
+fn parse_take_name(stem: &str) -> (String, Option<f32>) {
+    let lower = stem.to_ascii_lowercase();
+    if let Some(rest) = lower.strip_suffix("bpm") {
+        if let Some((name, bpm)) = rest.rsplit_once(['-', '_', ' ']) {
+            if let Ok(bpm) = bpm.parse::<f32>() {
+                if bpm > 0.0 {
+                    return (stem[..name.len()].to_string(), Some(bpm));
+                }
+            }
+        }
+    }
+    (stem.to_string(), None)
+}
+
+/// Backing loops in `dir`, grouped by name and sorted by it.
+pub fn scan_backing(dir: impl AsRef<Path>) -> Vec<BackingTrack> {
+    let Ok(entries) = std::fs::read_dir(dir.as_ref()) else {
+        return Vec::new();
+    };
+
+    let mut tracks: Vec<BackingTrack> = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        let ext = path.extension().and_then(|x| x.to_str()).map(|x| x.to_ascii_lowercase());
+        if !path.is_file() || !ext.is_some_and(|x| SAMPLE_EXTENSIONS.contains(&x.as_str())) {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        let (name, bpm) = parse_take_name(stem);
+        match tracks.iter_mut().find(|t| t.name == name) {
+            Some(track) => track.takes.push((bpm, path)),
+            None => tracks.push(BackingTrack {
+                name,
+                takes: vec![(bpm, path)],
+            }),
+        }
+    }
+
+    for track in &mut tracks {
+        track.takes.sort_by(|(a, _), (b, _)| a.unwrap_or(0.0).total_cmp(&b.unwrap_or(0.0)));
+    }
+    tracks.sort_by(|a, b| a.name.cmp(&b.name));
+    tracks
+}
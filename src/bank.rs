@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+use crate::note::Note;
+
+/// Root folder of the samples. Its own files are the default bank; each
+/// subfolder is another bank (voice / stop configuration).
+pub const SAMPLES_ROOT: &str = "harmonium-sounds";
+
+/// Sample formats the audio engine can play.
+pub const SAMPLE_EXTENSIONS: [&str; 4] = ["wav", "mp3", "ogg", "flac"];
+
+/// One set of samples (e.g. a single reed, a bright double-reed stop).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleBank {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+/// Banks under `root`, in program-change order: the root itself (if it has
+/// samples), then its subfolders sorted by name. Prefix folder names with
+/// numbers ("01-single-reed") to pick the order.
+pub fn scan_banks(root: impl AsRef<Path>) -> Vec<SampleBank> {
+    let root = root.as_ref();
+    let mut banks = Vec::new();
+
+    if has_samples(root) {
+        banks.push(SampleBank {
+            name: "default".to_string(),
+            dir: root.to_path_buf(),
+        });
+    }
+
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return banks;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && has_samples(p))
+        .collect();
+    subdirs.sort();
+
+    banks.extend(subdirs.into_iter().map(|dir| SampleBank {
+        name: dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        dir,
+    }));
+    banks
+}
+
+/// The sample file of `note` in `dir`, like `harmonium-sounds/c3.wav`
+/// (`.mp3`, `.ogg` and `.flac` too).
+pub fn sample_path(dir: &Path, note: &str) -> Option<PathBuf> {
+    SAMPLE_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{note}.{ext}")))
+        .find(|p| p.is_file())
+}
+
+/// Sample files in `dir` not named after a note ("take 3.wav"), sorted; their
+/// pitch says which note they are (see `sample_pitch`).
+pub fn unlabeled_samples(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && is_sample(p))
+        .filter(|p| p.file_stem().and_then(|s| s.to_str()).and_then(Note::parse).is_none())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn is_sample(path: &Path) -> bool {
+    let ext = path.extension().and_then(|x| x.to_str()).map(|x| x.to_ascii_lowercase());
+    ext.is_some_and(|x| SAMPLE_EXTENSIONS.contains(&x.as_str()))
+}
+
+fn has_samples(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+
+    entries.flatten().any(|e| {
+        let path = e.path();
+        path.is_file() && is_sample(&path)
+    })
+}
@@ -0,0 +1,89 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use egui::{Color32, Sense, Stroke, Vec2};
+
+use crate::metronome::{BeatKind, Tala};
+
+/// A tala cycle drawn as a clock face: one dot per beat, sam at the top,
+/// the vibhag markers (X, 2, 0, 3...) beside the beats that carry them, and
+/// a hand sweeping round while the metronome runs.
+pub struct BeatWheel {
+    tala: Tala,
+
+    /// Current beat and how far through it, while running.
+    position: Option<(usize, f32)>,
+}
+
+impl BeatWheel {
+    pub fn new(tala: Tala, position: Option<(usize, f32)>) -> Self {
+        Self { tala, position }
+    }
+}
+
+impl egui::Widget for BeatWheel {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let side = ui.available_width().min(200.0);
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(side), Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        let center = rect.center();
+        let radius = side / 2.0 - 24.0;
+        let count = self.tala.beat_count();
+
+        // Clockwise from the top.
+        let at = |turns: f32, r: f32| {
+            let t = turns * TAU - FRAC_PI_2;
+            center + Vec2::new(t.cos(), t.sin()) * r
+        };
+
+        painter.circle_stroke(center, radius, Stroke::new(1.0, visuals.weak_text_color()));
+
+        let accent = Color32::from_rgb(230, 140, 40);
+        for beat in 0..count {
+            let turns = beat as f32 / count as f32;
+            let kind = self.tala.beat_kind(beat);
+            let size = match kind {
+                BeatKind::Sam => 9.0,
+                BeatKind::Tali | BeatKind::Khali => 7.0,
+                BeatKind::Plain => 4.5,
+            };
+            let current = self.position.is_some_and(|(b, _)| b == beat);
+            let fill = match (current, kind) {
+                (true, _) => accent,
+                (false, BeatKind::Khali) => visuals.extreme_bg_color,
+                (false, _) => visuals.strong_text_color(),
+            };
+            let dot = at(turns, radius);
+            painter.circle(dot, size, fill, Stroke::new(1.5, visuals.strong_text_color()));
+
+            if let Some(marker) = self.tala.marker(beat) {
+                painter.text(
+                    at(turns, radius + 16.0),
+                    egui::Align2::CENTER_CENTER,
+                    marker,
+                    egui::FontId::proportional(14.0),
+                    visuals.text_color(),
+                );
+            }
+        }
+
+        if let Some((beat, fraction)) = self.position {
+            let turns = (beat as f32 + fraction) / count as f32;
+            painter.line_segment([center, at(turns, radius - 12.0)], Stroke::new(2.0, accent));
+            painter.text(
+                center + Vec2::new(0.0, 14.0),
+                egui::Align2::CENTER_CENTER,
+                format!("{}", beat + 1),
+                egui::FontId::proportional(18.0),
+                visuals.strong_text_color(),
+            );
+        }
+        painter.circle_filled(center, 3.0, visuals.strong_text_color());
+
+        response
+    }
+}
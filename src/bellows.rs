@@ -1,37 +1,199 @@
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::tremor::{TremorDetector, TremorParams, VibratoTarget};
+
+/// The learned noise floor never drops below this (deg/sec), so a sensor
+/// that reads dead still for a while can still learn from noise later.
+const MIN_NOISE_FLOOR_DEG_PER_S: f32 = 0.05;
+
+/// Most the learned deadzone grows to (deg/sec): the top of the manual
+/// slider.
+const MAX_ADAPTIVE_DEADZONE_DEG_PER_S: f32 = 40.0;
+
+/// How the lid drives the bellows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BellowsMode {
+    /// Classic harmonium: moving the lid pumps air (speed = volume).
+    Velocity,
+
+    /// Hold the lid at a level: the angle itself is the volume.
+    Position,
+
+    /// Mix of both: lid level sustains, fast pumps add accents on top.
+    Hybrid,
+}
+
+/// How angular velocity is estimated from the angle samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VelocityEstimator {
+    /// (θ_now - θ_prev) / dt, then EMA on the speed. Simple, noisy at low rates.
+    FiniteDifference,
+
+    /// Alpha-beta tracker over the angle. Much smoother omega at low sensor rates;
+    /// the EMA stage is skipped because the tracker already smooths.
+    AlphaBeta,
+}
+
+/// How the target amplitude becomes the final amplitude.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AirModel {
+    /// One-pole attack/release envelope.
+    Envelope,
+
+    /// Simulated bellows with inertia, stiffness and leakage: pumping pushes the
+    /// pressure up, it overshoots a little, and bleeds away when you stop.
+    SpringMass,
+}
 
 /// Settings (you'll control these with sliders in the GUI).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BellowsParams {
+    /// Which signal turns into amplitude (lid speed or lid angle).
+    pub mode: BellowsMode,
+
     /// Ignore motion smaller than this (deg/sec). Helps remove jitter.
     pub deadzone_deg_per_s: f32,
 
+    /// Learn the deadzone from the sensor's noise floor instead of using the slider.
+    pub adaptive_deadzone: bool,
+
+    /// Adaptive deadzone = learned noise floor × this.
+    pub noise_floor_multiplier: f32,
+
+    /// Once engaged, the bellows only disengages when speed drops below this (deg/sec).
+    /// Keep it below the deadzone; the gap is the hysteresis band that stops flutter.
+    pub deadzone_release_deg_per_s: f32,
+
+    /// Once engaged, stay engaged for at least this long (milliseconds).
+    pub min_hold_ms: f32,
+
     /// Motion speed (deg/sec) that should feel like "full pumping".
     pub vmax_deg_per_s: f32,
 
     /// Curve shaping. >1 makes it easier to play softly.
     pub gamma: f32,
 
-    /// Exponential moving average alpha for smoothing speed.
+    /// Which velocity estimator feeds the speed stage.
+    pub estimator: VelocityEstimator,
+
+    /// Alpha-beta tracker: how much a new reading corrects the angle estimate (0..1).
+    pub ab_alpha: f32,
+
+    /// Alpha-beta tracker: how much a new reading corrects the velocity estimate (0..1).
+    /// Keep it well below alpha.
+    pub ab_beta: f32,
+
+    /// Exponential moving average alpha used while speed is rising.
     /// Range: 0..1. Smaller = smoother but slower response.
-    pub ema_alpha: f32,
+    pub ema_alpha_rise: f32,
+
+    /// Exponential moving average alpha used while speed is falling.
+    /// Lower than the rise alpha = catch fast pumps but ignore stop jitter.
+    pub ema_alpha_fall: f32,
 
     /// How quickly the "air" rises when you start pumping (milliseconds).
     pub attack_ms: f32,
 
     /// How slowly the "air" falls when you stop pumping (milliseconds).
     pub release_ms: f32,
+
+    /// Envelope or physical spring-mass model for the final stage.
+    pub air_model: AirModel,
+
+    /// Spring-mass: how heavy the bellows is (bigger = slower, more overshoot).
+    pub spring_mass: f32,
+
+    /// Spring-mass: how hard the pumping pushes pressure toward the target.
+    pub spring_stiffness: f32,
+
+    /// Spring-mass: friction on pressure changes (bigger = less wobble).
+    pub spring_damping: f32,
+
+    /// Spring-mass: how fast the air leaks out through the reeds (per second).
+    pub spring_leak_per_s: f32,
+
+    /// Position mode: angle (deg) that maps to silence.
+    pub position_min_deg: f32,
+
+    /// Position mode: angle (deg) that maps to full volume.
+    pub position_max_deg: f32,
+
+    /// Hybrid mode: 0 = all velocity, 1 = all position.
+    pub hybrid_blend: f32,
+
+    /// High-pass cutoff (Hz) for drift rejection. Angle changes slower than this
+    /// (thermal drift, a slowly sagging hinge) are removed before velocity is
+    /// computed. 0 = off (the default; slow strokes would be filtered too).
+    /// Position mode still sees the real angle.
+    pub drift_cutoff_hz: f32,
+
+    /// How many recent angle samples the median filter looks at.
+    /// 1 = filter off. Odd numbers work best (3 or 5 is plenty).
+    pub median_window: usize,
+
+    /// Samples further than this (deg) from the recent median are treated as spikes
+    /// and replaced by the median. 0 = outlier rejection off.
+    pub outlier_max_jump_deg: f32,
+
+    /// A lid flick whose angular acceleration exceeds this (deg/s²) is an accent.
+    /// 0 = accent detection off.
+    pub accent_accel_deg_per_s2: f32,
+
+    /// How much an accent adds on top of the amplitude (sforzando), 0..1.
+    pub accent_boost: f32,
+
+    /// How quickly the accent boost fades away (milliseconds).
+    pub accent_decay_ms: f32,
+
+    /// Ignore new accents for this long after one fired (milliseconds).
+    pub accent_cooldown_ms: f32,
+
+    /// A small fast shake of the lid played as vibrato on the held notes.
+    pub tremor: TremorParams,
 }
 
 impl Default for BellowsParams {
     fn default() -> Self {
         Self {
+            mode: BellowsMode::Velocity,
             deadzone_deg_per_s: 8.0,
+            adaptive_deadzone: false,
+            noise_floor_multiplier: 3.0,
+            deadzone_release_deg_per_s: 5.0,
+            min_hold_ms: 80.0,
             vmax_deg_per_s: 50.0,
             gamma: 2.0,
-            ema_alpha: 0.12,
+            estimator: VelocityEstimator::FiniteDifference,
+            ab_alpha: 0.5,
+            ab_beta: 0.1,
+            ema_alpha_rise: 0.12,
+            ema_alpha_fall: 0.12,
             attack_ms: 250.0,
             release_ms: 400.0,
+            air_model: AirModel::Envelope,
+            spring_mass: 1.0,
+            spring_stiffness: 60.0,
+            spring_damping: 10.0,
+            spring_leak_per_s: 1.5,
+            position_min_deg: 60.0,
+            position_max_deg: 120.0,
+            hybrid_blend: 0.5,
+            drift_cutoff_hz: 0.0,
+            median_window: 3,
+            outlier_max_jump_deg: 20.0,
+            accent_accel_deg_per_s2: 0.0,
+            accent_boost: 0.4,
+            accent_decay_ms: 150.0,
+            accent_cooldown_ms: 250.0,
+            tremor: TremorParams::default(),
         }
     }
 }
@@ -54,8 +216,17 @@ pub struct BellowsOutput {
     /// Target amplitude after deadzone + normalization + curve.
     pub a_target: f32,
 
-    /// Final amplitude after attack/release envelope.
+    /// Angular acceleration in degrees/second² (used for accent detection).
+    pub accel_deg_per_s2: f32,
+
+    /// True on the update where a lid flick was detected.
+    pub accent: bool,
+
+    /// Final amplitude after attack/release envelope (+ any accent boost).
     pub a: f32,
+
+    /// Lid tremor, -1..1 (0 when the lid isn't shaking or vibrato is off).
+    pub vibrato: f32,
 }
 
 impl Default for BellowsOutput {
@@ -67,7 +238,10 @@ impl Default for BellowsOutput {
             speed_raw: 0.0,
             speed_smooth: 0.0,
             a_target: 0.0,
+            accel_deg_per_s2: 0.0,
+            accent: false,
             a: 0.0,
+            vibrato: 0.0,
         }
     }
 }
@@ -77,11 +251,39 @@ impl Default for BellowsOutput {
 pub struct BellowsState {
     pub params: BellowsParams,
 
+    /// Previous angle after drift removal (what velocity is computed from).
     prev_theta_deg: Option<f32>,
-    prev_t: Option<Instant>,
+    prev_t: Option<Duration>,
+
+    /// Slowly-tracking angle baseline for the drift high-pass.
+    drift_baseline: f32,
+
+    /// Recent raw angles for the median / outlier stage.
+    recent_theta: VecDeque<f32>,
+
+    /// Alpha-beta tracker memory (angle and velocity estimates).
+    ab_theta: f32,
+    ab_omega: f32,
+
+    /// Learned smoothed speed while the lid is at rest (deg/sec). None until learning starts.
+    noise_floor: Option<f32>,
+
+    /// Hysteresis state: is the velocity stage past the deadzone, and for how long.
+    engaged: bool,
+    engaged_for_sec: f32,
 
     speed_smooth: f32,
     a: f32,
+
+    /// Spring-mass model: rate of change of pressure (the model's "velocity").
+    pressure_rate: f32,
+
+    /// Accent detection: previous omega, current boost, time since last accent.
+    prev_omega: f32,
+    accent_level: f32,
+    since_accent_sec: f32,
+
+    tremor: TremorDetector,
 }
 
 impl BellowsState {
@@ -90,68 +292,146 @@ impl BellowsState {
             params,
             prev_theta_deg: None,
             prev_t: None,
+            drift_baseline: 0.0,
+            recent_theta: VecDeque::new(),
+            ab_theta: 0.0,
+            ab_omega: 0.0,
+            noise_floor: None,
+            engaged: false,
+            engaged_for_sec: 0.0,
             speed_smooth: 0.0,
             a: 0.0,
+            pressure_rate: 0.0,
+            prev_omega: 0.0,
+            accent_level: 0.0,
+            since_accent_sec: f32::INFINITY,
+            tremor: TremorDetector::default(),
         }
     }
 
     /// Update bellows using a new angle sample at time `t`.
     ///
+    /// `t` is a timestamp measured from any fixed origin (app start, sensor start,
+    /// or zero in a simulation). Only differences between calls matter, which keeps
+    /// the pipeline deterministic and independent of the wall clock.
+    ///
     /// This is the "math pipeline":
-    /// angle -> velocity -> abs speed -> smooth -> normalize -> curve -> envelope
-    pub fn update(&mut self, theta_deg: f32, t: Instant) -> BellowsOutput {
+    /// angle -> despike -> drift high-pass -> velocity -> abs speed -> smooth -> normalize
+    ///       -> curve -> envelope
+    pub fn update(&mut self, theta_deg: f32, t: Duration) -> BellowsOutput {
         // First sample: we can't compute velocity yet.
         let (prev_theta, prev_t) = match (self.prev_theta_deg, self.prev_t) {
             (Some(pt), Some(ptt)) => (pt, ptt),
             _ => {
-                self.prev_theta_deg = Some(theta_deg);
+                self.recent_theta.clear();
+                self.recent_theta.push_back(theta_deg);
+                self.drift_baseline = theta_deg;
+                self.ab_theta = 0.0;
+                self.ab_omega = 0.0;
+                self.prev_theta_deg = Some(0.0);
                 self.prev_t = Some(t);
 
                 let mut out = BellowsOutput::default();
                 out.theta_deg = theta_deg;
-                out.a = self.a;
+                out.a = clamp01(self.a + self.accent_level);
                 out.speed_smooth = self.speed_smooth;
                 return out;
             }
         };
 
-        let dt_sec = (t - prev_t).as_secs_f32();
+        let dt_sec = t.saturating_sub(prev_t).as_secs_f32();
         // Safety: if dt is too small (or 0), avoid division noise.
         if dt_sec <= 0.000_001 {
             let mut out = BellowsOutput::default();
             out.theta_deg = theta_deg;
-            out.a = self.a;
+            out.a = clamp01(self.a + self.accent_level);
             out.speed_smooth = self.speed_smooth;
             return out;
         }
 
+        // 0) Remove single-sample spikes before they turn into huge velocities.
+        //    (done after the dt check so repeated frames don't refill the window)
+        let theta_deg = self.despike(theta_deg);
+
+        // 0a) A small fast shake on top of the angle is a tremor (vibrato).
+        let vibrato = match self.params.tremor.vibrato {
+            VibratoTarget::Off => 0.0,
+            _ => self.tremor.update(theta_deg, dt_sec, &self.params.tremor),
+        };
+
+        // 0b) Remove slow drift (near-DC movement) so it doesn't read as pumping.
+        let theta_hp = self.remove_drift(theta_deg, dt_sec);
+
         // 1) Angular velocity (deg/s)
-        let omega = (theta_deg - prev_theta) / dt_sec;
+        //    The tracker runs whichever estimator is picked, so switching to
+        //    it mid-stroke doesn't start it from a stale angle.
+        let tracked = self.alpha_beta_step(theta_hp, dt_sec);
+        let omega = match self.params.estimator {
+            VelocityEstimator::FiniteDifference => (theta_hp - prev_theta) / dt_sec,
+            VelocityEstimator::AlphaBeta => tracked,
+        };
 
         // 2) Bellows cares about magnitude (direction doesn't matter)
         let speed_raw = omega.abs();
 
-        // 3) Smooth speed (EMA)
-        let alpha = clamp01(self.params.ema_alpha);
-        self.speed_smooth = ema(self.speed_smooth, speed_raw, alpha);
+        // 3) Smooth speed (EMA, separate rise/fall alphas).
+        //    The alpha-beta tracker is already smooth.
+        self.speed_smooth = match self.params.estimator {
+            VelocityEstimator::FiniteDifference => {
+                let alpha = if speed_raw > self.speed_smooth {
+                    self.params.ema_alpha_rise
+                } else {
+                    self.params.ema_alpha_fall
+                };
+                ema(self.speed_smooth, speed_raw, clamp01(alpha))
+            }
+            VelocityEstimator::AlphaBeta => speed_raw,
+        };
 
         // 4) Deadzone + normalize to 0..1
-        let x = normalize_with_deadzone(
-            self.speed_smooth,
-            self.params.deadzone_deg_per_s,
-            self.params.vmax_deg_per_s,
+        //    (both speed and angle are normalized; the mode picks which one is used)
+        self.learn_noise_floor(dt_sec);
+        let x_velocity = self.normalize_with_hysteresis(dt_sec);
+        let x_position = normalize_position(
+            theta_deg,
+            self.params.position_min_deg,
+            self.params.position_max_deg,
         );
 
-        // 5) Curve shaping
+        // 5) Curve shaping (+ blending in hybrid mode)
         let gamma = if self.params.gamma <= 0.0 { 1.0 } else { self.params.gamma };
-        let a_target = x.powf(gamma);
+        let a_target = match self.params.mode {
+            BellowsMode::Velocity => x_velocity.powf(gamma),
+            BellowsMode::Position => x_position.powf(gamma),
+            BellowsMode::Hybrid => {
+                let blend = clamp01(self.params.hybrid_blend);
+                let mixed = (1.0 - blend) * x_velocity + blend * x_position;
+                clamp01(mixed).powf(gamma)
+            }
+        };
+
+        // 6) Attack/Release envelope (smooth changes in amplitude),
+        //    or the physical bellows model
+        self.a = match self.params.air_model {
+            AirModel::Envelope => envelope_follow(
+                self.a,
+                a_target,
+                dt_sec,
+                self.params.attack_ms,
+                self.params.release_ms,
+            ),
+            AirModel::SpringMass => self.spring_mass_step(a_target, dt_sec),
+        };
 
-        // 6) Attack/Release envelope (smooth changes in amplitude)
-        self.a = envelope_follow(self.a, a_target, dt_sec, self.params.attack_ms, self.params.release_ms);
+        // 7) Accents: a sharp flick of the lid adds a short, decaying boost
+        let accel = (omega - self.prev_omega) / dt_sec;
+        let accent = self.detect_accent(accel, dt_sec);
+        let a_out = clamp01(self.a + self.accent_level);
 
         // Store current as previous
-        self.prev_theta_deg = Some(theta_deg);
+        self.prev_theta_deg = Some(theta_hp);
         self.prev_t = Some(t);
+        self.prev_omega = omega;
 
         BellowsOutput {
             dt_sec,
@@ -160,16 +440,243 @@ impl BellowsState {
             speed_raw,
             speed_smooth: self.speed_smooth,
             a_target,
-            a: self.a,
+            accel_deg_per_s2: accel,
+            accent,
+            a: a_out,
+            vibrato,
         }
     }
 
+    /// Decay the current accent boost and fire a new one on a sharp flick.
+    /// Returns true if an accent fired on this update.
+    fn detect_accent(&mut self, accel: f32, dt_sec: f32) -> bool {
+        // Decay the previous boost.
+        let decay_ms = self.params.accent_decay_ms;
+        self.accent_level = if decay_ms <= 0.0 {
+            0.0
+        } else {
+            self.accent_level * (-dt_sec * 1000.0 / decay_ms).exp()
+        };
+        self.since_accent_sec += dt_sec;
+
+        let threshold = self.params.accent_accel_deg_per_s2;
+        if threshold <= 0.0 || accel.abs() < threshold {
+            return false;
+        }
+        if self.since_accent_sec * 1000.0 < self.params.accent_cooldown_ms {
+            return false;
+        }
+
+        self.since_accent_sec = 0.0;
+        self.accent_level = clamp01(self.params.accent_boost);
+        debug!("accent: {accel:.0} deg/s²");
+        true
+    }
+
+    /// Median filter + outlier rejection over the last few raw angles.
+    ///
+    /// A reading that jumps further than `outlier_max_jump_deg` from the median
+    /// is replaced by the median; otherwise the median itself is returned.
+    fn despike(&mut self, theta_deg: f32) -> f32 {
+        let window = self.params.median_window.max(1);
+
+        self.recent_theta.push_back(theta_deg);
+        while self.recent_theta.len() > window {
+            self.recent_theta.pop_front();
+        }
+
+        if window == 1 {
+            return theta_deg;
+        }
+
+        let med = median(self.recent_theta.iter().copied());
+
+        let max_jump = self.params.outlier_max_jump_deg;
+        if max_jump > 0.0 && (theta_deg - med).abs() > max_jump {
+            // Don't let the spike pollute the window for the next samples.
+            if let Some(last) = self.recent_theta.back_mut() {
+                *last = med;
+            }
+        }
+
+        med
+    }
+
+    /// First-order high-pass on the angle: a baseline follows the angle slowly
+    /// (one-pole low-pass at `drift_cutoff_hz`) and is subtracted from it.
+    /// Steady drift at a constant rate settles to a constant offset, i.e. zero velocity.
+    ///
+    /// When the cutoff is 0 the baseline is frozen, so velocity is the raw velocity
+    /// and toggling the filter never causes a jump.
+    fn remove_drift(&mut self, theta_deg: f32, dt_sec: f32) -> f32 {
+        let fc = self.params.drift_cutoff_hz;
+        if fc > 0.0 {
+            let step = 1.0 - (-2.0 * std::f32::consts::PI * fc * dt_sec).exp();
+            self.drift_baseline += (theta_deg - self.drift_baseline) * step;
+        }
+
+        theta_deg - self.drift_baseline
+    }
+
+    /// Track the noise floor while the lid is still.
+    ///
+    /// A slow EMA (time constant ~2 s) of the smoothed speed, fed only with
+    /// speeds near the floor (up to `ACCEPT` times it). Pumping engages the
+    /// bellows and stops learning; slow pumping just under the deadzone is
+    /// well above the floor and is skipped too, so it can't drag the floor
+    /// (and with it the deadzone) up.
+    fn learn_noise_floor(&mut self, dt_sec: f32) {
+        const TAU_SEC: f32 = 2.0;
+        const ACCEPT: f32 = 2.0;
+
+        if self.engaged {
+            return;
+        }
+
+        let floor = self.noise_floor.unwrap_or_else(|| {
+            // Start from the manual deadzone so the first seconds behave sensibly.
+            self.params.deadzone_deg_per_s / self.params.noise_floor_multiplier.max(0.1)
+        });
+        if self.speed_smooth > floor * ACCEPT {
+            self.noise_floor = Some(floor);
+            return;
+        }
+
+        let step = 1.0 - (-dt_sec / TAU_SEC).exp();
+        let learned = floor + (self.speed_smooth - floor) * step;
+        self.noise_floor = Some(learned.max(MIN_NOISE_FLOOR_DEG_PER_S));
+    }
+
+    /// Learned noise floor (deg/sec), if any samples have been seen at rest.
+    pub fn noise_floor(&self) -> Option<f32> {
+        self.noise_floor
+    }
+
+    /// The engage threshold actually in use (manual slider or learned).
+    pub fn effective_deadzone(&self) -> f32 {
+        match (self.params.adaptive_deadzone, self.noise_floor) {
+            (true, Some(floor)) => (floor * self.params.noise_floor_multiplier.max(0.0))
+                .min(MAX_ADAPTIVE_DEADZONE_DEG_PER_S),
+            _ => self.params.deadzone_deg_per_s.max(0.0),
+        }
+    }
+
+    /// Release threshold, kept in the same ratio to the deadzone as the sliders.
+    fn effective_release(&self) -> f32 {
+        let manual_engage = self.params.deadzone_deg_per_s.max(0.000_1);
+        let ratio = (self.params.deadzone_release_deg_per_s / manual_engage).clamp(0.0, 1.0);
+
+        if self.params.adaptive_deadzone {
+            self.effective_deadzone() * ratio
+        } else {
+            self.params.deadzone_release_deg_per_s.clamp(0.0, self.effective_deadzone())
+        }
+    }
+
+    /// Deadzone with separate engage/disengage thresholds and a minimum hold time.
+    ///
+    /// - Not engaged: silent until speed rises above `deadzone_deg_per_s`.
+    /// - Engaged: measured from `deadzone_release_deg_per_s`, and only lets go once
+    ///   speed falls below it *and* `min_hold_ms` has passed.
+    fn normalize_with_hysteresis(&mut self, dt_sec: f32) -> f32 {
+        let engage = self.effective_deadzone();
+        let release = self.effective_release();
+        let speed = self.speed_smooth;
+
+        if self.engaged {
+            self.engaged_for_sec += dt_sec;
+
+            let held_long_enough = self.engaged_for_sec * 1000.0 >= self.params.min_hold_ms;
+            if speed < release && held_long_enough {
+                self.engaged = false;
+                debug!("bellows released at {speed:.1} deg/s");
+            }
+        } else if speed > engage {
+            debug!("bellows engaged at {speed:.1} deg/s");
+            self.engaged = true;
+            self.engaged_for_sec = 0.0;
+        }
+
+        if self.engaged {
+            normalize_with_deadzone(speed, release, self.params.vmax_deg_per_s)
+        } else {
+            0.0
+        }
+    }
+
+    /// One predict/correct step of the alpha-beta tracker. Returns the new omega estimate.
+    ///
+    ///   predict:  θp = θ + ω*dt
+    ///   residual: r  = θ_measured - θp
+    ///   correct:  θ  = θp + α*r,  ω = ω + β*r/dt
+    fn alpha_beta_step(&mut self, theta_deg: f32, dt_sec: f32) -> f32 {
+        let alpha = clamp01(self.params.ab_alpha);
+        let beta = clamp01(self.params.ab_beta);
+
+        let predicted = self.ab_theta + self.ab_omega * dt_sec;
+        let residual = theta_deg - predicted;
+
+        self.ab_theta = predicted + alpha * residual;
+        self.ab_omega += beta * residual / dt_sec;
+
+        self.ab_omega
+    }
+
+    /// Advance the spring-mass bellows by `dt_sec` with `drive` (0..1) as the pump force.
+    ///
+    ///   force    = stiffness*(drive - p) - damping*rate - leak*p
+    ///   rate    += force/mass * dt
+    ///   p       += rate * dt
+    ///
+    /// Integrated in small sub-steps so low sensor rates stay stable.
+    fn spring_mass_step(&mut self, drive: f32, dt_sec: f32) -> f32 {
+        const MAX_STEP_SEC: f32 = 0.002;
+
+        let mass = self.params.spring_mass.max(0.01);
+        let k = self.params.spring_stiffness.max(0.0);
+        let c = self.params.spring_damping.max(0.0);
+        let leak = self.params.spring_leak_per_s.max(0.0);
+
+        let steps = (dt_sec / MAX_STEP_SEC).ceil().clamp(1.0, 1000.0) as usize;
+        let h = dt_sec / steps as f32;
+
+        let mut p = self.a;
+        for _ in 0..steps {
+            let force = k * (drive - p) - c * self.pressure_rate - leak * p;
+            self.pressure_rate += force / mass * h;
+            p += self.pressure_rate * h;
+
+            // Bellows can't hold negative pressure, and the reeds saturate.
+            if p < 0.0 {
+                p = 0.0;
+                self.pressure_rate = self.pressure_rate.max(0.0);
+            } else if p > 1.0 {
+                p = 1.0;
+                self.pressure_rate = self.pressure_rate.min(0.0);
+            }
+        }
+
+        p
+    }
+
     /// Handy for debugging / calibration buttons later.
     pub fn reset(&mut self) {
         self.prev_theta_deg = None;
         self.prev_t = None;
+        self.recent_theta.clear();
+        self.drift_baseline = 0.0;
+        self.ab_theta = 0.0;
+        self.ab_omega = 0.0;
+        self.noise_floor = None;
+        self.engaged = false;
+        self.engaged_for_sec = 0.0;
         self.speed_smooth = 0.0;
         self.a = 0.0;
+        self.pressure_rate = 0.0;
+        self.prev_omega = 0.0;
+        self.accent_level = 0.0;
+        self.since_accent_sec = f32::INFINITY;
+        self.tremor.reset();
     }
 }
 
@@ -185,6 +692,23 @@ fn ema(prev: f32, input: f32, alpha: f32) -> f32 {
     prev + alpha * (input - prev)
 }
 
+/// Median of a handful of values (small windows, so a sort is fine).
+fn median(values: impl Iterator<Item = f32>) -> f32 {
+    let mut v: Vec<f32> = values.collect();
+    if v.is_empty() {
+        return 0.0;
+    }
+
+    v.sort_by(|a, b| a.total_cmp(b));
+    let mid = v.len() / 2;
+
+    if v.len() % 2 == 0 {
+        0.5 * (v[mid - 1] + v[mid])
+    } else {
+        v[mid]
+    }
+}
+
 /// Convert speed into [0..1] using deadzone and vmax.
 /// Anything below deadzone becomes 0.
 /// Anything above vmax becomes 1.
@@ -199,6 +723,17 @@ fn normalize_with_deadzone(speed: f32, deadzone: f32, vmax: f32) -> f32 {
     clamp01(x)
 }
 
+/// Convert an angle into [0..1] between `min_deg` and `max_deg`.
+/// If min > max the mapping is inverted (closing the lid gets louder).
+fn normalize_position(theta: f32, min_deg: f32, max_deg: f32) -> f32 {
+    let span = max_deg - min_deg;
+    if span.abs() < 0.000_1 {
+        return if theta >= max_deg { 1.0 } else { 0.0 };
+    }
+
+    clamp01((theta - min_deg) / span)
+}
+
 /// Smoothly move current amplitude toward target using different time constants for up vs down.
 /// Uses a simple one-pole filter:
 ///   step = 1 - exp(-dt/tau)
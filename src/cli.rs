@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::config::AppConfig;
+
+/// Play a harmonium by pumping your laptop lid.
+///
+/// Paths given here override the saved settings for this run only.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Folder with the note samples; each subfolder is another voice.
+    #[arg(long, value_name = "DIR")]
+    pub samples_dir: Option<PathBuf>,
+
+    /// Keymap file (.json, .toml or .yaml).
+    #[arg(long, value_name = "FILE")]
+    pub keymap: Option<String>,
+
+    /// Sample rate to ask the sensor for.
+    #[arg(long, value_name = "HZ")]
+    pub sensor_hz: Option<f32>,
+
+    /// Run the sensor and bellows without a window, printing the bellows
+    /// level once a second. Press Enter to stop.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Record incoming sensor samples to this CSV file from launch (for the
+    /// replay backend).
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Fold the command-line overrides into the loaded settings. Returns
+    /// what they replaced, to put back before the settings are saved.
+    pub fn apply(&self, config: &mut AppConfig) -> Overrides {
+        let mut overrides = Overrides::default();
+        if let Some(dir) = &self.samples_dir {
+            let saved = std::mem::replace(&mut config.audio.samples_root, dir.clone());
+            overrides.samples_root = Some((dir.clone(), saved));
+        }
+        if let Some(path) = &self.keymap {
+            let saved = config.keymap_path.replace(path.clone());
+            overrides.keymap_path = Some((path.clone(), saved));
+        }
+        if let Some(hz) = self.sensor_hz {
+            let saved = std::mem::replace(&mut config.devices.sensor_settings.hz, hz);
+            overrides.sensor_hz = Some((hz, saved));
+        }
+        overrides
+    }
+}
+
+/// Settings given on the command line, each with the saved value it
+/// replaced: (given, saved).
+#[derive(Debug, Default)]
+pub struct Overrides {
+    samples_root: Option<(PathBuf, PathBuf)>,
+    keymap_path: Option<(String, Option<String>)>,
+    sensor_hz: Option<(f32, f32)>,
+}
+
+impl Overrides {
+    /// Put the saved values back in `config` where it still holds what the
+    /// command line gave, so one run with `--keymap` doesn't change the
+    /// keymap of every later run. A setting changed in the app since is kept.
+    pub fn restore(&self, config: &mut AppConfig) {
+        if let Some((given, saved)) = &self.samples_root {
+            if config.audio.samples_root == *given {
+                config.audio.samples_root = saved.clone();
+            }
+        }
+        if let Some((given, saved)) = &self.keymap_path {
+            if config.keymap_path.as_ref() == Some(given) {
+                config.keymap_path = saved.clone();
+            }
+        }
+        if let Some((given, saved)) = self.sensor_hz {
+            if config.devices.sensor_settings.hz == given {
+                config.devices.sensor_settings.hz = saved;
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::arpeggiator::Arpeggiator;
+use crate::audio;
+use crate::backing::BackingConfig;
+use crate::bank;
+use crate::bellows::BellowsParams;
+use crate::dock::DockLayout;
+use crate::dual::DualConfig;
+use crate::metronome::MetronomeConfig;
+use crate::pitch_bend::AttackScoop;
+use crate::practice::PracticeConfig;
+use crate::punch::PunchConfig;
+use crate::remote::RemoteConfig;
+use crate::sensor::{SensorConfig, SensorKind};
+use crate::shortcuts::Shortcuts;
+
+/// Folder (inside the platform config dir) and file the settings live in.
+const CONFIG_DIR_NAME: &str = "harmonium-in-mac";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Where the settings file lives: e.g.
+/// `~/Library/Application Support/harmonium-in-mac/config.toml` on macOS,
+/// or next to the app if the platform has no config dir.
+pub fn config_path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(dir) => dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME),
+        None => PathBuf::from(CONFIG_FILE_NAME),
+    }
+}
+
+/// Every tunable setting that should survive a restart. Read on startup,
+/// written on exit; anything missing from the file keeps its default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub bellows: BellowsParams,
+    pub audio: AudioConfig,
+    pub fake_input: FakeInputConfig,
+    pub keymap_path: Option<String>,
+    pub devices: DeviceConfig,
+    pub shortcuts: Shortcuts,
+    pub ui: UiConfig,
+    pub dock: DockLayout,
+    pub metronome: MetronomeConfig,
+    pub backing: BackingConfig,
+    pub practice: PracticeConfig,
+    pub punch: PunchConfig,
+    pub arpeggiator: Arpeggiator,
+    pub remote: RemoteConfig,
+
+    /// The first-run setup was finished or skipped.
+    pub setup_done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub master_gain: f32,
+    pub enabled: bool,
+
+    /// Folder scanned for sample banks.
+    pub samples_root: PathBuf,
+
+    /// Sample folder of the bank that was playing.
+    pub sample_dir: Option<PathBuf>,
+
+    /// The second instrument of dual mode.
+    pub second: DualConfig,
+
+    /// How notes speak at low bellows pressure.
+    pub attack_scoop: AttackScoop,
+
+    /// Most notes sounding at once; past it the oldest is cut.
+    pub max_voices: usize,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            master_gain: 0.8,
+            enabled: true,
+            samples_root: PathBuf::from(bank::SAMPLES_ROOT),
+            sample_dir: None,
+            second: DualConfig::default(),
+            attack_scoop: AttackScoop::default(),
+            max_voices: audio::DEFAULT_MAX_VOICES,
+        }
+    }
+}
+
+/// The built-in sine / pump trajectory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FakeInputConfig {
+    pub pump_shape: bool,
+    pub frequency_hz: f32,
+    pub amplitude_deg: f32,
+}
+
+impl Default for FakeInputConfig {
+    fn default() -> Self {
+        Self {
+            pump_shape: false,
+            frequency_hz: 0.6,
+            amplitude_deg: 30.0,
+        }
+    }
+}
+
+/// How the window looks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    pub theme: Theme,
+
+    /// Zoom of the whole UI; 1 is the platform's normal size.
+    pub scale: f32,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Dark for a dim room, light for daylight, or whatever the OS is set to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "system",
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+
+    pub fn preference(self) -> egui::ThemePreference {
+        match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Dark => egui::ThemePreference::Dark,
+            Theme::Light => egui::ThemePreference::Light,
+        }
+    }
+}
+
+/// Which sensor and MIDI ports were in use. Ports are only reconnected if
+/// they were connected at exit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// Backend id of the sensor feeding the bellows (see `SensorKind::id`).
+    pub sensor: String,
+    pub sensor_settings: SensorConfig,
+
+    pub midi_out_port: String,
+    pub midi_out_connected: bool,
+    pub midi_channel: u8,
+    pub midi_mpe: bool,
+
+    pub midi_in_port: String,
+    pub midi_in_connected: bool,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            sensor: SensorKind::default_for_platform().id().to_string(),
+            sensor_settings: SensorConfig::default(),
+            midi_out_port: String::new(),
+            midi_out_connected: false,
+            midi_channel: 1,
+            midi_mpe: false,
+            midi_in_port: String::new(),
+            midi_in_connected: false,
+        }
+    }
+}
+
+impl DeviceConfig {
+    /// The saved sensor backend; an unknown id (e.g. from a newer version)
+    /// or one this platform can't run falls back to the platform's default.
+    pub fn sensor_kind(&self) -> SensorKind {
+        SensorKind::ALL
+            .into_iter()
+            .find(|k| k.id() == self.sensor && k.is_available())
+            .unwrap_or_else(SensorKind::default_for_platform)
+    }
+}
+
+impl AppConfig {
+    /// Load settings from `path`. A missing file just means a first run.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Failed to read settings file: {e}")),
+        };
+
+        toml::from_str(&text).map_err(|e| format!("Failed to parse settings TOML: {e}"))
+    }
+
+    /// Write settings to `path`, creating its folder if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create settings folder {dir:?}: {e}"))?;
+        }
+
+        let text = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to encode settings: {e}"))?;
+        fs::write(path, text).map_err(|e| format!("Failed to write settings file: {e}"))
+    }
+}
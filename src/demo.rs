@@ -0,0 +1,82 @@
+use std::time::Instant;
+
+use crate::note::Note;
+use crate::simulation::Trajectory;
+
+/// Sa of the demo phrase as a MIDI note before the tonic is added (c3).
+const SA_MIDI: i32 = 48;
+
+/// (start s, length s, semitones above Sa): a slow rise and fall through
+/// Bilawal over a low Sa drone, as a player might warm up.
+const PHRASE: &[(f32, f32, i32)] = &[
+    (0.0, 10.5, -12),
+    (0.5, 0.5, 0),
+    (1.0, 0.5, 2),
+    (1.5, 0.5, 4),
+    (2.0, 0.5, 5),
+    (2.5, 0.5, 7),
+    (3.0, 0.5, 9),
+    (3.5, 0.5, 11),
+    (4.0, 1.0, 12),
+    (5.0, 0.5, 11),
+    (5.5, 0.5, 9),
+    (6.0, 0.5, 7),
+    (6.5, 0.5, 5),
+    (7.0, 0.5, 4),
+    (7.5, 0.5, 2),
+    (8.0, 2.5, 0),
+];
+
+/// Whole demo, including the release after the last note.
+const DEMO_SEC: f32 = 11.5;
+
+/// The bellows stop pumping here, so the last notes fade out naturally.
+const PUMP_UNTIL_SEC: f32 = 9.5;
+
+/// The demo's bellows: a steady hand on the lid.
+const PUMP: Trajectory = Trajectory::Pump {
+    center_deg: 60.0,
+    amplitude_deg: 15.0,
+    period_sec: 1.6,
+};
+
+/// A scripted phrase with its own lid movement, played through the real
+/// keys, bellows and audio, so the instrument can be heard with no sensor.
+#[derive(Debug, Clone, Copy)]
+pub struct Demo {
+    started: Instant,
+}
+
+impl Demo {
+    pub fn start(now: Instant) -> Self {
+        Self { started: now }
+    }
+
+    fn t_sec(&self, now: Instant) -> f32 {
+        now.saturating_duration_since(self.started).as_secs_f32()
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        self.t_sec(now) >= DEMO_SEC
+    }
+
+    /// 0..1 through the demo.
+    pub fn progress(&self, now: Instant) -> f32 {
+        (self.t_sec(now) / DEMO_SEC).min(1.0)
+    }
+
+    /// Notes sounding at `now`, with Sa on pitch class `tonic`.
+    pub fn notes_at(&self, now: Instant, tonic: u8) -> Vec<Note> {
+        let t = self.t_sec(now);
+        PHRASE
+            .iter()
+            .filter(|(start, len, _)| (*start..start + len).contains(&t))
+            .filter_map(|(_, _, degree)| Note::from_midi(SA_MIDI + tonic as i32 + degree))
+            .collect()
+    }
+
+    /// Lid angle (deg) at `now`.
+    pub fn angle_at(&self, now: Instant) -> f32 {
+        PUMP.angle_at(self.t_sec(now).min(PUMP_UNTIL_SEC))
+    }
+}
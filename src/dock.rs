@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+/// A group of controls that can be moved around the window or hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Sensor,
+    Audio,
+    Controls,
+    Meters,
+    Keymap,
+    Log,
+    Rhythm,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 7] = [
+        Panel::Sensor,
+        Panel::Audio,
+        Panel::Controls,
+        Panel::Meters,
+        Panel::Keymap,
+        Panel::Log,
+        Panel::Rhythm,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Panel::Sensor => "Sensor",
+            Panel::Audio => "Audio, presets & MIDI",
+            Panel::Controls => "Controls",
+            Panel::Meters => "Live values",
+            Panel::Keymap => "Keymap & notes",
+            Panel::Log => "Log",
+            Panel::Rhythm => "Rhythm & practice",
+        }
+    }
+}
+
+/// Where a panel goes. The sides stack their panels top to bottom; the
+/// centre and the bottom put them side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dock {
+    Left,
+    Center,
+    Right,
+    Bottom,
+    Hidden,
+}
+
+impl Dock {
+    pub const ALL: [Dock; 5] = [Dock::Left, Dock::Center, Dock::Right, Dock::Bottom, Dock::Hidden];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Dock::Left => "left",
+            Dock::Center => "centre",
+            Dock::Right => "right",
+            Dock::Bottom => "bottom",
+            Dock::Hidden => "hidden",
+        }
+    }
+}
+
+/// Where every panel is and how big the side and bottom areas are. Saved
+/// with the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockLayout {
+    pub sensor: Dock,
+    pub audio: Dock,
+    pub controls: Dock,
+    pub meters: Dock,
+    pub keymap: Dock,
+    pub log: Dock,
+    pub rhythm: Dock,
+
+    pub left_width: f32,
+    pub right_width: f32,
+    pub bottom_height: f32,
+}
+
+impl Default for DockLayout {
+    fn default() -> Self {
+        Self {
+            sensor: Dock::Left,
+            audio: Dock::Left,
+            controls: Dock::Center,
+            meters: Dock::Center,
+            keymap: Dock::Bottom,
+            log: Dock::Bottom,
+            rhythm: Dock::Right,
+            left_width: 360.0,
+            right_width: 360.0,
+            bottom_height: 280.0,
+        }
+    }
+}
+
+impl DockLayout {
+    pub fn place(&self, panel: Panel) -> Dock {
+        match panel {
+            Panel::Sensor => self.sensor,
+            Panel::Audio => self.audio,
+            Panel::Controls => self.controls,
+            Panel::Meters => self.meters,
+            Panel::Keymap => self.keymap,
+            Panel::Log => self.log,
+            Panel::Rhythm => self.rhythm,
+        }
+    }
+
+    pub fn place_mut(&mut self, panel: Panel) -> &mut Dock {
+        match panel {
+            Panel::Sensor => &mut self.sensor,
+            Panel::Audio => &mut self.audio,
+            Panel::Controls => &mut self.controls,
+            Panel::Meters => &mut self.meters,
+            Panel::Keymap => &mut self.keymap,
+            Panel::Log => &mut self.log,
+            Panel::Rhythm => &mut self.rhythm,
+        }
+    }
+
+    /// The panels in `dock`, in the usual order.
+    pub fn panels_in(&self, dock: Dock) -> Vec<Panel> {
+        Panel::ALL.into_iter().filter(|p| self.place(*p) == dock).collect()
+    }
+}
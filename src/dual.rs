@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{AudioEngine, AudioError};
+use crate::harmonizer::Harmonizer;
+use crate::scale::ScaleLock;
+
+/// The second instrument of dual mode, saved with the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DualConfig {
+    pub enabled: bool,
+
+    /// Sample folder of its bank; None: the first bank found.
+    pub sample_dir: Option<PathBuf>,
+
+    /// Level under the master volume, against the first instrument's 1.0.
+    pub gain: f32,
+
+    /// Its own harmonizer stop (the first instrument keeps the main one).
+    pub harmonizer: Harmonizer,
+}
+
+impl Default for DualConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_dir: None,
+            gain: 0.7,
+            harmonizer: Harmonizer::default(),
+        }
+    }
+}
+
+/// A second engine beside the main one, e.g. a female-reed drone bank under
+/// a male-reed lead. It plays the keymap zones marked `"instrument": 2`; the
+/// bellows, sustain, meend and lid mute drive both alike. The two are mixed
+/// by the system, each on its own output stream.
+pub struct SecondInstrument {
+    pub engine: AudioEngine,
+    pub sample_dir: PathBuf,
+    pub gain: f32,
+    pub harmonizer: Harmonizer,
+}
+
+impl SecondInstrument {
+    pub fn open(cfg: &DualConfig, sample_dir: &Path) -> Result<Self, AudioError> {
+        Ok(Self {
+            engine: AudioEngine::new(sample_dir)?,
+            sample_dir: sample_dir.to_path_buf(),
+            gain: cfg.gain,
+            harmonizer: cfg.harmonizer.clone(),
+        })
+    }
+
+    /// Switch bank; sounding notes restart with the new samples.
+    pub fn set_samples_dir(&mut self, sample_dir: &Path) -> Result<(), AudioError> {
+        self.sample_dir = sample_dir.to_path_buf();
+        self.engine.set_samples_dir(sample_dir)
+    }
+
+    /// Follow the master volume at this instrument's level.
+    pub fn set_master_gain(&mut self, master: f32) {
+        self.engine.set_master_gain(master * self.gain);
+    }
+
+    pub fn update_harmonizer(&mut self, scale: &ScaleLock) {
+        self.engine.set_harmonizer(&self.harmonizer, scale);
+    }
+
+    /// What to save for the next run.
+    pub fn config(&self) -> DualConfig {
+        DualConfig {
+            enabled: true,
+            sample_dir: Some(self.sample_dir.clone()),
+            gain: self.gain,
+            harmonizer: self.harmonizer.clone(),
+        }
+    }
+}
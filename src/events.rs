@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::audio::AudioError;
+use crate::note::NoteName;
+use crate::sensor::{SensorError, SensorKind};
+
+/// Something that happened in the instrument. Published once, wherever it
+/// happens, and seen by every subscriber (MIDI output, the recorders, the
+/// practice stats, the UI) in the same order.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Note(NoteEvent),
+    Bellows(BellowsEvent),
+    Sensor(SensorEvent),
+    Config(ConfigEvent),
+    Load(LoadEvent),
+}
+
+/// A note started or stopped sounding, whatever played it (keys, MIDI in,
+/// the looper, a script...).
+#[derive(Debug, Clone, PartialEq)]
+pub enum NoteEvent {
+    On(NoteName),
+    Off(NoteName),
+}
+
+/// The instrument's level, once per frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BellowsEvent {
+    /// What is heard (0..1): bellows or air hold, times the lid gain.
+    pub a: f32,
+
+    /// Meend as pitch bend, -1..1.
+    pub bend: f32,
+
+    /// Length of the frame, in seconds.
+    pub dt_sec: f32,
+}
+
+#[derive(Debug, Clone)]
+pub enum SensorEvent {
+    /// A sensor that was delivering samples stopped.
+    Lost {
+        kind: SensorKind,
+        error: Arc<SensorError>,
+    },
+
+    /// A sensor delivers again after an error.
+    Reconnected(SensorKind),
+}
+
+/// Player actions the outputs follow along with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigEvent {
+    /// Sustain pedal down / up.
+    Sustain(bool),
+
+    /// Stop all notes (panic). Keys still held stay silent until pressed again.
+    StopAll,
+}
+
+/// Progress of the background load of the keymap and samples (at startup
+/// and on Reload).
+#[derive(Debug, Clone)]
+pub enum LoadEvent {
+    /// `done` of `total` sample files read so far.
+    Progress { done: usize, total: usize },
+
+    /// A sample file that can't be read or decoded.
+    Failed(Arc<AudioError>),
+
+    /// A sample whose pitch isn't the note in its file name.
+    Mislabeled {
+        path: PathBuf,
+        named: NoteName,
+        detected: NoteName,
+    },
+
+    /// `notes` samples in `dir` weren't named after a note and play the
+    /// note their pitch was found to be.
+    Mapped { dir: PathBuf, notes: usize },
+
+    /// Every file read; `failed` of them couldn't be.
+    Finished { loaded: usize, failed: usize },
+}
+
+/// Anything that observes the event stream.
+pub trait Subscriber {
+    /// Why an event couldn't be handled (a port or file gone);
+    /// [`Infallible`](std::convert::Infallible) for subscribers that can't
+    /// fail.
+    type Error: std::error::Error;
+
+    /// Handle one event, published at `t`. An error ends the subscription;
+    /// the app shows it and drops the subscriber.
+    fn on_event(&mut self, event: &Event, t: Instant) -> Result<(), Self::Error>;
+}
+
+/// Collects what is published during a frame; at the end of the frame it is
+/// taken and handed to every subscriber.
+#[derive(Debug, Default)]
+pub struct EventBus {
+    queue: Vec<(Instant, Event)>,
+
+    /// What is sounding, as of the note events published so far.
+    sounding: Vec<NoteName>,
+}
+
+impl EventBus {
+    pub fn publish(&mut self, event: Event) {
+        self.queue.push((Instant::now(), event));
+    }
+
+    /// Publish the note-offs, then the note-ons, that take what is sounding
+    /// to `active`.
+    pub fn publish_notes(&mut self, active: &[NoteName]) {
+        let now = Instant::now();
+        for note in self.sounding.iter().filter(|n| !active.contains(n)) {
+            self.queue.push((now, Event::Note(NoteEvent::Off(note.clone()))));
+        }
+        for note in active.iter().filter(|n| !self.sounding.contains(n)) {
+            self.queue.push((now, Event::Note(NoteEvent::On(note.clone()))));
+        }
+        self.sounding = active.to_vec();
+    }
+
+    /// Bring a new subscriber up to date: a note-on for every note already
+    /// sounding, so a recording or port opened mid-chord starts with it.
+    pub fn join<S: Subscriber>(&self, subscriber: &mut S) -> Result<(), S::Error> {
+        let now = Instant::now();
+        for note in &self.sounding {
+            subscriber.on_event(&Event::Note(NoteEvent::On(note.clone())), now)?;
+        }
+        Ok(())
+    }
+
+    /// Everything published since the last call, oldest first.
+    pub fn take(&mut self) -> Vec<(Instant, Event)> {
+        std::mem::take(&mut self.queue)
+    }
+}
+
+/// Hand `events` to `subscriber` in order, up to its first error.
+pub fn deliver<S: Subscriber>(
+    subscriber: &mut S,
+    events: &[(Instant, Event)],
+) -> Result<(), S::Error> {
+    events.iter().try_for_each(|(t, event)| subscriber.on_event(event, *t))
+}
@@ -0,0 +1,79 @@
+use crate::sensor::SensorSample;
+
+/// How two sensor streams are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FusionMode {
+    /// Weighted average of the two angles.
+    Weighted,
+
+    /// Follow whichever sensor moved more since the last sample. A sensor that
+    /// goes quiet or jittery can't hold back the one that's clearly pumping.
+    Max,
+}
+
+/// Combines two sensor streams (e.g. lid angle + phone accelerometer) into the
+/// single angle that feeds the bellows pipeline.
+#[derive(Debug, Clone)]
+pub struct SensorFusion {
+    pub mode: FusionMode,
+
+    /// Weight of the second stream (0 = only the first, 1 = only the second).
+    pub weight: f32,
+
+    /// Previous angles of both streams (for per-sample movement).
+    last: Option<(f32, f32)>,
+
+    /// Angle built up from the chosen movements in max mode.
+    fused_deg: f32,
+}
+
+impl SensorFusion {
+    /// In max mode the fused angle is pulled this far (per sample) toward the
+    /// weighted average, so it can't wander off from the real sensors.
+    const MAX_MODE_ANCHOR: f32 = 0.02;
+
+    pub fn new() -> Self {
+        Self {
+            mode: FusionMode::Weighted,
+            weight: 0.5,
+            last: None,
+            fused_deg: 0.0,
+        }
+    }
+
+    /// Forget history, e.g. after the selected sensors change.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Fuse the latest sample of each stream. The result carries the newer timestamp.
+    pub fn fuse(&mut self, a: &SensorSample, b: &SensorSample) -> SensorSample {
+        let w = self.weight.clamp(0.0, 1.0);
+        let weighted = (1.0 - w) * a.theta_deg + w * b.theta_deg;
+
+        let theta_deg = match (self.mode, self.last) {
+            (FusionMode::Weighted, _) | (FusionMode::Max, None) => weighted,
+            (FusionMode::Max, Some((last_a, last_b))) => {
+                let da = a.theta_deg - last_a;
+                let db = b.theta_deg - last_b;
+                let step = if da.abs() >= db.abs() { da } else { db };
+
+                let moved = self.fused_deg + step;
+                moved + (weighted - moved) * Self::MAX_MODE_ANCHOR
+            }
+        };
+
+        self.last = Some((a.theta_deg, b.theta_deg));
+        self.fused_deg = theta_deg;
+
+        let label = match self.mode {
+            FusionMode::Weighted => "blend",
+            FusionMode::Max => "max",
+        };
+        SensorSample {
+            theta_deg,
+            source: format!("{label}({}, {})", a.source, b.source),
+            t: a.t.max(b.t),
+        }
+    }
+}
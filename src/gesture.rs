@@ -0,0 +1,103 @@
+use std::time::Instant;
+
+use crate::simulation::sample_at;
+
+/// Records a stretch of bellows amplitude and loops it back.
+///
+/// Idea: pump the lid the way you like for a few seconds, then let the
+/// looper keep "breathing" that pattern while the lid stays still.
+#[derive(Debug, Clone, Default)]
+pub struct GestureLooper {
+    state: LooperState,
+
+    /// Recorded (seconds since record start, amplitude) points.
+    points: Vec<(f32, f32)>,
+
+    /// Total loop length in seconds.
+    length_sec: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LooperState {
+    #[default]
+    Idle,
+    Recording {
+        started: Instant,
+    },
+    Looping {
+        started: Instant,
+    },
+}
+
+impl GestureLooper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> LooperState {
+        self.state
+    }
+
+    pub fn has_loop(&self) -> bool {
+        self.points.len() >= 2 && self.length_sec > 0.0
+    }
+
+    pub fn length_sec(&self) -> f32 {
+        self.length_sec
+    }
+
+    /// Start recording a new gesture (throws away the old one).
+    pub fn start_recording(&mut self, now: Instant) {
+        self.points.clear();
+        self.length_sec = 0.0;
+        self.state = LooperState::Recording { started: now };
+    }
+
+    /// Stop recording and immediately start looping what was captured.
+    pub fn stop_recording_and_loop(&mut self, now: Instant) {
+        if let LooperState::Recording { started } = self.state {
+            self.length_sec = (now - started).as_secs_f32();
+        }
+
+        self.state = if self.has_loop() {
+            LooperState::Looping { started: now }
+        } else {
+            LooperState::Idle
+        };
+    }
+
+    /// Start looping a previously recorded gesture.
+    pub fn start_looping(&mut self, now: Instant) {
+        if self.has_loop() {
+            self.state = LooperState::Looping { started: now };
+        }
+    }
+
+    /// Stop recording/looping. The recorded gesture is kept.
+    pub fn stop(&mut self) {
+        self.state = LooperState::Idle;
+    }
+
+    /// Call once per frame with the live bellows amplitude.
+    /// Only stores it while recording.
+    pub fn feed(&mut self, a: f32, now: Instant) {
+        if let LooperState::Recording { started } = self.state {
+            let t = (now - started).as_secs_f32();
+            self.points.push((t, a));
+        }
+    }
+
+    /// The looped amplitude at `now`, or None if not looping.
+    pub fn value(&self, now: Instant) -> Option<f32> {
+        let LooperState::Looping { started } = self.state else {
+            return None;
+        };
+
+        if !self.has_loop() {
+            return None;
+        }
+
+        let t = (now - started).as_secs_f32() % self.length_sec;
+        Some(sample_at(&self.points, t))
+    }
+}
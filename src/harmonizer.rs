@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::note::{Note, NoteName};
+use crate::scale::ScaleLock;
+
+/// The note the harmonizer adds to each played one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HarmonyInterval {
+    FifthAbove,
+    FourthAbove,
+    OctaveBelow,
+}
+
+impl HarmonyInterval {
+    pub const ALL: [HarmonyInterval; 3] = [
+        HarmonyInterval::FifthAbove,
+        HarmonyInterval::FourthAbove,
+        HarmonyInterval::OctaveBelow,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HarmonyInterval::FifthAbove => "fifth above",
+            HarmonyInterval::FourthAbove => "fourth above",
+            HarmonyInterval::OctaveBelow => "octave below",
+        }
+    }
+}
+
+/// A stop that sounds a second note with every played one, like a coupler.
+/// Saved with the presets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Harmonizer {
+    pub enabled: bool,
+    pub interval: HarmonyInterval,
+
+    /// Level of the added note against the played one (0..1).
+    pub level: f32,
+}
+
+impl Default for Harmonizer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: HarmonyInterval::FifthAbove,
+            level: 0.6,
+        }
+    }
+}
+
+impl Harmonizer {
+    /// The note to add to `note`, kept in the scale selected in `scale`
+    /// (whether or not the lock is on). None with the stop off, or if no
+    /// near enough note is in the scale. The octave below doubles any note.
+    pub fn harmony(&self, note: &str, scale: &ScaleLock) -> Option<NoteName> {
+        if !self.enabled {
+            return None;
+        }
+        let played = Note::parse(note)?;
+
+        // Semitones to try: the pure interval, else the nearest in the scale
+        // (Pa over komal re in Marwa, say).
+        let steps: &[i32] = match self.interval {
+            HarmonyInterval::FifthAbove => &[7, 6, 8],
+            HarmonyInterval::FourthAbove => &[5, 6, 4],
+            HarmonyInterval::OctaveBelow => return played.transpose(-12).map(Note::name),
+        };
+        steps
+            .iter()
+            .filter_map(|&step| played.transpose(step))
+            .find(|n| scale.contains(n.pitch_class()))
+            .map(Note::name)
+    }
+}
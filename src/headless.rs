@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Duration, Instant};
+
+use crate::bellows::{BellowsOutput, BellowsParams, BellowsState};
+use crate::sensor::{self, SensorError, SensorRecorder, SensorSlot};
+
+/// How often the sensor is drained.
+const POLL_EVERY: Duration = Duration::from_millis(10);
+
+/// How often a status line is printed.
+const REPORT_EVERY: Duration = Duration::from_secs(1);
+
+/// Run without a window: sensor → bellows, printing the bellows level once a
+/// second and optionally recording the samples (e.g. to capture a rig's
+/// motion on a machine with no display). Stops on Enter or when stdin closes.
+pub fn run(
+    mut sensor: SensorSlot,
+    params: BellowsParams,
+    record: Option<&Path>,
+) -> Result<(), SensorError> {
+    if let Ok(offsets) = sensor::load_zero_offsets(sensor::DEFAULT_CALIBRATION_PATH) {
+        sensor.zero_offset_deg = offsets.get(&sensor.kind).copied().unwrap_or(0.0);
+    }
+
+    let mut recorder = record.map(SensorRecorder::create).transpose()?;
+    let mut bellows = BellowsState::new(params);
+    let mut out = BellowsOutput::default();
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = std::io::stdin().read_line(&mut line);
+        let _ = stop_tx.send(());
+    });
+
+    println!("Headless: {} → bellows. Press Enter to stop.", sensor.kind.label());
+    let start = Instant::now();
+    let mut last_report = start;
+
+    while matches!(stop_rx.try_recv(), Err(TryRecvError::Empty)) {
+        for sample in sensor.drain() {
+            if let Some(rec) = &mut recorder {
+                rec.push(&sample)?;
+            }
+
+            // Samples can predate the loop (the sensor thread starts first).
+            let t = sample.t.saturating_duration_since(start);
+            out = bellows.update(sample.theta_deg, t);
+        }
+
+        if last_report.elapsed() >= REPORT_EVERY {
+            last_report = Instant::now();
+            match &sensor.error {
+                Some(err) => println!("sensor error: {err}"),
+                None => println!(
+                    "theta {:7.2} deg   speed {:7.2} deg/s   a {:.3}",
+                    out.theta_deg, out.speed_smooth, out.a
+                ),
+            }
+        }
+
+        std::thread::sleep(POLL_EVERY);
+    }
+
+    if let (Some(rec), Some(path)) = (recorder, record) {
+        let count = rec.count();
+        rec.finish()?;
+        println!("Recorded {count} samples to {}", path.display());
+    }
+    Ok(())
+}
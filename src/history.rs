@@ -0,0 +1,36 @@
+use std::collections::VecDeque;
+
+use crate::bellows::BellowsOutput;
+
+/// Longest window the plots can show; older frames are dropped.
+pub const MAX_HISTORY_SEC: f64 = 30.0;
+
+/// The bellows signals over the last few seconds, one entry per frame, for
+/// the scrolling plots.
+#[derive(Debug, Default)]
+pub struct BellowsHistory {
+    /// (seconds since start, output), oldest first.
+    frames: VecDeque<(f64, BellowsOutput)>,
+}
+
+impl BellowsHistory {
+    pub fn push(&mut self, t_sec: f64, out: BellowsOutput) {
+        self.frames.push_back((t_sec, out));
+        while self.frames.front().is_some_and(|(t, _)| t_sec - t > MAX_HISTORY_SEC) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// One signal as plot points over the last `window_sec` seconds.
+    pub fn series(&self, window_sec: f64, value: impl Fn(&BellowsOutput) -> f32) -> Vec<[f64; 2]> {
+        let Some((now, _)) = self.frames.back() else {
+            return Vec::new();
+        };
+
+        self.frames
+            .iter()
+            .filter(|(t, _)| now - t <= window_sec)
+            .map(|(t, o)| [*t, value(o) as f64])
+            .collect()
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use rodio::Source;
+
+/// How many recent frames the frame-time figures cover (~2 s at 60 fps).
+const FRAME_WINDOW: usize = 120;
+
+/// UI frame times over the last couple of seconds.
+#[derive(Debug, Default)]
+pub struct FrameTimer {
+    frames_ms: VecDeque<f32>,
+}
+
+impl FrameTimer {
+    pub fn push(&mut self, dt_sec: f32) {
+        if self.frames_ms.len() == FRAME_WINDOW {
+            self.frames_ms.pop_front();
+        }
+        self.frames_ms.push_back(dt_sec * 1000.0);
+    }
+
+    pub fn average_ms(&self) -> f32 {
+        if self.frames_ms.is_empty() {
+            return 0.0;
+        }
+        self.frames_ms.iter().sum::<f32>() / self.frames_ms.len() as f32
+    }
+
+    /// The slowest recent frame: a single hitch shows up here, not in the average.
+    pub fn worst_ms(&self) -> f32 {
+        self.frames_ms.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+/// The audio thread falling behind real time by more than this counts as an
+/// underrun (well above a normal device buffer).
+const UNDERRUN_GAP: Duration = Duration::from_millis(40);
+
+/// Rate and layout of the watchdog's silent stream.
+const WATCHDOG_RATE: u32 = 44_100;
+const WATCHDOG_CHANNELS: u16 = 2;
+
+/// Check the clock every this many frames instead of on every sample.
+const WATCHDOG_CHECK_FRAMES: u64 = 64;
+
+/// Audio-thread stall counter, shared with `WatchdogSource`.
+#[derive(Debug, Default)]
+pub struct AudioHealth {
+    underruns: AtomicU32,
+}
+
+impl AudioHealth {
+    pub fn underruns(&self) -> u32 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
+
+/// A silent stream mixed in with the voices. The device pulls it at the
+/// same pace as everything else, so if it falls behind the wall clock the
+/// audio callback didn't run in time and the output glitched.
+pub struct WatchdogSource {
+    health: Arc<AudioHealth>,
+    start: Option<Instant>,
+    samples: u64,
+}
+
+impl WatchdogSource {
+    pub fn new(health: Arc<AudioHealth>) -> Self {
+        Self {
+            health,
+            start: None,
+            samples: 0,
+        }
+    }
+
+    fn check(&mut self) {
+        let now = Instant::now();
+        let start = *self.start.get_or_insert(now);
+        let frames = self.samples / WATCHDOG_CHANNELS as u64;
+        let played = Duration::from_secs_f64(frames as f64 / WATCHDOG_RATE as f64);
+
+        let wall = now.duration_since(start);
+        if wall > played + UNDERRUN_GAP {
+            self.health.underruns.fetch_add(1, Ordering::Relaxed);
+            // Catch up, so one stall is counted once.
+            self.start = Some(now - played);
+        }
+    }
+}
+
+impl Iterator for WatchdogSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.samples % (WATCHDOG_CHECK_FRAMES * WATCHDOG_CHANNELS as u64) == 0 {
+            self.check();
+        }
+        self.samples += 1;
+        Some(0.0)
+    }
+}
+
+impl Source for WatchdogSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        WATCHDOG_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        WATCHDOG_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
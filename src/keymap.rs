@@ -1,49 +1,785 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 
-/// A note name like "c#3" or "f4".
-pub type NoteName = String;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::note::{self, Note, NoteName};
+
+/// Built into the binary, so the instrument is playable on first launch
+/// without a keymap file.
+const DEFAULT_KEYMAP_JSON: &str = include_str!("default_keymap.json");
+
+/// Why a keymap couldn't be loaded or saved.
+#[derive(Debug, Error)]
+pub enum KeymapError {
+    #[error("Failed to read keymap file {path:?}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("Failed to write keymap file {path:?}: {source}")]
+    Write { path: PathBuf, source: io::Error },
+
+    /// Not valid JSON / TOML / YAML.
+    #[error("Failed to parse keymap {}: {message}", .format.label())]
+    Syntax {
+        format: KeymapFormat,
+        message: String,
+    },
+
+    /// Valid syntax, but not the keymap's shape (e.g. a number where the
+    /// zones go).
+    #[error("Failed to parse keymap: {0}")]
+    Shape(#[source] serde_json::Error),
+
+    #[error("Failed to encode keymap {}: {message}", .format.label())]
+    Encode {
+        format: KeymapFormat,
+        message: String,
+    },
+
+    #[error("Invalid key '{0}' in keymap. Keys must be exactly 1 character or 'code:<key name>'.")]
+    InvalidKey(String),
+
+    #[error("Unknown physical key '{0}' in keymap.")]
+    UnknownPhysicalKey(String),
+
+    #[error("MIDI note {0} is out of range (0-127).")]
+    MidiOutOfRange(i64),
+
+    #[error("Key '{0}' in keymap maps to an empty chord.")]
+    EmptyChord(String),
+
+    #[error("Key '{0}' in keymap has a nested voicing.")]
+    NestedVoicing(String),
+}
+
+impl KeymapError {
+    /// The file was read but what's in it is wrong, as opposed to trouble
+    /// reading or writing the file itself.
+    pub fn is_invalid(&self) -> bool {
+        !matches!(self, KeymapError::Read { .. } | KeymapError::Write { .. })
+    }
+}
+
+/// File formats a keymap can be stored in, picked by the file extension.
+/// All of them hold the same structure as the JSON file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeymapFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl KeymapFormat {
+    pub const ALL: [KeymapFormat; 3] = [KeymapFormat::Json, KeymapFormat::Toml, KeymapFormat::Yaml];
+
+    /// `.toml`, `.yaml`/`.yml`; anything else is read as JSON.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let ext = path.as_ref().extension().and_then(|e| e.to_str());
+        match ext.map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("toml") => KeymapFormat::Toml,
+            Some("yaml" | "yml") => KeymapFormat::Yaml,
+            _ => KeymapFormat::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            KeymapFormat::Json => "json",
+            KeymapFormat::Toml => "toml",
+            KeymapFormat::Yaml => "yaml",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeymapFormat::Json => "JSON",
+            KeymapFormat::Toml => "TOML",
+            KeymapFormat::Yaml => "YAML",
+        }
+    }
+
+    /// Read a file's text into the JSON shape the keymap is built from.
+    fn decode(self, text: &str) -> Result<serde_json::Value, KeymapError> {
+        match self {
+            KeymapFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            KeymapFormat::Toml => toml::from_str(text).map_err(|e| e.to_string()),
+            KeymapFormat::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+        }
+        .map_err(|message| KeymapError::Syntax {
+            format: self,
+            message,
+        })
+    }
+
+    fn encode(self, value: &serde_json::Value) -> Result<String, KeymapError> {
+        match self {
+            KeymapFormat::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            KeymapFormat::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            KeymapFormat::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        }
+        .map_err(|message| KeymapError::Encode {
+            format: self,
+            message,
+        })
+    }
+}
+
+/// Modifier keys held while a note key goes down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyMods {
+    pub shift: bool,
+    /// Ctrl, or Cmd on macOS.
+    pub command: bool,
+}
+
+impl KeyMods {
+    /// Octave layer selected by the modifiers: Shift = up one, Ctrl/Cmd = down one.
+    pub fn octave_offset(self) -> i32 {
+        match (self.shift, self.command) {
+            (true, false) => 1,
+            (false, true) => -1,
+            _ => 0,
+        }
+    }
+}
+
+/// One note in the JSON: a pitch name ("c#3") or a MIDI number (49).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawNote {
+    Name(String),
+    Midi(i64),
+}
+
+impl RawNote {
+    /// Canonical note name, so "Db3", "c#3" and 49 all find the same sample.
+    fn to_name(&self) -> Result<NoteName, KeymapError> {
+        let note = match self {
+            RawNote::Name(s) => Note::parse(s),
+            RawNote::Midi(n) => i32::try_from(*n).ok().and_then(Note::from_midi),
+        };
+
+        match (note, self) {
+            (Some(n), _) => Ok(n.name()),
+            // Unusual names are kept as-is (they still work if a sample has that name).
+            (None, RawNote::Name(s)) => Ok(s.clone()),
+            (None, RawNote::Midi(n)) => Err(KeymapError::MidiOutOfRange(*n)),
+        }
+    }
+}
+
+/// What one key maps to in the JSON: a single note, a chord, or either of
+/// those with options (`{"note": "c#3", "gain": 0.9, "cents": -8, "toggle": true}`).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawNotes {
+    One(RawNote),
+    Chord(Vec<RawNote>),
+    Voiced(Box<RawVoiced>),
+}
+
+#[derive(Debug, Deserialize)]
+struct RawVoiced {
+    note: RawNotes,
+    gain: Option<f32>,
+    cents: Option<f32>,
+    #[serde(default)]
+    toggle: bool,
+}
+
+/// One key's entry, parsed.
+struct Binding {
+    notes: Vec<NoteName>,
+    voicing: Option<Voicing>,
+    /// Press to start, press again to stop (instead of sounding while held).
+    toggle: bool,
+}
+
+impl RawNotes {
+    fn into_binding(self, key: &str) -> Result<Binding, KeymapError> {
+        let plain = |notes| Binding {
+            notes,
+            voicing: None,
+            toggle: false,
+        };
+
+        match self {
+            RawNotes::One(note) => Ok(plain(vec![note.to_name()?])),
+            RawNotes::Chord(notes) if notes.is_empty() => {
+                Err(KeymapError::EmptyChord(key.to_string()))
+            }
+            RawNotes::Chord(notes) => {
+                let names = notes.iter().map(RawNote::to_name).collect::<Result<_, _>>()?;
+                Ok(plain(names))
+            }
+            RawNotes::Voiced(v) => {
+                let RawVoiced {
+                    note,
+                    gain,
+                    cents,
+                    toggle,
+                } = *v;
+                if matches!(note, RawNotes::Voiced(_)) {
+                    return Err(KeymapError::NestedVoicing(key.to_string()));
+                }
+
+                let voicing = (gain.is_some() || cents.is_some()).then(|| Voicing {
+                    gain: gain.unwrap_or(1.0),
+                    cents: cents.unwrap_or(0.0),
+                });
+                Ok(Binding {
+                    notes: note.into_binding(key)?.notes,
+                    voicing,
+                    toggle,
+                })
+            }
+        }
+    }
+}
+
+/// Notes as written back to JSON: a plain string for one note, an array for a
+/// chord, wrapped in an object when the key has a voicing or toggles.
+fn notes_to_json(notes: &[NoteName], voicing: Option<&Voicing>, toggle: bool) -> serde_json::Value {
+    let notes = match notes {
+        [one] => serde_json::Value::from(one.as_str()),
+        chord => serde_json::Value::from(chord.to_vec()),
+    };
+    if voicing.is_none() && !toggle {
+        return notes;
+    }
+
+    let mut entry = serde_json::Map::new();
+    entry.insert("note".to_string(), notes);
+    if let Some(v) = voicing {
+        entry.insert("gain".to_string(), v.gain.into());
+        entry.insert("cents".to_string(), v.cents.into());
+    }
+    if toggle {
+        entry.insert("toggle".to_string(), true.into());
+    }
+    entry.into()
+}
+
+/// Fine voicing of one key, applied when its notes start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Voicing {
+    /// Level relative to the other keys (multiplied with the zone gain).
+    pub gain: f32,
+
+    /// Tuning offset in cents (100 = one semitone).
+    pub cents: f32,
+}
+
+impl Default for Voicing {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            cents: 0.0,
+        }
+    }
+}
+
+/// A `zones` entry in the JSON.
+#[derive(Debug, Deserialize)]
+struct RawZone {
+    name: String,
+    #[serde(default)]
+    latch: bool,
+    #[serde(default = "unity_gain")]
+    gain: f32,
+    #[serde(default = "first_instrument")]
+    instrument: u8,
+    keys: HashMap<String, RawNotes>,
+}
+
+fn unity_gain() -> f32 {
+    1.0
+}
+
+fn first_instrument() -> u8 {
+    1
+}
+
+/// A group of keys that behave the same way, e.g. latched drone keys for the
+/// left hand and normal melody keys for the right.
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub name: String,
+
+    /// Press once to start the notes, press again to stop them (drones).
+    pub latch: bool,
+
+    /// Level of this zone's notes relative to the others.
+    pub gain: f32,
+
+    /// Which instrument plays it in dual mode: 1 (the main one) or 2.
+    pub instrument: u8,
+}
+
+impl Zone {
+    /// Zone of every key listed at the top level of the file.
+    fn melody() -> Self {
+        Self {
+            name: "melody".to_string(),
+            latch: false,
+            gain: 1.0,
+            instrument: 1,
+        }
+    }
+}
+
+/// Letter and digit keys; each is named by its character.
+const CHAR_KEYS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// The other keys a keymap can name by position: punctuation, editing and
+/// arrow keys, F1 to F12.
+const NAMED_KEYS: &[&str] = &[
+    "Backtick",
+    "Minus",
+    "Equals",
+    "OpenBracket",
+    "CloseBracket",
+    "Backslash",
+    "Semicolon",
+    "Quote",
+    "Comma",
+    "Period",
+    "Slash",
+    "Colon",
+    "Pipe",
+    "Plus",
+    "Questionmark",
+    "Exclamationmark",
+    "OpenCurlyBracket",
+    "CloseCurlyBracket",
+    "Space",
+    "Tab",
+    "Enter",
+    "Backspace",
+    "Escape",
+    "Insert",
+    "Delete",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "ArrowDown",
+    "ArrowLeft",
+    "ArrowRight",
+    "ArrowUp",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+    "F10",
+    "F11",
+    "F12",
+];
+
+/// A key by where it sits, named as on a US QWERTY keyboard (`Z`, `Comma`,
+/// `1`) the way the UI toolkit names it, so no toolkit type is needed here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhysicalKey(&'static str);
+
+impl PhysicalKey {
+    /// The key called `name`, if there is one. Digits may also be written
+    /// `Num1`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = match name.strip_prefix("Num") {
+            Some(digit) if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) => digit,
+            _ => name,
+        };
+        let char_key = CHAR_KEYS
+            .find(name)
+            .filter(|_| name.len() == 1)
+            .map(|i| &CHAR_KEYS[i..i + 1]);
+        char_key
+            .or_else(|| NAMED_KEYS.iter().copied().find(|k| *k == name))
+            .map(PhysicalKey)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.0
+    }
+}
+
+/// A key in the keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyId {
+    /// The character the key types in the current layout (`"z"`, `","`).
+    Char(char),
+
+    /// Where the key physically sits, whatever the layout (`"code:Z"` is the
+    /// key labelled Z on a US QWERTY keyboard, Y on QWERTZ, W on AZERTY).
+    Physical(PhysicalKey),
+
+    /// A key of the on-screen keyboard, by MIDI note. Never stored in a keymap
+    /// file; it only lets clicks share `PressedKeys` with the real keys.
+    OnScreen(u8),
+
+    /// A note the phrase looper is playing, by MIDI note. Kept apart from the
+    /// on-screen keys so a loop and a click on the same note don't clash.
+    Looper(u8),
+
+    /// A note a hook script holds down with `play()`, by MIDI note.
+    Script(u8),
+
+    /// The one note of mono (legato) playing, held for whichever mono key
+    /// is sounding (see `MonoKeys`).
+    Mono,
+}
+
+impl KeyId {
+    /// Parse a JSON key: exactly one character, or `code:` + a key name.
+    fn parse(k: &str) -> Result<Self, KeymapError> {
+        if let Some(name) = k.strip_prefix("code:") {
+            return PhysicalKey::from_name(name)
+                .map(KeyId::Physical)
+                .ok_or_else(|| KeymapError::UnknownPhysicalKey(name.to_string()));
+        }
+
+        let mut chars = k.chars();
+        match (chars.next(), chars.next()) {
+            (Some(first), None) => Ok(KeyId::Char(first)), // exactly 1 char
+            _ => Err(KeymapError::InvalidKey(k.to_string())),
+        }
+    }
+}
+
+/// Same spelling as in the JSON file.
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyId::Char(c) => write!(f, "{c}"),
+            KeyId::Physical(k) => write!(f, "code:{}", k.name()),
+            KeyId::OnScreen(midi) => write!(f, "screen:{midi}"),
+            KeyId::Looper(midi) => write!(f, "loop:{midi}"),
+            KeyId::Script(midi) => write!(f, "script:{midi}"),
+            KeyId::Mono => write!(f, "mono"),
+        }
+    }
+}
 
 /// Stores the mapping from keyboard keys (like 'z', 's', ',') to note names.
+/// A key can hold several notes (a chord).
 #[derive(Debug, Clone)]
 pub struct KeyMap {
-    map: HashMap<char, NoteName>,
+    map: HashMap<KeyId, Vec<NoteName>>,
+
+    /// `zones[0]` is the plain melody zone; keys not in `key_zone` belong to it.
+    zones: Vec<Zone>,
+    key_zone: HashMap<KeyId, usize>,
+
+    /// Keys with their own gain / tuning; the rest use `Voicing::default()`.
+    voicing: HashMap<KeyId, Voicing>,
+
+    /// Sticky keys: each press toggles their notes on or off.
+    toggle_keys: HashSet<KeyId>,
+
+    /// True for the embedded default rather than a file.
+    builtin: bool,
 }
 
 impl KeyMap {
     /// Load keymap from a JSON file that looks like:
-    /// { "z": "c2", "s": "c#2", ... }
-    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, String> {
-        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read keymap file: {e}"))?;
+    /// { "z": "c2", "s": "c#2", "m": ["c3", "e3", "g3"], "code:Comma": 48, ... }
+    ///
+    /// Notes can be pitch names or MIDI numbers (48 = c3). Optional zones:
+    /// "zones": [{ "name": "drone", "latch": true, "gain": 0.5, "keys": { "1": "c3" } }]
+    ///
+    /// Any entry can carry a voicing: "x": { "note": "c#3", "gain": 0.9, "cents": -8 }
+    ///
+    /// `.toml` and `.yaml` files hold the same structure in those formats.
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, KeymapError> {
+        let path = path.as_ref();
+        let format = KeymapFormat::from_path(path);
+        let text = fs::read_to_string(path).map_err(|source| KeymapError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let km = format
+            .decode(&text)
+            .and_then(Self::from_value)
+            .inspect_err(|e| warn!("{e}"))?;
 
+        info!("{} loaded: {} keys", path.display(), km.map.len());
+        Ok(km)
+    }
+
+    /// Load `path`, or fall back to the built-in keymap if there is no such file.
+    /// A file that exists but doesn't parse is still an error.
+    pub fn load_or_builtin(path: impl AsRef<Path>) -> Result<Self, KeymapError> {
+        if path.as_ref().exists() {
+            Self::load_from_file(path)
+        } else {
+            Ok(Self::builtin())
+        }
+    }
+
+    /// A keymap from explicit bindings (e.g. a generated layout).
+    pub fn from_bindings(bindings: impl IntoIterator<Item = (KeyId, Vec<NoteName>)>) -> Self {
+        Self {
+            map: bindings.into_iter().collect(),
+            zones: vec![Zone::melody()],
+            key_zone: HashMap::new(),
+            voicing: HashMap::new(),
+            toggle_keys: HashSet::new(),
+            builtin: false,
+        }
+    }
+
+    /// The default mapping compiled into the binary.
+    pub fn builtin() -> Self {
+        let value = KeymapFormat::Json.decode(DEFAULT_KEYMAP_JSON);
+        let mut km = value.and_then(Self::from_value).expect("built-in keymap is valid");
+        km.builtin = true;
+        km
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.builtin
+    }
+
+    fn from_value(value: serde_json::Value) -> Result<Self, KeymapError> {
         // Parse into a temporary map with String keys, because JSON object keys are strings.
-        let raw: HashMap<String, String> =
-            serde_json::from_str(&text).map_err(|e| format!("Failed to parse keymap JSON: {e}"))?;
+        let mut top: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_value(value).map_err(KeymapError::Shape)?;
+        let raw_zones: Vec<RawZone> = match top.remove("zones") {
+            Some(z) => serde_json::from_value(z).map_err(KeymapError::Shape)?,
+            None => Vec::new(),
+        };
+        let raw: HashMap<String, RawNotes> =
+            serde_json::from_value(serde_json::Value::Object(top)).map_err(KeymapError::Shape)?;
 
-        let mut map: HashMap<char, NoteName> = HashMap::new();
+        let mut km = Self::from_bindings([]);
 
         for (k, v) in raw {
-            let mut chars = k.chars();
-
-            let ch = match (chars.next(), chars.next()) {
-                (Some(first), None) => first, // exactly 1 char
-                _ => {
-                    return Err(format!(
-                        "Invalid key '{k}' in keymap. Keys must be exactly 1 character."
-                    ));
-                }
-            };
+            km.insert_raw(&k, v)?;
+        }
+
+        for z in raw_zones {
+            let zone = km.zones.len();
+            for (k, v) in z.keys {
+                let id = km.insert_raw(&k, v)?;
+                km.key_zone.insert(id, zone);
+            }
+            km.zones.push(Zone {
+                name: z.name,
+                latch: z.latch,
+                gain: z.gain,
+                instrument: z.instrument,
+            });
+        }
+
+        Ok(km)
+    }
+
+    fn insert_raw(&mut self, k: &str, v: RawNotes) -> Result<KeyId, KeymapError> {
+        let id = KeyId::parse(k)?;
+        let binding = v.into_binding(k)?;
+        self.map.insert(id, binding.notes);
+        if let Some(voicing) = binding.voicing {
+            self.voicing.insert(id, voicing);
+        }
+        if binding.toggle {
+            self.toggle_keys.insert(id);
+        }
+        Ok(id)
+    }
+
+    /// The zone a key belongs to.
+    pub fn zone_of(&self, id: KeyId) -> &Zone {
+        let i = self.key_zone.get(&id).copied().unwrap_or(0);
+        &self.zones[i]
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    /// Whether a press toggles the key's notes (a sticky key, or a latching
+    /// zone) rather than sounding them while held.
+    pub fn is_toggle(&self, id: KeyId) -> bool {
+        self.toggle_keys.contains(&id) || self.zone_of(id).latch
+    }
+
+    /// Which instrument plays a key's notes in dual mode (1 or 2).
+    pub fn instrument_of(&self, id: KeyId) -> u8 {
+        self.zone_of(id).instrument
+    }
+
+    /// How a key's notes start: its own voicing, with the zone gain folded in.
+    pub fn voicing(&self, id: KeyId) -> Voicing {
+        let own = self.voicing.get(&id).copied().unwrap_or_default();
+        Voicing {
+            gain: own.gain * self.zone_of(id).gain,
+            cents: own.cents,
+        }
+    }
+
+    /// Which keymap entry a key event hits. A physical-key entry wins over a
+    /// character entry, so a layout-independent map isn't shadowed.
+    pub fn resolve(&self, ch: Option<char>, physical: Option<PhysicalKey>) -> Option<KeyId> {
+        let physical = physical.map(KeyId::Physical);
+        let ch = ch.map(KeyId::Char);
+
+        [physical, ch].into_iter().flatten().find(|id| self.map.contains_key(id))
+    }
+
+    /// Write the mapping back (keys sorted, single notes as plain strings), in
+    /// the format matching the file extension.
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), KeymapError> {
+        // One sorted key → notes object per zone; zone 0 goes at the top level.
+        let mut per_zone: Vec<BTreeMap<String, serde_json::Value>> =
+            vec![BTreeMap::new(); self.zones.len()];
+        for (id, notes) in &self.map {
+            let zone = self.key_zone.get(id).copied().unwrap_or(0);
+            let toggle = self.toggle_keys.contains(id);
+            let entry = notes_to_json(notes, self.voicing.get(id), toggle);
+            per_zone[zone].insert(id.to_string(), entry);
+        }
 
-            map.insert(ch, v);
+        let mut raw: serde_json::Map<String, serde_json::Value> =
+            per_zone[0].clone().into_iter().collect();
+        if self.zones.len() > 1 {
+            let zones: Vec<serde_json::Value> = self.zones[1..]
+                .iter()
+                .zip(&per_zone[1..])
+                .map(|(z, keys)| {
+                    serde_json::json!({
+                        "name": z.name,
+                        "latch": z.latch,
+                        "gain": z.gain,
+                        "instrument": z.instrument,
+                        "keys": keys,
+                    })
+                })
+                .collect();
+            raw.insert("zones".to_string(), zones.into());
         }
 
-        Ok(Self { map })
+        let text = KeymapFormat::from_path(&path).encode(&serde_json::Value::Object(raw))?;
+        fs::write(&path, text).map_err(|source| KeymapError::Write {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
     }
 
-    /// Look up a note name from a keyboard character.
-    pub fn note_for_char(&self, ch: char) -> Option<&str> {
-        self.map.get(&ch).map(|s| s.as_str())
+    /// Assign `notes` to a key, replacing whatever it played before (and its voicing).
+    /// Once edited, the keymap is no longer the built-in one.
+    pub fn bind(&mut self, id: KeyId, notes: Vec<NoteName>) {
+        self.map.insert(id, notes);
+        self.voicing.remove(&id);
+        self.builtin = false;
+    }
+
+    /// Keys that play `note` (alone or in a chord), sorted for display.
+    pub fn keys_for_note(&self, note: &str) -> Vec<KeyId> {
+        let mut keys: Vec<KeyId> = self
+            .map
+            .iter()
+            .filter(|(_, notes)| notes.iter().any(|n| n == note))
+            .map(|(id, _)| *id)
+            .collect();
+        keys.sort_by_key(|id| id.to_string());
+        keys
+    }
+
+    /// Every note used anywhere in the map.
+    pub fn all_notes(&self) -> Vec<NoteName> {
+        let mut notes: Vec<NoteName> = self.map.values().flatten().cloned().collect();
+        notes.sort_by_key(|n| Note::parse(n));
+        notes.dedup();
+        notes
+    }
+
+    /// Look up the note(s) of a key.
+    pub fn notes_for(&self, id: KeyId) -> Option<&[NoteName]> {
+        self.map.get(&id).map(|v| v.as_slice())
+    }
+
+    /// The notes a key plays, moved by `octaves` (modifier layer + global shift).
+    pub fn notes_for_key(&self, id: KeyId, octaves: i32) -> Vec<NoteName> {
+        let Some(notes) = self.notes_for(id) else {
+            return Vec::new();
+        };
+
+        match octaves {
+            0 => notes.to_vec(),
+            n => notes.iter().filter_map(|nt| note::transpose_name(nt, n * 12)).collect(),
+        }
+    }
+}
+
+/// Mono (legato) keys: one note at a time. A key pressed while another
+/// sounds takes over from it; letting go of the sounding key goes back to
+/// the newest one still held (last-note priority).
+#[derive(Debug, Default, Clone)]
+pub struct MonoKeys {
+    /// Mono keys down and their notes, oldest first; the last one sounds.
+    held: Vec<(KeyId, NoteName)>,
+}
+
+/// What a mono key press or release does to the sounding note.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonoMove {
+    Start(NoteName),
+    /// Move from one note to the other without restarting (portamento).
+    Glide { from: NoteName, to: NoteName },
+    Stop(NoteName),
+    /// A repeat, or a key that wasn't the sounding one.
+    Nothing,
+}
+
+impl MonoKeys {
+    pub fn key_down(&mut self, key: KeyId, note: NoteName) -> MonoMove {
+        if self.held.iter().any(|(k, _)| *k == key) {
+            return MonoMove::Nothing;
+        }
+        let from = self.sounding().cloned();
+        self.held.push((key, note.clone()));
+        match from {
+            Some(from) if from != note => MonoMove::Glide { from, to: note },
+            Some(_) => MonoMove::Nothing,
+            None => MonoMove::Start(note),
+        }
+    }
+
+    pub fn key_up(&mut self, key: KeyId) -> MonoMove {
+        let Some(i) = self.held.iter().position(|(k, _)| *k == key) else {
+            return MonoMove::Nothing;
+        };
+        let (_, note) = self.held.remove(i);
+        if i < self.held.len() {
+            return MonoMove::Nothing;
+        }
+        match self.held.last() {
+            Some((_, back)) if *back != note => MonoMove::Glide {
+                from: note,
+                to: back.clone(),
+            },
+            Some(_) => MonoMove::Nothing,
+            None => MonoMove::Stop(note),
+        }
+    }
+
+    pub fn sounding(&self) -> Option<&NoteName> {
+        self.held.last().map(|(_, note)| note)
+    }
+
+    /// Forget every key (stop all, leaving mono mode).
+    pub fn clear(&mut self) {
+        self.held.clear();
     }
 }
 
@@ -51,11 +787,26 @@ impl KeyMap {
 #[derive(Debug, Default, Clone)]
 pub struct PressedKeys {
     /// Which physical keys are down.
-    keys_down: HashSet<char>,
+    keys_down: HashSet<KeyId>,
 
-    /// For keys that are down, which note they started.
+    /// For keys that are down, which notes they started.
     /// (This matters later for audio "note off".)
-    key_to_note: HashMap<char, NoteName>,
+    key_to_notes: HashMap<KeyId, Vec<NoteName>>,
+
+    /// Latching keys (drone zones, sticky keys) that are on, and the notes they hold
+    /// until pressed again.
+    latched: HashMap<KeyId, Vec<NoteName>>,
+
+    /// How many held/latched keys are sounding each note. Several keys can
+    /// play the same note (aliases, chords, drones); the note only stops when
+    /// the last of them lets go.
+    note_refs: HashMap<NoteName, usize>,
+}
+
+/// What a press on a latching key did.
+pub enum Latch {
+    On(Vec<NoteName>),
+    Off(Vec<NoteName>),
 }
 
 impl PressedKeys {
@@ -63,38 +814,106 @@ impl PressedKeys {
         Self::default()
     }
 
-    /// Call this when a key is pressed.
-    /// Returns Some(note) only if this press activated a note (not a repeat).
-    pub fn key_down(&mut self, ch: char, keymap: &KeyMap) -> Option<NoteName> {
+    /// Call this when a key is pressed, with the notes it resolved to (after
+    /// octave layers, scale lock, ...).
+    /// Returns the notes this press started (empty for a repeat, an unmapped
+    /// key, or notes another key is already holding).
+    ///
+    /// Releasing the key stops those same notes, even if the octave shift or
+    /// scale changed in between.
+    pub fn key_down(&mut self, key: KeyId, notes: Vec<NoteName>) -> Vec<NoteName> {
         // If already down, ignore repeats.
-        if self.keys_down.contains(&ch) {
-            return None;
+        if !self.keys_down.insert(key) || notes.is_empty() {
+            return Vec::new();
         }
 
-        self.keys_down.insert(ch);
-
-        // Translate to a note if mapped.
-        let note = keymap.note_for_char(ch)?.to_string();
-        self.key_to_note.insert(ch, note.clone());
-        Some(note)
+        self.key_to_notes.insert(key, notes.clone());
+        self.acquire(notes)
     }
 
     /// Call this when a key is released.
-    /// Returns Some(note) only if that key had activated a note.
-    pub fn key_up(&mut self, ch: char) -> Option<NoteName> {
-        self.keys_down.remove(&ch);
-        self.key_to_note.remove(&ch)
+    /// Returns the notes to stop: the ones this key held that no other key holds.
+    pub fn key_up(&mut self, key: KeyId) -> Vec<NoteName> {
+        self.keys_down.remove(&key);
+        let notes = self.key_to_notes.remove(&key).unwrap_or_default();
+        self.release(notes)
+    }
+
+    /// Call this when a latching key is pressed: starts its notes, or stops
+    /// the ones it latched earlier. Releasing a latching key does nothing.
+    pub fn toggle_latch(&mut self, key: KeyId, notes: Vec<NoteName>) -> Latch {
+        match self.latched.remove(&key) {
+            Some(held) => Latch::Off(self.release(held)),
+            None if notes.is_empty() => Latch::On(Vec::new()),
+            None => {
+                self.latched.insert(key, notes.clone());
+                Latch::On(self.acquire(notes))
+            }
+        }
+    }
+
+    /// Unlatch every drone. Returns the notes to stop.
+    pub fn release_latched(&mut self) -> Vec<NoteName> {
+        let held: Vec<NoteName> = self.latched.drain().flat_map(|(_, notes)| notes).collect();
+        self.release(held)
+    }
+
+    /// Count `notes` as held once more. Returns the ones that just started.
+    fn acquire(&mut self, notes: Vec<NoteName>) -> Vec<NoteName> {
+        let mut started = Vec::new();
+        for note in notes {
+            let refs = self.note_refs.entry(note.clone()).or_insert(0);
+            *refs += 1;
+            if *refs == 1 {
+                started.push(note);
+            }
+        }
+        started
+    }
+
+    /// Count `notes` as held once less. Returns the ones nobody holds anymore.
+    fn release(&mut self, notes: Vec<NoteName>) -> Vec<NoteName> {
+        let mut stopped = Vec::new();
+        for note in notes {
+            let Some(refs) = self.note_refs.get_mut(&note) else {
+                continue;
+            };
+            *refs -= 1;
+            if *refs == 0 {
+                self.note_refs.remove(&note);
+                stopped.push(note);
+            }
+        }
+        stopped
+    }
+
+    /// Forget every key, latch and note (a panic).
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn latched_notes(&self) -> Vec<NoteName> {
+        let mut notes: Vec<NoteName> = self.latched.values().flatten().cloned().collect();
+        notes.sort();
+        notes
     }
 
     /// List of active notes (useful for UI display).
     pub fn active_notes(&self) -> Vec<NoteName> {
-        let mut notes: Vec<NoteName> = self.key_to_note.values().cloned().collect();
+        let mut notes: Vec<NoteName> = self
+            .key_to_notes
+            .values()
+            .chain(self.latched.values())
+            .flatten()
+            .cloned()
+            .collect();
         notes.sort();
+        notes.dedup();
         notes
     }
 
     /// Simple query: is this key currently held?
-    pub fn _is_down(&self, ch: char) -> bool {
-        self.keys_down.contains(&ch)
+    pub fn _is_down(&self, key: KeyId) -> bool {
+        self.keys_down.contains(&key)
     }
 }
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rodio::{Sample, Source};
+
+/// Shared between the UI thread and the audio thread.
+///
+/// The UI arms it right after pushing a new bellows volume; the next time the
+/// audio thread pulls samples from any probed voice it records the time.
+#[derive(Debug, Default)]
+pub struct LatencyProbe {
+    armed: AtomicBool,
+    applied_at: Mutex<Option<Instant>>,
+}
+
+impl LatencyProbe {
+    pub fn arm(&self) {
+        if let Ok(mut at) = self.applied_at.lock() {
+            *at = None;
+        }
+        self.armed.store(true, Ordering::Release);
+    }
+
+    /// Audio thread side: called for every sample, so keep it cheap.
+    fn on_audio_pull(&self) {
+        if self.armed.swap(false, Ordering::AcqRel) {
+            if let Ok(mut at) = self.applied_at.try_lock() {
+                *at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// When the audio thread first pulled samples after `arm`, if it has yet.
+    pub fn take_applied(&self) -> Option<Instant> {
+        self.applied_at.lock().ok().and_then(|mut at| at.take())
+    }
+}
+
+/// Wraps a voice's source so the probe sees when the audio thread reads it.
+pub struct ProbeSource<S> {
+    inner: S,
+    probe: Arc<LatencyProbe>,
+}
+
+impl<S> ProbeSource<S> {
+    pub fn new(inner: S, probe: Arc<LatencyProbe>) -> Self {
+        Self { inner, probe }
+    }
+}
+
+impl<S> Iterator for ProbeSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.probe.on_audio_pull();
+        self.inner.next()
+    }
+}
+
+impl<S> Source for ProbeSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// One measured gesture: sensor → bellows → audio.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencySample {
+    /// Sensor timestamp until the bellows pipeline produced the onset.
+    pub sensor_to_bellows: Duration,
+
+    /// Bellows onset until the audio thread pulled samples with the new volume.
+    pub bellows_to_audio: Duration,
+}
+
+impl LatencySample {
+    pub fn total(&self) -> Duration {
+        self.sensor_to_bellows + self.bellows_to_audio
+    }
+}
+
+/// UI-side state machine for the latency diagnostic.
+///
+/// Waits for a bellows onset (target amplitude rising through a threshold),
+/// arms the probe, then waits for the audio thread to report back.
+#[derive(Debug, Default)]
+pub struct LatencyMeter {
+    /// Was the target above the onset threshold last frame?
+    above: bool,
+
+    /// Onset being measured: (sensor time, bellows time).
+    in_flight: Option<(Instant, Instant)>,
+
+    /// Most recent measurements, newest last.
+    history: VecDeque<LatencySample>,
+}
+
+impl LatencyMeter {
+    const ONSET_THRESHOLD: f32 = 0.05;
+    const HISTORY_LEN: usize = 20;
+
+    /// Watch a fresh bellows output. `sensor_t` is the timestamp of the sample it
+    /// came from. Returns true when an onset started a measurement, in which case
+    /// the caller should `arm` the probe right after applying the new volume.
+    pub fn observe(&mut self, a_target: f32, sensor_t: Instant, now: Instant) -> bool {
+        let above = a_target >= Self::ONSET_THRESHOLD;
+        let onset = above && !self.above;
+        self.above = above;
+
+        if onset && self.in_flight.is_none() {
+            self.in_flight = Some((sensor_t, now));
+            return true;
+        }
+        false
+    }
+
+    /// Pick up the audio thread's answer for the measurement in flight.
+    pub fn poll(&mut self, probe: &LatencyProbe) {
+        let Some((sensor_t, bellows_t)) = self.in_flight else {
+            return;
+        };
+        let Some(audio_t) = probe.take_applied() else {
+            return;
+        };
+
+        self.in_flight = None;
+        self.history.push_back(LatencySample {
+            sensor_to_bellows: bellows_t.saturating_duration_since(sensor_t),
+            bellows_to_audio: audio_t.saturating_duration_since(bellows_t),
+        });
+        while self.history.len() > Self::HISTORY_LEN {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn is_waiting_for_audio(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    pub fn last(&self) -> Option<LatencySample> {
+        self.history.back().copied()
+    }
+
+    /// Average over the recent history.
+    pub fn average(&self) -> Option<LatencySample> {
+        let n = self.history.len() as u32;
+        if n == 0 {
+            return None;
+        }
+
+        let sum_sb: Duration = self.history.iter().map(|s| s.sensor_to_bellows).sum();
+        let sum_ba: Duration = self.history.iter().map(|s| s.bellows_to_audio).sum();
+
+        Some(LatencySample {
+            sensor_to_bellows: sum_sb / n,
+            bellows_to_audio: sum_ba / n,
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,92 @@
+use crate::keymap::{KeyId, KeyMap};
+use crate::note::Note;
+
+/// One row pair on the keyboard: white keys, and the row above them for black keys.
+///
+/// `black[i]` sits between `white[i]` and `white[i + 1]`; it only gets a note
+/// where the two white notes are a whole tone apart (so no key between E/F, B/C).
+struct RowPair {
+    white: &'static str,
+    black: &'static str,
+}
+
+const BOTTOM_ROWS: RowPair = RowPair {
+    white: "zxcvbnm,./",
+    black: "sdfghjkl;",
+};
+
+const TOP_ROWS: RowPair = RowPair {
+    white: "qwertyuiop[]",
+    black: "234567890-=",
+};
+
+/// Keyboard layouts the generator knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutTemplate {
+    /// Bottom letter row = white keys, home row = black keys (about 1.5 octaves).
+    BottomTwoRows,
+
+    /// Top letter row = white keys, number row = black keys.
+    TopTwoRows,
+
+    /// Both row pairs, bottom first and continuing on the top (about 3 octaves).
+    FourRows,
+}
+
+impl LayoutTemplate {
+    pub const ALL: [LayoutTemplate; 3] = [
+        LayoutTemplate::BottomTwoRows,
+        LayoutTemplate::TopTwoRows,
+        LayoutTemplate::FourRows,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LayoutTemplate::BottomTwoRows => "Two rows: Z row white, A row black",
+            LayoutTemplate::TopTwoRows => "Two rows: Q row white, number row black",
+            LayoutTemplate::FourRows => "Four rows (Z+A, then Q+numbers)",
+        }
+    }
+
+    fn rows(self) -> &'static [RowPair] {
+        match self {
+            LayoutTemplate::BottomTwoRows => &[BOTTOM_ROWS],
+            LayoutTemplate::TopTwoRows => &[TOP_ROWS],
+            LayoutTemplate::FourRows => &[BOTTOM_ROWS, TOP_ROWS],
+        }
+    }
+}
+
+/// The next white note at or above `note`.
+fn white_at_or_above(note: Note) -> Option<Note> {
+    (0..2).filter_map(|d| note.transpose(d)).find(|n| n.is_white())
+}
+
+/// Build a keymap laid out like a piano, starting at `start` (moved up to a
+/// white note if it's a black one).
+pub fn generate(template: LayoutTemplate, start: Note) -> KeyMap {
+    let mut bindings = Vec::new();
+    let mut next_white = white_at_or_above(start);
+
+    for rows in template.rows() {
+        let mut whites = Vec::new();
+        for ch in rows.white.chars() {
+            let Some(note) = next_white else {
+                break;
+            };
+            bindings.push((KeyId::Char(ch), vec![note.name()]));
+            whites.push(note);
+            next_white = note.transpose(1).and_then(white_at_or_above);
+        }
+
+        for (ch, pair) in rows.black.chars().zip(whites.windows(2)) {
+            if pair[1].midi() - pair[0].midi() == 2 {
+                if let Some(black) = pair[0].transpose(1) {
+                    bindings.push((KeyId::Char(ch), vec![black.name()]));
+                }
+            }
+        }
+    }
+
+    KeyMap::from_bindings(bindings)
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::Path;
+
+use crate::note::{Note, NoteName, SARGAM_NAMES};
+
+/// Where the lesson file is looked for unless told otherwise.
+pub const DEFAULT_LESSON_PATH: &str = "lessons/bilawal-aroha-avaroha.txt";
+
+/// Beats counted in before the first note is due.
+pub const COUNT_IN_BEATS: f64 = 4.0;
+
+/// How far (in beats) from its beat a note still counts as played on time.
+const WINDOW_BEATS: f64 = 0.5;
+
+/// Sa of the middle octave when the file doesn't set one (c3 on Sa = c), so
+/// a tar saptak (upper octave) Sa is still on the keyboard.
+const MIDDLE_SA_MIDI: i32 = 48;
+
+/// An exercise: notes to play, one per beat unless held longer.
+///
+/// The file is plain text. `#` starts a comment; `title:` names it and
+/// `sa:` sets the note its middle Sa is (else Sa of the third octave in
+/// the selected key). Everything else is notes, one beat each:
+///
+/// ```text
+/// title: Bilawal aroha
+/// Sa Re Ga ma Pa Dha Ni Sa' - -
+/// ```
+///
+/// A note is a swara as in the sargam labels (`re` komal, `Ma` tivra), with
+/// `'` for the octave above or `.` for the one below (`Ni.`), or a note name
+/// (`c4`). `-` holds the previous note one more beat; `_` is a beat of rest.
+#[derive(Debug, Clone)]
+pub struct Exercise {
+    pub title: String,
+    pub steps: Vec<Step>,
+}
+
+/// One note of an exercise and the beat it is due on (from 0).
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    pub note: Note,
+    pub beat: u32,
+}
+
+impl Exercise {
+    /// Read an exercise; swaras are placed with Sa on pitch class `tonic`.
+    pub fn load(path: impl AsRef<Path>, tonic: u8) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read lesson {}: {e}", path.display()))?;
+        Self::parse(&text, tonic).map_err(|e| format!("{e} in lesson {}", path.display()))
+    }
+
+    pub fn parse(text: &str, tonic: u8) -> Result<Self, String> {
+        let mut title = String::new();
+        let mut sa = MIDDLE_SA_MIDI + tonic as i32 % 12;
+        let mut steps = Vec::new();
+        let mut beat = 0;
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if let Some(rest) = line.strip_prefix("title:") {
+                title = rest.trim().to_string();
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("sa:") {
+                let note = Note::parse(rest.trim())
+                    .ok_or_else(|| format!("Bad Sa '{}' on line {}", rest.trim(), line_no + 1))?;
+                sa = note.midi() as i32;
+                continue;
+            }
+
+            for token in line.split_whitespace() {
+                match token {
+                    "-" if steps.is_empty() => {
+                        return Err(format!("'-' before any note on line {}", line_no + 1));
+                    }
+                    "-" | "_" => {}
+                    _ => {
+                        let note = parse_swara(token, sa)
+                            .or_else(|| Note::parse(token))
+                            .ok_or_else(|| format!("Bad note '{token}' on line {}", line_no + 1))?;
+                        steps.push(Step { note, beat });
+                    }
+                }
+                beat += 1;
+            }
+        }
+
+        if steps.is_empty() {
+            return Err("No notes".to_string());
+        }
+        Ok(Self { title, steps })
+    }
+}
+
+/// `Ga`, `Ni.`, `Sa'` with Sa at MIDI note `sa`.
+fn parse_swara(token: &str, sa: i32) -> Option<Note> {
+    let swara = token.trim_end_matches(['\'', '.']);
+    let marks = &token[swara.len()..];
+    let octave = match marks {
+        "" => 0,
+        _ if marks.chars().all(|c| c == '\'') => marks.len() as i32,
+        _ if marks.chars().all(|c| c == '.') => -(marks.len() as i32),
+        _ => return None,
+    };
+    let degree = SARGAM_NAMES.iter().position(|s| *s == swara)? as i32;
+    Note::from_midi(sa + degree + 12 * octave)
+}
+
+/// How one step went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepResult {
+    /// Right note, this many beats early (-) or late (+).
+    Hit(f32),
+    /// Another note was played on its beat.
+    Wrong,
+    /// Nothing was played on its beat.
+    Missed,
+}
+
+/// An exercise being played against the metronome.
+#[derive(Debug, Clone)]
+pub struct Lesson {
+    pub exercise: Exercise,
+
+    /// Metronome beat (as counted by `Metronome::beats_at`) the first note
+    /// is due on.
+    start_beat: f64,
+    pub results: Vec<StepResult>,
+    prev_notes: Vec<NoteName>,
+}
+
+impl Lesson {
+    /// Start with a count-in from metronome beat `beats_now`.
+    pub fn start(exercise: Exercise, beats_now: f64) -> Self {
+        Self {
+            exercise,
+            start_beat: beats_now.ceil() + COUNT_IN_BEATS,
+            results: Vec::new(),
+            prev_notes: Vec::new(),
+        }
+    }
+
+    /// The step to be played next, if any are left.
+    pub fn next_step(&self) -> Option<&Step> {
+        self.exercise.steps.get(self.results.len())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.results.len() >= self.exercise.steps.len()
+    }
+
+    /// Beats until the first note while counting in.
+    pub fn count_in_left(&self, beats_now: f64) -> Option<u32> {
+        (beats_now < self.start_beat).then(|| (self.start_beat - beats_now).ceil() as u32)
+    }
+
+    /// Call once per frame with the metronome's beat count and the sounding
+    /// notes; scores notes as they start and steps as their beat passes.
+    pub fn update(&mut self, beats_now: f64, active: &[NoteName]) {
+        let started: Vec<Note> = active
+            .iter()
+            .filter(|n| !self.prev_notes.contains(n))
+            .filter_map(|n| Note::parse(n))
+            .collect();
+        self.prev_notes = active.to_vec();
+
+        for note in started {
+            let Some(step) = self.next_step() else {
+                break;
+            };
+            let offset = beats_now - (self.start_beat + step.beat as f64);
+            // Too early to be meant for this step: ignore it.
+            if offset < -WINDOW_BEATS {
+                continue;
+            }
+            let result = if note == step.note {
+                StepResult::Hit(offset as f32)
+            } else {
+                StepResult::Wrong
+            };
+            self.results.push(result);
+        }
+
+        while let Some(step) = self.next_step() {
+            if beats_now <= self.start_beat + step.beat as f64 + WINDOW_BEATS {
+                break;
+            }
+            self.results.push(StepResult::Missed);
+        }
+    }
+
+    /// Share of steps hit (0..1), over the steps scored so far.
+    pub fn accuracy(&self) -> f32 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        let hits = self.results.iter().filter(|r| matches!(r, StepResult::Hit(_))).count();
+        hits as f32 / self.results.len() as f32
+    }
+
+    /// Mean offset of the hits in beats: below 0 rushing, above 0 dragging.
+    pub fn mean_offset(&self) -> Option<f32> {
+        let offsets: Vec<f32> = self
+            .results
+            .iter()
+            .filter_map(|r| match r {
+                StepResult::Hit(offset) => Some(*offset),
+                _ => None,
+            })
+            .collect();
+        (!offsets.is_empty()).then(|| offsets.iter().sum::<f32>() / offsets.len() as f32)
+    }
+
+    /// Mean distance of the hits from their beat, in beats.
+    pub fn mean_error(&self) -> Option<f32> {
+        let errors: Vec<f32> = self
+            .results
+            .iter()
+            .filter_map(|r| match r {
+                StepResult::Hit(offset) => Some(offset.abs()),
+                _ => None,
+            })
+            .collect();
+        (!errors.is_empty()).then(|| errors.iter().sum::<f32>() / errors.len() as f32)
+    }
+}
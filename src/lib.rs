@@ -0,0 +1,82 @@
+//! The harmonium engine without the window: everything from a lid angle to
+//! sound, for the app, the headless mode, tests and other front ends.
+//!
+//! The pieces, in the order a note travels through them:
+//!
+//! - [`sensor`]: where the angle comes from. A [`sensor::SensorSlot`] runs a
+//!   [`sensor::SensorSource`] (the lid sensor, OSC, a websocket, serial, a
+//!   HID axis, a recording or a fake sine) on a background thread and hands
+//!   the latest samples over; [`fusion`] combines two of them.
+//! - [`bellows`]: angle to air. [`bellows::BellowsState::update`] turns each
+//!   sample into a bellows amplitude `a` (0..1) using
+//!   [`bellows::BellowsParams`], and a shaking lid into vibrato
+//!   ([`tremor`]); [`simulation`] drives it offline.
+//! - [`keymap`]: keys to notes. A [`keymap::KeyMap`] maps characters or
+//!   key positions to notes and chords; [`keymap::PressedKeys`] tracks which
+//!   keys are down and the notes they sound.
+//! - [`audio`]: notes to sound. An [`audio::AudioEngine`] loops one sample
+//!   per held note and sets every voice's volume from the bellows, with the
+//!   sustain pedal, pitch bend ([`pitch_bend`]), the harmonizer stop
+//!   ([`harmonizer`]) and level meters ([`meter`]) on top; [`sample_pitch`]
+//!   checks each sample's pitch against its name (with [`tuner`]'s YIN).
+//!   [`voice`] is how a voice loops and resamples its sample, without
+//!   rodio, so the DAW plugin plays notes the same way.
+//! - [`metronome`]: the beat grid (tempo and tala cycle) and its click;
+//!   [`punch`] places a recording's count-in and punch points on it.
+//!
+//! A minimal player:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use harmonium_core::audio::AudioEngine;
+//! use harmonium_core::bellows::{BellowsParams, BellowsState};
+//!
+//! let mut engine = AudioEngine::new("harmonium-sounds")?;
+//! let mut bellows = BellowsState::new(BellowsParams::default());
+//!
+//! engine.note_on("c3")?;
+//! // Two angle samples 20 ms apart: the lid opening.
+//! bellows.update(30.0, Duration::ZERO);
+//! let out = bellows.update(31.0, Duration::from_millis(20));
+//! engine.set_bellows(out.a);
+//! # Ok::<(), harmonium_core::audio::AudioError>(())
+//! ```
+//!
+//! Errors are typed ([`audio::AudioError`], [`keymap::KeymapError`],
+//! [`sensor::SensorError`]) so a front end can tell a missing file from a
+//! busy device; their messages are meant to be shown to the player.
+//! Nothing here depends on the UI toolkit.
+
+// These need audio devices and threads: the browser build brings its own
+// sound (Web Audio) and angle (DeviceOrientation) and uses the rest.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audio;
+pub mod bank;
+pub mod bellows;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fusion;
+pub mod harmonizer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hud;
+pub mod keymap;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod latency;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod meter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metronome;
+pub mod note;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pitch_bend;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod punch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sample_pitch;
+pub mod scale;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sensor;
+pub mod simulation;
+pub mod tremor;
+pub mod tuner;
+pub mod voice;
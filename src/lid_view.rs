@@ -0,0 +1,88 @@
+use std::f32::consts::PI;
+
+use egui::{Color32, Pos2, Sense, Shape, Stroke, Vec2};
+
+/// Pleats drawn across the bellows wedge.
+const PLEATS: usize = 6;
+
+/// Points along the wedge's outer arc.
+const ARC_SEGMENTS: usize = 24;
+
+/// Side view of the laptop: the deck, the lid at `theta_deg` from it, and
+/// the bellows between them lit up by the amplitude `a`. Shows at a glance
+/// that the sensor is being read and how pumping turns into air.
+pub struct LidView {
+    theta_deg: f32,
+    a: f32,
+}
+
+impl LidView {
+    pub fn new(theta_deg: f32, a: f32) -> Self {
+        Self {
+            theta_deg: theta_deg.clamp(0.0, 180.0),
+            a: a.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl egui::Widget for LidView {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let width = ui.available_width().min(260.0);
+        let size = Vec2::new(width, width * 0.6);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        let line = Stroke::new(3.0, visuals.strong_text_color());
+
+        // Hinge in the middle of the bottom edge; the deck runs right of it
+        // and an open lid leans back over the left half.
+        let hinge = Pos2::new(rect.center().x, rect.bottom() - 10.0);
+        let length = (rect.width() / 2.0 - 10.0).min(rect.height() - 20.0);
+        let at = |angle_deg: f32, r: f32| {
+            let t = angle_deg * PI / 180.0;
+            hinge + Vec2::new(t.cos(), -t.sin()) * r
+        };
+
+        // Bellows: a wedge between deck and lid, brighter with more air.
+        let bellows_r = length * 0.85;
+        let glow = Color32::from_rgb(230, 140, 40);
+        let fill = visuals.extreme_bg_color.lerp_to_gamma(glow, self.a);
+        if self.theta_deg > 1.0 {
+            let rim: Vec<Pos2> = (0..=ARC_SEGMENTS)
+                .map(|i| at(self.theta_deg * i as f32 / ARC_SEGMENTS as f32, bellows_r))
+                .collect();
+            let wedge = std::iter::once(hinge).chain(rim.iter().copied()).collect();
+            painter.add(Shape::convex_polygon(wedge, fill, Stroke::NONE));
+
+            let pleat = Stroke::new(1.0, visuals.weak_text_color());
+            for i in 1..PLEATS {
+                let angle = self.theta_deg * i as f32 / PLEATS as f32;
+                painter.line_segment([hinge, at(angle, bellows_r)], pleat);
+            }
+
+            // A halo around the rim while air is flowing.
+            if self.a > 0.05 {
+                let halo = glow.gamma_multiply(self.a);
+                painter.add(Shape::line(rim, Stroke::new(2.0 + 6.0 * self.a, halo)));
+            }
+        }
+
+        painter.line_segment([hinge, at(0.0, length)], line);
+        painter.line_segment([hinge, at(self.theta_deg, length)], line);
+        painter.circle_filled(hinge, 4.0, visuals.strong_text_color());
+
+        painter.text(
+            rect.left_top() + Vec2::new(4.0, 2.0),
+            egui::Align2::LEFT_TOP,
+            format!("{:.0}°", self.theta_deg),
+            egui::FontId::proportional(14.0),
+            visuals.text_color(),
+        );
+
+        response
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, TryRecvError};
+
+use tracing::{info, warn};
+
+use crate::audio::{self, AudioError, SharedSample};
+use crate::bank;
+use crate::keymap::{KeyMap, KeymapError};
+use crate::note::{Note, NoteName};
+use crate::sample_pitch::{self, PitchCheck};
+
+/// What a load brings in: the keymap (if asked for) and the samples of
+/// every note it plays, from each bank folder, plus the folder's files not
+/// named after a note. Every sample's pitch is checked on the way.
+pub struct LoadJob {
+    /// None: keep the keymap playing and preload its notes.
+    pub keymap_path: Option<String>,
+    pub keymap: Option<KeyMap>,
+    pub sample_dirs: Vec<PathBuf>,
+}
+
+/// One step of a load, in the order they happen.
+pub enum LoadMsg {
+    Keymap(Result<KeyMap, KeymapError>),
+
+    /// The sample files to read; `Sample`s follow, one per file.
+    Started { total: usize },
+    Sample {
+        path: PathBuf,
+        result: Result<SharedSample, AudioError>,
+    },
+
+    /// A sample that sounds like another note than its name says.
+    Mislabeled {
+        path: PathBuf,
+        named: Note,
+        detected: Note,
+    },
+
+    /// The notes of `dir`'s unlabeled samples, found by their pitch.
+    NoteMap {
+        dir: PathBuf,
+        map: HashMap<NoteName, PathBuf>,
+    },
+    Finished,
+}
+
+/// Reads the keymap and the samples on a thread of its own, so the window
+/// keeps drawing meanwhile; the app takes the results each frame. Dropping
+/// it abandons the load.
+pub struct Loader {
+    messages: mpsc::Receiver<LoadMsg>,
+
+    /// Sample files read so far, of how many, and how many of them failed.
+    pub done: usize,
+    pub total: usize,
+    pub failed: usize,
+}
+
+impl Loader {
+    pub fn start(job: LoadJob) -> Self {
+        let (tx, messages) = mpsc::channel();
+        std::thread::spawn(move || run(job, &tx));
+        Self {
+            messages,
+            done: 0,
+            total: 0,
+            failed: 0,
+        }
+    }
+
+    /// Everything that arrived since the last call, counted; true once the
+    /// load is over (or its thread died).
+    pub fn poll(&mut self) -> (Vec<LoadMsg>, bool) {
+        let mut msgs = Vec::new();
+        loop {
+            match self.messages.try_recv() {
+                Ok(LoadMsg::Finished) => return (msgs, true),
+                Ok(msg) => {
+                    match &msg {
+                        LoadMsg::Started { total } => self.total = *total,
+                        LoadMsg::Sample { result, .. } => {
+                            self.done += 1;
+                            self.failed += usize::from(result.is_err());
+                        }
+                        _ => {}
+                    }
+                    msgs.push(msg);
+                }
+                Err(TryRecvError::Empty) => return (msgs, false),
+                Err(TryRecvError::Disconnected) => return (msgs, true),
+            }
+        }
+    }
+}
+
+/// The load itself. Stops early once nobody is listening.
+fn run(job: LoadJob, tx: &mpsc::Sender<LoadMsg>) {
+    let keymap = match job.keymap_path {
+        Some(path) => {
+            let result = KeyMap::load_or_builtin(&path);
+            let keymap = result.as_ref().ok().cloned();
+            if tx.send(LoadMsg::Keymap(result)).is_err() {
+                return;
+            }
+            keymap
+        }
+        None => job.keymap,
+    };
+
+    let notes = keymap.map(|km| km.all_notes()).unwrap_or_default();
+    let paths: Vec<PathBuf> = job
+        .sample_dirs
+        .iter()
+        .flat_map(|dir| notes.iter().filter_map(|n| bank::sample_path(dir, n)))
+        .collect();
+    let unlabeled: Vec<(PathBuf, Vec<PathBuf>)> =
+        job.sample_dirs.iter().map(|dir| (dir.clone(), bank::unlabeled_samples(dir))).collect();
+
+    let total = paths.len() + unlabeled.iter().map(|(_, files)| files.len()).sum::<usize>();
+    if tx.send(LoadMsg::Started { total }).is_err() {
+        return;
+    }
+    for path in paths {
+        let result = audio::load_sample(&path);
+        let check =
+            result.as_ref().map_or(PitchCheck::Unclear, |s| sample_pitch::analyse(&path, s));
+        if let PitchCheck::Mislabeled { named, detected } = check {
+            warn!("{} sounds like {detected}, not {named}", path.display());
+            let msg = LoadMsg::Mislabeled {
+                path: path.clone(),
+                named,
+                detected,
+            };
+            if tx.send(msg).is_err() {
+                return;
+            }
+        }
+        if tx.send(LoadMsg::Sample { path, result }).is_err() {
+            return;
+        }
+    }
+
+    for (dir, files) in unlabeled.into_iter().filter(|(_, files)| !files.is_empty()) {
+        let mut found = Vec::new();
+        for path in files {
+            let result = audio::load_sample(&path);
+            match result.as_ref().map(|s| sample_pitch::analyse(&path, s)) {
+                Ok(PitchCheck::Unlabeled { detected, cents }) => {
+                    found.push((path.clone(), detected, cents));
+                }
+                Ok(_) => warn!("{}: no clear pitch, not played", path.display()),
+                Err(_) => {}
+            }
+            if tx.send(LoadMsg::Sample { path, result }).is_err() {
+                return;
+            }
+        }
+        let map = sample_pitch::note_map(found);
+        info!("{} unlabeled samples in {} mapped by pitch", map.len(), dir.display());
+        if tx.send(LoadMsg::NoteMap { dir, map }).is_err() {
+            return;
+        }
+    }
+    info!("samples preloaded");
+    let _ = tx.send(LoadMsg::Finished);
+}
@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Our own events (everything under this crate) go to the log panel; other
+/// crates' (eframe, winit, ...) only reach stderr, and only warnings.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// How many entries the log panel keeps.
+const LOG_CAPACITY: usize = 500;
+
+/// One logged event.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Seconds since the app started.
+    pub t_sec: f32,
+    pub level: Level,
+
+    /// Top-level module it came from, e.g. `sensor` or `audio`.
+    pub module: String,
+    pub message: String,
+}
+
+/// The recent log, shared between the tracing layer (any thread) and the UI.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    start: Instant,
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(LOG_CAPACITY))),
+        }
+    }
+}
+
+impl LogBuffer {
+    fn push(&self, entry: LogEntry) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == LOG_CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    /// Entries at `max_level` or more severe, from `module` (None = all), oldest first.
+    pub fn filtered(&self, max_level: Level, module: Option<&str>) -> Vec<LogEntry> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+
+        entries
+            .iter()
+            .filter(|e| e.level <= max_level)
+            .filter(|e| module.is_none_or(|m| e.module == m))
+            .cloned()
+            .collect()
+    }
+
+    /// Every module that has logged something, sorted (for the filter).
+    pub fn modules(&self) -> Vec<String> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+
+        let mut modules: Vec<String> = entries.iter().map(|e| e.module.clone()).collect();
+        modules.sort();
+        modules.dedup();
+        modules
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+/// Install the global subscriber: our events at debug and up into `buffer`,
+/// and info and up (warnings for other crates) to stderr.
+pub fn init(buffer: LogBuffer) {
+    let stderr_filter = Targets::new()
+        .with_default(Level::WARN)
+        .with_target(CRATE_TARGET, Level::INFO);
+    let panel_filter = Targets::new().with_target(CRATE_TARGET, Level::DEBUG);
+
+    let _ = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(stderr_filter),
+        )
+        .with(PanelLayer { buffer }.with_filter(panel_filter))
+        .try_init();
+}
+
+/// Copies events into the log panel's buffer.
+struct PanelLayer {
+    buffer: LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for PanelLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+
+        self.buffer.push(LogEntry {
+            t_sec: self.buffer.start.elapsed().as_secs_f32(),
+            level: *meta.level(),
+            module: module_of(meta.target()).to_string(),
+            message: message.0,
+        });
+    }
+}
+
+/// `harmonium_in_mac::sensor::booklid` → `sensor`.
+fn module_of(target: &str) -> &str {
+    target.split("::").nth(1).unwrap_or(target)
+}
+
+/// The event's message, then any other fields as `name=value`.
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.push_str(value);
+        } else {
+            let _ = write!(self.0, " {}={value}", field.name());
+        }
+    }
+}
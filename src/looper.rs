@@ -0,0 +1,260 @@
+use std::time::Instant;
+
+use crate::note::NoteName;
+
+/// Records the notes of a phrase, loops them, and lets more layers be
+/// played over the top while the loop keeps going.
+///
+/// The first layer sets the loop length; every overdub is timed against it.
+/// Layers hold notes, not audio: they play through the live voice and the
+/// live bellows, so the loop breathes with whatever the lid is doing.
+#[derive(Debug, Clone, Default)]
+pub struct PhraseLooper {
+    state: PhraseLooperState,
+    layers: Vec<Layer>,
+
+    /// Loop length in seconds (0 until the first layer is done).
+    length_sec: f32,
+
+    /// Notes of the layer being recorded that are still held, with the time
+    /// (seconds since the loop started) they started.
+    open: Vec<(NoteName, f32)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PhraseLooperState {
+    #[default]
+    Idle,
+    /// Recording the first layer.
+    Recording { started: Instant },
+    Looping { started: Instant },
+    /// Looping and recording another layer over it.
+    Overdubbing { started: Instant },
+}
+
+/// One pass of recorded notes.
+#[derive(Debug, Clone, Default)]
+pub struct Layer {
+    spans: Vec<NoteSpan>,
+    pub muted: bool,
+}
+
+impl Layer {
+    pub fn note_count(&self) -> usize {
+        self.spans.len()
+    }
+}
+
+/// A note held from `start_sec` to `end_sec` of the loop. An end before the
+/// start wraps around the loop's end.
+#[derive(Debug, Clone)]
+struct NoteSpan {
+    note: NoteName,
+    start_sec: f32,
+    end_sec: f32,
+}
+
+impl NoteSpan {
+    fn sounds_at(&self, t: f32) -> bool {
+        if self.start_sec < self.end_sec {
+            (self.start_sec..self.end_sec).contains(&t)
+        } else {
+            t >= self.start_sec || t < self.end_sec
+        }
+    }
+}
+
+impl PhraseLooper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> PhraseLooperState {
+        self.state
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn length_sec(&self) -> f32 {
+        self.length_sec
+    }
+
+    /// Seconds since recording started, or into the current pass of the loop.
+    pub fn position_sec(&self, now: Instant) -> f32 {
+        match self.state {
+            PhraseLooperState::Idle => 0.0,
+            PhraseLooperState::Recording { started } => (now - started).as_secs_f32(),
+            PhraseLooperState::Looping { started } | PhraseLooperState::Overdubbing { started } => {
+                self.phase(started, now)
+            }
+        }
+    }
+
+    fn phase(&self, started: Instant, now: Instant) -> f32 {
+        if self.length_sec <= 0.0 {
+            return 0.0;
+        }
+        now.saturating_duration_since(started).as_secs_f32() % self.length_sec
+    }
+
+    /// Start recording the first layer (throws away any old loop).
+    pub fn start_recording(&mut self, now: Instant) {
+        self.layers.clear();
+        self.open.clear();
+        self.length_sec = 0.0;
+        self.layers.push(Layer::default());
+        self.state = PhraseLooperState::Recording { started: now };
+    }
+
+    /// End the first layer there and start looping it right away.
+    pub fn stop_recording_and_loop(&mut self, now: Instant) {
+        let PhraseLooperState::Recording { started } = self.state else {
+            return;
+        };
+        self.length_sec = (now - started).as_secs_f32();
+        self.close_open(self.length_sec);
+
+        let empty = self.layers.iter().all(|l| l.spans.is_empty());
+        if self.length_sec <= 0.0 || empty {
+            self.clear();
+        } else {
+            self.state = PhraseLooperState::Looping { started: now };
+        }
+    }
+
+    /// Start recording another layer over the playing loop.
+    pub fn start_overdub(&mut self) {
+        if let PhraseLooperState::Looping { started } = self.state {
+            self.layers.push(Layer::default());
+            self.state = PhraseLooperState::Overdubbing { started };
+        }
+    }
+
+    /// Keep the layer being overdubbed and go on looping.
+    pub fn stop_overdub(&mut self, now: Instant) {
+        let PhraseLooperState::Overdubbing { started } = self.state else {
+            return;
+        };
+        self.close_open(self.phase(started, now));
+        if self.layers.last().is_some_and(|l| l.spans.is_empty()) {
+            self.layers.pop();
+        }
+        self.state = PhraseLooperState::Looping { started };
+    }
+
+    /// Play the recorded loop again from its start.
+    pub fn start_looping(&mut self, now: Instant) {
+        if self.state == PhraseLooperState::Idle && self.length_sec > 0.0 {
+            self.state = PhraseLooperState::Looping { started: now };
+        }
+    }
+
+    /// Stop playing (and any overdub). The layers are kept.
+    pub fn stop(&mut self, now: Instant) {
+        match self.state {
+            PhraseLooperState::Recording { .. } => self.stop_recording_and_loop(now),
+            PhraseLooperState::Overdubbing { .. } => self.stop_overdub(now),
+            _ => {}
+        }
+        if self.length_sec > 0.0 {
+            self.state = PhraseLooperState::Idle;
+        }
+    }
+
+    /// Drop the newest layer: the overdub in progress, or the last one kept.
+    /// Undoing the first layer clears the loop.
+    pub fn undo(&mut self) {
+        if let PhraseLooperState::Overdubbing { started } = self.state {
+            self.open.clear();
+            self.layers.pop();
+            self.state = PhraseLooperState::Looping { started };
+            return;
+        }
+        self.layers.pop();
+        if self.layers.is_empty() {
+            self.clear();
+        }
+    }
+
+    pub fn set_muted(&mut self, layer: usize, muted: bool) {
+        if let Some(l) = self.layers.get_mut(layer) {
+            l.muted = muted;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Call once per frame with the notes being played live (not the ones
+    /// the loop is playing). Only stored while recording or overdubbing.
+    pub fn feed(&mut self, live: &[NoteName], now: Instant) {
+        let t = match self.state {
+            PhraseLooperState::Recording { started } => (now - started).as_secs_f32(),
+            PhraseLooperState::Overdubbing { started } => self.phase(started, now),
+            _ => return,
+        };
+
+        let released: Vec<NoteName> = self
+            .open
+            .iter()
+            .filter(|(note, _)| !live.contains(note))
+            .map(|(note, _)| note.clone())
+            .collect();
+        for note in released {
+            self.close(&note, t);
+        }
+
+        for note in live {
+            if !self.open.iter().any(|(n, _)| n == note) {
+                self.open.push((note.clone(), t));
+            }
+        }
+    }
+
+    /// End the open note `note` at loop time `t`.
+    fn close(&mut self, note: &NoteName, t: f32) {
+        let Some(i) = self.open.iter().position(|(n, _)| n == note) else {
+            return;
+        };
+        let (note, start_sec) = self.open.remove(i);
+        if start_sec == t {
+            return;
+        }
+        if let Some(layer) = self.layers.last_mut() {
+            layer.spans.push(NoteSpan { note, start_sec, end_sec: t });
+        }
+    }
+
+    fn close_open(&mut self, t: f32) {
+        let open: Vec<NoteName> = self.open.iter().map(|(n, _)| n.clone()).collect();
+        for note in open {
+            self.close(&note, t);
+        }
+    }
+
+    /// Notes the loop plays at `now`: every unmuted layer's, including what
+    /// an overdub has got so far (heard from its next pass on).
+    pub fn notes_at(&self, now: Instant) -> Vec<NoteName> {
+        let (PhraseLooperState::Looping { started } | PhraseLooperState::Overdubbing { started }) =
+            self.state
+        else {
+            return Vec::new();
+        };
+
+        let t = self.phase(started, now);
+        let mut notes: Vec<NoteName> = self
+            .layers
+            .iter()
+            .filter(|l| !l.muted)
+            .flat_map(|l| &l.spans)
+            .filter(|s| s.sounds_at(t))
+            .map(|s| s.note.clone())
+            .collect();
+        notes.sort();
+        notes.dedup();
+        notes
+    }
+}
@@ -1,17 +1,70 @@
+use clap::Parser;
+
+// The engine lives in the library; importing its modules here keeps the
+// `crate::audio`-style paths of the app working.
+use harmonium_core::{
+    audio, bank, bellows, fusion, harmonizer, hud, keymap, latency, meter, metronome, note,
+    pitch_bend, punch, sample_pitch, scale, sensor, simulation, tremor, tuner,
+};
+
 mod app;
-mod bellows;
-mod keymap;
-mod audio;
-mod sensor;
+mod arpeggiator;
+mod arrow_bellows;
+mod backing;
+mod beat_wheel;
+mod cli;
+mod config;
+mod demo;
+mod dock;
+mod dual;
+mod events;
+mod gesture;
+mod headless;
+mod history;
+mod lesson;
+mod lid_view;
+mod logging;
+mod looper;
+mod layout;
+mod loader;
+mod midi;
+mod practice;
+mod preset;
+mod remote;
+mod scripting;
+mod session;
+mod shortcuts;
+mod stats;
+mod toast;
+mod wizard;
 
 fn main() -> eframe::Result<()> {
-    // Create a standard (non-async) channel to send sensor messages to the GUI.
-    let (tx, rx) = std::sync::mpsc::channel::<sensor::SensorMsg>();
+    let cli = cli::Cli::parse();
+
+    let log = logging::LogBuffer::default();
+    logging::init(log.clone());
+
+    // Settings saved by the last run (defaults on the first), with the
+    // command-line overrides on top.
+    let mut config = config::AppConfig::load(config::config_path()).unwrap_or_else(|e| {
+        tracing::warn!("{e}");
+        config::AppConfig::default()
+    });
+    let overrides = cli.apply(&mut config);
 
     // Start the sensor in a background thread.
     // It will try to connect and stream angle samples.
     // If the device isn't available, you'll see the error in the UI.
-    let _sensor_thread = sensor::spawn_sensor_thread(60.0, tx);
+    let devices = &config.devices;
+    let first_sensor = sensor::SensorSlot::start(devices.sensor_kind(), &devices.sensor_settings);
+
+    if cli.headless {
+        if let Err(e) = headless::run(first_sensor, config.bellows, cli.record.as_deref()) {
+            tracing::error!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     let options = eframe::NativeOptions::default();
 
@@ -19,7 +72,11 @@ fn main() -> eframe::Result<()> {
         "Harmonium",
         options,
         Box::new(move |_cc| {
-            let harmonium = app::HarmoniumApp::new(rx);
+            let mut harmonium = app::HarmoniumApp::new(first_sensor, config, log);
+            harmonium.set_overrides(overrides);
+            if let Some(path) = &cli.record {
+                harmonium.start_sensor_recording(path);
+            }
 
             Ok(Box::new(EguiAppWrapper { inner: harmonium }))
         }),
@@ -34,4 +91,8 @@ impl eframe::App for EguiAppWrapper {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.inner.ui(ctx);
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.inner.shutdown();
+    }
 }
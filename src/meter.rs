@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rodio::Source;
+
+/// Samples (all channels) per level reading: about 23 ms of 44.1 kHz stereo.
+const BLOCK_LEN: usize = 2048;
+
+/// Mono frames kept for the tuner: about 93 ms at 44.1 kHz, a few periods
+/// of the lowest harmonium notes.
+const SNAPSHOT_LEN: usize = 4096;
+
+/// Bottom of the meter scale.
+const FLOOR_DB: f32 = -60.0;
+
+/// Level of one voice's sample, measured on the audio thread before the
+/// voice's volume is applied, plus its latest stretch of audio for the tuner.
+#[derive(Debug, Default)]
+pub struct VoiceMeter {
+    rms_bits: AtomicU32,
+    sample_rate: AtomicU32,
+
+    /// The last `SNAPSHOT_LEN` frames (first channel), oldest first.
+    snapshot: Mutex<Vec<f32>>,
+}
+
+impl VoiceMeter {
+    /// RMS of the last block the audio thread played (0..1).
+    pub fn rms(&self) -> f32 {
+        f32::from_bits(self.rms_bits.load(Ordering::Relaxed))
+    }
+
+    /// The voice's latest audio (mono) and its sample rate.
+    pub fn snapshot(&self) -> (Vec<f32>, u32) {
+        let frames = self.snapshot.lock().map(|s| s.clone()).unwrap_or_default();
+        (frames, self.sample_rate.load(Ordering::Relaxed))
+    }
+}
+
+/// An RMS level as a 0..1 bar length on a -60..0 dBFS scale.
+pub fn meter_fraction(rms: f32) -> f32 {
+    if rms <= 0.0 {
+        return 0.0;
+    }
+    ((20.0 * rms.log10() - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Wraps a voice's source and keeps its meter up to date.
+pub struct MeterSource<S> {
+    inner: S,
+    meter: Arc<VoiceMeter>,
+    sum_sq: f32,
+    count: usize,
+
+    /// Channel of the next sample, and the recent first-channel frames.
+    channel: u16,
+    recent: VecDeque<f32>,
+}
+
+impl<S> MeterSource<S> {
+    pub fn new(inner: S, meter: Arc<VoiceMeter>) -> Self {
+        Self {
+            inner,
+            meter,
+            sum_sq: 0.0,
+            count: 0,
+            channel: 0,
+            recent: VecDeque::with_capacity(SNAPSHOT_LEN),
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> MeterSource<S> {
+    /// End of a block: publish its level and the recent audio. Never waits
+    /// for the UI thread; a busy snapshot just keeps the previous one.
+    fn publish(&mut self) {
+        let meter = &self.meter;
+        meter.rms_bits.store((self.sum_sq / BLOCK_LEN as f32).sqrt().to_bits(), Ordering::Relaxed);
+        meter.sample_rate.store(self.inner.sample_rate(), Ordering::Relaxed);
+        if let Ok(mut snapshot) = meter.snapshot.try_lock() {
+            snapshot.clear();
+            snapshot.extend(self.recent.iter());
+        }
+
+        self.sum_sq = 0.0;
+        self.count = 0;
+    }
+}
+
+impl<S> Iterator for MeterSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        self.sum_sq += sample * sample;
+        self.count += 1;
+
+        if self.channel == 0 {
+            if self.recent.len() == SNAPSHOT_LEN {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(sample);
+        }
+        self.channel = (self.channel + 1) % self.inner.channels().max(1);
+
+        if self.count == BLOCK_LEN {
+            self.publish();
+        }
+        Some(sample)
+    }
+}
+
+impl<S> Source for MeterSource<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
@@ -0,0 +1,291 @@
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+/// Tempos the metronome offers (beats, or matras, per minute).
+pub const BPM_RANGE: RangeInclusive<f32> = 30.0..=400.0;
+
+/// Sample rate and length of one click.
+const CLICK_RATE: u32 = 44_100;
+const CLICK_SEC: f32 = 0.06;
+
+/// How quickly a click dies away (seconds to fall to 1/e).
+const CLICK_DECAY_SEC: f32 = 0.012;
+
+/// What the metronome counts: a plain bar of clicks, or a tala cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tala {
+    Click,
+    Teental,
+    Jhaptal,
+    Rupak,
+}
+
+/// How a beat is marked: sam (the first beat of the cycle), a clap (tali),
+/// a wave (khali), or nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BeatKind {
+    Sam,
+    Tali,
+    Khali,
+    Plain,
+}
+
+impl Tala {
+    pub const ALL: [Tala; 4] = [Tala::Click, Tala::Teental, Tala::Jhaptal, Tala::Rupak];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Tala::Click => "Click (4 beats)",
+            Tala::Teental => "Teental (16)",
+            Tala::Jhaptal => "Jhaptal (10)",
+            Tala::Rupak => "Rupak (7)",
+        }
+    }
+
+    /// The vibhags (sections) of the cycle: their length in beats, the
+    /// usual marker (X sam, 0 khali, numbers for the claps) and how their
+    /// first beat is played. Rupak starts on a khali, so its sam is a wave.
+    fn vibhags(self) -> &'static [(usize, &'static str, BeatKind)] {
+        match self {
+            Tala::Click => &[(4, "1", BeatKind::Sam)],
+            Tala::Teental => &[
+                (4, "X", BeatKind::Sam),
+                (4, "2", BeatKind::Tali),
+                (4, "0", BeatKind::Khali),
+                (4, "3", BeatKind::Tali),
+            ],
+            Tala::Jhaptal => &[
+                (2, "X", BeatKind::Sam),
+                (3, "2", BeatKind::Tali),
+                (2, "0", BeatKind::Khali),
+                (3, "3", BeatKind::Tali),
+            ],
+            Tala::Rupak => &[
+                (3, "0", BeatKind::Khali),
+                (2, "1", BeatKind::Tali),
+                (2, "2", BeatKind::Tali),
+            ],
+        }
+    }
+
+    /// Beats in one cycle.
+    pub fn beat_count(self) -> usize {
+        self.vibhags().iter().map(|(len, _, _)| len).sum()
+    }
+
+    /// The vibhag `beat` (0-based) falls in, and whether it is its first beat.
+    fn vibhag_of(self, beat: usize) -> Option<(&'static str, BeatKind, bool)> {
+        let mut start = 0;
+        for &(len, marker, kind) in self.vibhags() {
+            if beat < start + len {
+                return Some((marker, kind, beat == start));
+            }
+            start += len;
+        }
+        None
+    }
+
+    pub fn beat_kind(self, beat: usize) -> BeatKind {
+        match self.vibhag_of(beat) {
+            Some((_, kind, true)) => kind,
+            _ => BeatKind::Plain,
+        }
+    }
+
+    /// The marker written over `beat`, if it starts a vibhag.
+    pub fn marker(self, beat: usize) -> Option<&'static str> {
+        match self.vibhag_of(beat) {
+            Some((marker, _, true)) => Some(marker),
+            _ => None,
+        }
+    }
+}
+
+/// Tempo and cycle, saved with the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetronomeConfig {
+    pub bpm: f32,
+    pub tala: Tala,
+
+    /// Click level (0..1), separate from the master volume and the bellows.
+    pub volume: f32,
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        Self {
+            bpm: 80.0,
+            tala: Tala::Teental,
+            volume: 0.5,
+        }
+    }
+}
+
+/// Counts beats from when it was started. Driven by the UI clock: `tick`
+/// reports each new beat on the first frame after it falls.
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    config: MetronomeConfig,
+
+    /// When beat 0 fell, while running.
+    started: Option<Instant>,
+    last_beat: Option<u64>,
+}
+
+impl Metronome {
+    pub fn new(config: MetronomeConfig) -> Self {
+        Self {
+            config,
+            started: None,
+            last_beat: None,
+        }
+    }
+
+    pub fn config(&self) -> &MetronomeConfig {
+        &self.config
+    }
+
+    pub fn bpm(&self) -> f32 {
+        self.config.bpm
+    }
+
+    pub fn tala(&self) -> Tala {
+        self.config.tala
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.config.volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// Change the tempo without jumping: the beat in progress carries on
+    /// from where it was.
+    pub fn set_bpm(&mut self, bpm: f32, now: Instant) {
+        let beats = self.beats_at(now);
+        self.config.bpm = bpm.clamp(*BPM_RANGE.start(), *BPM_RANGE.end());
+        if let Some(beats) = beats {
+            let elapsed = Duration::from_secs_f64(beats * self.beat_sec());
+            self.started = now.checked_sub(elapsed).or(self.started);
+        }
+    }
+
+    /// Switch cycles; a running metronome starts the new one from sam.
+    pub fn set_tala(&mut self, tala: Tala, now: Instant) {
+        self.config.tala = tala;
+        if self.is_running() {
+            self.start(now);
+        }
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        self.started = Some(now);
+        self.last_beat = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.started = None;
+        self.last_beat = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Seconds per beat.
+    pub fn beat_sec(&self) -> f64 {
+        60.0 / self.config.bpm as f64
+    }
+
+    /// Beats (fractional) since the start, while running.
+    pub fn beats_at(&self, now: Instant) -> Option<f64> {
+        let started = self.started?;
+        Some(now.saturating_duration_since(started).as_secs_f64() / self.beat_sec())
+    }
+
+    /// Beat of the cycle (0-based) and how far through it (0..1), while running.
+    pub fn position(&self, now: Instant) -> Option<(usize, f32)> {
+        let beats = self.beats_at(now)?;
+        let beat = beats.floor() as u64 % self.config.tala.beat_count() as u64;
+        Some((beat as usize, beats.fract() as f32))
+    }
+
+    /// The kind of beat that fell since the last call, if one did. Beats
+    /// missed by a stalled frame are skipped, not played late.
+    pub fn tick(&mut self, now: Instant) -> Option<BeatKind> {
+        let beat = self.beats_at(now)?.floor() as u64;
+        if self.last_beat == Some(beat) {
+            return None;
+        }
+        self.last_beat = Some(beat);
+        let in_cycle = beat % self.config.tala.beat_count() as u64;
+        Some(self.config.tala.beat_kind(in_cycle as usize))
+    }
+
+    /// The click for a beat at the metronome's volume.
+    pub fn click(&self, kind: BeatKind) -> ClickSource {
+        ClickSource::new(kind, self.config.volume)
+    }
+}
+
+/// A short decaying sine blip: high and loud on sam, lower on a clap,
+/// soft on a wave, quietest on the other beats.
+pub struct ClickSource {
+    n: u32,
+    len: u32,
+    hz: f32,
+    gain: f32,
+}
+
+impl ClickSource {
+    pub fn new(kind: BeatKind, volume: f32) -> Self {
+        let (hz, level) = match kind {
+            BeatKind::Sam => (1760.0, 1.0),
+            BeatKind::Tali => (1320.0, 0.8),
+            BeatKind::Khali => (660.0, 0.5),
+            BeatKind::Plain => (990.0, 0.4),
+        };
+        Self {
+            n: 0,
+            len: (CLICK_RATE as f32 * CLICK_SEC) as u32,
+            hz,
+            gain: level * volume.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Iterator for ClickSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.n >= self.len {
+            return None;
+        }
+        let t = self.n as f32 / CLICK_RATE as f32;
+        self.n += 1;
+
+        let envelope = (-t / CLICK_DECAY_SEC).exp();
+        Some(self.gain * envelope * (std::f32::consts::TAU * self.hz * t).sin())
+    }
+}
+
+impl Source for ClickSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some((self.len - self.n) as usize)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        CLICK_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(CLICK_SEC))
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use crate::note::{Note, NoteName};
+
+mod error;
+mod input;
+mod learn;
+mod mpe;
+mod output;
+mod recorder;
+
+pub use error::MidiError;
+pub use input::{MidiEvent, MidiIn, available_input_ports};
+pub use learn::{DEFAULT_MIDI_LEARN_PATH, MidiLearn, MidiParam};
+pub use output::{MidiOut, available_output_ports};
+pub use recorder::{DEFAULT_MIDI_RECORDING_PATH, MidiRecorder};
+
+/// MIDI number of a note name (None for names that aren't a pitch).
+fn midi_number(note: &str) -> Option<u8> {
+    Note::parse(note).map(Note::midi)
+}
+
+/// Client name the app registers with the system MIDI service.
+const CLIENT_NAME: &str = "Harmonium";
+
+/// Sustain (damper) pedal controller; 64 and up = pressed.
+pub const CC_SUSTAIN: u8 = 64;
+
+/// Controller numbers we send.
+const CC_EXPRESSION: u8 = 11;
+const CC_ALL_NOTES_OFF: u8 = 123;
+
+/// Velocity for every note-on: a harmonium has no key velocity, the
+/// dynamics travel as CC11 instead.
+const NOTE_VELOCITY: u8 = 100;
+
+/// The performance as a MIDI stream on one channel: which notes are on and
+/// the last expression value, so each frame only the changes go out.
+/// Shared by the output port and the file recorder.
+#[derive(Debug)]
+struct Performance {
+    /// 0-based MIDI channel.
+    channel: u8,
+    sounding: HashSet<u8>,
+    last_expression: Option<u8>,
+    last_bend: Option<u16>,
+}
+
+impl Performance {
+    /// `channel` is 1..16.
+    fn new(channel: u8) -> Self {
+        Self {
+            channel: channel.clamp(1, 16) - 1,
+            sounding: HashSet::new(),
+            last_expression: None,
+            last_bend: None,
+        }
+    }
+
+    /// Note-offs, then note-ons, that turn what's sounding into `active`.
+    fn sync_notes(&mut self, active: &[NoteName]) -> Vec<[u8; 3]> {
+        let wanted: HashSet<u8> = active
+            .iter()
+            .filter_map(|n| Note::parse(n))
+            .map(Note::midi)
+            .collect();
+
+        let off: Vec<u8> = self.sounding.difference(&wanted).copied().collect();
+        let on: Vec<u8> = wanted.difference(&self.sounding).copied().collect();
+
+        let mut msgs = Vec::new();
+        for midi in off {
+            msgs.extend(self.note_off(midi));
+        }
+        for midi in on {
+            msgs.extend(self.note_on(midi));
+        }
+        msgs
+    }
+
+    /// Note-on for `midi`, unless it is already sounding.
+    fn note_on(&mut self, midi: u8) -> Option<[u8; 3]> {
+        self.sounding.insert(midi).then(|| [0x90 | self.channel, midi, NOTE_VELOCITY])
+    }
+
+    /// Note-off for `midi`, if it is sounding.
+    fn note_off(&mut self, midi: u8) -> Option<[u8; 3]> {
+        self.sounding.remove(&midi).then(|| [0x80 | self.channel, midi, 0])
+    }
+
+    /// The bellows amplitude (0..1) as CC11, if it changed.
+    fn expression(&mut self, a: f32) -> Option<[u8; 3]> {
+        let value = (a.clamp(0.0, 1.0) * 127.0).round() as u8;
+        if self.last_expression == Some(value) {
+            return None;
+        }
+
+        self.last_expression = Some(value);
+        Some(self.control(CC_EXPRESSION, value))
+    }
+
+    /// A pitch bend (-1..1 of the receiver's bend range), if it changed.
+    fn bend(&mut self, amount: f32) -> Option<[u8; 3]> {
+        let value = ((amount.clamp(-1.0, 1.0) + 1.0) * 8192.0).round().min(16383.0) as u16;
+        if self.last_bend == Some(value) {
+            return None;
+        }
+
+        self.last_bend = Some(value);
+        Some([0xE0 | self.channel, (value & 0x7F) as u8, (value >> 7) as u8])
+    }
+
+    fn control(&self, cc: u8, value: u8) -> [u8; 3] {
+        [0xB0 | self.channel, cc, value]
+    }
+}
@@ -0,0 +1,68 @@
+use std::io;
+use std::path::PathBuf;
+
+use midir::{ConnectErrorKind, InitError, SendError};
+use thiserror::Error;
+
+/// Why a MIDI port couldn't be opened or written to, or a MIDI file (a
+/// recording, the learned bindings) couldn't be read or written.
+#[derive(Debug, Error)]
+pub enum MidiError {
+    /// The system MIDI service isn't available.
+    #[error("MIDI init failed: {0}")]
+    Init(#[source] InitError),
+
+    #[error("MIDI {direction} port '{port}' not found")]
+    PortNotFound {
+        direction: &'static str,
+        port: String,
+    },
+
+    #[error("Failed to open MIDI port {port}: {kind}")]
+    Connect {
+        port: String,
+        kind: ConnectErrorKind,
+    },
+
+    /// The port was open but a message didn't go out (usually unplugged).
+    #[error("MIDI send to {port} failed: {source}")]
+    Send { port: String, source: SendError },
+
+    #[error("Failed to write MIDI file {path:?}: {source}")]
+    WriteFile { path: PathBuf, source: io::Error },
+
+    /// Reading or writing the learned CC bindings.
+    #[error("Failed to {action} MIDI bindings {path:?}: {source}")]
+    Bindings {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+
+    #[error("Bad MIDI bindings JSON: {0}")]
+    BindingsJson(#[source] serde_json::Error),
+}
+
+impl MidiError {
+    /// The port isn't there (unplugged, renamed, or gone while playing).
+    pub fn is_missing_port(&self) -> bool {
+        matches!(
+            self,
+            MidiError::PortNotFound { .. }
+                | MidiError::Connect { kind: ConnectErrorKind::InvalidPort, .. }
+                | MidiError::Send { .. }
+        )
+    }
+
+    /// What the player can do about it, under the message.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            _ if self.is_missing_port() => {
+                Some("Check the device is plugged in, then pick the port again")
+            }
+            MidiError::Connect { .. } => Some("Another program may have the port open"),
+            MidiError::Init(_) => Some("MIDI isn't available on this system right now"),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+use super::{CLIENT_NAME, MidiError};
+
+/// An incoming MIDI message the app cares about (any channel).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiEvent {
+    /// Control change: controller number and value (0..127).
+    Control { cc: u8, value: u8 },
+
+    /// Pitch bend wheel, -1..1 (0 = centre).
+    PitchBend(f32),
+
+    /// Program change (0..127), e.g. from a foot controller.
+    ProgramChange(u8),
+}
+
+impl MidiEvent {
+    /// Decode one raw message; everything we don't use is None.
+    fn parse(msg: &[u8]) -> Option<Self> {
+        match *msg {
+            [status, cc, value] if status & 0xF0 == 0xB0 => Some(MidiEvent::Control { cc, value }),
+            [status, lsb, msb] if status & 0xF0 == 0xE0 => {
+                let raw = (((msb as i32) << 7) | lsb as i32) - 8192;
+                Some(MidiEvent::PitchBend(raw as f32 / 8192.0))
+            }
+            [status, program, ..] if status & 0xF0 == 0xC0 => {
+                Some(MidiEvent::ProgramChange(program))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// MIDI input ports that exist right now (for the port picker).
+pub fn available_input_ports() -> Vec<String> {
+    let Ok(input) = MidiInput::new(CLIENT_NAME) else {
+        return Vec::new();
+    };
+
+    input
+        .ports()
+        .iter()
+        .filter_map(|p| input.port_name(p).ok())
+        .collect()
+}
+
+/// A connected MIDI input. midir calls back on its own thread; the events
+/// wait in a channel until the UI thread drains them.
+pub struct MidiIn {
+    _conn: MidiInputConnection<Sender<MidiEvent>>,
+    rx: Receiver<MidiEvent>,
+    port_name: String,
+}
+
+impl MidiIn {
+    pub fn connect(port_name: &str) -> Result<Self, MidiError> {
+        let mut input = MidiInput::new(CLIENT_NAME).map_err(MidiError::Init)?;
+        input.ignore(Ignore::All);
+
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|p| input.port_name(p).is_ok_and(|n| n == port_name))
+            .ok_or_else(|| MidiError::PortNotFound {
+                direction: "input",
+                port: port_name.to_string(),
+            })?;
+
+        let (tx, rx) = mpsc::channel();
+        let conn = input
+            .connect(
+                &port,
+                "harmonium-in",
+                |_stamp, msg, tx| {
+                    if let Some(event) = MidiEvent::parse(msg) {
+                        let _ = tx.send(event);
+                    }
+                },
+                tx,
+            )
+            .map_err(|e| MidiError::Connect {
+                port: port_name.to_string(),
+                kind: e.kind(),
+            })?;
+
+        Ok(Self {
+            _conn: conn,
+            rx,
+            port_name: port_name.to_string(),
+        })
+    }
+
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+
+    /// Everything that arrived since last frame, oldest first.
+    pub fn drain(&mut self) -> Vec<MidiEvent> {
+        self.rx.try_iter().collect()
+    }
+}
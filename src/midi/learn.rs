@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use super::MidiError;
+
+/// Where CC bindings are kept between runs.
+pub const DEFAULT_MIDI_LEARN_PATH: &str = "midi-learn.json";
+
+/// A slider that can be driven by a MIDI controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MidiParam {
+    Deadzone,
+    Vmax,
+    Gamma,
+    MasterGain,
+}
+
+impl MidiParam {
+    pub const ALL: [MidiParam; 4] = [
+        MidiParam::Deadzone,
+        MidiParam::Vmax,
+        MidiParam::Gamma,
+        MidiParam::MasterGain,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MidiParam::Deadzone => "deadzone",
+            MidiParam::Vmax => "vmax",
+            MidiParam::Gamma => "gamma",
+            MidiParam::MasterGain => "master volume",
+        }
+    }
+
+    /// Stable name used in the bindings file.
+    pub fn id(self) -> &'static str {
+        match self {
+            MidiParam::Deadzone => "deadzone",
+            MidiParam::Vmax => "vmax",
+            MidiParam::Gamma => "gamma",
+            MidiParam::MasterGain => "master_gain",
+        }
+    }
+
+    /// The slider's range; a CC sweeps all of it.
+    pub fn range(self) -> RangeInclusive<f32> {
+        match self {
+            MidiParam::Deadzone => 0.0..=40.0,
+            MidiParam::Vmax => 10.0..=500.0,
+            MidiParam::Gamma => 0.3..=4.0,
+            MidiParam::MasterGain => 0.0..=1.5,
+        }
+    }
+
+    /// Map a CC value (0..127) onto the range.
+    pub fn value_for(self, cc_value: u8) -> f32 {
+        let range = self.range();
+        let t = cc_value.min(127) as f32 / 127.0;
+        range.start() + t * (range.end() - range.start())
+    }
+}
+
+/// Which controller (CC number) drives which slider, plus the learn flow:
+/// pick a slider, move a knob, and that CC is bound to it.
+#[derive(Debug)]
+pub struct MidiLearn {
+    path: PathBuf,
+    bindings: HashMap<u8, MidiParam>,
+
+    /// Slider waiting for the next incoming CC.
+    learning: Option<MidiParam>,
+}
+
+impl MidiLearn {
+    /// Load bindings from `path`, e.g. `{ "gamma": 21, "master_gain": 7 }`.
+    /// A missing file just means nothing is bound yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MidiError> {
+        let mut learn = Self::empty(path);
+
+        let text = match fs::read_to_string(&learn.path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(learn),
+            Err(source) => {
+                return Err(MidiError::Bindings {
+                    action: "read",
+                    path: learn.path,
+                    source,
+                });
+            }
+        };
+        let raw: HashMap<String, u8> =
+            serde_json::from_str(&text).map_err(MidiError::BindingsJson)?;
+
+        // Unknown parameter names are ignored (e.g. from a newer version).
+        for param in MidiParam::ALL {
+            if let Some(cc) = raw.get(param.id()) {
+                learn.bindings.insert(*cc, param);
+            }
+        }
+        Ok(learn)
+    }
+
+    /// No bindings, saved to `path` once something is learned.
+    pub fn empty(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            bindings: HashMap::new(),
+            learning: None,
+        }
+    }
+
+    fn save(&self) -> Result<(), MidiError> {
+        let raw: HashMap<&str, u8> = self.bindings.iter().map(|(cc, p)| (p.id(), *cc)).collect();
+        let text = serde_json::to_string_pretty(&raw).map_err(MidiError::BindingsJson)?;
+        fs::write(&self.path, text).map_err(|source| MidiError::Bindings {
+            action: "write",
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    /// Wait for the next CC and bind it to `param`.
+    pub fn start(&mut self, param: MidiParam) {
+        self.learning = Some(param);
+    }
+
+    pub fn cancel(&mut self) {
+        self.learning = None;
+    }
+
+    pub fn learning(&self) -> Option<MidiParam> {
+        self.learning
+    }
+
+    /// The CC bound to a slider, if any.
+    pub fn cc_for(&self, param: MidiParam) -> Option<u8> {
+        self.bindings.iter().find(|(_, p)| **p == param).map(|(cc, _)| *cc)
+    }
+
+    /// Unbind a slider and save.
+    pub fn forget(&mut self, param: MidiParam) -> Result<(), MidiError> {
+        self.bindings.retain(|_, p| *p != param);
+        self.save()
+    }
+
+    /// If a slider is waiting to learn, bind `cc` to it and save.
+    pub fn finish_learning(&mut self, cc: u8) -> Result<(), MidiError> {
+        let Some(param) = self.learning.take() else {
+            return Ok(());
+        };
+
+        // One CC per slider, one slider per CC.
+        self.bindings.retain(|_, p| *p != param);
+        self.bindings.insert(cc, param);
+        self.save()
+    }
+
+    /// The slider an incoming CC drives, and its new value.
+    pub fn target(&self, cc: u8, value: u8) -> Option<(MidiParam, f32)> {
+        self.bindings.get(&cc).map(|p| (*p, p.value_for(value)))
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use super::{CC_ALL_NOTES_OFF, NOTE_VELOCITY};
+use crate::note::{Note, NoteName};
+
+/// MPE lower zone: channel 1 is the master channel, 2..16 carry one note each.
+const MEMBER_CHANNELS: usize = 15;
+
+/// Emulated key pressure builds from nothing to full over this long after a
+/// key goes down, so each note swells in on its own like a pressed reed.
+const PRESSURE_ATTACK: Duration = Duration::from_millis(300);
+
+/// One note per channel, each with its own pressure (channel aftertouch):
+/// the bellows times how long the key has been held.
+#[derive(Debug)]
+pub struct Mpe {
+    /// Per member channel (index 0 = channel 2): its note and when it started.
+    slots: [Option<(u8, Instant)>; MEMBER_CHANNELS],
+
+    /// Last pressure sent per member channel.
+    last_pressure: [Option<u8>; MEMBER_CHANNELS],
+}
+
+impl Mpe {
+    pub fn new() -> Self {
+        Self {
+            slots: [None; MEMBER_CHANNELS],
+            last_pressure: [None; MEMBER_CHANNELS],
+        }
+    }
+
+    /// MPE Configuration Message: a lower zone with 15 member channels
+    /// (RPN 6 on the master channel), then the RPN is closed again.
+    pub fn configure() -> Vec<[u8; 3]> {
+        vec![
+            [0xB0, 101, 0],
+            [0xB0, 100, 6],
+            [0xB0, 6, MEMBER_CHANNELS as u8],
+            [0xB0, 101, 127],
+            [0xB0, 100, 127],
+        ]
+    }
+
+    /// Note-offs and note-ons, each on its own channel, that turn what's
+    /// sounding into `active`. With all channels busy the oldest note is stolen.
+    pub fn sync_notes(&mut self, active: &[NoteName], now: Instant) -> Vec<[u8; 3]> {
+        let wanted: Vec<u8> = active
+            .iter()
+            .filter_map(|n| Note::parse(n))
+            .map(Note::midi)
+            .collect();
+
+        let mut msgs = Vec::new();
+        let sounding: Vec<u8> = self.slots.iter().flatten().map(|(m, _)| *m).collect();
+        for midi in sounding.into_iter().filter(|m| !wanted.contains(m)) {
+            msgs.extend(self.note_off(midi));
+        }
+        for midi in wanted {
+            msgs.extend(self.note_on(midi, now));
+        }
+        msgs
+    }
+
+    /// Note-on for `midi` on a free channel, stealing the oldest note's
+    /// channel if all are busy. Nothing if it is already sounding.
+    pub fn note_on(&mut self, midi: u8, now: Instant) -> Vec<[u8; 3]> {
+        if self.slots.iter().flatten().any(|(m, _)| *m == midi) {
+            return Vec::new();
+        }
+
+        let mut msgs = Vec::new();
+        let free = self.slots.iter().position(Option::is_none);
+        let i = match free {
+            Some(i) => i,
+            None => {
+                let oldest = (0..MEMBER_CHANNELS)
+                    .min_by_key(|i| self.slots[*i].map(|(_, t)| t))
+                    .unwrap_or(0);
+                msgs.push(self.release(oldest));
+                oldest
+            }
+        };
+
+        self.slots[i] = Some((midi, now));
+        self.last_pressure[i] = None;
+        msgs.push([0x90 | member_channel(i), midi, NOTE_VELOCITY]);
+        msgs
+    }
+
+    /// Note-off for `midi` on its channel, if it is sounding.
+    pub fn note_off(&mut self, midi: u8) -> Option<[u8; 3]> {
+        let i = self.slots.iter().position(|s| s.is_some_and(|(m, _)| m == midi))?;
+        Some(self.release(i))
+    }
+
+    /// Per-note pressure: bellows amplitude `a` (0..1) scaled by each key's
+    /// emulated pressure. Only changed values are returned.
+    pub fn pressure(&mut self, a: f32, now: Instant) -> Vec<[u8; 3]> {
+        let mut msgs = Vec::new();
+        for i in 0..MEMBER_CHANNELS {
+            let Some((_, started)) = self.slots[i] else {
+                continue;
+            };
+
+            let held = now.saturating_duration_since(started).as_secs_f32();
+            let key = (held / PRESSURE_ATTACK.as_secs_f32()).min(1.0);
+            let value = (a.clamp(0.0, 1.0) * key * 127.0).round() as u8;
+
+            if self.last_pressure[i] != Some(value) {
+                self.last_pressure[i] = Some(value);
+                msgs.push([0xD0 | member_channel(i), value, 0]);
+            }
+        }
+        msgs
+    }
+
+    /// All Notes Off on every member channel.
+    pub fn all_notes_off(&self) -> Vec<[u8; 3]> {
+        (0..MEMBER_CHANNELS)
+            .map(|i| [0xB0 | member_channel(i), CC_ALL_NOTES_OFF, 0])
+            .collect()
+    }
+
+    fn release(&mut self, i: usize) -> [u8; 3] {
+        let midi = self.slots[i].take().map_or(0, |(m, _)| m);
+        [0x80 | member_channel(i), midi, 0]
+    }
+}
+
+/// 0-based MIDI channel of member slot `i` (slot 0 = channel 2).
+fn member_channel(i: usize) -> u8 {
+    i as u8 + 1
+}
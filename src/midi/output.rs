@@ -0,0 +1,192 @@
+use midir::{MidiOutput, MidiOutputConnection};
+
+use std::time::Instant;
+
+use super::mpe::Mpe;
+use super::{CC_ALL_NOTES_OFF, CC_SUSTAIN, CLIENT_NAME, MidiError, Performance, midi_number};
+use crate::events::{ConfigEvent, Event, NoteEvent, Subscriber};
+use crate::note::NoteName;
+
+/// MIDI output ports that exist right now (for the port picker).
+pub fn available_output_ports() -> Vec<String> {
+    let Ok(out) = MidiOutput::new(CLIENT_NAME) else {
+        return Vec::new();
+    };
+
+    out.ports()
+        .iter()
+        .filter_map(|p| out.port_name(p).ok())
+        .collect()
+}
+
+/// Sends the performance to an external synth / DAW: the notes that sound,
+/// and the bellows as expression (CC11).
+///
+/// In MPE mode channel 1 is the master (expression, bend, sustain) and every
+/// note gets a channel of its own with its own pressure.
+pub struct MidiOut {
+    conn: MidiOutputConnection,
+    port_name: String,
+    performance: Performance,
+    mpe: Option<Mpe>,
+}
+
+impl MidiOut {
+    /// Open the output port called `port_name`. `channel` is 1..16 and is
+    /// ignored in MPE mode.
+    pub fn connect(port_name: &str, channel: u8, mpe: bool) -> Result<Self, MidiError> {
+        let out = MidiOutput::new(CLIENT_NAME).map_err(MidiError::Init)?;
+        let port = out
+            .ports()
+            .into_iter()
+            .find(|p| out.port_name(p).is_ok_and(|n| n == port_name))
+            .ok_or_else(|| MidiError::PortNotFound {
+                direction: "output",
+                port: port_name.to_string(),
+            })?;
+
+        let conn = out
+            .connect(&port, "harmonium-out")
+            .map_err(|e| MidiError::Connect {
+                port: port_name.to_string(),
+                kind: e.kind(),
+            })?;
+
+        let mut out = Self {
+            conn,
+            port_name: port_name.to_string(),
+            performance: Performance::new(if mpe { 1 } else { channel }),
+            mpe: mpe.then(Mpe::new),
+        };
+
+        if mpe {
+            for msg in Mpe::configure() {
+                out.send(&msg)?;
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+
+    pub fn is_mpe(&self) -> bool {
+        self.mpe.is_some()
+    }
+
+    /// Send note-ons / note-offs so the receiver plays exactly `active`.
+    pub fn sync_notes(&mut self, active: &[NoteName]) -> Result<(), MidiError> {
+        let msgs = match &mut self.mpe {
+            Some(mpe) => mpe.sync_notes(active, Instant::now()),
+            None => self.performance.sync_notes(active),
+        };
+        for msg in msgs {
+            self.send(&msg)?;
+        }
+        Ok(())
+    }
+
+    /// Start `note`, on its own channel in MPE mode.
+    pub fn note_on(&mut self, note: &str, now: Instant) -> Result<(), MidiError> {
+        let Some(midi) = midi_number(note) else {
+            return Ok(());
+        };
+        let msgs = match &mut self.mpe {
+            Some(mpe) => mpe.note_on(midi, now),
+            None => self.performance.note_on(midi).into_iter().collect(),
+        };
+        for msg in msgs {
+            self.send(&msg)?;
+        }
+        Ok(())
+    }
+
+    pub fn note_off(&mut self, note: &str) -> Result<(), MidiError> {
+        let Some(midi) = midi_number(note) else {
+            return Ok(());
+        };
+        let msg = match &mut self.mpe {
+            Some(mpe) => mpe.note_off(midi),
+            None => self.performance.note_off(midi),
+        };
+        match msg {
+            Some(msg) => self.send(&msg),
+            None => Ok(()),
+        }
+    }
+
+    /// Stream the bellows amplitude (0..1) as CC11, only when it changes.
+    /// In MPE mode each note also gets its own pressure from it.
+    pub fn send_expression(&mut self, a: f32) -> Result<(), MidiError> {
+        let mut msgs: Vec<[u8; 3]> = self.performance.expression(a).into_iter().collect();
+        if let Some(mpe) = &mut self.mpe {
+            msgs.extend(mpe.pressure(a, Instant::now()));
+        }
+        for msg in msgs {
+            self.send(&msg)?;
+        }
+        Ok(())
+    }
+
+    /// Send the meend as pitch bend (-1..1), only when it changes. Set the
+    /// synth's bend range to match the app's.
+    pub fn send_bend(&mut self, amount: f32) -> Result<(), MidiError> {
+        match self.performance.bend(amount) {
+            Some(msg) => self.send(&msg),
+            None => Ok(()),
+        }
+    }
+
+    /// Pass the sustain pedal on, so the receiver holds released notes too.
+    pub fn send_sustain(&mut self, down: bool) -> Result<(), MidiError> {
+        let value = if down { 127 } else { 0 };
+        let msg = self.performance.control(CC_SUSTAIN, value);
+        self.send(&msg)
+    }
+
+    /// Silence the receiver (panic). Held keys stay silent until pressed again,
+    /// like the audio engine's stop.
+    pub fn all_notes_off(&mut self) -> Result<(), MidiError> {
+        let msg = self.performance.control(CC_ALL_NOTES_OFF, 0);
+        self.send(&msg)?;
+
+        let member_msgs = self.mpe.as_ref().map(Mpe::all_notes_off).unwrap_or_default();
+        for msg in member_msgs {
+            self.send(&msg)?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, msg: &[u8]) -> Result<(), MidiError> {
+        self.conn.send(msg).map_err(|source| MidiError::Send {
+            port: self.port_name.clone(),
+            source,
+        })
+    }
+}
+
+/// Follows the performance: notes, the bellows and meend, the sustain pedal
+/// and stop all. Runs whether or not audio is enabled, so the app can be a
+/// pure controller.
+impl Subscriber for MidiOut {
+    type Error = MidiError;
+
+    fn on_event(&mut self, event: &Event, t: Instant) -> Result<(), MidiError> {
+        match event {
+            Event::Note(NoteEvent::On(note)) => self.note_on(note, t),
+            Event::Note(NoteEvent::Off(note)) => self.note_off(note),
+            Event::Bellows(b) => self.send_expression(b.a).and_then(|_| self.send_bend(b.bend)),
+            Event::Config(ConfigEvent::Sustain(down)) => self.send_sustain(*down),
+            Event::Config(ConfigEvent::StopAll) => self.all_notes_off(),
+            Event::Sensor(_) | Event::Load(_) => Ok(()),
+        }
+    }
+}
+
+/// Don't leave notes hanging on the synth when the port is closed or switched.
+impl Drop for MidiOut {
+    fn drop(&mut self) {
+        let _ = self.sync_notes(&[]);
+    }
+}
@@ -0,0 +1,148 @@
+use std::convert::Infallible;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{MidiError, Performance, midi_number};
+use crate::events::{Event, NoteEvent, Subscriber};
+
+/// Where MIDI recordings go unless told otherwise.
+pub const DEFAULT_MIDI_RECORDING_PATH: &str = "performance.mid";
+
+/// Ticks per quarter note in the file.
+const TICKS_PER_QUARTER: u16 = 480;
+
+/// The file's tempo: 120 bpm (500 000 µs per quarter), so 960 ticks = 1 s.
+const MICROS_PER_QUARTER: u32 = 500_000;
+const TICKS_PER_SEC: f64 = TICKS_PER_QUARTER as f64 * 1_000_000.0 / MICROS_PER_QUARTER as f64;
+
+/// Records the performance (notes + bellows as CC11) into a standard MIDI
+/// file (format 0, one track), for editing in a DAW.
+pub struct MidiRecorder {
+    path: PathBuf,
+    start: Instant,
+    performance: Performance,
+
+    /// (ticks since start, message), in order.
+    events: Vec<(u32, [u8; 3])>,
+}
+
+impl MidiRecorder {
+    /// Start recording now; nothing is written until `finish`.
+    pub fn start(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            start: Instant::now(),
+            performance: Performance::new(1),
+            events: Vec::new(),
+        }
+    }
+
+    fn tick_at(&self, now: Instant) -> u32 {
+        let secs = now.saturating_duration_since(self.start).as_secs_f64();
+        (secs * TICKS_PER_SEC).round() as u32
+    }
+
+    pub fn elapsed_sec(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write what has been recorded so far, so a crash doesn't lose all of
+    /// it. Notes still held stay open in the file until `finish`.
+    pub fn checkpoint(&self) -> Result<(), MidiError> {
+        self.write()
+    }
+
+    /// Close any notes still held and write the file.
+    pub fn finish(mut self) -> Result<(), MidiError> {
+        let end = self.tick_at(Instant::now());
+        for msg in self.performance.sync_notes(&[]) {
+            self.events.push((end, msg));
+        }
+        self.write()
+    }
+
+    fn write(&self) -> Result<(), MidiError> {
+        let mut track = Vec::new();
+        // Tempo meta event at tick 0.
+        write_vlq(&mut track, 0);
+        track.extend_from_slice(&[0xFF, 0x51, 0x03]);
+        track.extend_from_slice(&MICROS_PER_QUARTER.to_be_bytes()[1..]);
+
+        let mut last_tick = 0;
+        for (tick, msg) in &self.events {
+            write_vlq(&mut track, tick - last_tick);
+            track.extend_from_slice(msg);
+            last_tick = *tick;
+        }
+
+        // End of track.
+        write_vlq(&mut track, 0);
+        track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+        let mut file = Vec::with_capacity(track.len() + 22);
+        file.extend_from_slice(b"MThd");
+        file.extend_from_slice(&6u32.to_be_bytes());
+        file.extend_from_slice(&0u16.to_be_bytes()); // format 0
+        file.extend_from_slice(&1u16.to_be_bytes()); // one track
+        file.extend_from_slice(&TICKS_PER_QUARTER.to_be_bytes());
+        file.extend_from_slice(b"MTrk");
+        file.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        file.extend_from_slice(&track);
+
+        fs::write(&self.path, file).map_err(|source| MidiError::WriteFile {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+/// Records the notes, and the bellows level (CC11) and pitch bend when they
+/// change. The bellows are published ahead of a frame's notes, so a note
+/// starts the way it was played.
+impl Subscriber for MidiRecorder {
+    type Error = Infallible;
+
+    fn on_event(&mut self, event: &Event, t: Instant) -> Result<(), Infallible> {
+        let p = &mut self.performance;
+        let msgs: Vec<[u8; 3]> = match event {
+            Event::Note(NoteEvent::On(note)) => {
+                midi_number(note).and_then(|m| p.note_on(m)).into_iter().collect()
+            }
+            Event::Note(NoteEvent::Off(note)) => {
+                midi_number(note).and_then(|m| p.note_off(m)).into_iter().collect()
+            }
+            Event::Bellows(b) => p.expression(b.a).into_iter().chain(p.bend(b.bend)).collect(),
+            _ => Vec::new(),
+        };
+
+        let tick = self.tick_at(t);
+        for msg in msgs {
+            self.events.push((tick, msg));
+        }
+        Ok(())
+    }
+}
+
+/// MIDI variable-length quantity: 7 bits per byte, high bit set on all but the last.
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = [0u8; 4];
+    let mut n = 0;
+    loop {
+        bytes[n] = (value & 0x7F) as u8;
+        n += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+
+    for (i, byte) in bytes[..n].iter().enumerate().rev() {
+        let more = if i > 0 { 0x80 } else { 0 };
+        out.push(byte | more);
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+
+/// A note name like "c#3" or "f4".
+pub type NoteName = String;
+
+/// Pitch names as used by the sample files (sharps, lowercase).
+pub const PITCH_NAMES: [&str; 12] = [
+    "c", "c#", "d", "d#", "e", "f", "f#", "g", "g#", "a", "a#", "b",
+];
+
+/// Swaras by semitones above Sa. Lowercase is komal (flat), and `Ma` (upper
+/// case) is tivra: `re` = komal Re, `ma` = shuddha Ma.
+pub const SARGAM_NAMES: [&str; 12] = [
+    "Sa", "re", "Re", "ga", "Ga", "ma", "Ma", "Pa", "dha", "Dha", "ni", "Ni",
+];
+
+/// Concert pitch: a4 in equal temperament, which every note is tuned from.
+pub const A4_HZ: f32 = 440.0;
+
+/// A pitch as a MIDI note number (60 = c4, scientific pitch notation).
+///
+/// Keymaps and samples use names; anything that transposes goes through this
+/// type so it does arithmetic instead of editing strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Note(u8);
+
+impl Note {
+    pub fn from_midi(midi: i32) -> Option<Self> {
+        u8::try_from(midi).ok().filter(|m| *m <= 127).map(Note)
+    }
+
+    /// Parse a pitch name ("c#3", "Db3", "e-1") or a MIDI number ("60").
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Ok(midi) = s.parse::<i32>() {
+            return Self::from_midi(midi);
+        }
+
+        let split = s.find(|c: char| c.is_ascii_digit() || c == '-')?;
+        let (pitch, octave) = s.split_at(split);
+        let octave: i32 = octave.parse().ok()?;
+
+        let mut chars = pitch.chars();
+        let letter = chars.next()?.to_ascii_lowercase();
+        let natural = PITCH_NAMES.iter().position(|p| *p == letter.to_string())? as i32;
+
+        let accidental = match chars.as_str() {
+            "" => 0,
+            "#" => 1,
+            "b" => -1,
+            _ => return None,
+        };
+
+        Self::from_midi((octave + 1) * 12 + natural + accidental)
+    }
+
+    /// 0 = C ... 11 = B.
+    pub fn pitch_class(self) -> u8 {
+        self.0 % 12
+    }
+
+    /// A natural (white key on a piano), as opposed to a sharp.
+    pub fn is_white(self) -> bool {
+        matches!(self.pitch_class(), 0 | 2 | 4 | 5 | 7 | 9 | 11)
+    }
+
+    /// The swara this note is when Sa is pitch class `sa` (0 = C).
+    pub fn sargam(self, sa: u8) -> &'static str {
+        SARGAM_NAMES[((self.pitch_class() + 12 - sa % 12) % 12) as usize]
+    }
+
+    pub fn octave(self) -> i32 {
+        self.0 as i32 / 12 - 1
+    }
+
+    pub fn midi(self) -> u8 {
+        self.0
+    }
+
+    pub fn transpose(self, semitones: i32) -> Option<Self> {
+        Self::from_midi(self.0 as i32 + semitones)
+    }
+
+    /// Canonical name, matching the sample files ("c#3").
+    pub fn name(self) -> NoteName {
+        self.to_string()
+    }
+
+    /// Equal-tempered frequency, `cents` sharp (negative = flat).
+    pub fn frequency_hz(self, cents: f32) -> f32 {
+        A4_HZ * 2f32.powf((self.0 as f32 - 69.0 + cents / 100.0) / 12.0)
+    }
+
+    /// The note closest to `hz`, and how many cents `hz` is above it.
+    pub fn nearest(hz: f32) -> Option<(Self, f32)> {
+        if hz <= 0.0 {
+            return None;
+        }
+        let semitones = 69.0 + 12.0 * (hz / A4_HZ).log2();
+        let note = Self::from_midi(semitones.round() as i32)?;
+        Some((note, (semitones - note.0 as f32) * 100.0))
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            PITCH_NAMES[self.pitch_class() as usize],
+            self.octave()
+        )
+    }
+}
+
+/// Move a note name by `semitones`. None if it doesn't parse or leaves the MIDI range.
+pub fn transpose_name(note: &str, semitones: i32) -> Option<NoteName> {
+    Note::parse(note)?.transpose(semitones).map(Note::name)
+}
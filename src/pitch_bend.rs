@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use crate::voice::{LoopReader, Sample};
+
+/// Meend (glide between pitches): where the bend comes from and how far it goes.
+///
+/// Two inputs: MIDI pitch bend (already smooth, used as-is) and a glide key on
+/// the typing keyboard, which slides up to the full range while held and back
+/// when released, at a set speed.
+#[derive(Debug, Clone)]
+pub struct Meend {
+    /// How far a full bend goes, in semitones (up and down).
+    pub range_semitones: f32,
+
+    /// Time for the glide key to slide across the whole range.
+    pub glide_ms: f32,
+
+    /// Last MIDI pitch bend, -1..1.
+    pub midi_bend: f32,
+
+    /// Glide key currently held.
+    pub glide_held: bool,
+
+    /// Where the glide key's slide is, 0..1.
+    glide: f32,
+}
+
+impl Default for Meend {
+    fn default() -> Self {
+        Self {
+            range_semitones: 2.0,
+            glide_ms: 400.0,
+            midi_bend: 0.0,
+            glide_held: false,
+            glide: 0.0,
+        }
+    }
+}
+
+impl Meend {
+    /// Advance the glide by `dt` seconds. Returns the bend as a fraction of
+    /// the range (-1..1).
+    pub fn update(&mut self, dt: f32) -> f32 {
+        let target = if self.glide_held { 1.0 } else { 0.0 };
+        let step = dt * 1000.0 / self.glide_ms.max(1.0);
+        self.glide += (target - self.glide).clamp(-step, step);
+
+        (self.midi_bend + self.glide).clamp(-1.0, 1.0)
+    }
+
+    /// The bend in semitones for a fraction from `update`.
+    pub fn semitones(&self, amount: f32) -> f32 {
+        amount * self.range_semitones
+    }
+}
+
+/// Pitch bend shared between the UI thread and every playing voice, stored
+/// as the playback speed ratio (f32 bits, so it can be atomic).
+#[derive(Debug, Clone)]
+pub struct BendHandle(Arc<AtomicU32>);
+
+impl Default for BendHandle {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU32::new(1.0f32.to_bits())))
+    }
+}
+
+impl BendHandle {
+    pub fn set_semitones(&self, semitones: f32) {
+        let ratio = 2f32.powf(semitones / 12.0);
+        self.0.store(ratio.to_bits(), Ordering::Relaxed);
+    }
+
+    fn ratio(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// One voice's portamento: how far (in semitones) to move it from the note
+/// it started on, and how fast. Set from the UI thread; the voice's
+/// resampler slides there frame by frame, so the note glides instead of
+/// restarting.
+#[derive(Debug, Clone, Default)]
+pub struct GlideHandle(Arc<GlideTarget>);
+
+#[derive(Debug, Default)]
+struct GlideTarget {
+    /// f32 bits, like `BendHandle`.
+    semitones: AtomicU32,
+
+    /// Semitones per second; infinite to jump.
+    speed: AtomicU32,
+}
+
+impl GlideHandle {
+    /// Slide to `semitones` over about `time_ms` (0 jumps there).
+    pub fn glide_to(&self, semitones: f32, time_ms: f32) {
+        let from = self.target();
+        let speed = if time_ms > 0.0 {
+            (semitones - from).abs() * 1000.0 / time_ms
+        } else {
+            f32::INFINITY
+        };
+        self.0.speed.store(speed.to_bits(), Ordering::Relaxed);
+        self.0.semitones.store(semitones.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Where the voice is sliding to, in semitones.
+    pub fn target(&self) -> f32 {
+        f32::from_bits(self.0.semitones.load(Ordering::Relaxed))
+    }
+
+    fn speed(&self) -> f32 {
+        f32::from_bits(self.0.speed.load(Ordering::Relaxed))
+    }
+}
+
+/// How a reed speaks: under little pressure it sounds late and flat, then
+/// settles into pitch. A note starts `depth_cents` flat with the bellows at
+/// rest (none at full air) and reaches its pitch in about `time_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttackScoop {
+    /// 0 = off.
+    pub depth_cents: f32,
+    pub time_ms: f32,
+}
+
+impl Default for AttackScoop {
+    fn default() -> Self {
+        Self {
+            depth_cents: 0.0,
+            time_ms: 80.0,
+        }
+    }
+}
+
+impl AttackScoop {
+    /// How far a note started at bellows level `a` (0..1) begins below its
+    /// pitch, in semitones (0 or negative).
+    pub fn semitones(&self, a: f32) -> f32 {
+        -self.depth_cents.max(0.0) / 100.0 * (1.0 - a.clamp(0.0, 1.0))
+    }
+}
+
+/// Loops a voice's sample at a speed that can change while it plays (read
+/// through [`LoopReader`]), so held notes bend smoothly: the voice's tuning,
+/// times the shared meend, its own portamento and attack scoop.
+pub struct BendSource {
+    sample: Arc<Sample>,
+    reader: LoopReader,
+    bend: BendHandle,
+    channels: usize,
+
+    /// Fixed speed: the voice's cents offset, a borrowed sample's shift.
+    speed: f32,
+
+    /// Portamento: where the slide is, in semitones.
+    glide: GlideHandle,
+    glide_semitones: f32,
+
+    /// Attack scoop: semitones the voice still is off its pitch, and how
+    /// much of that is left after each output frame.
+    scoop_semitones: f32,
+    scoop_decay: f32,
+
+    /// Portamento and scoop together, as a speed ratio.
+    ratio: f32,
+
+    /// The frame being played out, and the channel of the next sample in it.
+    frame: Vec<f32>,
+    out_ch: usize,
+}
+
+impl BendSource {
+    pub fn new(sample: Arc<Sample>, bend: BendHandle) -> Self {
+        let channels = sample.channels.max(1);
+        Self {
+            sample,
+            reader: LoopReader::default(),
+            bend,
+            channels,
+            speed: 1.0,
+            glide: GlideHandle::default(),
+            glide_semitones: 0.0,
+            scoop_semitones: 0.0,
+            scoop_decay: 0.0,
+            ratio: 1.0,
+            frame: vec![0.0; channels],
+            out_ch: 0,
+        }
+    }
+
+    /// Play at `speed` times the sample's rate, before any bend.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Let `glide` move this voice's pitch (portamento).
+    pub fn with_glide(mut self, glide: GlideHandle) -> Self {
+        self.glide = glide;
+        self
+    }
+
+    /// Start `semitones` off pitch (see [`AttackScoop`]) and settle in
+    /// about `time_ms`.
+    pub fn with_scoop(mut self, semitones: f32, time_ms: f32) -> Self {
+        // Three time constants get it within 5% of the pitch.
+        let frames = time_ms.max(1.0) / 1000.0 * self.sample.rate.max(1) as f32 / 3.0;
+        self.scoop_semitones = semitones;
+        self.scoop_decay = (-1.0 / frames).exp();
+        self.ratio = 2f32.powf(semitones / 12.0);
+        self
+    }
+
+    /// Take the portamento and the scoop one output frame toward their
+    /// targets.
+    fn step_pitch(&mut self) {
+        let target = self.glide.target();
+        let gliding = self.glide_semitones != target;
+        if gliding {
+            let step = self.glide.speed() / self.sample.rate.max(1) as f32;
+            self.glide_semitones += (target - self.glide_semitones).clamp(-step, step);
+        }
+        let scooping = self.scoop_semitones != 0.0;
+        if scooping {
+            self.scoop_semitones *= self.scoop_decay;
+            if self.scoop_semitones.abs() < 0.001 {
+                self.scoop_semitones = 0.0;
+            }
+        }
+        if gliding || scooping {
+            self.ratio = 2f32.powf((self.glide_semitones + self.scoop_semitones) / 12.0);
+        }
+    }
+}
+
+impl Iterator for BendSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.out_ch == 0 {
+            // Step through the sample at the bent speed.
+            self.step_pitch();
+            let step = self.speed * self.bend.ratio() * self.ratio;
+            self.reader.read(&self.sample, f64::from(step), &mut self.frame);
+        }
+
+        let sample = self.frame[self.out_ch];
+        self.out_ch = (self.out_ch + 1) % self.channels;
+        Some(sample)
+    }
+}
+
+impl Source for BendSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
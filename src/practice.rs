@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// A song or practice recording to play along with, and how it makes room
+/// for the instrument. Saved with the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PracticeConfig {
+    /// The file last played.
+    pub path: PathBuf,
+
+    /// Start again from the top when it ends.
+    pub looped: bool,
+
+    /// Level (0..1) under the master volume; the bellows don't apply.
+    pub volume: f32,
+
+    /// Duck the track while the harmonium is loud.
+    pub duck: bool,
+
+    /// Instrument level (RMS) where ducking starts; it is full at twice this.
+    pub duck_threshold: f32,
+
+    /// How much of the track's volume full ducking takes away (0..1).
+    pub duck_depth: f32,
+
+    /// How quickly the track dips when you play, and comes back when you
+    /// stop (milliseconds).
+    pub duck_attack_ms: f32,
+    pub duck_release_ms: f32,
+}
+
+impl Default for PracticeConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            looped: false,
+            volume: 0.7,
+            duck: true,
+            duck_threshold: 0.05,
+            duck_depth: 0.6,
+            duck_attack_ms: 60.0,
+            duck_release_ms: 500.0,
+        }
+    }
+}
+
+/// Sidechain-style ducking: follows the instrument's level and turns it into
+/// a gain for the practice track, dipping fast and recovering slowly so the
+/// track doesn't pump with every bellows stroke.
+#[derive(Debug, Clone)]
+pub struct Ducker {
+    gain: f32,
+}
+
+impl Default for Ducker {
+    fn default() -> Self {
+        Self { gain: 1.0 }
+    }
+}
+
+impl Ducker {
+    /// Advance by `dt_sec` with the instrument at `level` (RMS). Returns the
+    /// track's gain, 1 when not ducked.
+    pub fn update(&mut self, level: f32, dt_sec: f32, config: &PracticeConfig) -> f32 {
+        let target = if config.duck && config.duck_threshold > 0.0 {
+            let amount = (level / config.duck_threshold - 1.0).clamp(0.0, 1.0);
+            1.0 - amount * config.duck_depth.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let ms = if target < self.gain { config.duck_attack_ms } else { config.duck_release_ms };
+        self.gain = if ms <= 0.0 {
+            target
+        } else {
+            self.gain + (target - self.gain) * (1.0 - (-dt_sec * 1000.0 / ms).exp())
+        };
+        self.gain
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bellows::BellowsParams;
+use crate::config;
+use crate::harmonizer::Harmonizer;
+use crate::scale::ScaleLock;
+
+/// File (next to the settings file) the presets are kept in.
+const PRESETS_FILE_NAME: &str = "presets.toml";
+
+/// How many presets have a number key (Alt+1..9).
+pub const PRESET_HOTKEYS: usize = 9;
+
+/// Where the presets live: beside `config.toml` in the platform config dir.
+pub fn presets_path() -> PathBuf {
+    config::config_path().with_file_name(PRESETS_FILE_NAME)
+}
+
+/// A named instrument setup for one song or style: how the bellows responds,
+/// which stop (sample bank) plays, which keymap, and the tuning.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    pub bellows: BellowsParams,
+
+    /// Sample folder of the bank to play; None keeps the current one.
+    pub sample_dir: Option<PathBuf>,
+
+    /// Keymap file to load; None keeps the current one.
+    pub keymap_path: Option<String>,
+
+    /// Octave shift and Sa / raga lock.
+    pub octave_shift: i32,
+    pub scale_lock: ScaleLock,
+
+    /// Harmonizer stop setting.
+    pub harmonizer: Harmonizer,
+}
+
+/// On disk: an array of `[[preset]]` tables.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresetFile {
+    #[serde(default, rename = "preset")]
+    presets: Vec<Preset>,
+}
+
+/// The saved presets, in the order shown (and numbered) in the UI.
+#[derive(Debug)]
+pub struct PresetLibrary {
+    path: PathBuf,
+    presets: Vec<Preset>,
+}
+
+impl PresetLibrary {
+    /// Load presets from `path`. A missing file just means none saved yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let mut library = Self::empty(path);
+
+        let text = match fs::read_to_string(&library.path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(library),
+            Err(e) => return Err(format!("Failed to read presets file: {e}")),
+        };
+
+        let file: PresetFile =
+            toml::from_str(&text).map_err(|e| format!("Failed to parse presets TOML: {e}"))?;
+        library.presets = file.presets;
+        Ok(library)
+    }
+
+    /// No presets; the first `store` creates the file.
+    pub fn empty(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            presets: Vec::new(),
+        }
+    }
+
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Preset> {
+        self.presets.get(index)
+    }
+
+    /// Save `preset`, replacing one with the same name. Returns its index.
+    pub fn store(&mut self, preset: Preset) -> Result<usize, String> {
+        let index = match self.presets.iter().position(|p| p.name == preset.name) {
+            Some(i) => {
+                self.presets[i] = preset;
+                i
+            }
+            None => {
+                self.presets.push(preset);
+                self.presets.len() - 1
+            }
+        };
+
+        self.save()?;
+        Ok(index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<(), String> {
+        if index < self.presets.len() {
+            self.presets.remove(index);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create presets folder {dir:?}: {e}"))?;
+        }
+
+        let file = PresetFile {
+            presets: self.presets.clone(),
+        };
+        let text =
+            toml::to_string_pretty(&file).map_err(|e| format!("Failed to encode presets: {e}"))?;
+        fs::write(&self.path, text).map_err(|e| format!("Failed to write presets file: {e}"))
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::metronome::Metronome;
+
+/// How a recording started with the metronome begins and ends. Saved with
+/// the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PunchConfig {
+    /// Beats clicked before recording starts (0 = start at once).
+    pub count_in_beats: u32,
+
+    /// Record only between the punch points below, on sam.
+    pub punch: bool,
+
+    /// Cycles of the tala to let go by after the count-in before punching in.
+    pub punch_in_cycles: u32,
+
+    /// Cycles to record before punching out (0 = until stopped).
+    pub punch_length_cycles: u32,
+}
+
+impl Default for PunchConfig {
+    fn default() -> Self {
+        Self {
+            count_in_beats: 4,
+            punch: false,
+            punch_in_cycles: 0,
+            punch_length_cycles: 1,
+        }
+    }
+}
+
+/// Where an armed recording is on the metronome's grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunchStage {
+    /// Counting in: this many beats to go.
+    CountIn(u32),
+
+    /// Count-in over, waiting for the punch-in sam.
+    Waiting,
+
+    Recording,
+
+    /// Past the punch-out.
+    Done,
+}
+
+/// A recording armed against the metronome: where its count-in ends and
+/// where it punches in and out, in beats since the metronome started, so
+/// they follow tempo changes.
+#[derive(Debug, Clone, Copy)]
+pub struct Punch {
+    count_in_beats: u32,
+    count_in_end: f64,
+    punch_in: f64,
+    punch_out: Option<f64>,
+}
+
+impl Punch {
+    /// Arm on a running `metronome`: the count-in starts on the next beat
+    /// (at once on a metronome that has just started).
+    pub fn arm(metronome: &Metronome, config: &PunchConfig, now: Instant) -> Self {
+        let cycle = metronome.tala().beat_count() as f64;
+        let from = metronome.beats_at(now).unwrap_or(0.0).ceil();
+        let count_in_end = from + f64::from(config.count_in_beats);
+
+        let (punch_in, punch_out) = if config.punch {
+            let sam = (count_in_end / cycle).ceil() * cycle;
+            let punch_in = sam + f64::from(config.punch_in_cycles) * cycle;
+            let length = f64::from(config.punch_length_cycles) * cycle;
+            (punch_in, (length > 0.0).then_some(punch_in + length))
+        } else {
+            (count_in_end, None)
+        };
+        Self {
+            count_in_beats: config.count_in_beats,
+            count_in_end,
+            punch_in,
+            punch_out,
+        }
+    }
+
+    /// The stage at `beats` since the metronome started.
+    pub fn stage(&self, beats: f64) -> PunchStage {
+        if self.punch_out.is_some_and(|out| beats >= out) {
+            PunchStage::Done
+        } else if beats >= self.punch_in {
+            PunchStage::Recording
+        } else if beats < self.count_in_end {
+            let left = (self.count_in_end - beats).ceil() as u32;
+            PunchStage::CountIn(left.min(self.count_in_beats))
+        } else {
+            PunchStage::Waiting
+        }
+    }
+}
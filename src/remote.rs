@@ -0,0 +1,295 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::{info, warn};
+
+use crate::note::NoteName;
+
+/// Served to plain HTTP requests: the remote control page for a phone or
+/// tablet, which then talks to the same port over a WebSocket.
+const REMOTE_PAGE: &str = include_str!("remote_page.html");
+
+/// Clients get the state at most this often.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Whether the remote control server runs and where. Saved with the
+/// settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub enabled: bool,
+
+    /// TCP port for both the page and the WebSocket.
+    pub port: u16,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8770,
+        }
+    }
+}
+
+/// Why the server couldn't start, or a page's connection broke.
+#[derive(Debug, Error)]
+pub enum RemoteError {
+    /// Usually another program (or another copy of the app) has the port.
+    #[error("Failed to listen on TCP port {port}: {source}")]
+    Listen { port: u16, source: io::Error },
+
+    #[error("WebSocket handshake with {peer} failed: {source}")]
+    Handshake {
+        peer: SocketAddr,
+        source: tungstenite::Error,
+    },
+
+    #[error("WebSocket error with {peer}: {source}")]
+    Socket {
+        peer: SocketAddr,
+        source: tungstenite::Error,
+    },
+
+    #[error("Failed to serve remote page: {0}")]
+    ServePage(#[source] io::Error),
+}
+
+impl RemoteError {
+    /// Another program holds the port: pick another one or close it.
+    pub fn is_port_busy(&self) -> bool {
+        match self {
+            RemoteError::Listen { source, .. } => source.kind() == io::ErrorKind::AddrInUse,
+            _ => false,
+        }
+    }
+}
+
+/// What the remote page shows, sent as one JSON object per update.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RemoteState {
+    pub theta_deg: f32,
+    pub speed: f32,
+    pub a_target: f32,
+    pub a: f32,
+
+    /// Sounding notes and their level in the output (RMS, 0..1).
+    pub notes: Vec<NoteLevel>,
+
+    /// Saved preset names, and the one in use.
+    pub presets: Vec<String>,
+    pub preset: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NoteLevel {
+    pub note: NoteName,
+    pub level: f32,
+}
+
+/// A command sent by a remote page, e.g. `{"command": "stop_all"}` or
+/// `{"command": "preset", "index": 2}`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    StopAll,
+    Preset { index: usize },
+}
+
+/// WebSocket server on its own thread: streams the app state to every
+/// connected page and passes their commands back. Stops when dropped.
+pub struct RemoteServer {
+    port: u16,
+    state_tx: watch::Sender<String>,
+    commands: mpsc::Receiver<RemoteCommand>,
+    last_publish: Option<Instant>,
+}
+
+impl RemoteServer {
+    /// Listen on `port` (all interfaces). Fails if the port is taken.
+    pub fn start(port: u16) -> Result<Self, RemoteError> {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", port))
+            .and_then(|l| l.set_nonblocking(true).map(|_| l))
+            .map_err(|source| RemoteError::Listen { port, source })?;
+
+        let (state_tx, state_rx) = watch::channel(String::new());
+        let (command_tx, commands) = mpsc::channel();
+        std::thread::spawn(move || {
+            let rt = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    warn!("remote control: failed to start Tokio runtime: {e}");
+                    return;
+                }
+            };
+            rt.block_on(async move {
+                match TcpListener::from_std(listener) {
+                    Ok(listener) => accept_loop(listener, state_rx, command_tx).await,
+                    Err(e) => warn!("remote control: {e}"),
+                }
+            });
+        });
+
+        info!("remote control on port {port}");
+        Ok(Self {
+            port,
+            state_tx,
+            commands,
+            last_publish: None,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Pages connected right now.
+    pub fn client_count(&self) -> usize {
+        // The accept loop holds one receiver too.
+        self.state_tx.receiver_count().saturating_sub(1)
+    }
+
+    /// Send `state` to the pages, at most every `PUBLISH_INTERVAL` and only
+    /// if it changed.
+    pub fn publish(&mut self, state: &RemoteState, now: Instant) {
+        if self.last_publish.is_some_and(|t| now - t < PUBLISH_INTERVAL) {
+            return;
+        }
+        self.last_publish = Some(now);
+
+        let Ok(json) = serde_json::to_string(state) else {
+            return;
+        };
+        self.state_tx.send_if_modified(|current| {
+            let changed = *current != json;
+            if changed {
+                *current = json;
+            }
+            changed
+        });
+    }
+
+    /// Commands received since the last call, oldest first.
+    pub fn commands(&self) -> Vec<RemoteCommand> {
+        self.commands.try_iter().collect()
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    mut state: watch::Receiver<String>,
+    commands: mpsc::Sender<RemoteCommand>,
+) {
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let Ok((stream, peer)) = accepted else {
+                    continue;
+                };
+                let (state, commands) = (state.clone(), commands.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = handle_client(stream, peer, state, commands).await {
+                        warn!("remote control: {e}");
+                    }
+                });
+            }
+            // The server was dropped (turned off): stop serving.
+            gone = state.changed() => {
+                if gone.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn handle_client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    mut state: watch::Receiver<String>,
+    commands: mpsc::Sender<RemoteCommand>,
+) -> Result<(), RemoteError> {
+    if !is_websocket_upgrade(&stream).await {
+        return serve_page(stream).await;
+    }
+
+    let ws = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|source| RemoteError::Handshake { peer, source })?;
+    let (mut write, mut read) = ws.split();
+    let socket_error = |source| RemoteError::Socket { peer, source };
+
+    // The current state at once, then every change.
+    let current = state.borrow_and_update().clone();
+    if !current.is_empty() {
+        write.send(Message::Text(current)).await.map_err(socket_error)?;
+    }
+
+    loop {
+        tokio::select! {
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let json = state.borrow_and_update().clone();
+                write.send(Message::Text(json)).await.map_err(socket_error)?;
+            }
+            msg = read.next() => {
+                let Some(msg) = msg else {
+                    return Ok(());
+                };
+                let msg = msg.map_err(socket_error)?;
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+
+                // Ignore commands we don't understand rather than dropping the page.
+                match serde_json::from_str::<RemoteCommand>(&text) {
+                    Ok(command) => {
+                        if commands.send(command).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => warn!("remote control: ignored '{text}' from {peer}: {e}"),
+                }
+            }
+        }
+    }
+}
+
+/// Look at (without consuming) the request head to see if it's a WebSocket upgrade.
+async fn is_websocket_upgrade(stream: &TcpStream) -> bool {
+    let mut buf = [0u8; 2048];
+    let Ok(n) = stream.peek(&mut buf).await else {
+        return false;
+    };
+
+    String::from_utf8_lossy(&buf[..n])
+        .to_ascii_lowercase()
+        .contains("upgrade: websocket")
+}
+
+/// Answer a plain HTTP request with the remote page.
+async fn serve_page(mut stream: TcpStream) -> Result<(), RemoteError> {
+    let mut request = [0u8; 2048];
+    let _ = stream.read(&mut request).await;
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{REMOTE_PAGE}",
+        REMOTE_PAGE.len()
+    );
+
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .map_err(RemoteError::ServePage)
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::note::{Note, NoteName};
+use crate::tuner;
+use crate::voice::Sample;
+
+/// Skipped at the start of a sample: the pitch settles after the attack.
+const SKIP_SEC: f32 = 0.3;
+
+/// Length analysed after that: a few dozen periods of the lowest notes.
+const WINDOW_SEC: f32 = 0.1;
+
+/// What a sample's pitch says about its file name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PitchCheck {
+    /// It sounds like the note it is named after (or an octave of it, which
+    /// YIN can mistake on reed tones).
+    Matches,
+
+    /// It sounds like another note.
+    Mislabeled { named: Note, detected: Note },
+
+    /// Not named after a note ("take 3.wav"); it plays `detected`, `cents`
+    /// sharp.
+    Unlabeled { detected: Note, cents: f32 },
+
+    /// No clear pitch (noise, silence, too short).
+    Unclear,
+}
+
+/// The fundamental of a sample read with [`crate::audio::load_sample`],
+/// after its attack. None if there's no clear pitch.
+pub fn detect(sample: &Sample) -> Option<f32> {
+    let skip = (SKIP_SEC * sample.rate as f32) as usize * sample.channels;
+    let len = (WINDOW_SEC * sample.rate as f32) as usize * sample.channels;
+    let window = Sample::new(
+        sample.samples.iter().skip(skip).take(len).copied().collect(),
+        sample.channels,
+        sample.rate,
+    );
+    tuner::detect_fundamental(&window.to_mono().samples, sample.rate)
+}
+
+/// Detect the pitch of the sample at `path` and hold it against its name.
+pub fn analyse(path: &Path, sample: &Sample) -> PitchCheck {
+    let Some((detected, cents)) = detect(sample).and_then(Note::nearest) else {
+        return PitchCheck::Unclear;
+    };
+    match path.file_stem().and_then(|s| s.to_str()).and_then(Note::parse) {
+        None => PitchCheck::Unlabeled { detected, cents },
+        Some(named) if named.pitch_class() == detected.pitch_class() => PitchCheck::Matches,
+        Some(named) => PitchCheck::Mislabeled { named, detected },
+    }
+}
+
+/// The note each unlabeled sample plays, as found by [`analyse`]: note →
+/// file. Of two files on one note, the one closer in tune wins.
+pub fn note_map(
+    found: impl IntoIterator<Item = (PathBuf, Note, f32)>,
+) -> HashMap<NoteName, PathBuf> {
+    let mut best: HashMap<Note, (PathBuf, f32)> = HashMap::new();
+    for (path, note, cents) in found {
+        match best.get(&note) {
+            Some((_, kept)) if kept.abs() <= cents.abs() => {}
+            _ => {
+                best.insert(note, (path, cents));
+            }
+        }
+    }
+    best.into_iter().map(|(note, (path, _))| (note.name(), path)).collect()
+}
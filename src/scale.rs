@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::note::{self, Note, NoteName};
+
+/// The ten thaats (parent scales of Hindustani ragas), plus a custom note set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    Bilawal,
+    Khamaj,
+    Kafi,
+    Asavari,
+    Bhairavi,
+    Bhairav,
+    Kalyan,
+    Marwa,
+    Purvi,
+    Todi,
+    Custom,
+}
+
+impl Scale {
+    pub const ALL: [Scale; 11] = [
+        Scale::Bilawal,
+        Scale::Khamaj,
+        Scale::Kafi,
+        Scale::Asavari,
+        Scale::Bhairavi,
+        Scale::Bhairav,
+        Scale::Kalyan,
+        Scale::Marwa,
+        Scale::Purvi,
+        Scale::Todi,
+        Scale::Custom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Scale::Bilawal => "Bilawal (major)",
+            Scale::Khamaj => "Khamaj",
+            Scale::Kafi => "Kafi",
+            Scale::Asavari => "Asavari",
+            Scale::Bhairavi => "Bhairavi",
+            Scale::Bhairav => "Bhairav",
+            Scale::Kalyan => "Kalyan (Yaman)",
+            Scale::Marwa => "Marwa",
+            Scale::Purvi => "Purvi",
+            Scale::Todi => "Todi",
+            Scale::Custom => "Custom",
+        }
+    }
+
+    /// Semitones above Sa. None for the custom set.
+    fn intervals(self) -> Option<&'static [u8]> {
+        Some(match self {
+            Scale::Bilawal => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Khamaj => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::Kafi => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Asavari => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::Bhairavi => &[0, 1, 3, 5, 7, 8, 10],
+            Scale::Bhairav => &[0, 1, 4, 5, 7, 8, 11],
+            Scale::Kalyan => &[0, 2, 4, 6, 7, 9, 11],
+            Scale::Marwa => &[0, 1, 4, 6, 7, 9, 11],
+            Scale::Purvi => &[0, 1, 4, 6, 7, 8, 11],
+            Scale::Todi => &[0, 1, 3, 6, 7, 8, 11],
+            Scale::Custom => return None,
+        })
+    }
+}
+
+/// What happens to a key whose note is outside the scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockMode {
+    /// The key stays silent.
+    Filter,
+
+    /// The key plays the nearest in-scale note (the lower one on a tie).
+    Snap,
+}
+
+/// Only lets in-scale notes sound, so beginners can practice a raga without
+/// wrong notes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScaleLock {
+    pub enabled: bool,
+    pub scale: Scale,
+    pub mode: LockMode,
+
+    /// Sa as a pitch class (0 = C).
+    pub tonic: u8,
+
+    /// Semitones above Sa allowed by the custom scale.
+    pub custom: [bool; 12],
+
+    /// Retune the whole instrument to Sa: keys (and scripts) play as if
+    /// written with Sa = C, like a scale-changer harmonium. Off, Sa only
+    /// names the notes and sets the scale.
+    pub retune: bool,
+}
+
+impl Default for ScaleLock {
+    fn default() -> Self {
+        let mut custom = [false; 12];
+        for i in [0, 2, 4, 5, 7, 9, 11] {
+            custom[i] = true;
+        }
+
+        Self {
+            enabled: false,
+            scale: Scale::Bilawal,
+            mode: LockMode::Filter,
+            tonic: 0,
+            custom,
+            retune: false,
+        }
+    }
+}
+
+impl ScaleLock {
+    /// Is this pitch class (0 = C) in the selected scale?
+    pub fn contains(&self, pitch_class: u8) -> bool {
+        let degree = (pitch_class + 12 - self.tonic % 12) % 12;
+        match self.scale.intervals() {
+            Some(iv) => iv.contains(&degree),
+            None => self.custom[degree as usize],
+        }
+    }
+
+    /// How far retune moves every key: Sa's pitch class, the nearer way
+    /// (Sa = A is three below C, not nine above). 0 with retune off.
+    pub fn retune_semitones(&self) -> i32 {
+        if !self.retune {
+            return 0;
+        }
+        let sa = i32::from(self.tonic % 12);
+        if sa > 6 { sa - 12 } else { sa }
+    }
+
+    /// `note` moved to Sa by retune. Names that can't be parsed pass through
+    /// untouched; None if it leaves the MIDI range.
+    pub fn retune(&self, note: &str) -> Option<NoteName> {
+        match self.retune_semitones() {
+            0 => Some(note.to_string()),
+            _ if Note::parse(note).is_none() => Some(note.to_string()),
+            semitones => note::transpose_name(note, semitones),
+        }
+    }
+
+    /// What a key written as `note` plays: retuned, then kept to the scale.
+    pub fn resolve(&self, note: &str) -> Option<NoteName> {
+        self.apply(&self.retune(note)?)
+    }
+
+    /// The note a key should actually play, or None if it must stay silent.
+    /// Names that can't be parsed pass through untouched.
+    pub fn apply(&self, note: &str) -> Option<NoteName> {
+        if !self.enabled {
+            return Some(note.to_string());
+        }
+        let Some(parsed) = Note::parse(note) else {
+            return Some(note.to_string());
+        };
+
+        if self.contains(parsed.pitch_class()) {
+            return Some(note.to_string());
+        }
+        if self.mode == LockMode::Filter {
+            return None;
+        }
+
+        // Search outward, lower neighbour first.
+        (1..12).find_map(|d| {
+            [-d, d]
+                .into_iter()
+                .filter_map(|step| parsed.transpose(step))
+                .find(|n| self.contains(n.pitch_class()))
+                .map(Note::name)
+        })
+    }
+}
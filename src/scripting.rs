@@ -0,0 +1,178 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use rhai::{AST, CallFnOptions, Dynamic, Engine, Map, Scope};
+use tracing::info;
+
+use crate::note::{self, NoteName};
+
+/// Script loaded unless told otherwise.
+pub const DEFAULT_SCRIPT_PATH: &str = "scripts/drone.rhai";
+
+/// Work one hook call may do before it is stopped, so a runaway loop can't
+/// freeze the UI.
+const MAX_OPERATIONS: u64 = 200_000;
+
+/// The hooks a script may define; any it leaves out are skipped.
+const HOOKS: [&str; 4] = ["map_key", "on_note_on", "on_note_off", "on_bellows_update"];
+
+/// A Rhai script hooked into the instrument, for mappings and behaviours
+/// that don't need a rebuild:
+///
+/// ```text
+/// fn map_key(ch)            // a key went down: return a note, an array of
+///                           // notes, or () to leave it to the keymap
+/// fn on_note_on(note)       // a note started (from any source but the script)
+/// fn on_note_off(note)      // ... and stopped
+/// fn on_bellows_update(a)   // every frame: return a number to replace the
+///                           // bellows level (0..1), or () to keep it
+/// ```
+///
+/// Hooks can call `play(note)`, `stop(note)`, `stop_all()` to hold notes
+/// down themselves (drones, echoes), `transpose(note, semitones)`, and
+/// `print`. State kept between calls goes on `this`, a map that starts
+/// empty: `this.count += 1`.
+pub struct ScriptHooks {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Dynamic,
+
+    /// Hooks the script defines and that haven't failed.
+    hooks: Vec<&'static str>,
+
+    /// Notes held down with `play()`, shared with the registered functions.
+    held: Rc<RefCell<BTreeSet<NoteName>>>,
+
+    /// The latest error, until taken.
+    error: Option<String>,
+}
+
+impl ScriptHooks {
+    /// Compile `path` and run its top level.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let held = Rc::new(RefCell::new(BTreeSet::new()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| info!("script: {text}"));
+
+        let h = Rc::clone(&held);
+        engine.register_fn("play", move |note: &str| {
+            if let Some(name) = note::transpose_name(note, 0) {
+                h.borrow_mut().insert(name);
+            }
+        });
+        let h = Rc::clone(&held);
+        engine.register_fn("stop", move |note: &str| {
+            if let Some(name) = note::transpose_name(note, 0) {
+                h.borrow_mut().remove(&name);
+            }
+        });
+        let h = Rc::clone(&held);
+        engine.register_fn("stop_all", move || h.borrow_mut().clear());
+        engine.register_fn("transpose", |note: &str, semitones: i64| -> Dynamic {
+            match note::transpose_name(note, semitones as i32) {
+                Some(name) => name.into(),
+                None => Dynamic::UNIT,
+            }
+        });
+
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| format!("Failed to load script {}: {e}", path.display()))?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| format!("Script {} failed: {e}", path.display()))?;
+
+        let hooks = HOOKS
+            .into_iter()
+            .filter(|hook| ast.iter_functions().any(|f| f.name == *hook && f.params.len() == 1))
+            .collect();
+        info!("script {} loaded", path.display());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            scope,
+            state: Map::new().into(),
+            hooks,
+            held,
+            error: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Hooks the script defines (and that are still running).
+    pub fn hooks(&self) -> &[&'static str] {
+        &self.hooks
+    }
+
+    /// Call `hook` with `arg`, if the script has it. A hook that fails is
+    /// turned off, so one mistake doesn't repeat every frame.
+    fn call(&mut self, hook: &'static str, arg: Dynamic) -> Option<Dynamic> {
+        if !self.hooks.contains(&hook) {
+            return None;
+        }
+
+        let options = CallFnOptions::new().bind_this_ptr(&mut self.state);
+        let result: Result<Dynamic, _> =
+            self.engine.call_fn_with_options(options, &mut self.scope, &self.ast, hook, (arg,));
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.hooks.retain(|h| *h != hook);
+                self.error = Some(format!("Script {hook} failed (now off): {e}"));
+                None
+            }
+        }
+    }
+
+    /// The notes the script wants `ch` to play, or None to use the keymap.
+    pub fn map_key(&mut self, ch: char) -> Option<Vec<NoteName>> {
+        let value = self.call("map_key", ch.into())?;
+        if let Some(array) = value.clone().try_cast::<rhai::Array>() {
+            let notes = array.into_iter().filter_map(|n| n.into_string().ok()).collect();
+            return Some(notes);
+        }
+        value.into_string().ok().map(|note| vec![note])
+    }
+
+    pub fn on_note_on(&mut self, note: &str) {
+        self.call("on_note_on", note.into());
+    }
+
+    pub fn on_note_off(&mut self, note: &str) {
+        self.call("on_note_off", note.into());
+    }
+
+    /// A new bellows level from the script, if it gives one.
+    pub fn on_bellows_update(&mut self, a: f32) -> Option<f32> {
+        let value = self.call("on_bellows_update", (a as f64).into())?;
+        let level = value.as_float().ok().or_else(|| value.as_int().ok().map(|i| i as f64))?;
+        Some((level as f32).clamp(0.0, 1.0))
+    }
+
+    /// Notes held down with `play()`.
+    pub fn held_notes(&self) -> Vec<NoteName> {
+        self.held.borrow().iter().cloned().collect()
+    }
+
+    /// Let go of every note held with `play()` (stop all notes).
+    pub fn release_all(&mut self) {
+        self.held.borrow_mut().clear();
+    }
+
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+}
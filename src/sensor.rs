@@ -1,12 +1,59 @@
-use std::sync::mpsc::Sender;
-use std::time::Instant;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use futures_util::StreamExt;
+use futures_util::future::LocalBoxFuture;
+use futures_util::stream::LocalBoxStream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+#[cfg(target_os = "macos")]
+mod booklid;
+mod calibration;
+mod channel;
+mod clock;
+mod companion;
+mod error;
+mod fake;
+mod health;
+mod hid;
+#[cfg(target_os = "linux")]
+mod iio;
+mod osc;
+mod recording;
+mod serial;
+mod websocket;
+#[cfg(windows)]
+mod windows_lid;
+
+pub use calibration::{DEFAULT_CALIBRATION_PATH, load_zero_offsets, save_zero_offsets};
+pub use error::SensorError;
+pub use hid::{HidDevice, available_hid_devices};
+pub use recording::SensorRecorder;
+
+use channel::{LatestReceiver, LatestSender};
+
+/// Default rate we ask sensors to stream at.
+pub const DEFAULT_SENSOR_HZ: f32 = 60.0;
+
+/// How many messages may wait for the UI before the oldest samples are dropped
+/// (about half a second at the default rate).
+const SENSOR_QUEUE_LEN: usize = 32;
 
 #[derive(Debug, Clone)]
 pub struct SensorSample {
     pub theta_deg: f32,
     pub source: String,
+
+    /// When the angle was captured, on the local monotonic clock.
+    ///
+    /// Backends that get timestamps from the device map them onto this clock;
+    /// the others stamp the sample as soon as it comes off the device, on the
+    /// sensor thread. Either way UI-side channel delay doesn't end up in dt.
     pub t: Instant,
 }
 
@@ -14,48 +61,441 @@ pub struct SensorSample {
 pub enum SensorMsg {
     Sample(SensorSample),
     Status(String),
-    Error(String),
+    Error(Arc<SensorError>),
+}
+
+/// Samples coming from a connected backend.
+pub type SampleStream<'a> = LocalBoxStream<'a, SensorSample>;
+
+/// What a backend hands back once it is connected.
+pub struct Connected<'a> {
+    /// Human-readable status line for the Sensor panel.
+    pub status: String,
+    pub samples: SampleStream<'a>,
+}
+
+/// Anything that can produce lid-angle-like samples for the bellows.
+///
+/// Each backend (lid sensor, fake sine, ...) implements this, and the
+/// sensor thread drives whichever one is selected the same way.
+pub trait SensorSource: Send {
+    /// Short name shown in the UI.
+    fn name(&self) -> &str;
+
+    /// Connect and return the sample stream. Runs on the sensor thread's runtime.
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>>;
+}
+
+/// Settings the backends need when they are created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorConfig {
+    /// Requested sample rate.
+    pub hz: f32,
+
+    /// Recording file played back by the replay backend.
+    pub replay_path: PathBuf,
+
+    /// UDP port the OSC backend listens on.
+    pub osc_port: u16,
+
+    /// TCP port the WebSocket backend (and its phone page) listens on.
+    pub websocket_port: u16,
+
+    /// UDP port the watch/phone companion backend listens on.
+    pub companion_port: u16,
+
+    /// Serial backend: device path (e.g. /dev/tty.usbmodem1101), speed and value scale.
+    pub serial_port: String,
+    pub serial_baud: u32,
+    pub serial_scale: f32,
+
+    /// HID backend: device name and axis (as listed by `available_hid_devices`).
+    pub hid_device: String,
+    pub hid_axis: String,
+
+    /// Per-backend angle subtracted from every sample (hinge bias), set with
+    /// "zero" in the Sensor panel. Kept in their own calibration file.
+    #[serde(skip)]
+    pub zero_offsets_deg: HashMap<SensorKind, f32>,
+}
+
+impl SensorConfig {
+    pub fn zero_offset(&self, kind: SensorKind) -> f32 {
+        self.zero_offsets_deg.get(&kind).copied().unwrap_or(0.0)
+    }
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            hz: DEFAULT_SENSOR_HZ,
+            replay_path: PathBuf::from(recording::DEFAULT_RECORDING_PATH),
+            osc_port: 9000,
+            websocket_port: 8765,
+            companion_port: 9100,
+            serial_port: "/dev/tty.usbmodem1101".to_string(),
+            serial_baud: 115_200,
+            serial_scale: 1.0,
+            hid_device: String::new(),
+            hid_axis: "LeftStickY".to_string(),
+            zero_offsets_deg: HashMap::new(),
+        }
+    }
 }
 
-pub fn spawn_sensor_thread(hz: f32, tx: Sender<SensorMsg>) -> std::thread::JoinHandle<()> {
+/// Serial ports that exist right now (for the port picker).
+pub fn available_serial_ports() -> Vec<String> {
+    serialport::available_ports()
+        .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
+        .unwrap_or_default()
+}
+
+/// The registered backends, in the order shown in the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SensorKind {
+    /// The laptop's own lid angle: booklid on macOS, IIO on Linux, the
+    /// sensor API on Windows. Elsewhere there is none (see `is_available`).
+    Lid,
+    Fake,
+    Replay,
+    Osc,
+    WebSocket,
+    Serial,
+    Hid,
+    Companion,
+}
+
+impl SensorKind {
+    pub const ALL: [SensorKind; 8] = [
+        SensorKind::Lid,
+        SensorKind::Fake,
+        SensorKind::Replay,
+        SensorKind::Osc,
+        SensorKind::WebSocket,
+        SensorKind::Serial,
+        SensorKind::Hid,
+        SensorKind::Companion,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SensorKind::Lid => LID_LABEL,
+            SensorKind::Fake => "Fake sine (sensor thread)",
+            SensorKind::Replay => "Replay recording",
+            SensorKind::Osc => "OSC (phone app)",
+            SensorKind::WebSocket => "WebSocket (phone browser)",
+            SensorKind::Serial => "Serial (Arduino rig)",
+            SensorKind::Hid => "HID axis (pedal / fader / joystick)",
+            SensorKind::Companion => "Watch / phone companion (wrist rotation)",
+        }
+    }
+
+    /// Stable name used in saved settings.
+    pub fn id(self) -> &'static str {
+        match self {
+            // Kept from when the lid was macOS-only, so saved settings load.
+            SensorKind::Lid => "booklid",
+            SensorKind::Fake => "fake",
+            SensorKind::Replay => "replay",
+            SensorKind::Osc => "osc",
+            SensorKind::WebSocket => "websocket",
+            SensorKind::Serial => "serial",
+            SensorKind::Hid => "hid",
+            SensorKind::Companion => "companion",
+        }
+    }
+
+    /// Whether this backend can run on this platform.
+    pub fn is_available(self) -> bool {
+        self != SensorKind::Lid || HAS_LID
+    }
+
+    /// The backend a fresh install starts with: the lid where this platform
+    /// has one, else the fake sine (with arrow-key input, see the app).
+    pub fn default_for_platform() -> SensorKind {
+        if HAS_LID { SensorKind::Lid } else { SensorKind::Fake }
+    }
+
+    /// Build a fresh source for this backend.
+    pub fn create(self, cfg: &SensorConfig) -> Box<dyn SensorSource> {
+        match self {
+            SensorKind::Lid => lid_source(cfg.hz),
+            SensorKind::Fake => Box::new(fake::FakeSource::new(cfg.hz)),
+            SensorKind::Replay => Box::new(recording::ReplaySource::new(&cfg.replay_path)),
+            SensorKind::Osc => Box::new(osc::OscSource::new(cfg.osc_port)),
+            SensorKind::WebSocket => Box::new(websocket::WebSocketSource::new(cfg.websocket_port)),
+            SensorKind::Serial => Box::new(serial::SerialSource::new(
+                &cfg.serial_port,
+                cfg.serial_baud,
+                cfg.serial_scale,
+            )),
+            SensorKind::Hid => Box::new(hid::HidAxisSource::new(&cfg.hid_device, &cfg.hid_axis)),
+            SensorKind::Companion => Box::new(companion::CompanionSource::new(cfg.companion_port)),
+        }
+    }
+}
+
+/// This platform has a lid sensor backend.
+const HAS_LID: bool = cfg!(any(target_os = "macos", target_os = "linux", windows));
+
+#[cfg(target_os = "macos")]
+const LID_LABEL: &str = "Laptop lid (booklid)";
+#[cfg(target_os = "linux")]
+const LID_LABEL: &str = "Laptop lid (IIO hinge / accelerometer)";
+#[cfg(windows)]
+const LID_LABEL: &str = "Laptop lid (Windows hinge / inclinometer)";
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+const LID_LABEL: &str = "Laptop lid (not on this platform)";
+
+#[cfg(target_os = "macos")]
+fn lid_source(hz: f32) -> Box<dyn SensorSource> {
+    Box::new(booklid::BooklidSource::new(hz))
+}
+
+#[cfg(target_os = "linux")]
+fn lid_source(hz: f32) -> Box<dyn SensorSource> {
+    Box::new(iio::IioSource::new(hz))
+}
+
+#[cfg(windows)]
+fn lid_source(hz: f32) -> Box<dyn SensorSource> {
+    Box::new(windows_lid::WindowsLidSource::new(hz))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+fn lid_source(_hz: f32) -> Box<dyn SensorSource> {
+    Box::new(NoLidSource)
+}
+
+/// Stands in for the lid on platforms without a backend: every connect
+/// fails, so the automatic fallback takes over.
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+struct NoLidSource;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+impl SensorSource for NoLidSource {
+    fn name(&self) -> &str {
+        "lid"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        Box::pin(async { Err(SensorError::NoLidBackend) })
+    }
+}
+
+/// One running backend, as seen from the UI thread.
+pub struct SensorSlot {
+    pub kind: SensorKind,
+    rx: LatestReceiver,
+    pub status: String,
+    pub error: Option<Arc<SensorError>>,
+    pub latest: Option<SensorSample>,
+    pub health: health::SensorHealth,
+
+    /// Samples are flowing: set by a sample, cleared by an error.
+    pub live: bool,
+
+    /// Subtracted from every incoming angle (see [`SensorConfig::zero_offsets_deg`]).
+    pub zero_offset_deg: f32,
+
+    /// Tells the sensor thread to stop (so does dropping it).
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SensorSlot {
+    /// Spawn the backend's sensor thread and start listening to it.
+    pub fn start(kind: SensorKind, cfg: &SensorConfig) -> Self {
+        let (tx, rx) = channel::latest_channel(SENSOR_QUEUE_LEN);
+        let (stop, stopped) = oneshot::channel();
+        let thread = spawn_sensor_thread(kind.create(cfg), tx, stopped);
+
+        Self {
+            kind,
+            rx,
+            status: "Starting sensor...".to_string(),
+            error: None,
+            latest: None,
+            health: health::SensorHealth::default(),
+            live: false,
+            zero_offset_deg: cfg.zero_offset(kind),
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Start over with fresh settings. The old backend is stopped and waited
+    /// for first, so a listener can bind the same port again.
+    pub fn restart(&mut self, cfg: &SensorConfig) {
+        self.stop();
+        *self = Self::start(self.kind, cfg);
+    }
+
+    /// Stop the sensor thread and wait until the backend has let go of its
+    /// socket or device.
+    pub fn stop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Pull everything that arrived since last frame. Returns the new samples, oldest first.
+    pub fn drain(&mut self) -> Vec<SensorSample> {
+        let mut fresh = Vec::new();
+
+        let (msgs, dropped) = self.rx.drain();
+        self.health.stale_dropped += dropped;
+
+        for msg in msgs {
+            match msg {
+                SensorMsg::Status(s) => {
+                    self.status = s;
+                    self.error = None;
+                }
+                SensorMsg::Error(e) => {
+                    self.health.on_error(Arc::clone(&e));
+                    self.error = Some(e);
+                    self.live = false;
+                }
+                SensorMsg::Sample(mut sample) => {
+                    self.live = true;
+                    sample.theta_deg -= self.zero_offset_deg;
+                    self.health.on_sample(sample.t);
+                    fresh.push(sample);
+                }
+            }
+        }
+
+        if let Some(last) = fresh.last() {
+            self.latest = Some(last.clone());
+        }
+        fresh
+    }
+}
+
+impl Drop for SensorSlot {
+    /// Tell the thread to stop without waiting for it, so removing a sensor
+    /// (or quitting) never stalls the UI on a slow backend.
+    fn drop(&mut self) {
+        self.stop.take();
+    }
+}
+
+/// Backoff between reconnect attempts: doubles from the first value up to the cap.
+const RECONNECT_FIRST: Duration = Duration::from_millis(500);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// How long a stopped backend's tasks get to wind down.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Why one connection of the sensor loop ended.
+enum LoopEnd {
+    /// The app dropped the receiver (closed or switched backend): stop for good.
+    ReceiverGone,
+
+    /// Connecting failed, or the stream ended / broke.
+    Failed {
+        error: SensorError,
+        got_samples: bool,
+    },
+}
+
+/// Run `source` on its own thread, forwarding samples into `tx`.
+///
+/// If connecting fails or the stream ends (unplug, sleep, ...), it keeps retrying
+/// with exponential backoff and reports each attempt, until the receiver is dropped
+/// or `stop` fires (or its sender is dropped).
+pub fn spawn_sensor_thread(
+    mut source: Box<dyn SensorSource>,
+    tx: LatestSender,
+    stop: oneshot::Receiver<()>,
+) -> JoinHandle<()> {
     std::thread::spawn(move || {
         let rt = match tokio::runtime::Runtime::new() {
             Ok(rt) => rt,
             Err(e) => {
-                let _ = tx.send(SensorMsg::Error(format!("Failed to start Tokio runtime: {e}")));
+                let _ = tx.send(SensorMsg::Error(Arc::new(SensorError::Runtime(e))));
                 return;
             }
         };
 
         rt.block_on(async move {
-            // Clone the sender so we can still use `tx` if run_sensor_loop fails.
-            let tx_for_loop = tx.clone();
-
-            if let Err(e) = run_sensor_loop(hz, tx_for_loop).await {
-                let _ = tx.send(SensorMsg::Error(format!("Sensor loop stopped: {e:?}")));
+            tokio::select! {
+                () = run_with_retries(source.as_mut(), &tx) => {}
+                _ = stop => {}
             }
         });
+
+        // Cancels what the backend spawned on the runtime (a listener's
+        // accept loop and its clients), closing their sockets.
+        rt.shutdown_timeout(SHUTDOWN_TIMEOUT);
     })
 }
 
-async fn run_sensor_loop(hz: f32, tx: Sender<SensorMsg>) -> booklid_rust::Result<()> {
-    let dev = booklid_rust::open(hz).await?;
-    let info = dev.info();
-    let _ = tx.send(SensorMsg::Status(format!("Connected. device_source={:?}", info.source)));
+async fn run_with_retries(source: &mut dyn SensorSource, tx: &LatestSender) {
+    let mut backoff = RECONNECT_FIRST;
+    let mut attempt: u32 = 0;
 
-    let mut stream = dev.subscribe();
+    loop {
+        let (error, got_samples) = match run_sensor_loop(source, tx).await {
+            LoopEnd::ReceiverGone => return,
+            LoopEnd::Failed { error, got_samples } => (error, got_samples),
+        };
 
-    while let Some(s) = stream.next().await {
-        let msg = SensorMsg::Sample(SensorSample {
-            theta_deg: s.angle_deg,
-            source: format!("{:?}", s.source),
-            t: Instant::now(),
-        });
+        // A connection that actually delivered data resets the backoff.
+        if got_samples {
+            backoff = RECONNECT_FIRST;
+            attempt = 0;
+        }
+        attempt += 1;
 
-        if tx.send(msg).is_err() {
-            break;
+        let error = SensorError::Retrying {
+            cause: Box::new(error),
+            retry_in: backoff,
+            attempt,
+        };
+        warn!("{}: {error}", source.name());
+        if tx.send(SensorMsg::Error(Arc::new(error))).is_err() {
+            return;
         }
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX);
     }
+}
 
-    Ok(())
+async fn run_sensor_loop(source: &mut dyn SensorSource, tx: &LatestSender) -> LoopEnd {
+    let connecting = SensorMsg::Status(format!("Connecting to {}...", source.name()));
+    if tx.send(connecting).is_err() {
+        return LoopEnd::ReceiverGone;
+    }
+
+    let Connected { status, mut samples } = match source.connect().await {
+        Ok(c) => c,
+        Err(error) => {
+            return LoopEnd::Failed {
+                error,
+                got_samples: false,
+            };
+        }
+    };
+    info!("{}: {status}", source.name());
+    if tx.send(SensorMsg::Status(status)).is_err() {
+        return LoopEnd::ReceiverGone;
+    }
+
+    let mut got_samples = false;
+    while let Some(sample) = samples.next().await {
+        got_samples = true;
+        if tx.send(SensorMsg::Sample(sample)).is_err() {
+            return LoopEnd::ReceiverGone;
+        }
+    }
+
+    LoopEnd::Failed {
+        error: SensorError::StreamEnded,
+        got_samples,
+    }
 }
@@ -0,0 +1,51 @@
+use std::time::Instant;
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// The real laptop hinge sensor, via booklid-rust.
+pub struct BooklidSource {
+    hz: f32,
+}
+
+impl BooklidSource {
+    pub fn new(hz: f32) -> Self {
+        Self { hz }
+    }
+}
+
+impl SensorSource for BooklidSource {
+    fn name(&self) -> &str {
+        "booklid"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let hz = self.hz;
+
+        async move {
+            let dev = booklid_rust::open(hz).await.map_err(|e| SensorError::Lid(format!("{e:?}")))?;
+            let info = dev.info();
+            let status = format!("Connected. device_source={:?}", info.source);
+
+            let stream = dev.subscribe();
+            let samples = stream.map(move |s| {
+                // The device has to outlive its stream, so the stream owns it.
+                let _device = &dev;
+
+                SensorSample {
+                    theta_deg: s.angle_deg,
+                    source: format!("{:?}", s.source),
+                    t: Instant::now(),
+                }
+            });
+
+            Ok(Connected {
+                status,
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::{SensorError, SensorKind};
+
+/// Where the zero-point offsets are kept between runs.
+pub const DEFAULT_CALIBRATION_PATH: &str = "sensor-calibration.json";
+
+/// Load per-backend zero offsets, e.g. `{ "booklid": 3.5 }`.
+/// A missing file just means nothing has been calibrated yet.
+pub fn load_zero_offsets(path: impl AsRef<Path>) -> Result<HashMap<SensorKind, f32>, SensorError> {
+    let path = path.as_ref();
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(source) => {
+            return Err(SensorError::File {
+                action: "read",
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+
+    let raw: HashMap<String, f32> =
+        serde_json::from_str(&text).map_err(SensorError::Calibration)?;
+
+    // Unknown backend names are ignored (e.g. from a newer version).
+    Ok(SensorKind::ALL
+        .into_iter()
+        .filter_map(|k| raw.get(k.id()).map(|off| (k, *off)))
+        .collect())
+}
+
+pub fn save_zero_offsets(
+    path: impl AsRef<Path>,
+    offsets: &HashMap<SensorKind, f32>,
+) -> Result<(), SensorError> {
+    let raw: HashMap<&str, f32> = offsets.iter().map(|(k, off)| (k.id(), *off)).collect();
+
+    let text = serde_json::to_string_pretty(&raw).map_err(SensorError::Calibration)?;
+    fs::write(&path, text).map_err(|source| SensorError::File {
+        action: "write",
+        path: path.as_ref().to_path_buf(),
+        source,
+    })
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::SensorMsg;
+
+/// Bounded sensor → UI channel that keeps the newest messages.
+///
+/// If the UI stalls (window dragged, app in the background, ...), a plain
+/// channel would queue every sample and replay the whole backlog at once when
+/// it wakes up, whipping the bellows with ancient motion. Here the oldest
+/// samples are dropped instead once `capacity` is reached.
+pub fn latest_channel(capacity: usize) -> (LatestSender, LatestReceiver) {
+    let shared = Arc::new(Mutex::new(Shared {
+        msgs: VecDeque::with_capacity(capacity),
+        capacity: capacity.max(1),
+        dropped: 0,
+        receiver_alive: true,
+    }));
+
+    (
+        LatestSender {
+            shared: shared.clone(),
+        },
+        LatestReceiver { shared },
+    )
+}
+
+struct Shared {
+    msgs: VecDeque<SensorMsg>,
+    capacity: usize,
+    dropped: u64,
+    receiver_alive: bool,
+}
+
+pub struct LatestSender {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl LatestSender {
+    /// Queue a message, evicting the oldest sample if full.
+    /// Fails (returning the message) once the receiver is gone.
+    pub fn send(&self, msg: SensorMsg) -> Result<(), SensorMsg> {
+        let Ok(mut shared) = self.shared.lock() else {
+            return Err(msg);
+        };
+        if !shared.receiver_alive {
+            return Err(msg);
+        }
+
+        if shared.msgs.len() >= shared.capacity {
+            // Status and error lines are rare and worth keeping; drop a sample first.
+            let victim = shared
+                .msgs
+                .iter()
+                .position(|m| matches!(m, SensorMsg::Sample(_)))
+                .unwrap_or(0);
+            shared.msgs.remove(victim);
+            shared.dropped += 1;
+        }
+
+        shared.msgs.push_back(msg);
+        Ok(())
+    }
+}
+
+pub struct LatestReceiver {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl LatestReceiver {
+    /// Everything queued since the last call, oldest first, plus how many
+    /// messages were evicted in the meantime.
+    pub fn drain(&self) -> (Vec<SensorMsg>, u64) {
+        let Ok(mut shared) = self.shared.lock() else {
+            return (Vec::new(), 0);
+        };
+        let dropped = std::mem::take(&mut shared.dropped);
+        (shared.msgs.drain(..).collect(), dropped)
+    }
+}
+
+impl Drop for LatestReceiver {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.receiver_alive = false;
+            shared.msgs.clear();
+        }
+    }
+}
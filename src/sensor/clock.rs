@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+/// Maps a device's own timestamps onto the local monotonic clock.
+///
+/// Receive times include network/channel jitter; device timestamps don't. We
+/// keep the smallest observed (receive - device) offset, i.e. the fastest
+/// delivery seen, and place every sample at `device time + that offset`.
+/// The offset is allowed to creep up slowly so clock drift between the two
+/// machines can't wedge it.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceClock {
+    base: Option<Instant>,
+    min_offset_sec: f64,
+    last_received: Option<Instant>,
+}
+
+impl DeviceClock {
+    /// How fast the offset may drift upward (seconds per second).
+    const CREEP_PER_SEC: f64 = 0.001;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Local time for a sample stamped `device_sec` (any epoch) that arrived at `received`.
+    pub fn map(&mut self, device_sec: f64, received: Instant) -> Instant {
+        let base = *self.base.get_or_insert(received);
+        let offset = received.saturating_duration_since(base).as_secs_f64() - device_sec;
+
+        self.min_offset_sec = match self.last_received {
+            None => offset,
+            Some(last) => {
+                let elapsed = received.saturating_duration_since(last).as_secs_f64();
+                (self.min_offset_sec + elapsed * Self::CREEP_PER_SEC).min(offset)
+            }
+        };
+        self.last_received = Some(received);
+
+        let since_base = (device_sec + self.min_offset_sec).max(0.0);
+        (base + Duration::from_secs_f64(since_base)).min(received)
+    }
+}
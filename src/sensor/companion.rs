@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use serde::Deserialize;
+use tokio::net::UdpSocket;
+
+use super::clock::DeviceClock;
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Protocol version sent back in `welcome`.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// How fast an integrated gyro angle is pulled back to 0° (per second), so
+/// gyro drift can't leave the bellows stuck open.
+const GYRO_LEAK_PER_SEC: f32 = 0.2;
+
+/// Receives wrist rotation from a watch or phone companion app, so the player
+/// can pump with a free hand instead of the laptop lid.
+///
+/// Protocol ("harmonium companion", version 1): UDP, one JSON object per
+/// datagram, sent to the Mac on the configured port (9100 by default).
+///
+/// - `{"type": "hello", "device": "Apple Watch"}`
+///   Optional. The app answers `{"type": "welcome", "app": "harmonium", "protocol": 1}`
+///   to the sender, so the companion can show that it's connected.
+/// - `{"type": "rotation", "t": 12.345, "roll_deg": 37.5}`
+///   Absolute wrist roll around the forearm (e.g. CMAttitude.roll), in degrees.
+///   Used directly as the angle.
+/// - `{"type": "gyro", "t": 12.345, "rate_dps": -45.0}`
+///   Rotation rate around the forearm in degrees per second, for devices
+///   without an attitude estimate. Integrated into an angle that slowly
+///   leaks back to 0°.
+///
+/// `t` is the capture time in seconds on the sender's own clock (any epoch,
+/// monotonic). It's optional but recommended: it keeps Wi-Fi/Bluetooth jitter
+/// out of the bellows velocity. Send 50–100 messages per second.
+pub struct CompanionSource {
+    port: u16,
+}
+
+impl CompanionSource {
+    pub fn new(port: u16) -> Self {
+        Self { port }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum CompanionMsg {
+    Hello {
+        #[serde(default)]
+        device: Option<String>,
+    },
+    Rotation {
+        #[serde(default)]
+        t: Option<f64>,
+        roll_deg: f32,
+    },
+    Gyro {
+        #[serde(default)]
+        t: Option<f64>,
+        rate_dps: f32,
+    },
+}
+
+/// What we remember about each sender.
+#[derive(Default)]
+struct Peer {
+    name: Option<String>,
+    clock: DeviceClock,
+
+    /// Integrated gyro angle and when it was last advanced.
+    gyro_deg: f32,
+    gyro_last: Option<Instant>,
+}
+
+struct Listener {
+    socket: UdpSocket,
+    peers: HashMap<SocketAddr, Peer>,
+}
+
+impl SensorSource for CompanionSource {
+    fn name(&self) -> &str {
+        "companion"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let port = self.port;
+
+        async move {
+            let socket = UdpSocket::bind(("0.0.0.0", port))
+                .await
+                .map_err(|source| SensorError::Listen {
+                    what: "the companion",
+                    port,
+                    source,
+                })?;
+
+            let listener = Listener {
+                socket,
+                peers: HashMap::new(),
+            };
+
+            let samples = futures_util::stream::unfold(listener, |mut l| async move {
+                let mut buf = [0u8; 2048];
+
+                loop {
+                    let Ok((len, from)) = l.socket.recv_from(&mut buf).await else {
+                        return None;
+                    };
+                    let received = Instant::now();
+
+                    let Ok(msg) = serde_json::from_slice::<CompanionMsg>(&buf[..len]) else {
+                        continue;
+                    };
+
+                    let peer = l.peers.entry(from).or_default();
+                    let (theta_deg, t) = match msg {
+                        CompanionMsg::Hello { device } => {
+                            peer.name = device;
+                            let welcome = format!(
+                                r#"{{"type":"welcome","app":"harmonium","protocol":{PROTOCOL_VERSION}}}"#
+                            );
+                            let _ = l.socket.send_to(welcome.as_bytes(), from).await;
+                            continue;
+                        }
+                        CompanionMsg::Rotation { t, roll_deg } => {
+                            (roll_deg, capture_time(&mut peer.clock, t, received))
+                        }
+                        CompanionMsg::Gyro { t, rate_dps } => {
+                            let t = capture_time(&mut peer.clock, t, received);
+                            (integrate_gyro(peer, rate_dps, t), t)
+                        }
+                    };
+
+                    let name = peer.name.clone().unwrap_or_else(|| from.to_string());
+                    let sample = SensorSample {
+                        theta_deg,
+                        source: format!("companion:{name}"),
+                        t,
+                    };
+                    return Some((sample, l));
+                }
+            });
+
+            Ok(Connected {
+                status: format!("Waiting for a watch/phone companion on UDP port {port}"),
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+fn capture_time(clock: &mut DeviceClock, device_t: Option<f64>, received: Instant) -> Instant {
+    match device_t {
+        Some(sec) => clock.map(sec, received),
+        None => received,
+    }
+}
+
+fn integrate_gyro(peer: &mut Peer, rate_dps: f32, t: Instant) -> f32 {
+    if let Some(last) = peer.gyro_last {
+        let dt = t.saturating_duration_since(last).as_secs_f32().min(0.5);
+        peer.gyro_deg += rate_dps * dt;
+        peer.gyro_deg -= peer.gyro_deg * (GYRO_LEAK_PER_SEC * dt).min(1.0);
+    }
+    peer.gyro_last = Some(t);
+    peer.gyro_deg
+}
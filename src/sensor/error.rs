@@ -0,0 +1,147 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
+
+/// Why a sensor backend couldn't connect or stopped, or a sensor file
+/// (recording, calibration) couldn't be read or written.
+#[derive(Debug, Error)]
+pub enum SensorError {
+    #[error("Failed to start Tokio runtime: {0}")]
+    Runtime(#[source] io::Error),
+
+    /// The lid sensor couldn't be opened (booklid, IIO or Windows sensors).
+    #[error("Lid sensor unavailable: {0}")]
+    Lid(String),
+
+    /// This platform has no way to read the lid.
+    #[error("No lid sensor backend on this platform")]
+    NoLidBackend,
+
+    /// A network backend couldn't listen, usually because another program
+    /// (or another copy of the app) has the port.
+    #[error("Failed to listen for {what} on port {port}: {source}")]
+    Listen {
+        what: &'static str,
+        port: u16,
+        source: io::Error,
+    },
+
+    /// A browser connected to the WebSocket backend but didn't upgrade.
+    #[error("WebSocket handshake with {peer} failed: {source}")]
+    Handshake {
+        peer: SocketAddr,
+        source: tungstenite::Error,
+    },
+
+    /// A connected browser's stream broke.
+    #[error("WebSocket error from {peer}: {source}")]
+    Socket {
+        peer: SocketAddr,
+        source: tungstenite::Error,
+    },
+
+    #[error("Failed to serve bellows page: {0}")]
+    ServePage(#[source] io::Error),
+
+    #[error("Failed to open serial port {port}: {source}")]
+    Serial {
+        port: String,
+        source: serialport::Error,
+    },
+
+    #[error("Failed to open HID devices: {0}")]
+    Hid(String),
+
+    #[error("HID device '{0}' not found")]
+    HidDeviceMissing(String),
+
+    /// Reading or writing a recording or the calibration file.
+    #[error("Failed to {action} {path:?}: {source}")]
+    File {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+
+    #[error("Bad line {line} in recording {path:?}: '{text}'")]
+    BadRecordingLine {
+        path: PathBuf,
+        line: usize,
+        text: String,
+    },
+
+    #[error("Recording {0:?} has no samples")]
+    EmptyRecording(PathBuf),
+
+    #[error("Bad calibration JSON: {0}")]
+    Calibration(#[source] serde_json::Error),
+
+    /// The backend connected but its samples stopped coming.
+    #[error("stream ended")]
+    StreamEnded,
+
+    /// The sensor thread is waiting to reconnect after `cause`.
+    #[error(
+        "Sensor loop stopped: {cause}. Retrying in {:.1} s (attempt {attempt})",
+        .retry_in.as_secs_f32()
+    )]
+    Retrying {
+        #[source]
+        cause: Box<SensorError>,
+        retry_in: Duration,
+        attempt: u32,
+    },
+}
+
+impl SensorError {
+    /// What actually went wrong, under any retry notice.
+    pub fn cause(&self) -> &SensorError {
+        match self {
+            SensorError::Retrying { cause, .. } => cause.cause(),
+            other => other,
+        }
+    }
+
+    /// Another program holds the port or device; retrying won't help until
+    /// it lets go.
+    pub fn is_busy(&self) -> bool {
+        match self.cause() {
+            SensorError::Listen { source, .. } => source.kind() == io::ErrorKind::AddrInUse,
+            SensorError::Serial { source, .. } => {
+                source.kind == serialport::ErrorKind::Io(io::ErrorKind::ResourceBusy)
+            }
+            _ => false,
+        }
+    }
+
+    /// The device or file isn't there (unplugged, renamed, never recorded).
+    pub fn is_missing(&self) -> bool {
+        match self.cause() {
+            SensorError::Serial { source, .. } => matches!(
+                source.kind,
+                serialport::ErrorKind::NoDevice
+                    | serialport::ErrorKind::Io(io::ErrorKind::NotFound)
+            ),
+            SensorError::File { source, .. } => source.kind() == io::ErrorKind::NotFound,
+            SensorError::HidDeviceMissing(_) => true,
+            _ => false,
+        }
+    }
+
+    /// What the player can do about it, for the sensor list.
+    pub fn hint(&self) -> Option<&'static str> {
+        if self.is_busy() {
+            Some("Another program is using it: close that program or pick another port")
+        } else if self.is_missing() {
+            Some("Not found: check it is plugged in, or pick another device or file")
+        } else if matches!(self.cause(), SensorError::NoLidBackend) {
+            Some("Play with the arrow keys, or pick another sensor backend")
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+
+use super::{Connected, SensorError, SensorSample, SensorSource};
+use crate::simulation::Trajectory;
+
+/// A sine-wave "lid" that runs through the real sensor thread and channel.
+/// Useful for exercising the whole input path without hardware.
+pub struct FakeSource {
+    hz: f32,
+    trajectory: Trajectory,
+}
+
+impl FakeSource {
+    pub fn new(hz: f32) -> Self {
+        Self {
+            hz,
+            // Centered on a typical open-lid angle.
+            trajectory: Trajectory::Sine {
+                center_deg: 100.0,
+                amplitude_deg: 30.0,
+                frequency_hz: 0.6,
+            },
+        }
+    }
+}
+
+impl SensorSource for FakeSource {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let period = Duration::from_secs_f32(1.0 / self.hz.max(1.0));
+        let trajectory = self.trajectory.clone();
+        let start = Instant::now();
+
+        async move {
+            let interval = tokio::time::interval(period);
+
+            let samples = futures_util::stream::unfold(interval, move |mut interval| {
+                let trajectory = trajectory.clone();
+                async move {
+                    interval.tick().await;
+
+                    let t = Instant::now();
+                    let sample = SensorSample {
+                        theta_deg: trajectory.angle_at((t - start).as_secs_f32()),
+                        source: "fake".to_string(),
+                        t,
+                    };
+                    Some((sample, interval))
+                }
+            });
+
+            Ok(Connected {
+                status: format!("Connected. fake sine at {:.0} Hz", 1.0 / period.as_secs_f32()),
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
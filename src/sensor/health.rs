@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::SensorError;
+
+/// Rolling statistics about a sensor stream, so a sluggish response can be
+/// blamed on the sensor (low rate, jitter, dropouts) or on the bellows tuning.
+#[derive(Debug, Clone, Default)]
+pub struct SensorHealth {
+    last_t: Option<Instant>,
+
+    /// Recent inter-sample intervals (seconds), newest last.
+    intervals: VecDeque<f32>,
+
+    /// Gaps much longer than the typical interval.
+    pub dropouts: u32,
+
+    pub total_samples: u64,
+
+    /// Samples thrown away because the UI fell behind (keep-latest queue).
+    pub stale_dropped: u64,
+
+    /// Last error reported by the backend (kept after it reconnects).
+    pub last_error: Option<Arc<SensorError>>,
+}
+
+impl SensorHealth {
+    /// How many intervals the rate/jitter window holds.
+    const WINDOW: usize = 120;
+
+    /// A gap this many times longer than the mean interval counts as a dropout.
+    const DROPOUT_FACTOR: f32 = 3.0;
+
+    pub fn on_sample(&mut self, t: Instant) {
+        self.total_samples += 1;
+
+        let Some(last) = self.last_t.replace(t) else {
+            return;
+        };
+
+        let dt = t.saturating_duration_since(last).as_secs_f32();
+
+        if let Some(mean) = self.mean_interval() {
+            if dt > mean * Self::DROPOUT_FACTOR {
+                self.dropouts += 1;
+            }
+        }
+
+        self.intervals.push_back(dt);
+        while self.intervals.len() > Self::WINDOW {
+            self.intervals.pop_front();
+        }
+    }
+
+    pub fn on_error(&mut self, e: Arc<SensorError>) {
+        self.last_error = Some(e);
+    }
+
+    fn mean_interval(&self) -> Option<f32> {
+        if self.intervals.len() < 2 {
+            return None;
+        }
+        Some(self.intervals.iter().sum::<f32>() / self.intervals.len() as f32)
+    }
+
+    /// Effective samples per second over the window.
+    pub fn rate_hz(&self) -> Option<f32> {
+        self.mean_interval().filter(|m| *m > 0.0).map(|m| 1.0 / m)
+    }
+
+    /// Standard deviation of the inter-sample interval, in milliseconds.
+    pub fn jitter_ms(&self) -> Option<f32> {
+        let mean = self.mean_interval()?;
+        let var = self.intervals.iter().map(|dt| (dt - mean).powi(2)).sum::<f32>()
+            / self.intervals.len() as f32;
+        Some(var.sqrt() * 1000.0)
+    }
+}
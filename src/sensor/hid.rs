@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use gilrs::{Axis, Event, EventType, Gilrs};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::clock::DeviceClock;
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Axes offered for every device. Joysticks without a known mapping report
+/// their axes as raw codes, which show up once they have moved.
+const NAMED_AXES: [Axis; 8] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::LeftZ,
+    Axis::RightStickX,
+    Axis::RightStickY,
+    Axis::RightZ,
+    Axis::DPadX,
+    Axis::DPadY,
+];
+
+/// How often the reader thread checks for new events.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// A joystick/HID device and the axes it can be read from.
+#[derive(Debug, Clone)]
+pub struct HidDevice {
+    pub name: String,
+    pub axes: Vec<String>,
+}
+
+/// Connected game controllers / joysticks (for the device and axis pickers).
+pub fn available_hid_devices() -> Vec<HidDevice> {
+    let Ok(mut gilrs) = Gilrs::new() else {
+        return Vec::new();
+    };
+
+    // Pending events fill in the state, which is where raw axes show up.
+    while gilrs.next_event().is_some() {}
+
+    gilrs
+        .gamepads()
+        .map(|(_, pad)| {
+            let mut axes: Vec<String> = NAMED_AXES.iter().map(|a| format!("{a:?}")).collect();
+            for (code, _) in pad.state().axes() {
+                let raw = raw_axis_label(code.into_u32());
+                if !axes.contains(&raw) {
+                    axes.push(raw);
+                }
+            }
+
+            HidDevice {
+                name: pad.name().to_string(),
+                axes,
+            }
+        })
+        .collect()
+}
+
+fn raw_axis_label(code: u32) -> String {
+    format!("code {code}")
+}
+
+/// Reads one axis of a HID joystick: expression pedals, faders and DIY
+/// controllers that present themselves as game controllers.
+///
+/// The axis value (-1..1) is mapped linearly onto 0..180°, so position mode
+/// treats the axis like a lid angle and velocity mode reacts to moving it.
+pub struct HidAxisSource {
+    device: String,
+    axis: String,
+}
+
+impl HidAxisSource {
+    pub fn new(device: &str, axis: &str) -> Self {
+        Self {
+            device: device.to_string(),
+            axis: axis.to_string(),
+        }
+    }
+}
+
+impl SensorSource for HidAxisSource {
+    fn name(&self) -> &str {
+        "hid"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let device = self.device.clone();
+        let axis = self.axis.clone();
+
+        async move {
+            let gilrs = Gilrs::new().map_err(|e| SensorError::Hid(e.to_string()))?;
+
+            if !gilrs.gamepads().any(|(_, pad)| pad.name() == device) {
+                return Err(SensorError::HidDeviceMissing(device));
+            }
+
+            // gilrs is polled, so it gets its own thread (and stays on it).
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<SensorSample>();
+            let source = format!("hid:{device}/{axis}");
+            let (dev, ax) = (device.clone(), axis.clone());
+            std::thread::spawn(move || read_axis(gilrs, dev, ax, source, tx));
+
+            let samples = futures_util::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|s| (s, rx))
+            });
+
+            Ok(Connected {
+                status: format!("Reading {axis} of {device}"),
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+fn read_axis(
+    mut gilrs: Gilrs,
+    device: String,
+    axis: String,
+    source: String,
+    tx: UnboundedSender<SensorSample>,
+) {
+    let mut clock = DeviceClock::new();
+
+    loop {
+        while let Some(Event { id, event, time, .. }) = gilrs.next_event() {
+            if gilrs.gamepad(id).name() != device {
+                continue;
+            }
+
+            let value = match event {
+                EventType::AxisChanged(a, v, code)
+                    if format!("{a:?}") == axis || raw_axis_label(code.into_u32()) == axis =>
+                {
+                    v
+                }
+                // Unplugging ends the stream; the sensor thread reconnects.
+                EventType::Disconnected => return,
+                _ => continue,
+            };
+
+            let received = Instant::now();
+            let t = match time.duration_since(UNIX_EPOCH) {
+                Ok(d) => clock.map(d.as_secs_f64(), received),
+                Err(_) => received,
+            };
+
+            let sample = SensorSample {
+                theta_deg: (value.clamp(-1.0, 1.0) + 1.0) * 90.0,
+                source: source.clone(),
+                t,
+            };
+            if tx.send(sample).is_err() {
+                return;
+            }
+        }
+
+        if tx.is_closed() {
+            return;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
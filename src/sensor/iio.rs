@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Where the kernel lists its Industrial I/O sensors.
+const IIO_DEVICES: &str = "/sys/bus/iio/devices";
+
+/// The laptop lid on Linux, read from the kernel's IIO sensors in sysfs.
+///
+/// A hinge sensor (`hid-sensor-hinge`, found on recent convertibles) gives
+/// the hinge angle directly. Otherwise the display's accelerometer does:
+/// with the base lying flat, the display's tilt against gravity is the lid
+/// angle (0° closed, 90° upright, 180° flat open). Machines that mount the
+/// accelerometer rotated read off by a constant; zero it in the Sensor panel.
+pub struct IioSource {
+    hz: f32,
+}
+
+impl IioSource {
+    pub fn new(hz: f32) -> Self {
+        Self { hz }
+    }
+}
+
+impl SensorSource for IioSource {
+    fn name(&self) -> &str {
+        "iio"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let period = Duration::from_secs_f32(1.0 / self.hz.max(1.0));
+
+        async move {
+            let lid = LidReader::find().ok_or_else(|| {
+                SensorError::Lid(format!("no IIO hinge sensor or accelerometer in {IIO_DEVICES}"))
+            })?;
+            // Fail now rather than with an empty stream if it can't be read.
+            lid.read().ok_or_else(|| {
+                SensorError::Lid(format!("can't read {} (permissions?)", lid.dir().display()))
+            })?;
+            let status = format!("Connected. {}", lid.describe());
+            let source = format!("iio:{}", lid.kind());
+
+            let interval = tokio::time::interval(period);
+            let samples = futures_util::stream::unfold(interval, move |mut interval| {
+                let lid = lid.clone();
+                let source = source.clone();
+                async move {
+                    interval.tick().await;
+
+                    // A failed read (device gone) ends the stream; the sensor
+                    // thread reconnects.
+                    let sample = SensorSample {
+                        theta_deg: lid.read()?,
+                        source,
+                        t: Instant::now(),
+                    };
+                    Some((sample, interval))
+                }
+            });
+
+            Ok(Connected {
+                status,
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+/// One IIO device that can tell the lid angle.
+#[derive(Debug, Clone)]
+enum LidReader {
+    /// `in_angl0_raw` is the hinge angle (radians after scaling).
+    Hinge { dir: PathBuf, scale: f32 },
+
+    /// The display's accelerometer, for its tilt.
+    Accel { dir: PathBuf },
+}
+
+impl LidReader {
+    /// A hinge sensor if there is one, else the display's accelerometer, else
+    /// any accelerometer.
+    fn find() -> Option<Self> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(IIO_DEVICES)
+            .ok()?
+            .flatten()
+            .map(|e| e.path())
+            .collect();
+        devices.sort();
+
+        if let Some(dir) = devices.iter().find(|d| d.join("in_angl0_raw").is_file()) {
+            let scale = read_f32(&dir.join("in_angl_scale"))
+                .or_else(|| read_f32(&dir.join("in_angl0_scale")))
+                .unwrap_or(1.0);
+            return Some(LidReader::Hinge {
+                dir: dir.clone(),
+                scale,
+            });
+        }
+
+        let accels: Vec<&PathBuf> =
+            devices.iter().filter(|d| d.join("in_accel_x_raw").is_file()).collect();
+        let display = accels.iter().find(|d| is_display(d)).or(accels.first())?;
+        Some(LidReader::Accel {
+            dir: (*display).clone(),
+        })
+    }
+
+    fn dir(&self) -> &Path {
+        match self {
+            LidReader::Hinge { dir, .. } | LidReader::Accel { dir } => dir,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            LidReader::Hinge { .. } => "hinge",
+            LidReader::Accel { .. } => "accel",
+        }
+    }
+
+    fn describe(&self) -> String {
+        let name = read_trimmed(&self.dir().join("name")).unwrap_or_else(|| "?".to_string());
+        match self {
+            LidReader::Hinge { .. } => format!("IIO hinge sensor {name}"),
+            LidReader::Accel { .. } => format!("IIO accelerometer {name} (display tilt)"),
+        }
+    }
+
+    /// The lid angle in degrees, or None if the device can't be read.
+    fn read(&self) -> Option<f32> {
+        match self {
+            LidReader::Hinge { dir, scale } => {
+                Some((read_f32(&dir.join("in_angl0_raw"))? * scale).to_degrees())
+            }
+            LidReader::Accel { dir } => {
+                // Only the direction matters, so the axes' scale doesn't.
+                let y = read_f32(&dir.join("in_accel_y_raw"))?;
+                let z = read_f32(&dir.join("in_accel_z_raw"))?;
+                Some(y.atan2(-z).to_degrees().rem_euclid(360.0))
+            }
+        }
+    }
+}
+
+/// The accelerometer in the lid, as drivers label it: `accel-display`
+/// (ACPI / HID) or location `lid` (ChromeOS EC).
+fn is_display(dir: &Path) -> bool {
+    read_trimmed(&dir.join("label")).is_some_and(|l| l == "accel-display")
+        || read_trimmed(&dir.join("location")).is_some_and(|l| l == "lid")
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_f32(path: &Path) -> Option<f32> {
+    read_trimmed(path)?.parse().ok()
+}
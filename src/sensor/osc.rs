@@ -0,0 +1,99 @@
+use std::time::Instant;
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use rosc::{OscPacket, OscType};
+use tokio::net::UdpSocket;
+
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Listens for OSC over UDP, e.g. from TouchOSC or Sensors2OSC on a phone.
+///
+/// Understood messages:
+/// - `/angle <deg>` — an angle in degrees, used as-is
+/// - `/accxyz x y z` (TouchOSC) or `/accelerometer x y z` (Sensors2OSC) — the
+///   tilt of the phone, atan2(y, z) in degrees, so rocking the phone pumps the bellows
+pub struct OscSource {
+    port: u16,
+}
+
+impl OscSource {
+    pub fn new(port: u16) -> Self {
+        Self { port }
+    }
+}
+
+impl SensorSource for OscSource {
+    fn name(&self) -> &str {
+        "osc"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let port = self.port;
+
+        async move {
+            let socket = UdpSocket::bind(("0.0.0.0", port))
+                .await
+                .map_err(|source| SensorError::Listen {
+                    what: "OSC",
+                    port,
+                    source,
+                })?;
+
+            let samples = futures_util::stream::unfold(socket, |socket| async move {
+                let mut buf = [0u8; rosc::decoder::MTU];
+
+                loop {
+                    let Ok((len, from)) = socket.recv_from(&mut buf).await else {
+                        return None;
+                    };
+
+                    let Ok((_, packet)) = rosc::decoder::decode_udp(&buf[..len]) else {
+                        continue;
+                    };
+
+                    if let Some(theta_deg) = angle_from_packet(&packet) {
+                        let sample = SensorSample {
+                            theta_deg,
+                            source: format!("osc:{from}"),
+                            t: Instant::now(),
+                        };
+                        return Some((sample, socket));
+                    }
+                }
+            });
+
+            Ok(Connected {
+                status: format!("Listening for OSC on UDP port {port} (/angle, /accxyz)"),
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+/// Pull an angle out of a packet (the last usable message wins in a bundle).
+fn angle_from_packet(packet: &OscPacket) -> Option<f32> {
+    match packet {
+        OscPacket::Message(msg) => {
+            let args: Vec<f32> = msg.args.iter().filter_map(osc_number).collect();
+
+            match (msg.addr.as_str(), args.as_slice()) {
+                ("/angle", [deg, ..]) => Some(*deg),
+                ("/accxyz" | "/accelerometer", [_x, y, z, ..]) => Some(y.atan2(*z).to_degrees()),
+                _ => None,
+            }
+        }
+        OscPacket::Bundle(bundle) => bundle.content.iter().filter_map(angle_from_packet).last(),
+    }
+}
+
+fn osc_number(arg: &OscType) -> Option<f32> {
+    match arg {
+        OscType::Float(f) => Some(*f),
+        OscType::Double(d) => Some(*d as f32),
+        OscType::Int(i) => Some(*i as f32),
+        OscType::Long(l) => Some(*l as f32),
+        _ => None,
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Where recordings go unless told otherwise.
+pub const DEFAULT_RECORDING_PATH: &str = "sensor-recording.csv";
+
+/// Writes incoming sensor samples to a small CSV file:
+///
+/// ```text
+/// t_sec,theta_deg
+/// 0.000000,101.25
+/// 0.016667,101.31
+/// ```
+///
+/// Times are relative to the first recorded sample.
+pub struct SensorRecorder {
+    path: PathBuf,
+    out: BufWriter<File>,
+    first_t: Option<Instant>,
+    count: usize,
+}
+
+impl SensorRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, SensorError> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path).map_err(|e| file_error("create", &path, e))?;
+
+        let mut out = BufWriter::new(file);
+        writeln!(out, "t_sec,theta_deg").map_err(|e| file_error("write", &path, e))?;
+
+        Ok(Self {
+            path,
+            out,
+            first_t: None,
+            count: 0,
+        })
+    }
+
+    pub fn push(&mut self, sample: &SensorSample) -> Result<(), SensorError> {
+        let first = *self.first_t.get_or_insert(sample.t);
+        let t_sec = sample.t.saturating_duration_since(first).as_secs_f64();
+
+        writeln!(self.out, "{t_sec:.6},{}", sample.theta_deg)
+            .map_err(|e| file_error("write", &self.path, e))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Flush to disk. Also happens when the recorder is dropped.
+    pub fn finish(mut self) -> Result<(), SensorError> {
+        self.out.flush().map_err(|e| file_error("flush", &self.path, e))
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Plays a recording back as a sensor, with the original timing, looping forever.
+pub struct ReplaySource {
+    path: PathBuf,
+}
+
+impl ReplaySource {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl SensorSource for ReplaySource {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let path = self.path.clone();
+
+        async move {
+            let points = Rc::new(load_recording(&path)?);
+            let length = points.last().map(|(t, _)| *t).unwrap_or(0.0);
+            let status = format!(
+                "Replaying {} ({} samples, {length:.1} s, looping)",
+                path.display(),
+                points.len()
+            );
+            let source = format!("replay:{}", path.display());
+
+            // State: (index of next point, when the current pass started)
+            let samples = futures_util::stream::unfold((0usize, None), move |(i, pass_start)| {
+                let points = Rc::clone(&points);
+                let source = source.clone();
+                async move {
+                    let pass_start = pass_start.unwrap_or_else(tokio::time::Instant::now);
+                    let (t_sec, theta_deg) = points[i];
+
+                    let due = pass_start + Duration::from_secs_f64(t_sec);
+                    tokio::time::sleep_until(due).await;
+
+                    // Stamp with the recorded time, not whenever the timer woke us.
+                    let sample = SensorSample {
+                        theta_deg,
+                        source,
+                        t: due.into_std(),
+                    };
+
+                    // Wrap around at the end and start a new pass.
+                    let next = if i + 1 < points.len() {
+                        (i + 1, Some(pass_start))
+                    } else {
+                        (0, None)
+                    };
+                    Some((sample, next))
+                }
+            });
+
+            Ok(Connected {
+                status,
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+/// Read a recording written by `SensorRecorder`. Returns (t_sec, theta_deg) points.
+fn load_recording(path: &Path) -> Result<Vec<(f64, f32)>, SensorError> {
+    let text = fs::read_to_string(path).map_err(|e| file_error("read", path, e))?;
+
+    let mut points = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("t_sec") {
+            continue;
+        }
+
+        let parsed = line.split_once(',').and_then(|(t, theta)| {
+            Some((t.trim().parse::<f64>().ok()?, theta.trim().parse::<f32>().ok()?))
+        });
+
+        match parsed {
+            Some(p) => points.push(p),
+            None => {
+                return Err(SensorError::BadRecordingLine {
+                    path: path.to_path_buf(),
+                    line: line_no + 1,
+                    text: line.to_string(),
+                });
+            }
+        }
+    }
+
+    if points.is_empty() {
+        return Err(SensorError::EmptyRecording(path.to_path_buf()));
+    }
+
+    Ok(points)
+}
+
+fn file_error(action: &'static str, path: &Path, source: std::io::Error) -> SensorError {
+    SensorError::File {
+        action,
+        path: path.to_path_buf(),
+        source,
+    }
+}
@@ -0,0 +1,144 @@
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::time::{Duration, Instant};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::clock::DeviceClock;
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Reads values from a serial port, e.g. an Arduino with a potentiometer on the
+/// hinge of a real bellows rig, or a pressure sensor inside it.
+///
+/// Line protocol (one value per line, `\n` or `\r\n`):
+/// - `97.5`              → a bare number
+/// - `angle 97.5` / `a:97.5`
+/// - `pressure 512` / `p:512`
+/// - any of the above followed by `,<millis>`, e.g. `97.5,123456`, to send the
+///   board's own capture time (`millis()`) for accurate timing
+///
+/// Every value is multiplied by `scale` and used as the angle. For a pressure
+/// sensor pick a scale that lands in a sensible range and use position mode.
+pub struct SerialSource {
+    port: String,
+    baud: u32,
+    scale: f32,
+}
+
+impl SerialSource {
+    pub fn new(port: &str, baud: u32, scale: f32) -> Self {
+        Self {
+            port: port.to_string(),
+            baud,
+            scale,
+        }
+    }
+}
+
+impl SensorSource for SerialSource {
+    fn name(&self) -> &str {
+        "serial"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let port_name = self.port.clone();
+        let baud = self.baud;
+        let scale = self.scale;
+
+        async move {
+            let port = serialport::new(&port_name, baud)
+                .timeout(Duration::from_millis(500))
+                .open()
+                .map_err(|source| SensorError::Serial {
+                    port: port_name.clone(),
+                    source,
+                })?;
+
+            // Serial reads block, so they get their own thread.
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<SensorSample>();
+            let source = format!("serial:{port_name}");
+            std::thread::spawn(move || read_lines(port, scale, source, tx));
+
+            let samples = futures_util::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|s| (s, rx))
+            });
+
+            Ok(Connected {
+                status: format!("Reading serial port {port_name} at {baud} baud"),
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+fn read_lines(
+    port: Box<dyn serialport::SerialPort>,
+    scale: f32,
+    source: String,
+    tx: UnboundedSender<SensorSample>,
+) {
+    let mut reader = BufReader::new(port);
+    let mut line = String::new();
+    let mut clock = DeviceClock::new();
+
+    loop {
+        line.clear();
+
+        match reader.read_line(&mut line) {
+            Ok(0) => return, // port closed
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::TimedOut => {
+                // Quiet line: just make sure someone is still listening.
+                if tx.is_closed() {
+                    return;
+                }
+                continue;
+            }
+            Err(_) => return,
+        }
+
+        let received = Instant::now();
+
+        let (value_part, millis) = match line.trim().split_once(',') {
+            Some((v, ms)) => (v, ms.trim().parse::<f64>().ok()),
+            None => (line.as_str(), None),
+        };
+        let Some(value) = parse_line(value_part) else {
+            continue;
+        };
+
+        let t = match millis {
+            Some(ms) => clock.map(ms / 1000.0, received),
+            None => received,
+        };
+
+        let sample = SensorSample {
+            theta_deg: value * scale,
+            source: source.clone(),
+            t,
+        };
+        if tx.send(sample).is_err() {
+            return;
+        }
+    }
+}
+
+/// Parse one protocol line into its number.
+fn parse_line(line: &str) -> Option<f32> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    if let Ok(v) = line.parse::<f32>() {
+        return Some(v);
+    }
+
+    let (key, value) = line.split_once([':', ' ', '='])?;
+    match key.trim().to_ascii_lowercase().as_str() {
+        "a" | "angle" | "p" | "pressure" => value.trim().parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,171 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::warn;
+
+use super::clock::DeviceClock;
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// Served to plain HTTP requests, so a phone browser can open the bellows page
+/// from the same port it then streams to.
+const BELLOWS_PAGE: &str = include_str!("bellows_page.html");
+
+/// One frame sent by the browser page.
+#[derive(Debug, Deserialize)]
+struct AngleFrame {
+    angle_deg: f32,
+
+    /// Capture time on the phone in milliseconds (e.g. `performance.now()`).
+    #[serde(default)]
+    timestamp: Option<f64>,
+}
+
+/// WebSocket server: browsers send `{"angle_deg": 95.2, "timestamp": 1234.5}` frames,
+/// e.g. from the DeviceOrientation API on a phone. Any number of clients may connect.
+pub struct WebSocketSource {
+    port: u16,
+}
+
+impl WebSocketSource {
+    pub fn new(port: u16) -> Self {
+        Self { port }
+    }
+}
+
+impl SensorSource for WebSocketSource {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let port = self.port;
+
+        async move {
+            let listener = TcpListener::bind(("0.0.0.0", port))
+                .await
+                .map_err(|source| SensorError::Listen {
+                    what: "WebSocket clients",
+                    port,
+                    source,
+                })?;
+
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<SensorSample>();
+            tokio::spawn(accept_loop(listener, tx));
+
+            let samples = futures_util::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|s| (s, rx))
+            });
+
+            Ok(Connected {
+                status: format!(
+                    "WebSocket server on port {port}. Open http://<this-mac>:{port}/ on a phone."
+                ),
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+async fn accept_loop(listener: TcpListener, tx: UnboundedSender<SensorSample>) {
+    loop {
+        let Ok((stream, peer)) = listener.accept().await else {
+            continue;
+        };
+
+        // Nobody is listening any more (backend switched): stop serving.
+        if tx.is_closed() {
+            return;
+        }
+
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            // One browser dropping out doesn't stop the others.
+            if let Err(e) = handle_client(stream, peer, tx).await {
+                warn!("websocket sensor: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    tx: UnboundedSender<SensorSample>,
+) -> Result<(), SensorError> {
+    if !is_websocket_upgrade(&stream).await {
+        return serve_page(stream).await;
+    }
+
+    let mut ws = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|source| SensorError::Handshake { peer, source })?;
+
+    // Each phone has its own clock.
+    let mut clock = DeviceClock::new();
+
+    while let Some(msg) = ws.next().await {
+        let msg = msg.map_err(|source| SensorError::Socket { peer, source })?;
+
+        let Message::Text(text) = msg else {
+            continue;
+        };
+
+        // Ignore frames we don't understand rather than dropping the client.
+        let Ok(frame) = serde_json::from_str::<AngleFrame>(&text) else {
+            continue;
+        };
+
+        let received = Instant::now();
+        let t = match frame.timestamp {
+            Some(ms) => clock.map(ms / 1000.0, received),
+            None => received,
+        };
+
+        let sample = SensorSample {
+            theta_deg: frame.angle_deg,
+            source: format!("ws:{peer}"),
+            t,
+        };
+        if tx.send(sample).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Look at (without consuming) the request head to see if it's a WebSocket upgrade.
+async fn is_websocket_upgrade(stream: &TcpStream) -> bool {
+    let mut buf = [0u8; 2048];
+    let Ok(n) = stream.peek(&mut buf).await else {
+        return false;
+    };
+
+    String::from_utf8_lossy(&buf[..n])
+        .to_ascii_lowercase()
+        .contains("upgrade: websocket")
+}
+
+/// Answer a plain HTTP request with the bellows page.
+async fn serve_page(mut stream: TcpStream) -> Result<(), SensorError> {
+    let mut request = [0u8; 2048];
+    let _ = stream.read(&mut request).await;
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{BELLOWS_PAGE}",
+        BELLOWS_PAGE.len()
+    );
+
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .map_err(SensorError::ServePage)
+}
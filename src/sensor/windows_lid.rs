@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use windows::Devices::Sensors::{HingeAngleSensor, Inclinometer};
+
+use super::{Connected, SensorError, SensorSample, SensorSource};
+
+/// The laptop lid on Windows, through the Windows sensor API.
+///
+/// Dual-screen and convertible devices with a hinge angle sensor report the
+/// hinge angle directly. Otherwise the inclinometer does: its pitch is the
+/// tilt of whatever it is mounted in, which on most laptops is the display,
+/// so it follows the lid (off by a constant; zero it in the Sensor panel).
+pub struct WindowsLidSource {
+    hz: f32,
+}
+
+impl WindowsLidSource {
+    pub fn new(hz: f32) -> Self {
+        Self { hz }
+    }
+}
+
+impl SensorSource for WindowsLidSource {
+    fn name(&self) -> &str {
+        "windows"
+    }
+
+    fn connect(&mut self) -> LocalBoxFuture<'_, Result<Connected<'_>, SensorError>> {
+        let period = Duration::from_secs_f32(1.0 / self.hz.max(1.0));
+
+        async move {
+            let lid = LidReader::open()?;
+            let status = format!("Connected. {}", lid.describe());
+            let source = format!("windows:{}", lid.kind());
+
+            let interval = tokio::time::interval(period);
+            let samples = futures_util::stream::unfold(
+                (interval, lid),
+                move |(mut interval, lid)| {
+                    let source = source.clone();
+                    async move {
+                        interval.tick().await;
+
+                        // A failed read (sensor gone) ends the stream; the
+                        // sensor thread reconnects.
+                        let sample = SensorSample {
+                            theta_deg: lid.read()?,
+                            source,
+                            t: Instant::now(),
+                        };
+                        Some((sample, (interval, lid)))
+                    }
+                },
+            );
+
+            Ok(Connected {
+                status,
+                samples: samples.boxed_local(),
+            })
+        }
+        .boxed_local()
+    }
+}
+
+enum LidReader {
+    Hinge(HingeAngleSensor),
+    Inclinometer(Inclinometer),
+}
+
+impl LidReader {
+    /// The hinge sensor if there is one, else the inclinometer.
+    fn open() -> Result<Self, SensorError> {
+        // Both getters fail (rather than return nothing) on a machine without
+        // the sensor.
+        let hinge = HingeAngleSensor::GetDefaultAsync().and_then(|op| op.get());
+        if let Ok(sensor) = hinge {
+            return Ok(LidReader::Hinge(sensor));
+        }
+
+        match Inclinometer::GetDefault() {
+            Ok(sensor) => {
+                let _ = sensor.SetReportInterval(sensor.MinimumReportInterval().unwrap_or(16));
+                Ok(LidReader::Inclinometer(sensor))
+            }
+            Err(e) => Err(SensorError::Lid(format!(
+                "no hinge angle sensor or inclinometer ({})",
+                e.message()
+            ))),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            LidReader::Hinge(_) => "hinge",
+            LidReader::Inclinometer(_) => "inclinometer",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            LidReader::Hinge(_) => "Windows hinge angle sensor".to_string(),
+            LidReader::Inclinometer(_) => "Windows inclinometer (display pitch)".to_string(),
+        }
+    }
+
+    /// The lid angle in degrees, or None if the sensor can't be read.
+    fn read(&self) -> Option<f32> {
+        match self {
+            LidReader::Hinge(sensor) => {
+                let reading = sensor.GetCurrentReadingAsync().ok()?.get().ok()?;
+                reading.AngleInDegrees().ok().map(|a| a as f32)
+            }
+            LidReader::Inclinometer(sensor) => {
+                // Pitch is -180..180 with the display level at 0.
+                let reading = sensor.GetCurrentReading().ok()?;
+                reading.PitchDegrees().ok().map(|p| p + 90.0)
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::preset::Preset;
+
+mod recording;
+
+pub use recording::{
+    DEFAULT_SESSION_RECORDING_PATH, RecordingError, SessionEvent, SessionPlayer, SessionRecorder,
+};
+
+/// Files (next to the settings file) the running session is kept in.
+const SESSION_FILE_NAME: &str = "session.toml";
+const SESSION_KEYMAP_FILE_NAME: &str = "session-keymap.toml";
+
+/// How often the session is written while the app runs.
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Where the session lives: beside `config.toml` in the platform config dir.
+pub fn session_path() -> PathBuf {
+    config::config_path().with_file_name(SESSION_FILE_NAME)
+}
+
+/// Copy of a keymap whose edits could not be written to its own file.
+pub fn session_keymap_path() -> PathBuf {
+    config::config_path().with_file_name(SESSION_KEYMAP_FILE_NAME)
+}
+
+/// What was going on at the last autosave. Deleted on a clean exit, so
+/// finding one at startup means the last run crashed or was force-quit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The instrument setup; its name is the preset that was selected.
+    pub setup: Preset,
+    pub preset_index: Option<usize>,
+
+    /// Set if the keymap had edits its file doesn't have; they are in
+    /// `session_keymap_path()`.
+    pub unsaved_keymap: bool,
+
+    /// MIDI recording that was running, and how long it had been going.
+    pub midi_recording: Option<PathBuf>,
+    pub midi_recording_sec: f32,
+}
+
+impl Session {
+    /// The session left behind by the last run, if it didn't exit cleanly.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read session file: {e}")),
+        };
+
+        toml::from_str(&text)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse session TOML: {e}"))
+    }
+
+    /// Write the session to `path`, creating its folder if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create session folder {dir:?}: {e}"))?;
+        }
+
+        let text =
+            toml::to_string_pretty(self).map_err(|e| format!("Failed to encode session: {e}"))?;
+        fs::write(path, text).map_err(|e| format!("Failed to write session file: {e}"))
+    }
+
+    /// A clean exit: nothing to recover next time.
+    pub fn clear(path: impl AsRef<Path>) -> Result<(), String> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove session file: {e}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
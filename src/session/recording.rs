@@ -0,0 +1,253 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use thiserror::Error;
+
+use crate::events::{Event, NoteEvent, Subscriber};
+use crate::note::NoteName;
+
+/// Where session recordings go unless told otherwise.
+pub const DEFAULT_SESSION_RECORDING_PATH: &str = "session-recording.csv";
+
+/// Bellows changes smaller than this aren't written (keeps files small).
+const BELLOWS_STEP: f32 = 0.002;
+
+/// One thing that happened in a performance.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    NoteOn(NoteName),
+    NoteOff(NoteName),
+
+    /// Bellows output (0..1) from here on.
+    Bellows(f32),
+}
+
+/// A session recording's file couldn't be created or written (disk full,
+/// folder gone).
+#[derive(Debug, Error)]
+#[error("Failed to write session recording {path:?}: {source}")]
+pub struct RecordingError {
+    path: PathBuf,
+    source: io::Error,
+}
+
+/// Writes a performance as it happens, one change per line:
+///
+/// ```text
+/// t_sec,event,value
+/// 0.000000,a,0.000
+/// 0.412345,on,c3
+/// 0.433000,a,0.118
+/// 1.020000,off,c3
+/// ```
+///
+/// Times are relative to the start; bellows lines only when it moves.
+pub struct SessionRecorder {
+    path: PathBuf,
+    out: BufWriter<File>,
+    start: Instant,
+    notes: Vec<NoteName>,
+    last_a: Option<f32>,
+    count: usize,
+}
+
+impl SessionRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let path = path.as_ref().to_path_buf();
+        let header = File::create(&path).map(BufWriter::new).and_then(|mut out| {
+            writeln!(out, "t_sec,event,value")?;
+            Ok(out)
+        });
+        let out = match header {
+            Ok(out) => out,
+            Err(source) => return Err(RecordingError { path, source }),
+        };
+
+        Ok(Self {
+            path,
+            out,
+            start: Instant::now(),
+            notes: Vec::new(),
+            last_a: None,
+            count: 0,
+        })
+    }
+
+    fn write(&mut self, event: &SessionEvent, now: Instant) -> Result<(), RecordingError> {
+        let t_sec = now.saturating_duration_since(self.start).as_secs_f64();
+        let line = match event {
+            SessionEvent::NoteOn(note) => format!("{t_sec:.6},on,{note}"),
+            SessionEvent::NoteOff(note) => format!("{t_sec:.6},off,{note}"),
+            SessionEvent::Bellows(a) => format!("{t_sec:.6},a,{a:.3}"),
+        };
+        writeln!(self.out, "{line}").map_err(|source| self.error(source))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Release any notes still held and flush to disk.
+    pub fn finish(mut self) -> Result<(), RecordingError> {
+        let now = Instant::now();
+        for note in std::mem::take(&mut self.notes) {
+            self.write(&SessionEvent::NoteOff(note), now)?;
+        }
+        self.out.flush().map_err(|source| self.error(source))
+    }
+
+    fn error(&self, source: io::Error) -> RecordingError {
+        RecordingError {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn elapsed_sec(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+}
+
+/// Writes the notes as they start and stop, and the bellows level when it
+/// moves.
+impl Subscriber for SessionRecorder {
+    type Error = RecordingError;
+
+    fn on_event(&mut self, event: &Event, t: Instant) -> Result<(), RecordingError> {
+        let event = match event {
+            Event::Note(NoteEvent::On(note)) => {
+                self.notes.push(note.clone());
+                SessionEvent::NoteOn(note.clone())
+            }
+            Event::Note(NoteEvent::Off(note)) => {
+                self.notes.retain(|n| n != note);
+                SessionEvent::NoteOff(note.clone())
+            }
+            Event::Bellows(b) => {
+                if self.last_a.is_some_and(|last| (b.a - last).abs() < BELLOWS_STEP) {
+                    return Ok(());
+                }
+                self.last_a = Some(b.a);
+                SessionEvent::Bellows(b.a)
+            }
+            _ => return Ok(()),
+        };
+        self.write(&event, t)
+    }
+}
+
+/// Plays a recorded session back with its original timing.
+pub struct SessionPlayer {
+    events: Vec<(f64, SessionEvent)>,
+    next: usize,
+    started: Instant,
+
+    /// Notes held and bellows level as of the last `poll`.
+    notes: Vec<NoteName>,
+    bellows: f32,
+}
+
+impl SessionPlayer {
+    /// Load `path` and start playing it at `now`.
+    pub fn load(path: impl AsRef<Path>, now: Instant) -> Result<Self, String> {
+        Ok(Self {
+            events: load_session(path.as_ref())?,
+            next: 0,
+            started: now,
+            notes: Vec::new(),
+            bellows: 0.0,
+        })
+    }
+
+    /// Events that have come due by `now`, in order.
+    pub fn poll(&mut self, now: Instant) -> Vec<SessionEvent> {
+        let t_sec = now.saturating_duration_since(self.started).as_secs_f64();
+        let due = self.events[self.next..].iter().take_while(|(t, _)| *t <= t_sec).count();
+
+        let events: Vec<SessionEvent> = self.events[self.next..self.next + due]
+            .iter()
+            .map(|(_, e)| e.clone())
+            .collect();
+        self.next += due;
+
+        for event in &events {
+            match event {
+                SessionEvent::NoteOn(note) if !self.notes.contains(note) => {
+                    self.notes.push(note.clone());
+                }
+                SessionEvent::NoteOff(note) => self.notes.retain(|n| n != note),
+                SessionEvent::Bellows(a) => self.bellows = *a,
+                _ => {}
+            }
+        }
+        events
+    }
+
+    pub fn notes(&self) -> &[NoteName] {
+        &self.notes
+    }
+
+    pub fn bellows(&self) -> f32 {
+        self.bellows
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.events.len()
+    }
+
+    /// 0..1 through the recording.
+    pub fn progress(&self, now: Instant) -> f32 {
+        let length = self.events.last().map_or(0.0, |(t, _)| *t);
+        if length <= 0.0 {
+            return 1.0;
+        }
+        let t_sec = now.saturating_duration_since(self.started).as_secs_f64();
+        (t_sec / length).min(1.0) as f32
+    }
+}
+
+/// Read a recording written by `SessionRecorder`.
+fn load_session(path: &Path) -> Result<Vec<(f64, SessionEvent)>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read session recording {path:?}: {e}"))?;
+
+    let mut events = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("t_sec") {
+            continue;
+        }
+
+        let mut fields = line.splitn(3, ',').map(str::trim);
+        let parsed = (|| {
+            let t = fields.next()?.parse::<f64>().ok()?;
+            let event = match (fields.next()?, fields.next()?) {
+                ("on", note) => SessionEvent::NoteOn(note.to_string()),
+                ("off", note) => SessionEvent::NoteOff(note.to_string()),
+                ("a", a) => SessionEvent::Bellows(a.parse().ok()?),
+                _ => return None,
+            };
+            Some((t, event))
+        })();
+
+        match parsed {
+            Some(e) => events.push(e),
+            None => {
+                return Err(format!(
+                    "Bad line {} in session recording {path:?}: '{line}'",
+                    line_no + 1
+                ));
+            }
+        }
+    }
+
+    if events.is_empty() {
+        return Err(format!("Session recording {path:?} has no events"));
+    }
+
+    Ok(events)
+}
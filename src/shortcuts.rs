@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, HashMap};
+
+use egui::Key;
+use serde::{Deserialize, Serialize};
+
+/// Something the app does on a key press instead of playing a note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    StopAll,
+    ToggleSustain,
+    OctaveDown,
+    OctaveUp,
+    PrevPreset,
+    NextPreset,
+    ToggleRecord,
+    PerformanceMode,
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::StopAll,
+        Action::ToggleSustain,
+        Action::OctaveDown,
+        Action::OctaveUp,
+        Action::PrevPreset,
+        Action::NextPreset,
+        Action::ToggleRecord,
+        Action::PerformanceMode,
+        Action::Help,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::StopAll => "stop all notes",
+            Action::ToggleSustain => "toggle sustain",
+            Action::OctaveDown => "octave down",
+            Action::OctaveUp => "octave up",
+            Action::PrevPreset => "previous preset",
+            Action::NextPreset => "next preset",
+            Action::ToggleRecord => "start / stop MIDI recording",
+            Action::PerformanceMode => "performance mode",
+            Action::Help => "keyboard help",
+        }
+    }
+
+    /// Stable name used in the settings file.
+    fn id(self) -> &'static str {
+        match self {
+            Action::StopAll => "stop_all",
+            Action::ToggleSustain => "toggle_sustain",
+            Action::OctaveDown => "octave_down",
+            Action::OctaveUp => "octave_up",
+            Action::PrevPreset => "prev_preset",
+            Action::NextPreset => "next_preset",
+            Action::ToggleRecord => "toggle_record",
+            Action::PerformanceMode => "performance_mode",
+            Action::Help => "help",
+        }
+    }
+
+    fn default_key(self) -> Key {
+        match self {
+            Action::StopAll => Key::Escape,
+            Action::ToggleSustain => Key::Enter,
+            Action::OctaveDown => Key::ArrowLeft,
+            Action::OctaveUp => Key::ArrowRight,
+            Action::PrevPreset => Key::PageUp,
+            Action::NextPreset => Key::PageDown,
+            Action::ToggleRecord => Key::F9,
+            Action::PerformanceMode => Key::F11,
+            Action::Help => Key::F1,
+        }
+    }
+}
+
+/// Which key triggers which action. A bound key never plays a note, so the
+/// UI warns when the keymap also maps it. One key, one action.
+///
+/// Saved with the settings as `action = "KeyName"`; actions missing from the
+/// file get their default key, and an empty name leaves one unbound.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct Shortcuts {
+    keys: HashMap<Action, Key>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL.into_iter().map(|a| (a, a.default_key())).collect(),
+        }
+    }
+}
+
+impl Shortcuts {
+    pub fn key_for(&self, action: Action) -> Option<Key> {
+        self.keys.get(&action).copied()
+    }
+
+    pub fn action_for(&self, key: Key) -> Option<Action> {
+        self.keys.iter().find(|(_, k)| **k == key).map(|(a, _)| *a)
+    }
+
+    /// Bind `key` to `action`, taking it away from any other action.
+    pub fn bind(&mut self, action: Action, key: Key) {
+        self.keys.retain(|_, k| *k != key);
+        self.keys.insert(action, key);
+    }
+
+    pub fn unbind(&mut self, action: Action) {
+        self.keys.remove(&action);
+    }
+}
+
+impl From<BTreeMap<String, String>> for Shortcuts {
+    fn from(raw: BTreeMap<String, String>) -> Self {
+        let mut shortcuts = Self::default();
+        for action in Action::ALL {
+            // Unknown key names (e.g. from a newer egui) keep the default.
+            match raw.get(action.id()).map(String::as_str) {
+                Some("") => shortcuts.unbind(action),
+                Some(name) => {
+                    if let Some(key) = Key::from_name(name) {
+                        shortcuts.bind(action, key);
+                    }
+                }
+                None => {}
+            }
+        }
+        shortcuts
+    }
+}
+
+impl From<Shortcuts> for BTreeMap<String, String> {
+    fn from(shortcuts: Shortcuts) -> Self {
+        Action::ALL
+            .into_iter()
+            .map(|a| {
+                let name = shortcuts.key_for(a).map_or("", |k| k.name());
+                (a.id().to_string(), name.to_string())
+            })
+            .collect()
+    }
+}
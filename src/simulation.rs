@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use crate::bellows::{BellowsOutput, BellowsParams, BellowsState};
+
+/// A scripted lid-angle trajectory (angle as a function of time).
+///
+/// Used for the fake input and for replaying the bellows pipeline offline,
+/// where every run with the same params gives exactly the same output.
+#[derive(Debug, Clone)]
+pub enum Trajectory {
+    /// Smooth back-and-forth pumping.
+    Sine {
+        center_deg: f32,
+        amplitude_deg: f32,
+        frequency_hz: f32,
+    },
+
+    /// Constant-speed pumping (triangle wave), like a steady hand.
+    Pump {
+        center_deg: f32,
+        amplitude_deg: f32,
+        period_sec: f32,
+    },
+
+    /// Keyframes (seconds, degrees), linearly interpolated.
+    /// Holds the first/last angle outside the keyframe range.
+    Keyframes(Vec<(f32, f32)>),
+}
+
+impl Trajectory {
+    /// Lid angle (deg) at `t_sec` seconds into the trajectory.
+    pub fn angle_at(&self, t_sec: f32) -> f32 {
+        match self {
+            Trajectory::Sine {
+                center_deg,
+                amplitude_deg,
+                frequency_hz,
+            } => {
+                center_deg
+                    + amplitude_deg * (2.0 * std::f32::consts::PI * frequency_hz * t_sec).sin()
+            }
+
+            Trajectory::Pump {
+                center_deg,
+                amplitude_deg,
+                period_sec,
+            } => {
+                if *period_sec <= 0.0 {
+                    return *center_deg;
+                }
+
+                // Phase 0..1 -> triangle -1..1..-1
+                let phase = (t_sec / period_sec).rem_euclid(1.0);
+                let tri = 1.0 - 4.0 * (phase - 0.5).abs();
+                center_deg + amplitude_deg * tri
+            }
+
+            Trajectory::Keyframes(keys) => sample_at(keys, t_sec),
+        }
+    }
+}
+
+/// A short scripted stroke: two quick pumps over one second, then the lid stays
+/// still. Good for seeing how fast the sound builds and how long it lingers.
+pub fn pump_then_still(amplitude_deg: f32) -> Trajectory {
+    Trajectory::Keyframes(vec![
+        (0.0, 0.0),
+        (0.25, amplitude_deg),
+        (0.5, 0.0),
+        (0.75, amplitude_deg),
+        (1.0, 0.0),
+    ])
+}
+
+/// Run the bellows pipeline over a trajectory at a fixed sample rate.
+///
+/// Starts from a fresh `BellowsState` built from `params`, so the result only
+/// depends on the inputs.
+pub fn simulate(
+    params: &BellowsParams,
+    trajectory: &Trajectory,
+    rate_hz: f32,
+    duration_sec: f32,
+) -> Vec<BellowsOutput> {
+    let rate_hz = rate_hz.max(1.0);
+    let steps = (duration_sec.max(0.0) * rate_hz).round() as usize;
+
+    let mut state = BellowsState::new(params.clone());
+    let mut outputs = Vec::with_capacity(steps + 1);
+
+    for i in 0..=steps {
+        let t_sec = i as f32 / rate_hz;
+        let theta = trajectory.angle_at(t_sec);
+        outputs.push(state.update(theta, Duration::from_secs_f32(t_sec)));
+    }
+
+    outputs
+}
+
+/// A few numbers that describe how a simulated run "felt".
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimSummary {
+    pub peak_a: f32,
+    pub mean_a: f32,
+
+    /// First time the amplitude reached half of its peak (seconds).
+    pub time_to_half_peak_sec: Option<f32>,
+}
+
+pub fn summarize(outputs: &[BellowsOutput]) -> SimSummary {
+    if outputs.is_empty() {
+        return SimSummary::default();
+    }
+
+    let peak_a = outputs.iter().map(|o| o.a).fold(0.0, f32::max);
+    let mean_a = outputs.iter().map(|o| o.a).sum::<f32>() / outputs.len() as f32;
+
+    let mut elapsed = 0.0;
+    let mut time_to_half_peak_sec = None;
+    for o in outputs {
+        elapsed += o.dt_sec;
+        if peak_a > 0.0 && o.a >= 0.5 * peak_a {
+            time_to_half_peak_sec = Some(elapsed);
+            break;
+        }
+    }
+
+    SimSummary {
+        peak_a,
+        mean_a,
+        time_to_half_peak_sec,
+    }
+}
+
+/// Linear interpolation into (time, value) keyframes sorted by time, holding
+/// the first/last value outside them (0 with none). Scripted trajectories and
+/// recorded bellows gestures both play back through this.
+pub fn sample_at(keys: &[(f32, f32)], t: f32) -> f32 {
+    let Some(first) = keys.first() else {
+        return 0.0;
+    };
+
+    let idx = keys.partition_point(|(kt, _)| *kt <= t);
+    if idx == 0 {
+        return first.1;
+    }
+    if idx >= keys.len() {
+        return keys[keys.len() - 1].1;
+    }
+
+    let (t0, a0) = keys[idx - 1];
+    let (t1, a1) = keys[idx];
+    if t1 - t0 <= 0.0 {
+        return a1;
+    }
+
+    a0 + (a1 - a0) * (t - t0) / (t1 - t0)
+}
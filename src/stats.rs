@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::events::{Event, NoteEvent, Subscriber};
+use crate::note::NoteName;
+
+/// File (next to the settings file) the lifetime totals are kept in.
+const STATS_FILE_NAME: &str = "stats.toml";
+
+/// Bellows levels are counted in this many equal bins over 0..1.
+pub const BELLOWS_BINS: usize = 10;
+
+/// Where the lifetime totals live: beside `config.toml`.
+pub fn stats_path() -> PathBuf {
+    config::config_path().with_file_name(STATS_FILE_NAME)
+}
+
+/// Totals for one session or for every session so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PracticeStats {
+    /// Sessions counted (lifetime only).
+    pub sessions: u32,
+
+    /// Seconds with at least one note sounding.
+    pub played_sec: f64,
+
+    /// Notes started.
+    pub note_count: u64,
+
+    /// Seconds played at each bellows level, in `BELLOWS_BINS` bins.
+    pub bellows_sec: Vec<f64>,
+
+    /// How often each note was started.
+    pub notes: BTreeMap<NoteName, u64>,
+}
+
+impl PracticeStats {
+    /// The lifetime totals saved so far; none yet if there is no file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Failed to read stats file: {e}")),
+        };
+
+        toml::from_str(&text).map_err(|e| format!("Failed to parse stats TOML: {e}"))
+    }
+
+    /// Write the totals to `path`, creating its folder if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create stats folder {dir:?}: {e}"))?;
+        }
+
+        let text =
+            toml::to_string_pretty(self).map_err(|e| format!("Failed to encode stats: {e}"))?;
+        fs::write(path, text).map_err(|e| format!("Failed to write stats file: {e}"))
+    }
+
+    pub fn notes_per_minute(&self) -> f64 {
+        if self.played_sec < 1.0 {
+            return 0.0;
+        }
+        self.note_count as f64 * 60.0 / self.played_sec
+    }
+
+    /// The `n` notes played most, most first.
+    pub fn top_notes(&self, n: usize) -> Vec<(&NoteName, u64)> {
+        let mut notes: Vec<(&NoteName, u64)> = self.notes.iter().map(|(k, v)| (k, *v)).collect();
+        notes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        notes.truncate(n);
+        notes
+    }
+
+    /// Seconds per bellows bin, always `BELLOWS_BINS` long.
+    pub fn bellows_histogram(&self) -> Vec<f64> {
+        let mut bins = self.bellows_sec.clone();
+        bins.resize(BELLOWS_BINS, 0.0);
+        bins
+    }
+
+    /// These totals with `other`'s added on.
+    pub fn merged(&self, other: &PracticeStats) -> PracticeStats {
+        let mut sum = self.clone();
+        sum.sessions += other.sessions;
+        sum.played_sec += other.played_sec;
+        sum.note_count += other.note_count;
+        sum.bellows_sec = self
+            .bellows_histogram()
+            .iter()
+            .zip(other.bellows_histogram())
+            .map(|(a, b)| a + b)
+            .collect();
+        for (note, count) in &other.notes {
+            *sum.notes.entry(note.clone()).or_insert(0) += count;
+        }
+        sum
+    }
+}
+
+/// Counts the running session, on top of the lifetime totals it started from.
+#[derive(Debug, Clone)]
+pub struct StatsTracker {
+    /// Lifetime totals as loaded, before this session.
+    pub before: PracticeStats,
+    pub session: PracticeStats,
+    sounding: Vec<NoteName>,
+}
+
+impl StatsTracker {
+    pub fn new(before: PracticeStats) -> Self {
+        Self {
+            before,
+            session: PracticeStats {
+                sessions: 1,
+                ..Default::default()
+            },
+            sounding: Vec::new(),
+        }
+    }
+
+    /// Lifetime totals including this session (what gets saved). A session
+    /// that played nothing isn't counted.
+    pub fn lifetime(&self) -> PracticeStats {
+        if self.session.played_sec <= 0.0 {
+            return self.before.clone();
+        }
+        self.before.merged(&self.session)
+    }
+
+    /// Forget every total, this session's included.
+    pub fn reset(&mut self) {
+        *self = Self::new(PracticeStats::default());
+    }
+}
+
+/// Counts each note as it starts, and time played (while something sounds)
+/// by bellows level, frame by frame.
+impl Subscriber for StatsTracker {
+    type Error = Infallible;
+
+    fn on_event(&mut self, event: &Event, _t: Instant) -> Result<(), Infallible> {
+        match event {
+            Event::Note(NoteEvent::On(note)) => {
+                self.session.note_count += 1;
+                *self.session.notes.entry(note.clone()).or_insert(0) += 1;
+                self.sounding.push(note.clone());
+            }
+            Event::Note(NoteEvent::Off(note)) => self.sounding.retain(|n| n != note),
+            Event::Bellows(b) if !self.sounding.is_empty() => {
+                let dt = b.dt_sec as f64;
+                self.session.played_sec += dt;
+                let bin =
+                    ((b.a.clamp(0.0, 1.0) * BELLOWS_BINS as f32) as usize).min(BELLOWS_BINS - 1);
+                self.session.bellows_sec.resize(BELLOWS_BINS, 0.0);
+                self.session.bellows_sec[bin] += dt;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
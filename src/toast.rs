@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long a toast stays up unless it is dismissed first.
+const TOAST_LIFETIME: Duration = Duration::from_secs(6);
+
+/// At most this many on screen; the oldest makes room.
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl ToastLevel {
+    fn color(self) -> egui::Color32 {
+        match self {
+            ToastLevel::Info => egui::Color32::LIGHT_BLUE,
+            ToastLevel::Warning => egui::Color32::YELLOW,
+            ToastLevel::Error => egui::Color32::RED,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Toast {
+    level: ToastLevel,
+    text: String,
+    shown_at: Instant,
+}
+
+/// Transient problems (a missing sample, a failed reload, a device that went
+/// away) shown briefly in a corner instead of as a status line that sticks.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(ToastLevel::Info, text.into());
+    }
+
+    pub fn warn(&mut self, text: impl Into<String>) {
+        self.push(ToastLevel::Warning, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(ToastLevel::Error, text.into());
+    }
+
+    /// The same message again (e.g. the same missing note pressed twice)
+    /// restarts its timer instead of stacking a copy.
+    fn push(&mut self, level: ToastLevel, text: String) {
+        let now = Instant::now();
+        if let Some(t) = self.queue.iter_mut().find(|t| t.text == text) {
+            t.shown_at = now;
+            return;
+        }
+
+        if self.queue.len() == MAX_TOASTS {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(Toast {
+            level,
+            text,
+            shown_at: now,
+        });
+    }
+
+    /// Draw the live toasts in the bottom-right corner, newest at the bottom.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.queue.retain(|t| t.shown_at.elapsed() < TOAST_LIFETIME);
+        if self.queue.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+            .show(ctx, |ui| {
+                for (i, toast) in self.queue.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(360.0);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(toast.level.color(), &toast.text);
+                            if ui.small_button("✕").clicked() {
+                                dismissed = Some(i);
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some(i) = dismissed {
+            self.queue.remove(i);
+        }
+    }
+}
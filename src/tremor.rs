@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// What a lid tremor does to the held notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VibratoTarget {
+    /// Tremor detection off.
+    Off,
+
+    /// The notes' pitch wavers with the lid.
+    Pitch,
+
+    /// Their volume does (the tremolo of a shaking bellows hand).
+    Amplitude,
+}
+
+/// Settings for the lid tremor and the vibrato it plays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TremorParams {
+    pub vibrato: VibratoTarget,
+
+    /// Oscillation rates (Hz) that count as a tremor. Pumping is slower.
+    pub min_hz: f32,
+    pub max_hz: f32,
+
+    /// Swing (deg, from the middle) a tremor must have: more than the sensor
+    /// jitter, less than a pumping stroke.
+    pub min_deg: f32,
+    pub max_deg: f32,
+
+    /// Pitch vibrato at full tremor, cents either way.
+    pub depth_cents: f32,
+
+    /// Amplitude vibrato at full tremor, as a share of the volume (0..1).
+    pub depth_gain: f32,
+}
+
+impl Default for TremorParams {
+    fn default() -> Self {
+        Self {
+            vibrato: VibratoTarget::Off,
+            min_hz: 3.0,
+            max_hz: 10.0,
+            min_deg: 0.3,
+            max_deg: 4.0,
+            depth_cents: 25.0,
+            depth_gain: 0.15,
+        }
+    }
+}
+
+/// Half periods kept for the rate estimate.
+const HALF_PERIODS: usize = 4;
+
+/// How fast the tremor level fades in and out (seconds).
+const ATTACK_SEC: f32 = 0.08;
+const RELEASE_SEC: f32 = 0.2;
+
+/// How long the swing peak is remembered (seconds).
+const PEAK_DECAY_SEC: f32 = 0.15;
+
+/// Finds a small, fast oscillation of the lid on top of whatever it is
+/// doing: a shaking hand rather than a pumping stroke.
+///
+/// The angle minus a slow baseline is the wobble; its zero crossings give the
+/// rate and its recent peak the swing. Only the rates and swings of
+/// [`TremorParams`] count: pumping is slower and wider, sensor jitter
+/// narrower.
+#[derive(Debug, Clone, Default)]
+pub struct TremorDetector {
+    baseline: Option<f32>,
+    prev_dev: f32,
+    since_crossing_sec: f32,
+    half_periods: VecDeque<f32>,
+    peak_deg: f32,
+    level: f32,
+}
+
+impl TremorDetector {
+    /// Feed one angle `dt_sec` after the last; returns the wobble, -1..1,
+    /// scaled by how surely it is a tremor (0 when it isn't one).
+    pub fn update(&mut self, theta_deg: f32, dt_sec: f32, params: &TremorParams) -> f32 {
+        let baseline = self.baseline.get_or_insert(theta_deg);
+        // A baseline well below the slowest tremor lets the tremor through.
+        let cutoff_hz = (params.min_hz * 0.5).max(0.1);
+        let step = 1.0 - (-std::f32::consts::TAU * cutoff_hz * dt_sec).exp();
+        *baseline += (theta_deg - *baseline) * step;
+        let dev = theta_deg - *baseline;
+
+        self.peak_deg = dev.abs().max(self.peak_deg * (-dt_sec / PEAK_DECAY_SEC).exp());
+
+        self.since_crossing_sec += dt_sec;
+        if dev != 0.0 && self.prev_dev != 0.0 && dev.signum() != self.prev_dev.signum() {
+            self.half_periods.push_back(self.since_crossing_sec);
+            if self.half_periods.len() > HALF_PERIODS {
+                self.half_periods.pop_front();
+            }
+            self.since_crossing_sec = 0.0;
+        }
+        if dev != 0.0 {
+            self.prev_dev = dev;
+        }
+        // Far too long since the last crossing: whatever was there has stopped.
+        if params.min_hz > 0.0 && self.since_crossing_sec > 1.5 / (2.0 * params.min_hz) {
+            self.half_periods.clear();
+        }
+
+        let present = self.rate_hz().is_some_and(|hz| hz >= params.min_hz && hz <= params.max_hz)
+            && self.peak_deg >= params.min_deg
+            && self.peak_deg <= params.max_deg;
+        let target = if present { 1.0 } else { 0.0 };
+        let tau = if target > self.level { ATTACK_SEC } else { RELEASE_SEC };
+        self.level += (target - self.level) * (1.0 - (-dt_sec / tau).exp());
+
+        if self.peak_deg <= f32::EPSILON {
+            return 0.0;
+        }
+        (dev / self.peak_deg).clamp(-1.0, 1.0) * self.level
+    }
+
+    /// The oscillation rate from the last few zero crossings, once there
+    /// are enough of them.
+    pub fn rate_hz(&self) -> Option<f32> {
+        if self.half_periods.len() < HALF_PERIODS - 1 {
+            return None;
+        }
+        let mean = self.half_periods.iter().sum::<f32>() / self.half_periods.len() as f32;
+        (mean > 0.0).then(|| 1.0 / (2.0 * mean))
+    }
+
+    /// How surely a tremor is going on, 0..1.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,60 @@
+/// Lowest and highest fundamentals looked for (a bit beyond the sample range).
+const MIN_HZ: f32 = 50.0;
+const MAX_HZ: f32 = 2000.0;
+
+/// YIN threshold: the first dip of the normalized difference below this is
+/// taken as the period. Lower is stricter.
+const THRESHOLD: f32 = 0.15;
+
+/// The fundamental frequency of `frames` (mono, `sample_rate` Hz), by the YIN
+/// method. None for silence or if no clear period is found.
+pub fn detect_fundamental(frames: &[f32], sample_rate: u32) -> Option<f32> {
+    let rate = sample_rate as f32;
+    let min_lag = (rate / MAX_HZ).floor().max(2.0) as usize;
+    let max_lag = ((rate / MIN_HZ).ceil() as usize).min(frames.len() / 2);
+    if max_lag <= min_lag || frames.iter().all(|s| s.abs() < 1e-4) {
+        return None;
+    }
+
+    // Difference function d(τ) over a window of half the frames.
+    let window = frames.len() - max_lag;
+    let diff: Vec<f32> = (0..=max_lag)
+        .map(|lag| {
+            (0..window)
+                .map(|i| {
+                    let d = frames[i] - frames[i + lag];
+                    d * d
+                })
+                .sum()
+        })
+        .collect();
+
+    // Cumulative mean normalized difference d'(τ).
+    let mut cmnd = vec![1.0; diff.len()];
+    let mut running = 0.0;
+    for lag in 1..diff.len() {
+        running += diff[lag];
+        cmnd[lag] = if running > 0.0 { diff[lag] * lag as f32 / running } else { 1.0 };
+    }
+
+    // First dip under the threshold, followed down to its bottom.
+    let mut lag = (min_lag..=max_lag).find(|&l| cmnd[l] < THRESHOLD)?;
+    while lag < max_lag && cmnd[lag + 1] < cmnd[lag] {
+        lag += 1;
+    }
+
+    // Parabolic interpolation between the neighbouring lags.
+    let period = if lag > min_lag && lag < max_lag {
+        let (a, b, c) = (cmnd[lag - 1], cmnd[lag], cmnd[lag + 1]);
+        let denom = a - 2.0 * b + c;
+        if denom.abs() > f32::EPSILON {
+            lag as f32 + 0.5 * (a - c) / denom
+        } else {
+            lag as f32
+        }
+    } else {
+        lag as f32
+    };
+
+    Some(rate / period)
+}
@@ -0,0 +1,68 @@
+/// A note's sample decoded into memory: interleaved frames at the file's
+/// own rate. Shared by every voice that plays it.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub rate: u32,
+}
+
+impl Sample {
+    pub fn new(samples: Vec<f32>, channels: usize, rate: u32) -> Self {
+        Self {
+            samples,
+            channels: channels.max(1),
+            rate,
+        }
+    }
+
+    /// Length in frames.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    /// The sample mixed down to one channel.
+    pub fn to_mono(&self) -> Sample {
+        let channels = self.channels.max(1);
+        let samples = self
+            .samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        Sample::new(samples, 1, self.rate)
+    }
+}
+
+/// Where a voice is in its sample, which it loops for as long as the note
+/// is held. The app's voices and the plugin's both read through this, so
+/// they loop and resample alike.
+#[derive(Debug, Clone, Default)]
+pub struct LoopReader {
+    /// Read position, in frames.
+    pos: f64,
+}
+
+impl LoopReader {
+    /// Write the frame at the read position into `out` (a sample per
+    /// channel, interpolated linearly between the frames either side), then
+    /// move on by `step` frames: the playback speed against the sample's
+    /// rate. Past the last frame it goes round to the first.
+    pub fn read(&mut self, sample: &Sample, step: f64, out: &mut [f32]) {
+        let len = sample.frames();
+        if len == 0 {
+            out.fill(0.0);
+            return;
+        }
+
+        let i = self.pos as usize;
+        let frac = (self.pos - i as f64) as f32;
+        let channels = sample.channels.max(1);
+        let a = &sample.samples[(i % len) * channels..][..channels];
+        let b = &sample.samples[((i + 1) % len) * channels..][..channels];
+        for (c, out) in out.iter_mut().enumerate() {
+            let c = c.min(channels - 1);
+            *out = a[c] + (b[c] - a[c]) * frac;
+        }
+        self.pos = (self.pos + step.max(0.0)) % len as f64;
+    }
+}
@@ -0,0 +1,41 @@
+/// The pages of the first-run setup, in order. Each one fixes one of the
+/// things a fresh install is missing, so the last ends on a playable
+/// instrument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardStep {
+    Samples,
+    Keymap,
+    Calibrate,
+    TestAudio,
+}
+
+impl WizardStep {
+    pub const ALL: [WizardStep; 4] = [
+        WizardStep::Samples,
+        WizardStep::Keymap,
+        WizardStep::Calibrate,
+        WizardStep::TestAudio,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            WizardStep::Samples => "Sample pack",
+            WizardStep::Keymap => "Keymap",
+            WizardStep::Calibrate => "Bellows calibration",
+            WizardStep::TestAudio => "Test the sound",
+        }
+    }
+
+    /// 1-based position, for "step 2 of 4".
+    pub fn number(self) -> usize {
+        Self::ALL.iter().position(|s| *s == self).map_or(0, |i| i + 1)
+    }
+
+    pub fn next(self) -> Option<Self> {
+        Self::ALL.get(self.number()).copied()
+    }
+
+    pub fn prev(self) -> Option<Self> {
+        self.number().checked_sub(2).map(|i| Self::ALL[i])
+    }
+}
@@ -0,0 +1,43 @@
+//! Reed speech: a note starts flat under little air and settles into pitch.
+
+use std::sync::Arc;
+
+use harmonium_core::pitch_bend::{AttackScoop, BendHandle, BendSource};
+use harmonium_core::voice::Sample;
+
+const RATE: u32 = 1000;
+
+/// Play a ramp (sample n = n) through a voice's resampler: each output
+/// sample is how far into the sample the voice has got.
+fn positions(scoop_semitones: f32) -> Vec<f32> {
+    let ramp: Vec<f32> = (0..1000).map(|n| n as f32).collect();
+    let sample = Arc::new(Sample::new(ramp, 1, RATE));
+    BendSource::new(sample, BendHandle::default())
+        .with_scoop(scoop_semitones, 100.0)
+        .take(600)
+        .collect()
+}
+
+#[test]
+fn the_scoop_is_deepest_at_rest_and_gone_at_full_air() {
+    let scoop = AttackScoop {
+        depth_cents: 40.0,
+        time_ms: 80.0,
+    };
+    assert_eq!(scoop.semitones(0.0), -0.4);
+    assert_eq!(scoop.semitones(0.5), -0.2);
+    assert_eq!(scoop.semitones(1.0), 0.0);
+    assert_eq!(AttackScoop::default().semitones(0.0), 0.0);
+}
+
+#[test]
+fn a_scooped_voice_starts_slow_then_plays_at_pitch() {
+    let plain = positions(0.0);
+    let scooped = positions(-1.0);
+
+    assert_eq!(plain[50], 50.0);
+    assert!(scooped[50] < 49.0, "{}", scooped[50]);
+
+    // Settled: a frame of output per frame of sample again.
+    assert!((scooped[500] - scooped[400] - 100.0).abs() < 0.01);
+}
@@ -0,0 +1,390 @@
+//! The audio engine against a capture output: what each voice's track is
+//! told to do, with no sound card involved.
+
+use std::path::PathBuf;
+
+use harmonium_core::audio::{self, AudioEngine, AudioError, CaptureSink, CapturedTrack};
+use harmonium_core::harmonizer::Harmonizer;
+use harmonium_core::scale::ScaleLock;
+
+fn samples() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("harmonium-sounds")
+}
+
+fn engine() -> (AudioEngine, CaptureSink) {
+    let output = CaptureSink::default();
+    let engine = AudioEngine::with_output(samples(), Box::new(output.clone()));
+    (engine, output)
+}
+
+fn assert_volume(track: CapturedTrack, expected: f32) {
+    assert!(
+        (track.volume - expected).abs() < 1e-6,
+        "volume {} (expected {expected})",
+        track.volume
+    );
+}
+
+#[test]
+fn note_on_opens_a_silent_playing_track() {
+    let (mut engine, output) = engine();
+    engine.note_on("c3").unwrap();
+
+    assert_eq!(engine.voice_count(), 1);
+    let tracks = output.tracks();
+    assert_eq!(tracks.len(), 1);
+    assert!(!tracks[0].paused && !tracks[0].stopped);
+    // No air yet.
+    assert_volume(tracks[0], 0.0);
+}
+
+#[test]
+fn note_off_stops_its_track() {
+    let (mut engine, output) = engine();
+    engine.note_on("c3").unwrap();
+    engine.note_on("e3").unwrap();
+    engine.note_off("c3");
+
+    assert_eq!(engine.voice_count(), 1);
+    assert!(output.tracks()[0].stopped);
+    assert_eq!(output.live_tracks().len(), 1);
+    assert_eq!(engine.voice_level("c3"), None);
+}
+
+#[test]
+fn a_sounding_note_played_again_keeps_its_voice() {
+    let (mut engine, output) = engine();
+    engine.note_on_voiced("c3", 0.5, 0.0).unwrap();
+    engine.note_on_voiced("c3", 1.0, 20.0).unwrap();
+
+    assert_eq!(output.tracks().len(), 1);
+    assert_eq!(engine.voice_cents("c3"), Some(0.0));
+}
+
+#[test]
+fn a_missing_sample_is_an_error_and_no_track() {
+    let (mut engine, output) = engine();
+    let err = engine.note_on("c9").unwrap_err();
+
+    assert!(matches!(err, AudioError::MissingSample { .. }));
+    assert!(err.is_missing_file());
+    assert!(output.tracks().is_empty());
+    assert_eq!(engine.voice_count(), 0);
+}
+
+#[test]
+fn volume_is_master_times_bellows_times_gain() {
+    let (mut engine, output) = engine();
+    engine.note_on_voiced("c3", 1.0, 0.0).unwrap();
+    engine.note_on_voiced("e3", 0.5, 0.0).unwrap();
+
+    engine.set_master_gain(0.8);
+    engine.set_bellows(0.5);
+    let tracks = output.tracks();
+    assert_volume(tracks[0], 0.4);
+    assert_volume(tracks[1], 0.2);
+
+    // A note started afterwards joins at the current level.
+    engine.note_on_voiced("g3", 0.25, 0.0).unwrap();
+    assert_volume(output.tracks()[2], 0.1);
+}
+
+#[test]
+fn bellows_and_master_are_clamped() {
+    let (mut engine, output) = engine();
+    engine.note_on_voiced("c3", 2.0, 0.0).unwrap();
+
+    engine.set_bellows(1.5);
+    engine.set_master_gain(5.0);
+    // Bellows stops at 1 and master at 2; the track at 2.
+    assert_volume(output.tracks()[0], 2.0);
+
+    engine.set_bellows(-1.0);
+    assert_volume(output.tracks()[0], 0.0);
+}
+
+#[test]
+fn volume_fades_with_the_bellows() {
+    let (mut engine, output) = engine();
+    engine.set_master_gain(1.0);
+    engine.note_on("c3").unwrap();
+
+    for step in 0..=10 {
+        let a = 1.0 - step as f32 / 10.0;
+        engine.set_bellows(a);
+        assert_volume(output.tracks()[0], a);
+    }
+    // Faded out, not stopped: more air brings it straight back.
+    assert!(!output.tracks()[0].stopped);
+    engine.set_bellows(0.3);
+    assert_volume(output.tracks()[0], 0.3);
+}
+
+#[test]
+fn inaudible_notes_keep_playing_silently() {
+    let (mut engine, output) = engine();
+    engine.set_master_gain(1.0);
+    engine.set_bellows(1.0);
+    engine.note_on("c3").unwrap();
+    engine.note_on("e3").unwrap();
+
+    engine.set_audible(Some(&["e3".to_string()]));
+    let tracks = output.tracks();
+    assert_volume(tracks[0], 0.0);
+    assert_volume(tracks[1], 1.0);
+    assert!(!tracks[0].stopped);
+
+    engine.set_audible(None);
+    assert_volume(output.tracks()[0], 1.0);
+    assert_eq!(output.tracks().len(), 2);
+}
+
+#[test]
+fn sustain_holds_released_notes_until_pedal_up() {
+    let (mut engine, output) = engine();
+    engine.note_on("c3").unwrap();
+    engine.set_sustain(true);
+    engine.note_off("c3");
+
+    assert_eq!(engine.voice_count(), 1);
+    assert_eq!(output.live_tracks().len(), 1);
+
+    engine.set_sustain(false);
+    assert_eq!(engine.voice_count(), 0);
+    assert!(output.tracks()[0].stopped);
+}
+
+#[test]
+fn a_sustained_note_played_again_is_held_again() {
+    let (mut engine, output) = engine();
+    engine.set_sustain(true);
+    engine.note_on("c3").unwrap();
+    engine.note_off("c3");
+    engine.note_on("c3").unwrap();
+    engine.set_sustain(false);
+
+    // Same voice, still sounding after pedal-up.
+    assert_eq!(output.tracks().len(), 1);
+    assert!(!output.tracks()[0].stopped);
+}
+
+#[test]
+fn a_bank_switch_replaces_every_voice() {
+    let (mut engine, output) = engine();
+    engine.set_master_gain(1.0);
+    engine.set_bellows(1.0);
+    engine.note_on_voiced("c3", 0.5, 10.0).unwrap();
+    engine.note_on_voiced("e3", 1.0, 0.0).unwrap();
+
+    engine.set_samples_dir(samples()).unwrap();
+
+    let tracks = output.tracks();
+    assert_eq!(tracks.len(), 4);
+    assert!(tracks[..2].iter().all(|t| t.stopped));
+    assert_eq!(output.live_tracks().len(), 2);
+    assert_eq!(engine.voice_count(), 2);
+    // The new voices keep their voicing.
+    assert_eq!(engine.voice_cents("c3"), Some(10.0));
+    let mut volumes: Vec<f32> = output.live_tracks().iter().map(|t| t.volume).collect();
+    volumes.sort_by(f32::total_cmp);
+    assert_eq!(volumes, [0.5, 1.0]);
+}
+
+#[test]
+fn stop_all_stops_every_track() {
+    let (mut engine, output) = engine();
+    engine.set_sustain(true);
+    engine.chord_on(&["c3".into(), "e3".into(), "g3".into()], 1.0, 0.0).unwrap();
+    engine.chord_off(&["e3".into()]);
+
+    engine.stop_all();
+    assert_eq!(engine.voice_count(), 0);
+    assert!(output.live_tracks().is_empty());
+
+    // Nothing was left waiting for pedal-up.
+    engine.note_on("e3").unwrap();
+    engine.set_sustain(false);
+    assert_eq!(output.live_tracks().len(), 1);
+}
+
+#[test]
+fn past_the_voice_limit_the_oldest_note_is_stolen() {
+    let (mut engine, output) = engine();
+    engine.set_max_voices(2);
+    engine.note_on("c3").unwrap();
+    engine.note_on("e3").unwrap();
+    engine.note_on("g3").unwrap();
+
+    assert_eq!(engine.voice_count(), 2);
+    assert_eq!(engine.voice_level("c3"), None);
+    let tracks = output.tracks();
+    assert!(tracks[0].stopped);
+    assert!(!tracks[1].stopped && !tracks[2].stopped);
+    assert_eq!(output.live_tracks().len(), 2);
+
+    // Played again, c3 is the newest; e3 goes next.
+    engine.note_on("c3").unwrap();
+    assert_eq!(engine.voice_level("e3"), None);
+    assert_eq!(output.live_tracks().len(), 2);
+}
+
+#[test]
+fn a_sustained_note_can_be_stolen_and_isnt_released_twice() {
+    let (mut engine, output) = engine();
+    engine.set_max_voices(1);
+    engine.set_sustain(true);
+    engine.note_on("c3").unwrap();
+    engine.note_off("c3");
+    engine.note_on("e3").unwrap();
+    engine.set_sustain(false);
+
+    // c3 was cut by e3, which is still held after pedal-up.
+    assert_eq!(engine.voice_count(), 1);
+    assert_eq!(output.live_tracks().len(), 1);
+    assert!(engine.voice_level("e3").is_some());
+}
+
+#[test]
+fn lowering_the_voice_limit_steals_at_once() {
+    let (mut engine, output) = engine();
+    engine.chord_on(&["c3".into(), "e3".into(), "g3".into()], 1.0, 0.0).unwrap();
+    engine.set_max_voices(1);
+
+    assert_eq!(engine.voice_count(), 1);
+    assert!(engine.voice_level("g3").is_some());
+    assert_eq!(output.live_tracks().len(), 1);
+}
+
+#[test]
+fn a_panic_lets_go_of_everything_and_fades_it_out() {
+    let (mut engine, output) = engine();
+    engine.set_sustain(true);
+    engine.chord_on(&["c3".into(), "e3".into()], 1.0, 0.0).unwrap();
+    engine.note_off("e3");
+
+    engine.panic();
+    assert_eq!(engine.voice_count(), 0);
+    // Fading on the audio thread: not cut off.
+    assert_eq!(output.live_tracks().len(), 2);
+    assert!(!engine.finish_panic().unwrap());
+
+    // The pedal was let go too: a new note stops when released.
+    engine.note_on("g3").unwrap();
+    engine.note_off("g3");
+    assert_eq!(engine.voice_count(), 0);
+}
+
+#[test]
+fn voices_still_going_after_a_panic_are_stopped_and_the_output_reopened() {
+    let (mut engine, output) = engine();
+    engine.note_on("c3").unwrap();
+    engine.panic();
+
+    // Nothing plays a capture's tracks, so the fade never ends: stuck.
+    std::thread::sleep(std::time::Duration::from_millis(400));
+    assert!(engine.finish_panic().unwrap());
+    assert!(output.live_tracks().is_empty());
+    assert_eq!(output.reopened(), 1);
+    assert!(!engine.finish_panic().unwrap());
+}
+
+#[test]
+fn suspend_pauses_and_resume_plays() {
+    let (mut engine, output) = engine();
+    engine.note_on("c3").unwrap();
+    engine.suspend();
+
+    assert!(engine.is_suspended());
+    assert!(output.tracks()[0].paused);
+    // Started while suspended: it waits with the others.
+    engine.note_on("e3").unwrap();
+    assert!(output.tracks()[1].paused);
+
+    engine.resume();
+    assert!(output.tracks().iter().all(|t| !t.paused));
+    assert_eq!(engine.voice_level("c3"), Some(0.0));
+}
+
+#[test]
+fn the_practice_track_plays_under_the_master_volume_and_ducks() {
+    let (mut engine, output) = engine();
+    let path = harmonium_core::bank::sample_path(&samples(), "c3").unwrap();
+    engine.set_master_gain(1.0);
+    engine.set_practice_volume(0.5);
+    engine.play_practice(&path, false).unwrap();
+    assert!(engine.practice_playing());
+    assert_volume(output.tracks()[0], 0.5);
+
+    engine.set_practice_duck(0.4);
+    assert_volume(output.tracks()[0], 0.2);
+
+    // Paused by the player: the lid reopening doesn't start it again.
+    engine.pause_practice(true);
+    engine.suspend();
+    engine.resume();
+    assert!(output.tracks()[0].paused);
+
+    engine.stop_practice();
+    assert!(!engine.practice_playing());
+    assert!(output.live_tracks().is_empty());
+}
+
+#[test]
+fn notes_play_from_preloaded_samples() {
+    let (mut engine, output) = engine();
+    let path = harmonium_core::bank::sample_path(&samples(), "c3").unwrap();
+    engine.preload(path.clone(), audio::load_sample(&path).unwrap());
+    engine.note_on("c3").unwrap();
+    engine.note_on("e3").unwrap();
+
+    // e3 was read at its first note, and is kept too.
+    assert_eq!(engine.preloaded_count(), 2);
+    assert_eq!(output.tracks().len(), 2);
+}
+
+#[test]
+fn loading_a_file_that_isnt_audio_is_a_decode_error() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert!(matches!(audio::load_sample(&path), Err(AudioError::Decode { .. })));
+}
+
+#[test]
+fn retuned_notes_past_the_bank_borrow_a_neighbour_sample() {
+    let (mut engine, output) = engine();
+    assert!(engine.note_on("f5").is_err());
+
+    let scale = ScaleLock {
+        retune: true,
+        tonic: 2,
+        ..ScaleLock::default()
+    };
+    engine.set_harmonizer(&Harmonizer::default(), &scale);
+    // d5 is the top sample: f5 is it, three semitones up.
+    engine.note_on("f5").unwrap();
+    assert_eq!(output.tracks().len(), 1);
+    assert!(engine.note_on("c7").is_err());
+}
+
+#[test]
+fn a_glide_moves_the_voice_without_a_new_track() {
+    let (mut engine, output) = engine();
+    engine.note_on("c3").unwrap();
+    engine.glide("c3", "e3", 100.0).unwrap();
+
+    assert_eq!(output.tracks().len(), 1);
+    assert!(!output.tracks()[0].stopped);
+    assert_eq!(engine.voice_cents("e3"), Some(0.0));
+    assert_eq!(engine.voice_cents("c3"), None);
+
+    engine.note_off("e3");
+    assert!(output.tracks()[0].stopped);
+}
+
+#[test]
+fn a_glide_without_a_voice_starts_the_note() {
+    let (mut engine, output) = engine();
+    engine.glide("c3", "e3", 100.0).unwrap();
+
+    assert_eq!(output.tracks().len(), 1);
+    assert_eq!(engine.voice_count(), 1);
+}
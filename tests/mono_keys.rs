@@ -0,0 +1,32 @@
+//! Mono (legato) keys: which note sounds as keys go down and up.
+
+use harmonium_core::keymap::{KeyId, MonoKeys, MonoMove};
+
+fn glide(from: &str, to: &str) -> MonoMove {
+    MonoMove::Glide {
+        from: from.to_string(),
+        to: to.to_string(),
+    }
+}
+
+#[test]
+fn a_second_key_glides_and_letting_go_glides_back() {
+    let mut mono = MonoKeys::default();
+    assert_eq!(mono.key_down(KeyId::Char('z'), "c3".into()), MonoMove::Start("c3".into()));
+    assert_eq!(mono.key_down(KeyId::Char('x'), "d3".into()), glide("c3", "d3"));
+    assert_eq!(mono.key_up(KeyId::Char('x')), glide("d3", "c3"));
+    assert_eq!(mono.key_up(KeyId::Char('z')), MonoMove::Stop("c3".into()));
+    assert_eq!(mono.sounding(), None);
+}
+
+#[test]
+fn letting_go_of_a_key_that_isnt_sounding_changes_nothing() {
+    let mut mono = MonoKeys::default();
+    mono.key_down(KeyId::Char('z'), "c3".into());
+    mono.key_down(KeyId::Char('x'), "d3".into());
+    mono.key_down(KeyId::Char('c'), "e3".into());
+
+    assert_eq!(mono.key_up(KeyId::Char('x')), MonoMove::Nothing);
+    assert_eq!(mono.key_up(KeyId::Char('c')), glide("e3", "c3"));
+    assert_eq!(mono.key_down(KeyId::Char('z'), "c3".into()), MonoMove::Nothing);
+}
@@ -0,0 +1,84 @@
+//! Count-in and punch points on the metronome grid.
+
+use std::time::{Duration, Instant};
+
+use harmonium_core::metronome::{Metronome, MetronomeConfig, Tala};
+use harmonium_core::punch::{Punch, PunchConfig, PunchStage};
+
+/// Teental at 60 BPM: a beat a second, sam every 16.
+fn metronome(now: Instant) -> Metronome {
+    let mut metronome = Metronome::new(MetronomeConfig {
+        bpm: 60.0,
+        tala: Tala::Teental,
+        ..MetronomeConfig::default()
+    });
+    metronome.start(now);
+    metronome
+}
+
+fn config(count_in_beats: u32, punch: bool) -> PunchConfig {
+    PunchConfig {
+        count_in_beats,
+        punch,
+        punch_in_cycles: 0,
+        punch_length_cycles: 1,
+    }
+}
+
+#[test]
+fn no_count_in_records_at_once() {
+    let now = Instant::now();
+    let punch = Punch::arm(&metronome(now), &config(0, false), now);
+    assert_eq!(punch.stage(0.0), PunchStage::Recording);
+    assert_eq!(punch.stage(1000.0), PunchStage::Recording);
+}
+
+#[test]
+fn count_in_starts_on_the_next_beat() {
+    let start = Instant::now();
+    let armed = start + Duration::from_millis(500);
+    let punch = Punch::arm(&metronome(start), &config(4, false), armed);
+
+    // Half a beat to the next one, then four: never more than four shown.
+    assert_eq!(punch.stage(0.5), PunchStage::CountIn(4));
+    assert_eq!(punch.stage(1.0), PunchStage::CountIn(4));
+    assert_eq!(punch.stage(4.5), PunchStage::CountIn(1));
+    assert_eq!(punch.stage(5.0), PunchStage::Recording);
+}
+
+#[test]
+fn count_in_ending_on_sam_punches_in_there() {
+    let now = Instant::now();
+    let punch = Punch::arm(&metronome(now), &config(16, true), now);
+    assert_eq!(punch.stage(15.5), PunchStage::CountIn(1));
+    assert_eq!(punch.stage(16.0), PunchStage::Recording);
+    assert_eq!(punch.stage(31.9), PunchStage::Recording);
+    assert_eq!(punch.stage(32.0), PunchStage::Done);
+}
+
+#[test]
+fn punch_in_waits_for_the_next_sam() {
+    let now = Instant::now();
+    let config = PunchConfig {
+        punch_in_cycles: 1,
+        ..config(4, true)
+    };
+    let punch = Punch::arm(&metronome(now), &config, now);
+    assert_eq!(punch.stage(3.0), PunchStage::CountIn(1));
+    assert_eq!(punch.stage(4.0), PunchStage::Waiting);
+    assert_eq!(punch.stage(16.0), PunchStage::Waiting);
+    assert_eq!(punch.stage(32.0), PunchStage::Recording);
+    assert_eq!(punch.stage(48.0), PunchStage::Done);
+}
+
+#[test]
+fn zero_length_punch_records_until_stopped() {
+    let now = Instant::now();
+    let config = PunchConfig {
+        punch_length_cycles: 0,
+        ..config(0, true)
+    };
+    let punch = Punch::arm(&metronome(now), &config, now);
+    assert_eq!(punch.stage(0.0), PunchStage::Recording);
+    assert_eq!(punch.stage(10_000.0), PunchStage::Recording);
+}
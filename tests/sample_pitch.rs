@@ -0,0 +1,54 @@
+//! Pitch checks on the bundled samples: the analysis has to agree with the
+//! names they were given.
+
+use std::path::{Path, PathBuf};
+
+use harmonium_core::audio;
+use harmonium_core::note::Note;
+use harmonium_core::sample_pitch::{self, PitchCheck};
+
+fn sample(note: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("harmonium-sounds/{note}.wav"))
+}
+
+fn note(name: &str) -> Note {
+    Note::parse(name).unwrap()
+}
+
+#[test]
+fn bundled_samples_match_their_names() {
+    for name in ["c3", "g3", "c4"] {
+        let path = sample(name);
+        let bytes = audio::load_sample(&path).unwrap();
+        assert_eq!(sample_pitch::analyse(&path, &bytes), PitchCheck::Matches, "{name}");
+    }
+}
+
+#[test]
+fn a_sample_under_another_name_is_mislabeled() {
+    let bytes = audio::load_sample(&sample("c3")).unwrap();
+    let check = sample_pitch::analyse(Path::new("e3.wav"), &bytes);
+    assert!(matches!(check, PitchCheck::Mislabeled { named, .. } if named == note("e3")));
+}
+
+#[test]
+fn an_unnamed_sample_gets_the_note_it_sounds() {
+    let bytes = audio::load_sample(&sample("g3")).unwrap();
+    let check = sample_pitch::analyse(Path::new("take 7.wav"), &bytes);
+    let PitchCheck::Unlabeled { detected, .. } = check else {
+        panic!("{check:?}");
+    };
+    assert_eq!(detected.pitch_class(), note("g3").pitch_class());
+}
+
+#[test]
+fn the_best_tuned_file_wins_a_note() {
+    let map = sample_pitch::note_map([
+        (PathBuf::from("a.wav"), note("c3"), 30.0),
+        (PathBuf::from("b.wav"), note("c3"), -8.0),
+        (PathBuf::from("c.wav"), note("d3"), 12.0),
+    ]);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["c3"], PathBuf::from("b.wav"));
+    assert_eq!(map["d3"], PathBuf::from("c.wav"));
+}
@@ -0,0 +1,35 @@
+//! Sensor slots: a restarted listener gets its port back.
+
+use std::time::{Duration, Instant};
+
+use harmonium_core::sensor::{SensorConfig, SensorKind, SensorSlot};
+
+/// Drain `slot` until its backend reports it is listening, or fails.
+fn wait_until_listening(slot: &mut SensorSlot) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        slot.drain();
+        if let Some(e) = &slot.error {
+            panic!("{e}");
+        }
+        if slot.status.starts_with("Listening") {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("still '{}'", slot.status);
+}
+
+#[test]
+fn a_restarted_osc_listener_binds_the_same_port() {
+    let cfg = SensorConfig {
+        osc_port: 47_301,
+        ..SensorConfig::default()
+    };
+    let mut slot = SensorSlot::start(SensorKind::Osc, &cfg);
+    wait_until_listening(&mut slot);
+
+    // Nothing is sent to the old socket, so only a stop signal frees it.
+    slot.restart(&cfg);
+    wait_until_listening(&mut slot);
+}
@@ -0,0 +1,108 @@
+//! Scripted lid trajectories through `BellowsState::update`, offline.
+
+use std::time::Duration;
+
+use harmonium_core::bellows::{BellowsParams, BellowsState, VelocityEstimator};
+use harmonium_core::simulation::{self, Trajectory};
+
+const RATE_HZ: f32 = 100.0;
+
+/// Steady pumping: 20° either way once a second, 80°/s (past `vmax`).
+fn steady_pump() -> Trajectory {
+    Trajectory::Pump {
+        center_deg: 90.0,
+        amplitude_deg: 20.0,
+        period_sec: 1.0,
+    }
+}
+
+/// The bellows level at each sample of a run.
+fn levels(trajectory: &Trajectory, duration_sec: f32) -> Vec<f32> {
+    simulation::simulate(&BellowsParams::default(), trajectory, RATE_HZ, duration_sec)
+        .iter()
+        .map(|o| o.a)
+        .collect()
+}
+
+#[test]
+fn the_same_run_gives_the_same_output() {
+    assert_eq!(levels(&steady_pump(), 3.0), levels(&steady_pump(), 3.0));
+}
+
+#[test]
+fn steady_pumping_builds_air_within_the_attack() {
+    let outputs = simulation::simulate(&BellowsParams::default(), &steady_pump(), RATE_HZ, 3.0);
+    let summary = simulation::summarize(&outputs);
+    assert!(summary.peak_a > 0.8, "peak {}", summary.peak_a);
+    assert!(summary.mean_a > 0.5, "mean {}", summary.mean_a);
+    let half = summary.time_to_half_peak_sec.expect("never reached half the peak");
+    assert!(half < 0.5, "half peak after {half}s");
+}
+
+#[test]
+fn a_still_lid_stays_silent() {
+    let still = Trajectory::Keyframes(vec![(0.0, 90.0)]);
+    let outputs = simulation::simulate(&BellowsParams::default(), &still, RATE_HZ, 2.0);
+    let summary = simulation::summarize(&outputs);
+    assert_eq!(summary.peak_a, 0.0);
+    assert_eq!(summary.time_to_half_peak_sec, None);
+}
+
+#[test]
+fn the_sound_dies_away_once_the_lid_stops() {
+    let trajectory = simulation::pump_then_still(30.0);
+    let outputs = simulation::simulate(&BellowsParams::default(), &trajectory, RATE_HZ, 3.0);
+    let summary = simulation::summarize(&outputs);
+    assert!(summary.peak_a > 0.5, "peak {}", summary.peak_a);
+
+    let last = outputs.last().unwrap().a;
+    assert!(last < 0.1 * summary.peak_a, "still {last} two seconds after stopping");
+}
+
+#[test]
+fn switching_the_velocity_estimator_mid_stroke_doesnt_burst() {
+    // 10° either way once a second: 40°/s, well inside the range.
+    let pump = Trajectory::Pump {
+        center_deg: 90.0,
+        amplitude_deg: 10.0,
+        period_sec: 1.0,
+    };
+    let mut state = BellowsState::new(BellowsParams::default());
+    let run = |state: &mut BellowsState, from: usize, to: usize| {
+        (from..to)
+            .map(|i| {
+                let t = i as f32 / RATE_HZ;
+                state.update(pump.angle_at(t), Duration::from_secs_f32(t))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let settled = run(&mut state, 0, 300);
+    let loudest = settled[100..].iter().map(|o| o.a).fold(0.0, f32::max);
+    let fastest = settled[100..].iter().map(|o| o.speed_smooth).fold(0.0, f32::max);
+
+    for (estimator, from) in [
+        (VelocityEstimator::AlphaBeta, 300),
+        (VelocityEstimator::FiniteDifference, 500),
+    ] {
+        state.params.estimator = estimator;
+        for out in run(&mut state, from, from + 200) {
+            assert!(
+                out.speed_smooth < 1.5 * fastest,
+                "{estimator:?}: {}°/s",
+                out.speed_smooth
+            );
+            assert!(out.a < loudest + 0.1, "{estimator:?}: level {}", out.a);
+        }
+    }
+}
+
+#[test]
+fn keyframes_interpolate_and_hold_their_ends() {
+    let keys = [(0.0, 10.0), (1.0, 20.0), (1.0, 30.0), (2.0, 40.0)];
+    assert_eq!(simulation::sample_at(&keys, -1.0), 10.0);
+    assert_eq!(simulation::sample_at(&keys, 0.5), 15.0);
+    assert_eq!(simulation::sample_at(&keys, 1.5), 35.0);
+    assert_eq!(simulation::sample_at(&keys, 5.0), 40.0);
+    assert_eq!(simulation::sample_at(&[], 1.0), 0.0);
+}
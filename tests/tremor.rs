@@ -0,0 +1,65 @@
+//! Lid tremor: a small fast shake is vibrato, pumping and jitter aren't.
+
+use std::time::Duration;
+
+use harmonium_core::bellows::{BellowsParams, BellowsState};
+use harmonium_core::tremor::{TremorDetector, TremorParams, VibratoTarget};
+
+const RATE_HZ: f32 = 100.0;
+
+/// Feed two seconds of `angle` at the sensor rate; the largest wobble of
+/// the second half.
+fn strongest_wobble(angle: impl Fn(f32) -> f32) -> f32 {
+    let params = TremorParams {
+        vibrato: VibratoTarget::Pitch,
+        ..TremorParams::default()
+    };
+    let mut detector = TremorDetector::default();
+    let mut strongest = 0.0_f32;
+    for i in 0..200 {
+        let wobble = detector.update(angle(i as f32 / RATE_HZ), 1.0 / RATE_HZ, &params);
+        if i > 100 {
+            strongest = strongest.max(wobble.abs());
+        }
+    }
+    strongest
+}
+
+fn sine(center: f32, amplitude: f32, hz: f32) -> impl Fn(f32) -> f32 {
+    move |t| center + amplitude * (std::f32::consts::TAU * hz * t).sin()
+}
+
+#[test]
+fn a_small_fast_shake_is_a_tremor() {
+    assert!(strongest_wobble(sine(90.0, 1.5, 6.0)) > 0.9);
+}
+
+#[test]
+fn pumping_strokes_are_not() {
+    assert_eq!(strongest_wobble(sine(90.0, 20.0, 1.0)), 0.0);
+}
+
+#[test]
+fn sensor_jitter_and_a_still_lid_are_not() {
+    let jitter = |t: f32| if (t * RATE_HZ).round() as i32 % 2 == 0 { 89.95 } else { 90.05 };
+    assert_eq!(strongest_wobble(jitter), 0.0);
+    assert_eq!(strongest_wobble(|_| 90.0), 0.0);
+}
+
+#[test]
+fn the_bellows_reports_vibrato_only_when_it_is_on() {
+    let shake = sine(90.0, 1.5, 6.0);
+    let run = |vibrato| {
+        let mut params = BellowsParams::default();
+        params.tremor.vibrato = vibrato;
+        let mut bellows = BellowsState::new(params);
+        (0..200)
+            .map(|i| {
+                let t = i as f32 / RATE_HZ;
+                bellows.update(shake(t), Duration::from_secs_f32(t)).vibrato.abs()
+            })
+            .fold(0.0_f32, f32::max)
+    };
+    assert!(run(VibratoTarget::Amplitude) > 0.9);
+    assert_eq!(run(VibratoTarget::Off), 0.0);
+}
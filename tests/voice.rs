@@ -0,0 +1,39 @@
+//! The loop every voice reads its sample through, app and plugin alike.
+
+use harmonium_core::voice::{LoopReader, Sample};
+
+fn read(sample: &Sample, step: f64, count: usize) -> Vec<f32> {
+    let mut reader = LoopReader::default();
+    let mut out = [0.0];
+    (0..count)
+        .map(|_| {
+            reader.read(sample, step, &mut out);
+            out[0]
+        })
+        .collect()
+}
+
+#[test]
+fn at_the_sample_rate_it_plays_the_frames_then_loops() {
+    let sample = Sample::new(vec![0.0, 1.0, 2.0, 3.0], 1, 4);
+    assert_eq!(read(&sample, 1.0, 6), [0.0, 1.0, 2.0, 3.0, 0.0, 1.0]);
+}
+
+#[test]
+fn between_frames_it_interpolates_and_wraps_to_the_start() {
+    let sample = Sample::new(vec![0.0, 2.0, 4.0, 6.0], 1, 4);
+    assert_eq!(read(&sample, 0.5, 8), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 3.0]);
+}
+
+#[test]
+fn stereo_mixes_down_to_the_mean() {
+    let sample = Sample::new(vec![1.0, 3.0, -1.0, 1.0], 2, 4);
+    let mono = sample.to_mono();
+    assert_eq!((mono.channels, mono.frames()), (1, 2));
+    assert_eq!(mono.samples, [2.0, 0.0]);
+}
+
+#[test]
+fn an_empty_sample_is_silence() {
+    assert_eq!(read(&Sample::default(), 1.0, 3), [0.0; 3]);
+}
@@ -0,0 +1,319 @@
+use std::time::Duration;
+
+use harmonium_core::bellows::{BellowsMode, BellowsParams, BellowsState};
+use harmonium_core::keymap::{KeyId, KeyMap, KeyMods, Latch, PhysicalKey, PressedKeys};
+use harmonium_core::note::{Note, NoteName};
+
+use crate::orientation::{self, Orientation};
+use crate::web_audio::WebAudio;
+
+/// Served next to the page by trunk (see index.html).
+const SAMPLES_URL: &str = "harmonium-sounds";
+
+/// The on-screen keyboard: two octaves from C3.
+const SCREEN_LOW: u8 = 48;
+const SCREEN_KEYS: u8 = 25;
+
+/// The instrument in a browser tab: keys (on screen or the computer's),
+/// bellows from the phone's tilt (or a slider), sound through Web Audio.
+pub struct WebApp {
+    audio: Option<WebAudio>,
+    audio_error: Option<String>,
+
+    orientation: Option<Orientation>,
+    /// An orientation event has arrived: the phone is the bellows.
+    phone: bool,
+
+    bellows: BellowsState,
+    theta_deg: f32,
+    a: f32,
+    master_gain: f32,
+
+    keymap: KeyMap,
+    pressed: PressedKeys,
+    /// Taps latch notes on and off, for chords with one finger.
+    sticky: bool,
+    /// On-screen keys held down by the pointer last frame.
+    screen_held: Option<u8>,
+}
+
+impl WebApp {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        let keymap = KeyMap::builtin();
+
+        let (audio, audio_error) = match WebAudio::new() {
+            Ok(audio) => {
+                let mut notes = keymap.all_notes();
+                notes.extend(screen_notes().into_iter().map(|(_, n)| n));
+                notes.sort();
+                notes.dedup();
+                audio.load(SAMPLES_URL, &notes);
+                (Some(audio), None)
+            }
+            Err(e) => (None, Some(format!("Web Audio unavailable: {e:?}"))),
+        };
+
+        Self {
+            audio,
+            audio_error,
+            orientation: Orientation::listen(),
+            phone: false,
+            bellows: BellowsState::new(BellowsParams::default()),
+            theta_deg: 90.0,
+            a: 0.0,
+            master_gain: 0.8,
+            keymap,
+            pressed: PressedKeys::new(),
+            sticky: false,
+            screen_held: None,
+        }
+    }
+
+    fn notes_on(&mut self, notes: &[NoteName]) {
+        if let Some(audio) = &mut self.audio {
+            for note in notes {
+                audio.note_on(note);
+            }
+        }
+    }
+
+    fn notes_off(&mut self, notes: &[NoteName]) {
+        if let Some(audio) = &mut self.audio {
+            for note in notes {
+                audio.note_off(note);
+            }
+        }
+    }
+
+    /// A key went down: start its notes, or toggle them when sticky.
+    fn press(&mut self, id: KeyId, notes: Vec<NoteName>) {
+        let notes = if self.sticky {
+            match self.pressed.toggle_latch(id, notes) {
+                Latch::On(notes) => notes,
+                Latch::Off(notes) => {
+                    self.notes_off(&notes);
+                    return;
+                }
+            }
+        } else {
+            self.pressed.key_down(id, notes)
+        };
+        self.notes_on(&notes);
+    }
+
+    fn release(&mut self, id: KeyId) {
+        let notes = self.pressed.key_up(id);
+        self.notes_off(&notes);
+    }
+
+    fn stop_all(&mut self) {
+        self.pressed = PressedKeys::new();
+        if let Some(audio) = &mut self.audio {
+            audio.stop_all();
+        }
+    }
+
+    /// The computer keyboard, through the same keymap as the desktop app.
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        let events = ctx.input(|i| i.events.clone());
+        for event in events {
+            let egui::Event::Key {
+                key,
+                physical_key,
+                pressed,
+                repeat: false,
+                modifiers,
+            } = event
+            else {
+                continue;
+            };
+
+            let ch = key_char(key);
+            let physical = physical_key.and_then(|k| PhysicalKey::from_name(k.name()));
+            if pressed {
+                let Some(id) = self.keymap.resolve(ch, physical) else {
+                    continue;
+                };
+                let mods = KeyMods {
+                    shift: modifiers.shift,
+                    command: modifiers.ctrl || modifiers.command,
+                };
+                let notes = self.keymap.notes_for_key(id, mods.octave_offset());
+                self.press(id, notes);
+            } else {
+                // Release whichever id the press was held under.
+                let ids = [physical.map(KeyId::Physical), ch.map(KeyId::Char)];
+                for id in ids.into_iter().flatten() {
+                    self.release(id);
+                }
+            }
+        }
+    }
+
+    fn update_bellows(&mut self, ctx: &egui::Context) {
+        if let Some(theta) = self.orientation.as_ref().and_then(Orientation::take) {
+            self.phone = true;
+            self.theta_deg = theta;
+        }
+
+        let t = Duration::from_secs_f64(ctx.input(|i| i.time));
+        self.a = self.bellows.update(self.theta_deg, t).a;
+        if let Some(audio) = &mut self.audio {
+            audio.set_master_gain(self.master_gain);
+            audio.set_bellows(self.a);
+        }
+    }
+
+    /// Browsers keep audio (and, on iOS, the tilt) off until a tap.
+    fn ui_start(&mut self, ui: &mut egui::Ui) -> bool {
+        let Some(audio) = &self.audio else {
+            return true;
+        };
+        if audio.is_running() {
+            return true;
+        }
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 3.0);
+            let start = egui::Button::new(egui::RichText::new("Tap to start").size(32.0));
+            if ui.add(start).clicked() {
+                audio.resume();
+                orientation::request_permission();
+            }
+            ui.label("Then tilt the phone back and forth to pump the bellows.");
+        });
+        false
+    }
+
+    fn ui_status(&mut self, ui: &mut egui::Ui) {
+        if let Some(e) = &self.audio_error {
+            ui.colored_label(egui::Color32::RED, e);
+        }
+        let stop = ui.horizontal_wrapped(|ui| {
+            if let Some(audio) = &self.audio {
+                let (loaded, failed) = audio.loaded();
+                ui.label(format!("samples: {loaded}"));
+                if failed > 0 {
+                    ui.colored_label(egui::Color32::YELLOW, format!("({failed} missing)"));
+                }
+                ui.label(format!("voices: {}", audio.voice_count()));
+            }
+            ui.separator();
+            ui.add(egui::Slider::new(&mut self.master_gain, 0.0..=1.5).text("volume"));
+            ui.checkbox(&mut self.sticky, "hold notes");
+            ui.button("Stop all").clicked()
+        });
+        if stop.inner {
+            self.stop_all();
+        }
+    }
+
+    fn ui_bellows(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("air");
+            ui.add(egui::ProgressBar::new(self.a).desired_width(ui.available_width()));
+        });
+
+        if self.phone {
+            ui.label(format!("Phone tilt: {:.0}°", self.theta_deg));
+        } else {
+            // No tilt sensor (a laptop's browser): drag the lid by hand.
+            ui.horizontal(|ui| {
+                ui.label("lid");
+                let slider = egui::Slider::new(&mut self.theta_deg, 0.0..=180.0).suffix("°");
+                ui.add_sized([ui.available_width(), 24.0], slider);
+            });
+            ui.label("No tilt sensor: drag the lid slider back and forth to pump.");
+        }
+
+        let params = &mut self.bellows.params;
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut params.mode, BellowsMode::Velocity, "pump");
+            ui.radio_value(&mut params.mode, BellowsMode::Position, "hold");
+        });
+    }
+
+    /// Two octaves of touch keys. A tap plays while held, or toggles when
+    /// sticky.
+    fn ui_keyboard(&mut self, ui: &mut egui::Ui) {
+        let keys = screen_notes();
+        let per_row = if ui.available_width() < 600.0 { 13 } else { keys.len() };
+        let width = ui.available_width() / per_row as f32 - ui.spacing().item_spacing.x;
+        let height = (ui.available_height() / 2.0).clamp(48.0, 160.0);
+
+        let active = self.pressed.active_notes();
+        let mut held = None;
+        for row in keys.chunks(per_row) {
+            ui.horizontal(|ui| {
+                for (midi, name) in row {
+                    let white = Note::from_midi(i32::from(*midi)).is_some_and(|n| n.is_white());
+                    let fill = match (active.contains(name), white) {
+                        (true, _) => egui::Color32::from_rgb(230, 160, 0),
+                        (false, true) => egui::Color32::from_gray(220),
+                        (false, false) => egui::Color32::from_gray(40),
+                    };
+                    let text = if white { egui::Color32::BLACK } else { egui::Color32::WHITE };
+                    let key = egui::Button::new(egui::RichText::new(name.as_str()).color(text))
+                        .fill(fill)
+                        .sense(egui::Sense::click_and_drag());
+                    let response = ui.add_sized([width, height], key);
+                    if response.is_pointer_button_down_on() {
+                        held = Some(*midi);
+                    }
+                }
+            });
+        }
+
+        if held != self.screen_held {
+            if let Some(midi) = self.screen_held.take() {
+                if !self.sticky {
+                    self.release(KeyId::OnScreen(midi));
+                }
+            }
+            if let Some(midi) = held {
+                let name = keys.iter().find(|(m, _)| *m == midi).map(|(_, n)| n.clone());
+                self.press(KeyId::OnScreen(midi), name.into_iter().collect());
+            }
+            self.screen_held = held;
+        }
+    }
+}
+
+impl eframe::App for WebApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.update_bellows(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if !self.ui_start(ui) {
+                return;
+            }
+            self.handle_keys(ctx);
+
+            ui.heading("Harmonium");
+            self.ui_status(ui);
+            ui.separator();
+            self.ui_bellows(ui);
+            ui.separator();
+            self.ui_keyboard(ui);
+        });
+
+        // The bellows moves every frame.
+        ctx.request_repaint();
+    }
+}
+
+/// The on-screen keys, lowest first, by MIDI note.
+fn screen_notes() -> Vec<(u8, NoteName)> {
+    (SCREEN_LOW..SCREEN_LOW + SCREEN_KEYS)
+        .filter_map(|midi| Some((midi, Note::from_midi(i32::from(midi))?.name())))
+        .collect()
+}
+
+/// The character a letter or digit key types.
+fn key_char(key: egui::Key) -> Option<char> {
+    let mut chars = key.name().chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }
+}
@@ -0,0 +1,61 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::Closure;
+use web_sys::DeviceOrientationEvent;
+
+/// The phone's front/back tilt (DeviceOrientation `beta`, -180..180°), used
+/// as the lid angle: flat on the table is 0°, upright is 90°, the same as
+/// the desktop app's phone page.
+pub struct Orientation {
+    latest: Rc<Cell<Option<f32>>>,
+    _listener: Closure<dyn FnMut(DeviceOrientationEvent)>,
+}
+
+impl Orientation {
+    /// Start listening. Desktop browsers accept the listener but never call
+    /// it, so `take` just stays None there.
+    pub fn listen() -> Option<Self> {
+        let latest = Rc::new(Cell::new(None));
+        let sink = Rc::clone(&latest);
+        let listener = Closure::<dyn FnMut(DeviceOrientationEvent)>::new(
+            move |e: DeviceOrientationEvent| {
+                if let Some(beta) = e.beta() {
+                    sink.set(Some(beta as f32));
+                }
+            },
+        );
+
+        let callback = listener.as_ref().unchecked_ref();
+        web_sys::window()?
+            .add_event_listener_with_callback("deviceorientation", callback)
+            .ok()?;
+
+        Some(Self {
+            latest,
+            _listener: listener,
+        })
+    }
+
+    /// The newest angle since the last call, if one arrived.
+    pub fn take(&self) -> Option<f32> {
+        self.latest.take()
+    }
+}
+
+/// iOS only sends orientation events once the page asks, from a tap. Other
+/// browsers don't have `requestPermission` and need nothing.
+pub fn request_permission() {
+    let global = js_sys::global();
+    let Ok(event) = js_sys::Reflect::get(&global, &"DeviceOrientationEvent".into()) else {
+        return;
+    };
+    let Ok(request) = js_sys::Reflect::get(&event, &"requestPermission".into()) else {
+        return;
+    };
+    if let Some(request) = request.dyn_ref::<js_sys::Function>() {
+        // The answer arrives as events (or not); nothing to wait for.
+        let _ = request.call0(&event);
+    }
+}
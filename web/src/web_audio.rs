@@ -0,0 +1,189 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use harmonium_core::note::NoteName;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioBuffer, AudioBufferSourceNode, AudioContext, AudioContextState, AudioScheduledSourceNode,
+    GainNode, Response,
+};
+
+/// Time constant of volume changes, in seconds: quick enough to follow the
+/// bellows, slow enough not to click.
+const SMOOTHING_SEC: f64 = 0.015;
+
+/// How long a released note takes to fade out before it is stopped.
+const RELEASE_SEC: f64 = 0.08;
+
+/// The browser's side of `AudioEngine`: one looping buffer per held note,
+/// all through a master gain that follows master volume × bellows.
+pub struct WebAudio {
+    ctx: AudioContext,
+    master: GainNode,
+
+    /// Decoded samples, filled in as they arrive, and how many failed.
+    buffers: Rc<RefCell<HashMap<NoteName, AudioBuffer>>>,
+    failed: Rc<Cell<usize>>,
+
+    voices: HashMap<NoteName, Voice>,
+    master_gain: f32,
+    bellows_a: f32,
+}
+
+/// One sounding note.
+struct Voice {
+    source: AudioBufferSourceNode,
+    gain: GainNode,
+}
+
+impl WebAudio {
+    pub fn new() -> Result<Self, JsValue> {
+        let ctx = AudioContext::new()?;
+        let master = ctx.create_gain()?;
+        master.gain().set_value(0.0);
+        master.connect_with_audio_node(&ctx.destination())?;
+
+        Ok(Self {
+            ctx,
+            master,
+            buffers: Rc::default(),
+            failed: Rc::default(),
+            voices: HashMap::new(),
+            master_gain: 0.8,
+            bellows_a: 0.0,
+        })
+    }
+
+    /// Fetch and decode `{base_url}/{note}.wav` for every note, in the
+    /// background.
+    pub fn load(&self, base_url: &str, notes: &[NoteName]) {
+        for note in notes {
+            // `#` starts a fragment in a URL.
+            let url = format!("{base_url}/{}.wav", note.replace('#', "%23"));
+            let ctx = self.ctx.clone();
+            let buffers = Rc::clone(&self.buffers);
+            let failed = Rc::clone(&self.failed);
+            let note = note.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match fetch_buffer(&ctx, &url).await {
+                    Ok(buffer) => {
+                        buffers.borrow_mut().insert(note, buffer);
+                    }
+                    Err(e) => {
+                        web_sys::console::warn_2(&format!("can't load {url}:").into(), &e);
+                        failed.set(failed.get() + 1);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Samples decoded so far, and samples that couldn't be loaded.
+    pub fn loaded(&self) -> (usize, usize) {
+        (self.buffers.borrow().len(), self.failed.get())
+    }
+
+    /// Browsers start audio suspended until the page is tapped or clicked.
+    pub fn is_running(&self) -> bool {
+        self.ctx.state() == AudioContextState::Running
+    }
+
+    /// Call from a tap or click.
+    pub fn resume(&self) {
+        let _ = self.ctx.resume();
+    }
+
+    /// Start a note if it isn't already playing. False if its sample isn't
+    /// loaded (yet).
+    pub fn note_on(&mut self, note: &str) -> bool {
+        if self.voices.contains_key(note) {
+            return true;
+        }
+        let Some(buffer) = self.buffers.borrow().get(note).cloned() else {
+            return false;
+        };
+
+        match self.start_voice(&buffer) {
+            Ok(voice) => {
+                self.voices.insert(note.to_string(), voice);
+                true
+            }
+            Err(e) => {
+                web_sys::console::warn_1(&e);
+                false
+            }
+        }
+    }
+
+    fn start_voice(&self, buffer: &AudioBuffer) -> Result<Voice, JsValue> {
+        let source = self.ctx.create_buffer_source()?;
+        source.set_buffer(Some(buffer));
+        source.set_loop(true);
+
+        let gain = self.ctx.create_gain()?;
+        source.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&self.master)?;
+        scheduled(&source).start()?;
+
+        Ok(Voice { source, gain })
+    }
+
+    /// Fade a note out and stop it.
+    pub fn note_off(&mut self, note: &str) {
+        let Some(voice) = self.voices.remove(note) else {
+            return;
+        };
+        let now = self.ctx.current_time();
+        let _ = voice.gain.gain().set_target_at_time(0.0, now, RELEASE_SEC / 4.0);
+        let _ = scheduled(&voice.source).stop_with_when(now + RELEASE_SEC);
+    }
+
+    pub fn stop_all(&mut self) {
+        let notes: Vec<NoteName> = self.voices.keys().cloned().collect();
+        for note in notes {
+            self.note_off(&note);
+        }
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn set_master_gain(&mut self, gain: f32) {
+        self.master_gain = gain.clamp(0.0, 2.0);
+        self.refresh_volume();
+    }
+
+    /// Set current bellows amplitude (0..1). Call this every frame.
+    pub fn set_bellows(&mut self, a: f32) {
+        self.bellows_a = a.clamp(0.0, 1.0);
+        self.refresh_volume();
+    }
+
+    fn refresh_volume(&self) {
+        let vol = (self.master_gain * self.bellows_a).clamp(0.0, 2.0);
+        let now = self.ctx.current_time();
+        let _ = self.master.gain().set_target_at_time(vol, now, SMOOTHING_SEC);
+    }
+}
+
+/// Buffer sources also have deprecated `start` / `stop` of their own; these
+/// are the standard ones.
+fn scheduled(source: &AudioBufferSourceNode) -> &AudioScheduledSourceNode {
+    source.as_ref()
+}
+
+async fn fetch_buffer(ctx: &AudioContext, url: &str) -> Result<AudioBuffer, JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
+    let response: Response = JsFuture::from(window.fetch_with_str(url)).await?.dyn_into()?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+
+    let bytes = JsFuture::from(response.array_buffer()?).await?;
+    let decoded = JsFuture::from(ctx.decode_audio_data(&bytes.dyn_into()?)?).await?;
+    decoded.dyn_into()
+}